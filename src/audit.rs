@@ -0,0 +1,609 @@
+//! Chronological and biological consistency checks for the `audit` tool.
+//!
+//! Dates are intervals, so a rule only fires when every reading of the dates
+//! breaks it: `ABT 1850` against `1851` is never reported as impossible.
+
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+
+use crate::date::{GedcomDate, completed_years};
+use crate::gedcom::{Event, Family, GedcomStore, Individual};
+use crate::listing::compare_ids;
+use crate::pedigree::{AncestorCycle, ancestor_cycles};
+
+/// Youngest age at which someone is accepted as a parent.
+const MIN_PARENT_AGE: i64 = 13;
+/// Oldest age at which a woman is accepted as a mother.
+const MAX_MOTHER_AGE: i64 = 55;
+const MIN_MARRIAGE_AGE: i64 = 14;
+const MAX_LIFESPAN: i64 = 110;
+/// Nine months in days: the longest a child can be born after the father's
+/// death, and the shortest spacing between siblings who are not twins.
+const NINE_MONTHS_DAYS: i64 = 274;
+/// Births this close together are taken to be twins.
+const TWIN_DAYS: i64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    BornAfterDeath,
+    BaptizedBeforeBirth,
+    BornAfterMotherDeath,
+    BornLongAfterFatherDeath,
+    ParentTooYoung,
+    MotherTooOld,
+    MarriedTooYoung,
+    LifespanTooLong,
+    SiblingsTooClose,
+    MarriedAfterDeath,
+    AncestorCycle,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 11] = [
+        Rule::BornAfterDeath,
+        Rule::BaptizedBeforeBirth,
+        Rule::BornAfterMotherDeath,
+        Rule::BornLongAfterFatherDeath,
+        Rule::ParentTooYoung,
+        Rule::MotherTooOld,
+        Rule::MarriedTooYoung,
+        Rule::LifespanTooLong,
+        Rule::SiblingsTooClose,
+        Rule::MarriedAfterDeath,
+        Rule::AncestorCycle,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::BornAfterDeath => "born_after_death",
+            Rule::BaptizedBeforeBirth => "baptized_before_birth",
+            Rule::BornAfterMotherDeath => "born_after_mother_death",
+            Rule::BornLongAfterFatherDeath => "born_long_after_father_death",
+            Rule::ParentTooYoung => "parent_too_young",
+            Rule::MotherTooOld => "mother_too_old",
+            Rule::MarriedTooYoung => "married_too_young",
+            Rule::LifespanTooLong => "lifespan_too_long",
+            Rule::SiblingsTooClose => "siblings_too_close",
+            Rule::MarriedAfterDeath => "married_after_death",
+            Rule::AncestorCycle => "ancestor_cycle",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Rule::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+
+    /// Impossible orderings are errors; biologically unlikely ones warnings.
+    pub fn severity(self) -> Severity {
+        match self {
+            Rule::BornAfterDeath
+            | Rule::BaptizedBeforeBirth
+            | Rule::BornAfterMotherDeath
+            | Rule::MarriedAfterDeath
+            | Rule::AncestorCycle => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub rule: Rule,
+    pub severity: Severity,
+    /// Individuals and families involved, the one the finding is about first.
+    pub records: Vec<String>,
+    pub message: String,
+    pub suggestion: String,
+}
+
+/// An event date as written, with its interval of Julian days.
+struct Dated<'a> {
+    raw: &'a str,
+    earliest: Option<i64>,
+    latest: Option<i64>,
+}
+
+fn dated(event: Option<&Event>) -> Option<Dated<'_>> {
+    let raw = event?.date.as_deref()?;
+    let date = GedcomDate::parse(raw).ok()?;
+    Some(Dated {
+        raw,
+        earliest: date.earliest_jdn,
+        latest: date.latest_jdn,
+    })
+}
+
+/// Whether `a` ends more than `days` before `b` starts.
+fn before_by(a: &Dated, b: &Dated, days: i64) -> bool {
+    matches!((a.latest, b.earliest), (Some(a), Some(b)) if b - a > days)
+}
+
+/// Fewest and most completed years between a birth and a later date.
+fn age(birth: &Dated, at: &Dated) -> (Option<i64>, Option<i64>) {
+    (
+        birth
+            .latest
+            .zip(at.earliest)
+            .map(|(born, at)| completed_years(born, at)),
+        birth
+            .earliest
+            .zip(at.latest)
+            .map(|(born, at)| completed_years(born, at)),
+    )
+}
+
+fn label(individual: &Individual) -> String {
+    match &individual.name {
+        Some(name) => format!("{} ({})", name.replace('/', ""), individual.id),
+        None => individual.id.clone(),
+    }
+}
+
+struct Auditor<'a> {
+    store: &'a GedcomStore,
+    rules: &'a BTreeSet<Rule>,
+    findings: Vec<Finding>,
+}
+
+impl Auditor<'_> {
+    fn report(&mut self, rule: Rule, records: Vec<String>, message: String, suggestion: &str) {
+        if self.rules.contains(&rule) {
+            self.findings.push(Finding {
+                rule,
+                severity: rule.severity(),
+                records,
+                message,
+                suggestion: suggestion.to_owned(),
+            });
+        }
+    }
+
+    fn individual(&mut self, individual: &Individual) {
+        let id = &individual.id;
+        let birth = dated(individual.birth.as_ref());
+        let death = dated(individual.death.as_ref());
+
+        if let (Some(birth), Some(death)) = (&birth, &death) {
+            if before_by(death, birth, 0) {
+                self.report(
+                    Rule::BornAfterDeath,
+                    vec![id.clone()],
+                    format!(
+                        "{} was born ({}) after their death ({})",
+                        label(individual),
+                        birth.raw,
+                        death.raw
+                    ),
+                    "Check both dates for a mistyped year, or whether one belongs to another person.",
+                );
+            }
+            if let (Some(min), _) = age(birth, death)
+                && min > MAX_LIFESPAN
+            {
+                self.report(
+                    Rule::LifespanTooLong,
+                    vec![id.clone()],
+                    format!(
+                        "{} lived at least {min} years (born {}, died {})",
+                        label(individual),
+                        birth.raw,
+                        death.raw
+                    ),
+                    "Check both dates; the birth and death may belong to different people of the same name.",
+                );
+            }
+        }
+
+        if let Some(birth) = &birth {
+            for event in individual
+                .events
+                .iter()
+                .filter(|event| matches!(event.tag.as_deref(), Some("BAPM" | "CHR")))
+            {
+                if let Some(baptism) = dated(Some(event))
+                    && before_by(&baptism, birth, 0)
+                {
+                    self.report(
+                        Rule::BaptizedBeforeBirth,
+                        vec![id.clone()],
+                        format!(
+                            "{} was baptized ({}) before their birth ({})",
+                            label(individual),
+                            baptism.raw,
+                            birth.raw
+                        ),
+                        "If the birth date was estimated from the baptism, correct the estimate; otherwise check both dates.",
+                    );
+                }
+            }
+        }
+
+        for family in self.store.families_as_spouse(id) {
+            let Some(marriage) = family
+                .events
+                .iter()
+                .find(|event| event.tag.as_deref() == Some("MARR"))
+                .and_then(|event| dated(Some(event)))
+            else {
+                continue;
+            };
+            let records = vec![id.clone(), family.id.clone()];
+            if let Some(birth) = &birth
+                && let (_, Some(max)) = age(birth, &marriage)
+                && max < MIN_MARRIAGE_AGE
+            {
+                self.report(
+                    Rule::MarriedTooYoung,
+                    records.clone(),
+                    format!(
+                        "{} was at most {max} when married ({}) in {}",
+                        label(individual),
+                        marriage.raw,
+                        family.id
+                    ),
+                    "Check the birth and marriage dates, or whether the right spouse is linked to the family.",
+                );
+            }
+            if let Some(death) = &death
+                && before_by(death, &marriage, 0)
+            {
+                self.report(
+                    Rule::MarriedAfterDeath,
+                    records,
+                    format!(
+                        "{} died ({}) before the marriage ({}) in {}",
+                        label(individual),
+                        death.raw,
+                        marriage.raw,
+                        family.id
+                    ),
+                    "Check the dates, or whether the right spouse is linked to the family.",
+                );
+            }
+        }
+    }
+
+    fn parents(&mut self, family: &Family, child: &Individual) {
+        let Some(birth) = dated(child.birth.as_ref()) else {
+            return;
+        };
+        let parent =
+            |id: &Option<String>| id.as_deref().and_then(|id| self.store.get_individual(id));
+        let (father, mother) = (parent(&family.husband), parent(&family.wife));
+        let records =
+            |parent: &Individual| vec![child.id.clone(), parent.id.clone(), family.id.clone()];
+
+        for (role, parent) in [("father", father), ("mother", mother)] {
+            let Some(parent) = parent else {
+                continue;
+            };
+            let Some(parent_birth) = dated(parent.birth.as_ref()) else {
+                continue;
+            };
+            let (min, max) = age(&parent_birth, &birth);
+            if let Some(max) = max
+                && max < MIN_PARENT_AGE
+            {
+                self.report(
+                    Rule::ParentTooYoung,
+                    records(parent),
+                    if max < 0 {
+                        format!(
+                            "{} was born ({}) after their child {} ({})",
+                            label(parent),
+                            parent_birth.raw,
+                            label(child),
+                            birth.raw
+                        )
+                    } else {
+                        format!(
+                            "{} was at most {max} when their child {} was born ({})",
+                            label(parent),
+                            label(child),
+                            birth.raw
+                        )
+                    },
+                    &format!(
+                        "Check the {role}'s birth date, or whether the child is linked to the right {role}."
+                    ),
+                );
+            }
+            if role == "mother"
+                && let Some(min) = min
+                && min > MAX_MOTHER_AGE
+            {
+                self.report(
+                    Rule::MotherTooOld,
+                    records(parent),
+                    format!(
+                        "{} was at least {min} when her child {} was born ({})",
+                        label(parent),
+                        label(child),
+                        birth.raw
+                    ),
+                    "Check the mother's birth date; the child may belong to a daughter or daughter-in-law.",
+                );
+            }
+        }
+
+        if let Some(mother) = mother
+            && let Some(death) = dated(mother.death.as_ref())
+            && before_by(&death, &birth, 0)
+        {
+            self.report(
+                Rule::BornAfterMotherDeath,
+                records(mother),
+                format!(
+                    "{} was born ({}) after their mother {} died ({})",
+                    label(child),
+                    birth.raw,
+                    label(mother),
+                    death.raw
+                ),
+                "Check the dates, or whether the child is linked to the right mother.",
+            );
+        }
+        if let Some(father) = father
+            && let Some(death) = dated(father.death.as_ref())
+            && before_by(&death, &birth, NINE_MONTHS_DAYS)
+        {
+            self.report(
+                Rule::BornLongAfterFatherDeath,
+                records(father),
+                format!(
+                    "{} was born ({}) more than nine months after their father {} died ({})",
+                    label(child),
+                    birth.raw,
+                    label(father),
+                    death.raw
+                ),
+                "Check the dates, or whether the child is linked to the right father.",
+            );
+        }
+    }
+
+    fn cycle(&mut self, cycle: &AncestorCycle) {
+        let Some(first) = cycle.chain.first() else {
+            return;
+        };
+        let mut records: Vec<String> = cycle.chain.iter().map(|link| link.id.clone()).collect();
+        records.extend(cycle.chain.iter().map(|link| link.family.clone()));
+        let path: Vec<&str> = cycle
+            .chain
+            .iter()
+            .chain([first])
+            .map(|link| link.id.as_str())
+            .collect();
+        let who = self
+            .store
+            .get_individual(&first.id)
+            .map_or_else(|| first.id.clone(), label);
+        self.report(
+            Rule::AncestorCycle,
+            records,
+            format!(
+                "{who} is their own ancestor: {} (each a child of the next)",
+                path.join(" → ")
+            ),
+            "Remove the link that closes the loop; usually a child was attached to the wrong family.",
+        );
+    }
+
+    fn siblings(&mut self, family: &Family, children: &[&Individual]) {
+        let born: Vec<(&Individual, Dated)> = children
+            .iter()
+            .filter_map(|child| Some((*child, dated(child.birth.as_ref())?)))
+            .collect();
+        for (i, (a, a_birth)) in born.iter().enumerate() {
+            for (b, b_birth) in &born[i + 1..] {
+                let (Some(a_start), Some(a_end), Some(b_start), Some(b_end)) = (
+                    a_birth.earliest,
+                    a_birth.latest,
+                    b_birth.earliest,
+                    b_birth.latest,
+                ) else {
+                    continue;
+                };
+                let shortest = (b_start - a_end).max(a_start - b_end);
+                let longest = (b_end - a_start).max(a_end - b_start);
+                if shortest > TWIN_DAYS && longest < NINE_MONTHS_DAYS {
+                    self.report(
+                        Rule::SiblingsTooClose,
+                        vec![a.id.clone(), b.id.clone(), family.id.clone()],
+                        format!(
+                            "{} ({}) and {} ({}) were born less than nine months apart",
+                            label(a),
+                            a_birth.raw,
+                            label(b),
+                            b_birth.raw
+                        ),
+                        "Check both birth dates; twins should share a date, and a baptism date may have been entered as the birth.",
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Runs the enabled `rules` over every individual and family. Findings are
+/// ordered by the record they are about, then by rule.
+pub fn audit(store: &GedcomStore, rules: &BTreeSet<Rule>) -> Vec<Finding> {
+    let mut auditor = Auditor {
+        store,
+        rules,
+        findings: Vec::new(),
+    };
+    for individual in store.individuals() {
+        auditor.individual(individual);
+    }
+    for family in store.families() {
+        let children: Vec<&Individual> = family
+            .children
+            .iter()
+            .filter_map(|id| store.get_individual(id))
+            .collect();
+        for child in &children {
+            auditor.parents(family, child);
+        }
+        auditor.siblings(family, &children);
+    }
+    if rules.contains(&Rule::AncestorCycle) {
+        for cycle in ancestor_cycles(store) {
+            auditor.cycle(&cycle);
+        }
+    }
+
+    let mut findings = auditor.findings;
+    findings.sort_by(compare_findings);
+    findings
+}
+
+/// Orders findings by the record they are about, then rule, then the other
+/// records involved.
+fn compare_findings(a: &Finding, b: &Finding) -> Ordering {
+    let (a_first, a_rest) = a.records.split_at(a.records.len().min(1));
+    let (b_first, b_rest) = b.records.split_at(b.records.len().min(1));
+    compare_records(a_first, b_first)
+        .then(a.rule.cmp(&b.rule))
+        .then_with(|| compare_records(a_rest, b_rest))
+}
+
+fn compare_records(a: &[String], b: &[String]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| compare_ids(a, b))
+        .find(|order| order.is_ne())
+        .unwrap_or_else(|| a.len().cmp(&b.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> GedcomStore {
+        GedcomStore::from_data(
+            crate::gedcom::parse_gedcom(
+                r#"0 @I1@ INDI
+1 NAME Peter /Lang/
+1 BIRT
+2 DATE 1800
+1 DEAT
+2 DATE 10 JAN 1840
+0 @I2@ INDI
+1 NAME Rosa /Lang/
+1 BIRT
+2 DATE 1790
+1 DEAT
+2 DATE 1830
+0 @I3@ INDI
+1 NAME Karl /Lang/
+1 BIRT
+2 DATE 1 DEC 1840
+1 CHR
+2 DATE 20 NOV 1840
+0 @I4@ INDI
+1 NAME Maria /Lang/
+1 BIRT
+2 DATE 1 MAR 1841
+0 @I5@ INDI
+1 NAME Old /Man/
+1 BIRT
+2 DATE ABT 1700
+1 DEAT
+2 DATE 1815
+0 @I6@ INDI
+1 NAME Twin /Lang/
+1 BIRT
+2 DATE 2 MAR 1841
+0 @F1@ FAM
+1 HUSB @I1@
+1 WIFE @I2@
+1 MARR
+2 DATE 1835
+1 CHIL @I3@
+1 CHIL @I4@
+1 CHIL @I6@
+"#,
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn reports_impossible_and_unlikely_dates() {
+        let store = store();
+        let all: BTreeSet<Rule> = Rule::ALL.into_iter().collect();
+        let findings = audit(&store, &all);
+        let found: Vec<(Rule, Vec<&str>)> = findings
+            .iter()
+            .map(|finding| {
+                let records = finding.records.iter().map(String::as_str).collect();
+                (finding.rule, records)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Rule::MarriedAfterDeath, vec!["I2", "F1"]),
+                (Rule::BaptizedBeforeBirth, vec!["I3"]),
+                (Rule::BornAfterMotherDeath, vec!["I3", "I2", "F1"]),
+                (Rule::BornLongAfterFatherDeath, vec!["I3", "I1", "F1"]),
+                (Rule::SiblingsTooClose, vec!["I3", "I4", "F1"]),
+                (Rule::SiblingsTooClose, vec!["I3", "I6", "F1"]),
+                (Rule::BornAfterMotherDeath, vec!["I4", "I2", "F1"]),
+                (Rule::BornLongAfterFatherDeath, vec!["I4", "I1", "F1"]),
+                (Rule::LifespanTooLong, vec!["I5"]),
+                (Rule::BornAfterMotherDeath, vec!["I6", "I2", "F1"]),
+                (Rule::BornLongAfterFatherDeath, vec!["I6", "I1", "F1"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn runs_only_enabled_rules() {
+        let store = store();
+        let rules = BTreeSet::from([Rule::LifespanTooLong, Rule::MotherTooOld]);
+        let findings = audit(&store, &rules);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert_eq!(
+            findings[0].message,
+            "Old Man (I5) lived at least 114 years (born ABT 1700, died 1815)"
+        );
+        assert_eq!(Rule::parse("mother_too_old"), Some(Rule::MotherTooOld));
+        assert_eq!(Rule::parse("nope"), None);
+    }
+
+    #[test]
+    fn orders_findings_by_record_then_rule() {
+        let finding = |rule, records: &[&str]| Finding {
+            rule,
+            severity: rule.severity(),
+            records: records.iter().map(|id| id.to_string()).collect(),
+            message: String::new(),
+            suggestion: String::new(),
+        };
+        let a = finding(Rule::LifespanTooLong, &["I3"]);
+        let b = finding(Rule::SiblingsTooClose, &["I3", "I4", "F1"]);
+        let c = finding(Rule::BornAfterMotherDeath, &["I3", "I5", "F1"]);
+        let d = finding(Rule::SiblingsTooClose, &["I3", "I10", "F1"]);
+        let e = finding(Rule::BornAfterDeath, &["I10"]);
+        assert_eq!(compare_findings(&c, &a), Ordering::Less);
+        assert_eq!(compare_findings(&a, &b), Ordering::Less);
+        assert_eq!(compare_findings(&c, &b), Ordering::Less);
+        assert_eq!(compare_findings(&b, &d), Ordering::Less);
+
+        let mut findings = vec![e.clone(), d.clone(), b.clone(), a.clone(), c.clone()];
+        findings.sort_by(compare_findings);
+        assert_eq!(findings, vec![c, a, b, d, e]);
+    }
+}
@@ -11,6 +11,7 @@ pub struct Config {
     pub bind_addr: SocketAddr,
     pub gedcom_path: PathBuf,
     pub persistence_path: Option<PathBuf>,
+    pub gazetteer_path: Option<PathBuf>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -19,6 +20,8 @@ struct RawConfig {
     gedcom_path: PathBuf,
     #[serde(default)]
     persistence_path: Option<PathBuf>,
+    #[serde(default)]
+    gazetteer_path: Option<PathBuf>,
 }
 
 #[derive(Debug, Error)]
@@ -40,6 +43,7 @@ impl Config {
             bind_addr,
             gedcom_path: raw.gedcom_path,
             persistence_path: raw.persistence_path,
+            gazetteer_path: raw.gazetteer_path,
         })
     }
 
@@ -60,6 +64,7 @@ mod tests {
             bind_address = "127.0.0.1:8080"
             gedcom_path = "/data/example.ged"
             persistence_path = "/data/state.json"
+            gazetteer_path = "/data/US.txt"
             "#,
         )
         .expect("config should parse");
@@ -70,6 +75,7 @@ mod tests {
                 bind_addr: "127.0.0.1:8080".parse().unwrap(),
                 gedcom_path: PathBuf::from("/data/example.ged"),
                 persistence_path: Some(PathBuf::from("/data/state.json")),
+                gazetteer_path: Some(PathBuf::from("/data/US.txt")),
             }
         );
     }
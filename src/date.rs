@@ -0,0 +1,655 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Calendar {
+    Gregorian,
+    Julian,
+    Hebrew,
+    FrenchRepublican,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateKind {
+    Exact,
+    About,
+    Calculated,
+    Estimated,
+    Before,
+    After,
+    Between,
+    From,
+    To,
+    FromTo,
+    Interpreted,
+    Phrase,
+}
+
+/// A single calendar date, possibly partial (month/year or year only).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DatePoint {
+    pub calendar: Calendar,
+    /// The year as written; see `bc` for the era.
+    pub year: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub month: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub day: Option<u8>,
+    /// New-style year of a dual date such as `1750/51`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dual_year: Option<i32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub bc: bool,
+}
+
+/// A parsed GEDCOM date value.
+///
+/// Every date is normalized to an interval of Julian day numbers so dates in
+/// different calendars and with different precision can be compared. An
+/// open bound (e.g. the start of `BEF 1900`) is `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GedcomDate {
+    pub kind: DateKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<DatePoint>,
+    /// Second date of a `BET ... AND ...` or `FROM ... TO ...` range, or the
+    /// only date of a `TO ...` period.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<DatePoint>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phrase: Option<String>,
+    pub earliest_jdn: Option<i64>,
+    pub latest_jdn: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unrecognized GEDCOM date: {0}")]
+pub struct DateError(String);
+
+impl GedcomDate {
+    pub fn parse(input: &str) -> Result<Self, DateError> {
+        let error = || DateError(input.to_owned());
+        let trimmed = input.trim();
+
+        // Phrases keep their original case, everything else is matched
+        // case-insensitively.
+        let (body, phrase) = match (trimmed.find('('), trimmed.rfind(')')) {
+            (Some(open), Some(close)) if open < close => (
+                trimmed[..open].trim(),
+                Some(trimmed[open + 1..close].trim().to_owned()),
+            ),
+            (None, None) => (trimmed, None),
+            _ => return Err(error()),
+        };
+        let upper = body
+            .to_ascii_uppercase()
+            .replace("@#DFRENCH R@", "@#DFRENCH_R@");
+        let tokens: Vec<&str> = upper.split_whitespace().collect();
+
+        let (kind, date, end) = match tokens.split_first() {
+            None if phrase.is_some() => (DateKind::Phrase, None, None),
+            None => return Err(error()),
+            Some((&"INT", rest)) if phrase.is_some() => {
+                (DateKind::Interpreted, Some(parse_point(rest)?), None)
+            }
+            Some((&"FROM", rest)) => match split_at_keyword(rest, "TO") {
+                Some((from, to)) => (
+                    DateKind::FromTo,
+                    Some(parse_point(from)?),
+                    Some(parse_point(to)?),
+                ),
+                None => (DateKind::From, Some(parse_point(rest)?), None),
+            },
+            Some((&"TO", rest)) => (DateKind::To, None, Some(parse_point(rest)?)),
+            Some((&"BET", rest)) => {
+                let (from, to) = split_at_keyword(rest, "AND").ok_or_else(error)?;
+                (
+                    DateKind::Between,
+                    Some(parse_point(from)?),
+                    Some(parse_point(to)?),
+                )
+            }
+            Some((&"BEF", rest)) => (DateKind::Before, Some(parse_point(rest)?), None),
+            Some((&"AFT", rest)) => (DateKind::After, Some(parse_point(rest)?), None),
+            Some((&"ABT", rest)) => (DateKind::About, Some(parse_point(rest)?), None),
+            Some((&"CAL", rest)) => (DateKind::Calculated, Some(parse_point(rest)?), None),
+            Some((&"EST", rest)) => (DateKind::Estimated, Some(parse_point(rest)?), None),
+            Some(_) => (DateKind::Exact, Some(parse_point(&tokens)?), None),
+        };
+        if phrase.is_some() && !matches!(kind, DateKind::Interpreted | DateKind::Phrase) {
+            return Err(error());
+        }
+
+        let first = date.map(|point| point.jdn_range()).transpose()?;
+        let second = end.map(|point| point.jdn_range()).transpose()?;
+        let (earliest_jdn, latest_jdn) = match kind {
+            DateKind::Phrase => (None, None),
+            DateKind::Before => (None, first.map(|(start, _)| start - 1)),
+            DateKind::After => (first.map(|(_, end)| end + 1), None),
+            DateKind::From => (first.map(|(start, _)| start), None),
+            DateKind::To => (None, second.map(|(_, end)| end)),
+            DateKind::Between | DateKind::FromTo => {
+                (first.map(|(start, _)| start), second.map(|(_, end)| end))
+            }
+            _ => (first.map(|(start, _)| start), first.map(|(_, end)| end)),
+        };
+        if let (Some(earliest), Some(latest)) = (earliest_jdn, latest_jdn)
+            && earliest > latest
+        {
+            return Err(error());
+        }
+
+        Ok(Self {
+            kind,
+            date,
+            end,
+            phrase,
+            earliest_jdn,
+            latest_jdn,
+        })
+    }
+}
+
+/// Whole years elapsed between two Julian day numbers, counted on the
+/// Gregorian calendar (birthdays on 29 February fall on 1 March).
+pub fn completed_years(from_jdn: i64, to_jdn: i64) -> i64 {
+    let (from_year, from_month, from_day) = jdn_to_gregorian(from_jdn);
+    let (to_year, to_month, to_day) = jdn_to_gregorian(to_jdn);
+    let mut years = to_year - from_year;
+    if (to_month, to_day) < (from_month, from_day) {
+        years -= 1;
+    }
+    years
+}
+
+/// Current UTC date as a Julian day number, and the seconds since midnight.
+pub fn now_utc() -> (i64, u64) {
+    const UNIX_EPOCH_JDN: i64 = 2440588;
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    (UNIX_EPOCH_JDN + (seconds / 86_400) as i64, seconds % 86_400)
+}
+
+/// Julian day number of the current UTC date.
+pub fn today_jdn() -> i64 {
+    now_utc().0
+}
+
+/// Formats a Julian day number as a Gregorian GEDCOM date, e.g. `1 JAN 2020`.
+pub fn format_gregorian(jdn: i64) -> String {
+    let (year, month, day) = jdn_to_gregorian(jdn);
+    format!("{day} {} {year}", GREGORIAN_MONTHS[month as usize - 1])
+}
+
+/// Gregorian year of a Julian day number.
+pub fn gregorian_year(jdn: i64) -> i64 {
+    jdn_to_gregorian(jdn).0
+}
+
+fn jdn_to_gregorian(jdn: i64) -> (i64, i64, i64) {
+    let a = jdn + 32044;
+    let b = (4 * a + 3).div_euclid(146_097);
+    let c = a - (146_097 * b).div_euclid(4);
+    let d = (4 * c + 3).div_euclid(1461);
+    let e = c - (1461 * d).div_euclid(4);
+    let m = (5 * e + 2).div_euclid(153);
+    let day = e - (153 * m + 2).div_euclid(5) + 1;
+    let month = m + 3 - 12 * m.div_euclid(10);
+    let year = 100 * b + d - 4800 + m.div_euclid(10);
+    (year, month, day)
+}
+
+fn split_at_keyword<'a>(
+    tokens: &'a [&'a str],
+    keyword: &str,
+) -> Option<(&'a [&'a str], &'a [&'a str])> {
+    let idx = tokens.iter().position(|token| *token == keyword)?;
+    Some((&tokens[..idx], &tokens[idx + 1..]))
+}
+
+fn parse_point(tokens: &[&str]) -> Result<DatePoint, DateError> {
+    let error = || DateError(tokens.join(" "));
+
+    let (calendar, mut tokens) = match tokens.split_first() {
+        Some((&"@#DGREGORIAN@", rest)) => (Calendar::Gregorian, rest),
+        Some((&"@#DJULIAN@", rest)) => (Calendar::Julian, rest),
+        Some((&"@#DHEBREW@", rest)) => (Calendar::Hebrew, rest),
+        Some((&"@#DFRENCH_R@", rest)) => (Calendar::FrenchRepublican, rest),
+        Some((escape, _)) if escape.starts_with("@#") => return Err(error()),
+        _ => (Calendar::Gregorian, tokens),
+    };
+
+    let mut bc = false;
+    if let Some((last, rest)) = tokens.split_last()
+        && matches!(*last, "B.C." | "BC" | "BCE" | "B.C.E.")
+    {
+        bc = true;
+        tokens = rest;
+    }
+
+    let (day, month, year) = match tokens {
+        [year] => (None, None, *year),
+        [month, year] => (None, Some(*month), *year),
+        [day, month, year] => (Some(*day), Some(*month), *year),
+        _ => return Err(error()),
+    };
+
+    let (year, dual_year) = match year.split_once('/') {
+        Some((first, second)) if calendar == Calendar::Gregorian && !bc => {
+            let first: i32 = first.parse().map_err(|_| error())?;
+            let mut dual: i32 = second.parse().map_err(|_| error())?;
+            if second.len() < first.to_string().len() {
+                let scale = 10_i32.checked_pow(second.len() as u32).ok_or_else(error)?;
+                dual = first
+                    .checked_sub(first.rem_euclid(scale))
+                    .and_then(|century| century.checked_add(dual))
+                    .ok_or_else(error)?;
+                if dual <= first {
+                    dual = dual.checked_add(scale).ok_or_else(error)?;
+                }
+            }
+            if first.checked_add(1) != Some(dual) {
+                return Err(error());
+            }
+            (first, Some(dual))
+        }
+        Some(_) => return Err(error()),
+        None => (year.parse().map_err(|_| error())?, None),
+    };
+    if year <= 0 || (bc && !matches!(calendar, Calendar::Gregorian | Calendar::Julian)) {
+        return Err(error());
+    }
+
+    let month = month
+        .map(|name| month_number(calendar, name).ok_or_else(error))
+        .transpose()?;
+    let day = day
+        .map(|day| day.parse::<u8>().map_err(|_| error()))
+        .transpose()?;
+
+    let point = DatePoint {
+        calendar,
+        year,
+        month,
+        day,
+        dual_year,
+        bc,
+    };
+    point.jdn_range()?;
+    Ok(point)
+}
+
+const GREGORIAN_MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+fn month_number(calendar: Calendar, name: &str) -> Option<u8> {
+    let names: &[&str] = match calendar {
+        Calendar::Gregorian | Calendar::Julian => &GREGORIAN_MONTHS,
+        Calendar::Hebrew => &[
+            "TSH", "CSH", "KSL", "TVT", "SHV", "ADR", "ADS", "NSN", "IYR", "SVN", "TMZ", "AAV",
+            "ELL",
+        ],
+        Calendar::FrenchRepublican => &[
+            "VEND", "BRUM", "FRIM", "NIVO", "PLUV", "VENT", "GERM", "FLOR", "PRAI", "MESS", "THER",
+            "FRUC", "COMP",
+        ],
+    };
+    names
+        .iter()
+        .position(|candidate| *candidate == name)
+        .map(|idx| idx as u8 + 1)
+}
+
+impl DatePoint {
+    /// Year used for calendar arithmetic: new-style for dual dates and
+    /// astronomical numbering (1 B.C. = year 0) for B.C. dates.
+    fn effective_year(&self) -> i64 {
+        let year = i64::from(self.dual_year.unwrap_or(self.year));
+        if self.bc { 1 - year } else { year }
+    }
+
+    /// First and last Julian day number covered by this (possibly partial)
+    /// date.
+    pub fn jdn_range(&self) -> Result<(i64, i64), DateError> {
+        let year = self.effective_year();
+        let invalid = || DateError(format!("{self:?}"));
+        let months = months_in_year(self.calendar);
+        let (first_month, last_month) = match self.month {
+            Some(month) if i64::from(month) > months => return Err(invalid()),
+            // Adar Sheni only exists in leap years.
+            Some(7) if self.calendar == Calendar::Hebrew && !hebrew_leap(year) => {
+                return Err(invalid());
+            }
+            Some(month) => (i64::from(month), i64::from(month)),
+            None => (1, months),
+        };
+        if self.month.is_none() && self.day.is_some() {
+            return Err(invalid());
+        }
+        let last_day = days_in_month(self.calendar, year, last_month);
+        let (first_day, last_day) = match self.day {
+            Some(day) if day == 0 || i64::from(day) > last_day => return Err(invalid()),
+            Some(day) => (i64::from(day), i64::from(day)),
+            None => (1, last_day),
+        };
+        Ok((
+            to_jdn(self.calendar, year, first_month, first_day),
+            to_jdn(self.calendar, year, last_month, last_day),
+        ))
+    }
+}
+
+/// Number of months in GEDCOM numbering. Hebrew years always have 13 month
+/// codes; `ADS` is simply invalid in common years.
+fn months_in_year(calendar: Calendar) -> i64 {
+    match calendar {
+        Calendar::Gregorian | Calendar::Julian => 12,
+        Calendar::Hebrew | Calendar::FrenchRepublican => 13,
+    }
+}
+
+/// Month numbers follow GEDCOM order, so Hebrew months start with Tishrei
+/// and French Republican months with Vendémiaire.
+fn days_in_month(calendar: Calendar, year: i64, month: i64) -> i64 {
+    match calendar {
+        Calendar::Gregorian | Calendar::Julian => match month {
+            2 if julian_or_gregorian_leap(calendar, year) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        },
+        Calendar::Hebrew => hebrew_month_days(year, hebrew_month(year, month)),
+        Calendar::FrenchRepublican if month == 13 => {
+            if french_leap(year) {
+                6
+            } else {
+                5
+            }
+        }
+        Calendar::FrenchRepublican => 30,
+    }
+}
+
+fn julian_or_gregorian_leap(calendar: Calendar, year: i64) -> bool {
+    match calendar {
+        Calendar::Julian => year.rem_euclid(4) == 0,
+        _ => year.rem_euclid(4) == 0 && (year.rem_euclid(100) != 0 || year.rem_euclid(400) == 0),
+    }
+}
+
+fn to_jdn(calendar: Calendar, year: i64, month: i64, day: i64) -> i64 {
+    match calendar {
+        Calendar::Gregorian | Calendar::Julian => {
+            let a = (14 - month) / 12;
+            let y = year + 4800 - a;
+            let m = month + 12 * a - 3;
+            let base = day + (153 * m + 2) / 5 + 365 * y + y.div_euclid(4);
+            if calendar == Calendar::Julian {
+                base - 32083
+            } else {
+                base - y.div_euclid(100) + y.div_euclid(400) - 32045
+            }
+        }
+        Calendar::Hebrew => hebrew_to_jdn(year, hebrew_month(year, month), day),
+        Calendar::FrenchRepublican => french_year_start(year) + (month - 1) * 30 + day - 1,
+    }
+}
+
+/// Maps a GEDCOM Hebrew month (1 = Tishrei) to the traditional numbering
+/// (1 = Nisan, 7 = Tishrei, 12 = Adar / Adar I, 13 = Adar II). In common
+/// years `ADR` denotes the single Adar; `ADS` is rejected before this.
+fn hebrew_month(year: i64, month: i64) -> i64 {
+    match month {
+        1..=5 => month + 6,
+        6 => 12,
+        7 if hebrew_leap(year) => 13,
+        7 => 12,
+        _ => month - 7,
+    }
+}
+
+fn hebrew_leap(year: i64) -> bool {
+    (year * 7 + 1).rem_euclid(19) < 7
+}
+
+fn hebrew_months(year: i64) -> i64 {
+    if hebrew_leap(year) { 13 } else { 12 }
+}
+
+fn hebrew_delay_1(year: i64) -> i64 {
+    let months = (235 * year - 234).div_euclid(19);
+    let parts = 12084 + 13753 * months;
+    let mut day = months * 29 + parts.div_euclid(25920);
+    if (3 * (day + 1)).rem_euclid(7) < 3 {
+        day += 1;
+    }
+    day
+}
+
+fn hebrew_delay_2(year: i64) -> i64 {
+    let last = hebrew_delay_1(year - 1);
+    let present = hebrew_delay_1(year);
+    let next = hebrew_delay_1(year + 1);
+    if next - present == 356 {
+        2
+    } else if present - last == 382 {
+        1
+    } else {
+        0
+    }
+}
+
+fn hebrew_year_days(year: i64) -> i64 {
+    hebrew_to_jdn(year + 1, 7, 1) - hebrew_to_jdn(year, 7, 1)
+}
+
+fn hebrew_month_days(year: i64, month: i64) -> i64 {
+    match month {
+        2 | 4 | 6 | 10 | 13 => 29,
+        12 if !hebrew_leap(year) => 29,
+        8 if hebrew_year_days(year).rem_euclid(10) != 5 => 29,
+        9 if hebrew_year_days(year).rem_euclid(10) == 3 => 29,
+        _ => 30,
+    }
+}
+
+fn hebrew_to_jdn(year: i64, month: i64, day: i64) -> i64 {
+    const HEBREW_EPOCH: i64 = 347_996;
+    let mut jdn = HEBREW_EPOCH + hebrew_delay_1(year) + hebrew_delay_2(year) + day + 1;
+    if month < 7 {
+        jdn += (7..=hebrew_months(year))
+            .chain(1..month)
+            .map(|mon| hebrew_month_days(year, mon))
+            .sum::<i64>();
+    } else {
+        jdn += (7..month)
+            .map(|mon| hebrew_month_days(year, mon))
+            .sum::<i64>();
+    }
+    jdn
+}
+
+/// Sextile years as observed (3, 7, 11) and Romme's rule afterwards.
+fn french_leap(year: i64) -> bool {
+    if year < 15 {
+        year.rem_euclid(4) == 3
+    } else {
+        year.rem_euclid(4) == 0 && (year.rem_euclid(100) != 0 || year.rem_euclid(400) == 0)
+    }
+}
+
+fn french_year_start(year: i64) -> i64 {
+    // 1 Vendémiaire An I = 22 September 1792.
+    const EPOCH: i64 = 2_375_840;
+    // Leap years before `year`: the sextiles 3, 7 and 11, then Romme's
+    // years from 15 on.
+    let romme = |last: i64| last / 4 - last / 100 + last / 400;
+    let sextiles = (year / 4).clamp(0, 3);
+    let later = if year > 15 {
+        romme(year - 1) - romme(14)
+    } else {
+        0
+    };
+    EPOCH + 365 * (year - 1) + sextiles + later
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(input: &str) -> (Option<i64>, Option<i64>) {
+        let date = GedcomDate::parse(input).expect("should parse");
+        (date.earliest_jdn, date.latest_jdn)
+    }
+
+    #[test]
+    fn parses_exact_and_partial_dates() {
+        assert_eq!(range("1 JAN 2000"), (Some(2_451_545), Some(2_451_545)));
+        assert_eq!(range("FEB 2000"), (Some(2_451_576), Some(2_451_604)));
+        assert_eq!(range("2000"), (Some(2_451_545), Some(2_451_910)));
+        assert_eq!(range("1 jan 2000"), range("1 JAN 2000"));
+
+        let date = GedcomDate::parse("15 MAR 1820").expect("should parse");
+        assert_eq!(date.kind, DateKind::Exact);
+        assert_eq!(
+            date.date,
+            Some(DatePoint {
+                calendar: Calendar::Gregorian,
+                year: 1820,
+                month: Some(3),
+                day: Some(15),
+                dual_year: None,
+                bc: false,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_qualifiers_and_ranges() {
+        let about = GedcomDate::parse("ABT 1900").expect("should parse");
+        assert_eq!(about.kind, DateKind::About);
+        assert_eq!(
+            (about.earliest_jdn, about.latest_jdn),
+            range("1900"),
+            "approximate dates keep the interval of the underlying date"
+        );
+        assert_eq!(
+            GedcomDate::parse("CAL 1900").unwrap().kind,
+            DateKind::Calculated
+        );
+        assert_eq!(
+            GedcomDate::parse("EST 1900").unwrap().kind,
+            DateKind::Estimated
+        );
+
+        assert_eq!(range("BEF 1 JAN 2000"), (None, Some(2_451_544)));
+        assert_eq!(range("AFT 2000"), (Some(2_451_911), None));
+        assert_eq!(
+            range("BET 1900 AND 1910"),
+            (range("1900").0, range("1910").1)
+        );
+        assert_eq!(
+            range("FROM 1900 TO 1910"),
+            (range("1900").0, range("1910").1)
+        );
+        assert_eq!(range("FROM 1900"), (range("1900").0, None));
+        assert_eq!(range("TO 1910"), (None, range("1910").1));
+
+        let between = GedcomDate::parse("BET 1900 AND 1910").unwrap();
+        assert_eq!(between.kind, DateKind::Between);
+        assert_eq!(between.end.map(|point| point.year), Some(1910));
+    }
+
+    #[test]
+    fn parses_phrases_and_interpreted_dates() {
+        let interpreted = GedcomDate::parse("INT 1 JAN 1900 (New Year's Day, 1900)").unwrap();
+        assert_eq!(interpreted.kind, DateKind::Interpreted);
+        assert_eq!(interpreted.phrase.as_deref(), Some("New Year's Day, 1900"));
+        assert_eq!(
+            (interpreted.earliest_jdn, interpreted.latest_jdn),
+            range("1 JAN 1900")
+        );
+
+        let phrase = GedcomDate::parse("(Stillborn)").unwrap();
+        assert_eq!(phrase.kind, DateKind::Phrase);
+        assert_eq!(phrase.phrase.as_deref(), Some("Stillborn"));
+        assert_eq!((phrase.earliest_jdn, phrase.latest_jdn), (None, None));
+    }
+
+    #[test]
+    fn parses_dual_dates_and_bc() {
+        let dual = GedcomDate::parse("11 FEB 1731/32").unwrap();
+        assert_eq!(dual.date.unwrap().dual_year, Some(1732));
+        assert_eq!(range("11 FEB 1731/32"), range("11 FEB 1732"));
+        assert_eq!(
+            GedcomDate::parse("1799/00")
+                .unwrap()
+                .date
+                .unwrap()
+                .dual_year,
+            Some(1800)
+        );
+
+        let bc = GedcomDate::parse("@#DJULIAN@ 15 MAR 44 B.C.").unwrap();
+        assert!(bc.date.unwrap().bc);
+        assert_eq!(bc.earliest_jdn, Some(1_705_426));
+        assert_eq!(range("44 BC").0, range("44 B.C.").0);
+    }
+
+    #[test]
+    fn converts_other_calendars() {
+        // The Julian date 4 OCT 1582 was followed by Gregorian 15 OCT 1582.
+        assert_eq!(
+            range("@#DJULIAN@ 4 OCT 1582").0.unwrap() + 1,
+            range("15 OCT 1582").0.unwrap()
+        );
+        // 1 Tishrei 5784 = 16 September 2023.
+        assert_eq!(range("@#DHEBREW@ 1 TSH 5784"), range("16 SEP 2023"));
+        // 15 Nisan 5783 = 6 April 2023.
+        assert_eq!(range("@#DHEBREW@ 15 NSN 5783"), range("6 APR 2023"));
+        // 18 Brumaire An VIII = 9 November 1799.
+        assert_eq!(range("@#DFRENCH R@ 18 BRUM 8"), range("9 NOV 1799"));
+        assert_eq!(range("@#DFRENCH R@ 1 VEND 1"), range("22 SEP 1792"));
+        // 1 Vendémiaire An CCXXXV = 22 September 2026.
+        assert_eq!(range("@#DFRENCH R@ 1 VEND 235"), range("22 SEP 2026"));
+        assert!(GedcomDate::parse("@#DFRENCH R@ 200000000").is_ok());
+    }
+
+    #[test]
+    fn counts_completed_years() {
+        let jdn = |input: &str| range(input).0.unwrap();
+        assert_eq!(completed_years(jdn("15 MAR 1900"), jdn("14 MAR 1972")), 71);
+        assert_eq!(completed_years(jdn("15 MAR 1900"), jdn("15 MAR 1972")), 72);
+        assert_eq!(completed_years(jdn("29 FEB 2000"), jdn("28 FEB 2001")), 0);
+        assert_eq!(jdn_to_gregorian(2_451_545), (2000, 1, 1));
+        assert_eq!(format_gregorian(2_451_545), "1 JAN 2000");
+        assert_eq!(format_gregorian(jdn("18 OCT 2026")), "18 OCT 2026");
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        for input in [
+            "",
+            "31 FEB 2000",
+            "1 FOO 2000",
+            "BET 1900",
+            "ABT",
+            "@#DROMAN@ 1 JAN 2000",
+            "BET 1910 AND 1900",
+            "1 ADS 5783",
+            "1900 (phrase)",
+            "2147483647/8",
+            "2000000000/999999999",
+            "-2147483648/7",
+        ] {
+            assert!(
+                GedcomDate::parse(input).is_err(),
+                "{input} should not parse"
+            );
+        }
+    }
+}
@@ -0,0 +1,649 @@
+//! Candidate duplicate individuals for `find_duplicates`.
+//!
+//! Only people sharing a blocking key are compared: the Soundex code of a
+//! surname with the first given initial, or the Soundex code of the first
+//! given name with the birth decade. Each candidate pair is then scored on
+//! names, dates, places and relatives.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::date::GedcomDate;
+use crate::gedcom::{Event, GedcomStore, Individual, Sex};
+use crate::listing::compare_ids;
+use crate::names::{searchable_names, split_name};
+use crate::pedigree::{other_spouse, parents};
+use crate::phonetic::soundex;
+use crate::text::{edit_distance, fold};
+
+/// Blocks larger than this are split further (see [`refinement`]), so a very
+/// common name does not make the comparison quadratic in the size of the tree.
+const MAX_BLOCK: usize = 100;
+/// Number of keys [`refinement`] can split a block by.
+const REFINEMENTS: usize = 5;
+/// Pairs whose names are less alike than this are not scored further.
+const MIN_NAME_SIMILARITY: f64 = 0.7;
+/// Relatives count as the same person from this name similarity on.
+const MIN_RELATIVE_SIMILARITY: f64 = 0.8;
+/// Years between dates from which they count against a match.
+const CONFLICTING_YEARS: f64 = 10.0;
+
+const NAME_WEIGHT: f64 = 0.4;
+const BIRTH_WEIGHT: f64 = 0.2;
+const BIRTH_PLACE_WEIGHT: f64 = 0.1;
+const DEATH_WEIGHT: f64 = 0.1;
+const DEATH_PLACE_WEIGHT: f64 = 0.05;
+const RELATIVES_WEIGHT: f64 = 0.15;
+const BIRTH_CONFLICT_PENALTY: f64 = 0.3;
+const DEATH_CONFLICT_PENALTY: f64 = 0.2;
+
+/// One piece of evidence for a pair, with its similarity between 0 and 1.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldMatch {
+    pub field: &'static str,
+    pub similarity: f64,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Duplicate {
+    pub a: String,
+    pub b: String,
+    pub score: f64,
+    pub matches: Vec<FieldMatch>,
+    /// Evidence against the pair, already subtracted from the score.
+    pub conflicts: Vec<String>,
+}
+
+/// What is compared of each individual, folded once up front.
+struct Profile<'a> {
+    individual: &'a Individual,
+    given: Vec<String>,
+    /// Birth and married surnames.
+    surnames: Vec<String>,
+    birth: Option<Dated<'a>>,
+    death: Option<Dated<'a>>,
+    /// Relatives as (relation, ID, folded name).
+    relatives: Vec<(&'static str, &'a str, String)>,
+}
+
+struct Dated<'a> {
+    raw: &'a str,
+    earliest: i64,
+    latest: i64,
+}
+
+fn dated(event: Option<&Event>) -> Option<Dated<'_>> {
+    let raw = event?.date.as_deref()?;
+    let date = GedcomDate::parse(raw).ok()?;
+    let (earliest, latest) = match (date.earliest_jdn, date.latest_jdn) {
+        (Some(earliest), Some(latest)) => (earliest, latest),
+        _ => return None,
+    };
+    Some(Dated {
+        raw,
+        earliest,
+        latest,
+    })
+}
+
+fn words(text: &str) -> Vec<String> {
+    fold(text).split_whitespace().map(str::to_owned).collect()
+}
+
+fn profile<'a>(store: &'a GedcomStore, individual: &'a Individual) -> Profile<'a> {
+    let (given, _) = individual
+        .name
+        .as_deref()
+        .map(split_name)
+        .unwrap_or_default();
+    let surnames = searchable_names(individual)
+        .iter()
+        .filter_map(|name| split_name(name).1.map(fold))
+        .filter(|surname| !surname.is_empty())
+        .collect();
+
+    let name_of = |id: &str| {
+        store
+            .get_individual(id)
+            .and_then(|relative| relative.name.as_deref())
+            .map(fold)
+            .unwrap_or_default()
+    };
+    let (father, mother) = parents(store, &individual.id);
+    let mut relatives: Vec<(&'static str, &str, String)> = [("father", father), ("mother", mother)]
+        .into_iter()
+        .filter_map(|(relation, id)| Some((relation, id?, name_of(id?))))
+        .collect();
+    for family in store.families_as_spouse(&individual.id) {
+        if let Some(spouse) = other_spouse(family, &individual.id) {
+            relatives.push(("spouse", spouse, name_of(spouse)));
+        }
+        for child in &family.children {
+            relatives.push(("child", child, name_of(child)));
+        }
+    }
+
+    Profile {
+        individual,
+        given: given.map(words).unwrap_or_default(),
+        surnames,
+        birth: dated(individual.birth.as_ref()),
+        death: dated(individual.death.as_ref()),
+        relatives,
+    }
+}
+
+fn decade(dated: &Dated) -> i64 {
+    let year = crate::date::gregorian_year(dated.earliest + (dated.latest - dated.earliest) / 2);
+    year.div_euclid(10)
+}
+
+fn birth_decade(profile: &Profile) -> Option<i64> {
+    profile.birth.as_ref().map(decade)
+}
+
+fn blocking_keys(profile: &Profile) -> Vec<String> {
+    let mut keys = Vec::new();
+    let initial = profile.given.first().and_then(|given| given.chars().next());
+    for surname in &profile.surnames {
+        if let Some(code) = soundex(surname) {
+            keys.push(format!("s:{code}:{}", initial.unwrap_or('-')));
+        }
+    }
+    if let (Some(given), Some(decade)) = (profile.given.first(), birth_decade(profile))
+        && let Some(code) = soundex(given)
+    {
+        keys.push(format!("g:{code}:{decade}"));
+    }
+    keys
+}
+
+/// Similarity of two folded words: edit distance relative to the longer
+/// one, raised to 0.8 when they sound alike.
+fn word_similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    let longest = a.chars().count().max(b.chars().count()).max(1);
+    let similarity = 1.0 - edit_distance(a, b) as f64 / longest as f64;
+    if soundex(a).is_some() && soundex(a) == soundex(b) {
+        similarity.max(0.8)
+    } else {
+        similarity
+    }
+}
+
+/// Average best similarity of the words of the shorter list, so `Johann`
+/// fully matches `Johann Jakob`.
+fn words_similarity(a: &[String], b: &[String]) -> Option<f64> {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if short.is_empty() {
+        return None;
+    }
+    let total: f64 = short
+        .iter()
+        .map(|word| {
+            long.iter()
+                .map(|other| word_similarity(word, other))
+                .fold(0.0, f64::max)
+        })
+        .sum();
+    Some(total / short.len() as f64)
+}
+
+fn name_similarity(a: &Profile, b: &Profile) -> Option<f64> {
+    let surname = a
+        .surnames
+        .iter()
+        .flat_map(|x| b.surnames.iter().map(move |y| word_similarity(x, y)))
+        .reduce(f64::max);
+    match (words_similarity(&a.given, &b.given), surname) {
+        (Some(given), Some(surname)) => Some((given + surname) / 2.0),
+        (given, surname) => given.or(surname),
+    }
+}
+
+/// Years between two dates, zero when they overlap.
+fn years_apart(a: &Dated, b: &Dated) -> f64 {
+    let gap = (b.earliest - a.latest).max(a.earliest - b.latest).max(0);
+    gap as f64 / 365.25
+}
+
+fn components(place: &str) -> Vec<String> {
+    place
+        .split(',')
+        .map(fold)
+        .filter(|component| !component.is_empty())
+        .collect()
+}
+
+/// 1 for the same place, 0.8 for the same town with more or less of its
+/// jurisdictions, 0.5 when one lies within the other.
+fn place_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (components(a), components(b));
+    let (short, long) = if a.len() <= b.len() {
+        (&a, &b)
+    } else {
+        (&b, &a)
+    };
+    if short.is_empty() {
+        0.0
+    } else if a == b {
+        1.0
+    } else if a[0] == b[0] {
+        0.8
+    } else if long
+        .windows(short.len())
+        .any(|window| window == short.as_slice())
+    {
+        0.5
+    } else {
+        0.0
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+/// Highest score `a` and `b` could reach from the fields both have.
+fn best_possible(a: &Profile, b: &Profile) -> f64 {
+    let place = |profile: &Profile, death: bool| {
+        let event = if death {
+            profile.individual.death.as_ref()
+        } else {
+            profile.individual.birth.as_ref()
+        };
+        event.is_some_and(|event| event.place.is_some())
+    };
+    [
+        (true, NAME_WEIGHT),
+        (a.birth.is_some() && b.birth.is_some(), BIRTH_WEIGHT),
+        (place(a, false) && place(b, false), BIRTH_PLACE_WEIGHT),
+        (a.death.is_some() && b.death.is_some(), DEATH_WEIGHT),
+        (place(a, true) && place(b, true), DEATH_PLACE_WEIGHT),
+        (
+            !a.relatives.is_empty() && !b.relatives.is_empty(),
+            RELATIVES_WEIGHT,
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(both, weight)| both.then_some(weight))
+    .sum()
+}
+
+fn score(a: &Profile, b: &Profile) -> Option<Duplicate> {
+    if let (Some(x @ (Sex::Male | Sex::Female)), Some(y @ (Sex::Male | Sex::Female))) =
+        (a.individual.sex, b.individual.sex)
+        && x != y
+    {
+        return None;
+    }
+    let name = name_similarity(a, b)?;
+    if name < MIN_NAME_SIMILARITY {
+        return None;
+    }
+
+    let display = |profile: &Profile| profile.individual.name.clone().unwrap_or_default();
+    let mut matches = vec![FieldMatch {
+        field: "name",
+        similarity: round(name),
+        detail: format!("{} ~ {}", display(a), display(b)),
+    }];
+    let mut conflicts = Vec::new();
+    let mut total = NAME_WEIGHT * name;
+
+    let events = [
+        (
+            "birth",
+            &a.birth,
+            &b.birth,
+            BIRTH_WEIGHT,
+            BIRTH_CONFLICT_PENALTY,
+        ),
+        (
+            "death",
+            &a.death,
+            &b.death,
+            DEATH_WEIGHT,
+            DEATH_CONFLICT_PENALTY,
+        ),
+    ];
+    for (field, x, y, weight, penalty) in events {
+        let (Some(x), Some(y)) = (x, y) else {
+            continue;
+        };
+        let years = years_apart(x, y);
+        if years > CONFLICTING_YEARS {
+            total -= penalty;
+            conflicts.push(format!(
+                "{field} dates {} and {} are at least {} years apart",
+                x.raw,
+                y.raw,
+                years.floor()
+            ));
+        } else {
+            let similarity = (1.0 - years / 5.0).max(0.0);
+            total += weight * similarity;
+            matches.push(FieldMatch {
+                field,
+                similarity: round(similarity),
+                detail: format!("{} ~ {}", x.raw, y.raw),
+            });
+        }
+    }
+
+    let place = |individual: &'_ Individual, death: bool| {
+        let event = if death {
+            individual.death.as_ref()
+        } else {
+            individual.birth.as_ref()
+        };
+        event.and_then(|event| event.place.clone())
+    };
+    for (field, death, weight) in [
+        ("birth_place", false, BIRTH_PLACE_WEIGHT),
+        ("death_place", true, DEATH_PLACE_WEIGHT),
+    ] {
+        if let (Some(x), Some(y)) = (place(a.individual, death), place(b.individual, death)) {
+            let similarity = place_similarity(&x, &y);
+            if similarity > 0.0 {
+                total += weight * similarity;
+                matches.push(FieldMatch {
+                    field,
+                    similarity,
+                    detail: format!("{x} ~ {y}"),
+                });
+            }
+        }
+    }
+
+    let mut shared = Vec::new();
+    let mut used = HashSet::new();
+    for (relation, id, name) in &a.relatives {
+        let found = b
+            .relatives
+            .iter()
+            .enumerate()
+            .find(|(idx, (other, other_id, other_name))| {
+                other == relation
+                    && !used.contains(idx)
+                    && (id == other_id
+                        || (!name.is_empty()
+                            && words_similarity(&words(name), &words(other_name))
+                                .is_some_and(|similarity| similarity >= MIN_RELATIVE_SIMILARITY)))
+            });
+        if let Some((idx, (_, other_id, _))) = found {
+            used.insert(idx);
+            shared.push(if id == other_id {
+                format!("{relation} {id}")
+            } else {
+                format!("{relation} {id} ~ {other_id}")
+            });
+        }
+    }
+    let fewest = a.relatives.len().min(b.relatives.len());
+    if !shared.is_empty() {
+        let similarity = shared.len() as f64 / fewest as f64;
+        total += RELATIVES_WEIGHT * similarity;
+        matches.push(FieldMatch {
+            field: "relatives",
+            similarity: round(similarity),
+            detail: shared.join(", "),
+        });
+    }
+
+    Some(Duplicate {
+        a: a.individual.id.clone(),
+        b: b.individual.id.clone(),
+        score: round(total.clamp(0.0, 1.0)),
+        matches,
+        conflicts,
+    })
+}
+
+/// The `level`th key an oversized block is split by: birth decade, first
+/// given name, first surname, birth town, then death decade. People missing
+/// the value share a part.
+fn refinement(profile: &Profile, level: usize) -> Option<String> {
+    match level {
+        0 => birth_decade(profile).map(|decade| decade.to_string()),
+        1 => profile.given.first().and_then(|given| soundex(given)),
+        2 => profile
+            .surnames
+            .first()
+            .and_then(|surname| soundex(surname)),
+        3 => profile
+            .individual
+            .birth
+            .as_ref()
+            .and_then(|birth| birth.place.as_deref())
+            .and_then(|place| components(place).into_iter().next()),
+        _ => profile
+            .death
+            .as_ref()
+            .map(|death| decade(death).to_string()),
+    }
+}
+
+/// Splits an oversized block by each refinement in turn. What is still too
+/// large after all of them is compared in consecutive runs of [`MAX_BLOCK`]
+/// people, in ID order.
+fn split_block(block: Vec<usize>, profiles: &[Profile], level: usize) -> Vec<Vec<usize>> {
+    if block.len() <= MAX_BLOCK {
+        return vec![block];
+    }
+    if level == REFINEMENTS {
+        return block.chunks(MAX_BLOCK).map(<[usize]>::to_vec).collect();
+    }
+    let mut parts: BTreeMap<Option<String>, Vec<usize>> = BTreeMap::new();
+    for idx in block {
+        parts
+            .entry(refinement(&profiles[idx], level))
+            .or_default()
+            .push(idx);
+    }
+    parts
+        .into_values()
+        .flat_map(|part| split_block(part, profiles, level + 1))
+        .collect()
+}
+
+/// Pairs scoring at least `min_score`, best first, then by IDs. With `only`,
+/// just the pairs involving that individual, from the blocks they are in.
+pub fn find_duplicates(store: &GedcomStore, min_score: f64, only: Option<&str>) -> Vec<Duplicate> {
+    let mut individuals: Vec<&Individual> = store.individuals().collect();
+    individuals.sort_by(|a, b| compare_ids(&a.id, &b.id));
+    let profiles: Vec<Profile> = individuals
+        .into_iter()
+        .map(|individual| profile(store, individual))
+        .collect();
+    let target = match only {
+        Some(id) => match profiles
+            .iter()
+            .position(|profile| profile.individual.id == id)
+        {
+            Some(target) => Some(target),
+            None => return Vec::new(),
+        },
+        None => None,
+    };
+    let wanted: Option<HashSet<String>> =
+        target.map(|target| blocking_keys(&profiles[target]).into_iter().collect());
+
+    let mut blocks: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (idx, profile) in profiles.iter().enumerate() {
+        for key in blocking_keys(profile) {
+            if wanted.as_ref().is_some_and(|wanted| !wanted.contains(&key)) {
+                continue;
+            }
+            let block = blocks.entry(key).or_default();
+            if block.last() != Some(&idx) {
+                block.push(idx);
+            }
+        }
+    }
+
+    let mut compared = HashSet::new();
+    let mut found = Vec::new();
+    let mut compare = |x: usize, y: usize| {
+        if compared.insert((x, y))
+            && best_possible(&profiles[x], &profiles[y]) >= min_score
+            && let Some(duplicate) = score(&profiles[x], &profiles[y])
+            && duplicate.score >= min_score
+        {
+            found.push(duplicate);
+        }
+    };
+    for block in blocks.into_values() {
+        for block in split_block(block, &profiles, 0) {
+            match target {
+                Some(target) if block.contains(&target) => {
+                    for &other in block.iter().filter(|&&other| other != target) {
+                        compare(target.min(other), target.max(other));
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    for (i, &x) in block.iter().enumerate() {
+                        for &y in &block[i + 1..] {
+                            compare(x, y);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    found.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| compare_ids(&a.a, &b.a))
+            .then_with(|| compare_ids(&a.b, &b.b))
+    });
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> GedcomStore {
+        GedcomStore::from_data(
+            crate::gedcom::parse_gedcom(
+                r#"0 @I1@ INDI
+1 NAME Johann /Müller/
+1 SEX M
+1 BIRT
+2 DATE 12 MAR 1801
+2 PLAC Bern, Switzerland
+0 @I2@ INDI
+1 NAME Johan /Mueller/
+1 SEX M
+1 BIRT
+2 DATE ABT 1801
+2 PLAC Bern
+0 @I3@ INDI
+1 NAME Johann /Müller/
+1 SEX M
+1 BIRT
+2 DATE 1850
+0 @I4@ INDI
+1 NAME Peter /Müller/
+0 @I5@ INDI
+1 NAME Peter /Muller/
+0 @I6@ INDI
+1 NAME Johanna /Müller/
+1 SEX F
+1 BIRT
+2 DATE 1801
+0 @F1@ FAM
+1 HUSB @I4@
+1 CHIL @I1@
+0 @F2@ FAM
+1 HUSB @I5@
+1 CHIL @I2@
+"#,
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn ranks_pairs_with_explanations() {
+        let store = store();
+        let found = find_duplicates(&store, 0.5, None);
+        let pairs: Vec<(&str, &str)> = found
+            .iter()
+            .map(|pair| (pair.a.as_str(), pair.b.as_str()))
+            .collect();
+        // I1/I3 are fifty years apart and Johanna is a woman.
+        assert_eq!(pairs, vec![("I1", "I2"), ("I4", "I5")]);
+
+        let best = &found[0];
+        let fields: Vec<&str> = best.matches.iter().map(|m| m.field).collect();
+        assert_eq!(fields, vec!["name", "birth", "birth_place", "relatives"]);
+        assert_eq!(best.matches[1].detail, "12 MAR 1801 ~ ABT 1801");
+        assert_eq!(best.matches[2].similarity, 0.8);
+        assert_eq!(best.matches[3].detail, "father I4 ~ I5");
+        assert_eq!(best.score, 0.768);
+
+        let with_conflicts = find_duplicates(&store, 0.0, Some("I3"));
+        assert_eq!(with_conflicts[0].b, "I3");
+        assert_eq!(
+            with_conflicts[0].conflicts,
+            vec!["birth dates 12 MAR 1801 and 1850 are at least 48 years apart"]
+        );
+    }
+
+    #[test]
+    fn compares_names_and_places() {
+        assert_eq!(word_similarity("muller", "muller"), 1.0);
+        assert!((word_similarity("muller", "mueller") - 6.0 / 7.0).abs() < 1e-9);
+        assert_eq!(word_similarity("smith", "smyth"), 0.8);
+        assert_eq!(
+            place_similarity("Bern, Switzerland", "bern,switzerland"),
+            1.0
+        );
+        assert_eq!(
+            place_similarity("Thun, Bern, Switzerland", "Bern, Switzerland"),
+            0.5
+        );
+        assert_eq!(place_similarity("Thun", "Bern"), 0.0);
+    }
+
+    #[test]
+    fn splits_common_names_into_bounded_blocks() {
+        use crate::gedcom::GedcomData;
+
+        // 300 undated John Smiths in three towns: too many for one block,
+        // but the town keeps every pair that could reach 0.5 together.
+        let individuals = (1..=300)
+            .map(|n| Individual {
+                id: format!("I{n}"),
+                name: Some("John /Smith/".into()),
+                birth: Some(Event {
+                    place: Some(format!("Town {}", n % 3)),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .collect();
+        let store = GedcomStore::from_data(GedcomData {
+            individuals,
+            ..Default::default()
+        });
+        let found = find_duplicates(&store, 0.5, None);
+        assert_eq!(found.len(), 3 * 100 * 99 / 2);
+        assert!(found.iter().all(|pair| pair.score == 0.5));
+
+        let only = find_duplicates(&store, 0.5, Some("I7"));
+        let expected: Vec<Duplicate> = found
+            .into_iter()
+            .filter(|pair| pair.a == "I7" || pair.b == "I7")
+            .collect();
+        assert_eq!(only.len(), 99);
+        assert_eq!(only, expected);
+        assert!(find_duplicates(&store, 0.5, Some("I999")).is_empty());
+    }
+}
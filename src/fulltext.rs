@@ -0,0 +1,859 @@
+//! Full-text index over names, places, occupations, notes and source
+//! citations, and the `search` query syntax: words (`ship*` for a prefix),
+//! `"quoted phrases"`, `AND` (implied between terms), `OR`, `NOT` / `-` and
+//! parentheses. Matching ignores case and diacritics.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::{Bound, Range};
+
+use crate::gedcom::{Citation, Event, Family, GedcomStore, Individual, NoteRecord, Notes, Source};
+use crate::listing::compare_ids;
+use crate::place::RecordRef;
+use crate::query::{MAX_CONDITIONS, MAX_NESTING, QueryError};
+use crate::text::fold;
+
+/// Words shown before the first match of a snippet, and in total.
+const SNIPPET_CONTEXT: usize = 6;
+const SNIPPET_WORDS: usize = 20;
+/// Snippets returned per record.
+const MAX_SNIPPETS: usize = 3;
+
+/// Where a piece of indexed text comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextField {
+    Name,
+    Occupation,
+    Place,
+    Note,
+    Citation,
+    Source,
+}
+
+impl TextField {
+    /// A match in a name says more about a record than one in a long note.
+    fn weight(self) -> f64 {
+        match self {
+            TextField::Name => 3.0,
+            TextField::Occupation => 2.0,
+            TextField::Place => 1.5,
+            TextField::Note | TextField::Citation | TextField::Source => 1.0,
+        }
+    }
+}
+
+/// An indexed document: an individual or family, or a shared note or source
+/// that matches on behalf of the records pointing at it.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Document {
+    Record(RecordRef),
+    Note(String),
+    Source(String),
+}
+
+type Texts<'a> = Vec<(TextField, &'a str)>;
+
+fn note_texts<'a>(notes: &'a Notes, texts: &mut Texts<'a>) {
+    texts.extend(
+        notes
+            .text
+            .iter()
+            .map(|text| (TextField::Note, text.as_str())),
+    );
+}
+
+fn citation_texts<'a>(citations: &'a [Citation], texts: &mut Texts<'a>) {
+    for citation in citations {
+        texts.extend(
+            citation
+                .page
+                .iter()
+                .map(|page| (TextField::Citation, page.as_str())),
+        );
+        texts.extend(
+            citation
+                .text
+                .iter()
+                .map(|text| (TextField::Citation, text.as_str())),
+        );
+    }
+}
+
+fn event_texts<'a>(tag: &str, event: &'a Event, texts: &mut Texts<'a>) {
+    texts.extend(
+        event
+            .place
+            .iter()
+            .map(|place| (TextField::Place, place.as_str())),
+    );
+    if tag == "OCCU" {
+        texts.extend(
+            event
+                .value
+                .iter()
+                .map(|value| (TextField::Occupation, value.as_str())),
+        );
+    }
+    note_texts(&event.notes, texts);
+    citation_texts(&event.citations, texts);
+}
+
+/// Text of an individual itself, without shared notes and sources.
+pub fn individual_texts(individual: &Individual) -> Texts<'_> {
+    let mut texts: Texts = individual
+        .name
+        .iter()
+        .chain(&individual.vendor.married_name)
+        .map(|name| (TextField::Name, name.as_str()))
+        .collect();
+    for (tag, event) in individual.tagged_events() {
+        event_texts(tag, event, &mut texts);
+    }
+    note_texts(&individual.notes, &mut texts);
+    citation_texts(&individual.citations, &mut texts);
+    texts
+}
+
+pub fn family_texts(family: &Family) -> Texts<'_> {
+    let mut texts = Vec::new();
+    for event in &family.events {
+        event_texts(event.tag.as_deref().unwrap_or("EVEN"), event, &mut texts);
+    }
+    note_texts(&family.notes, &mut texts);
+    citation_texts(&family.citations, &mut texts);
+    texts
+}
+
+pub fn note_record_texts(note: &NoteRecord) -> Texts<'_> {
+    vec![(TextField::Note, note.text.as_str())]
+}
+
+pub fn source_texts(source: &Source) -> Texts<'_> {
+    [
+        &source.title,
+        &source.author,
+        &source.publication,
+        &source.text,
+    ]
+    .into_iter()
+    .flatten()
+    .map(|text| (TextField::Source, text.as_str()))
+    .collect()
+}
+
+/// Shared note and source IDs a record points at, from the record itself and
+/// its events.
+pub fn pointers<'a>(
+    notes: impl IntoIterator<Item = &'a Notes>,
+    citations: impl IntoIterator<Item = &'a [Citation]>,
+) -> (BTreeSet<&'a str>, BTreeSet<&'a str>) {
+    let notes = notes
+        .into_iter()
+        .flat_map(|notes| notes.records.iter().map(String::as_str))
+        .collect();
+    let sources = citations
+        .into_iter()
+        .flatten()
+        .filter_map(|citation| citation.source.as_deref())
+        .collect();
+    (notes, sources)
+}
+
+pub fn individual_pointers(individual: &Individual) -> (BTreeSet<&str>, BTreeSet<&str>) {
+    let events: Vec<&Event> = individual.tagged_events().map(|(_, event)| event).collect();
+    pointers(
+        events
+            .iter()
+            .map(|event| &event.notes)
+            .chain([&individual.notes]),
+        events
+            .iter()
+            .map(|event| event.citations.as_slice())
+            .chain([individual.citations.as_slice()]),
+    )
+}
+
+pub fn family_pointers(family: &Family) -> (BTreeSet<&str>, BTreeSet<&str>) {
+    pointers(
+        family
+            .events
+            .iter()
+            .map(|event| &event.notes)
+            .chain([&family.notes]),
+        family
+            .events
+            .iter()
+            .map(|event| event.citations.as_slice())
+            .chain([family.citations.as_slice()]),
+    )
+}
+
+/// Folded words of `text` with their byte ranges in it.
+fn words(text: &str) -> Vec<(String, Range<usize>)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (idx, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        if c.is_alphanumeric() {
+            start.get_or_insert(idx);
+        } else if let Some(from) = start.take() {
+            words.push((fold(&text[from..idx]), from..idx));
+        }
+    }
+    words
+}
+
+/// Word -> documents containing it.
+#[derive(Debug, Clone, Default)]
+pub struct TextIndex {
+    postings: BTreeMap<String, BTreeSet<Document>>,
+}
+
+impl TextIndex {
+    pub fn add(&mut self, document: &Document, texts: &[(TextField, &str)]) {
+        for (_, text) in texts {
+            for (word, _) in words(text) {
+                self.postings
+                    .entry(word)
+                    .or_default()
+                    .insert(document.clone());
+            }
+        }
+    }
+
+    pub fn remove(&mut self, document: &Document, texts: &[(TextField, &str)]) {
+        for (_, text) in texts {
+            for (word, _) in words(text) {
+                if let Some(documents) = self.postings.get_mut(&word) {
+                    documents.remove(document);
+                    if documents.is_empty() {
+                        self.postings.remove(&word);
+                    }
+                }
+            }
+        }
+    }
+
+    fn documents(&self, word: &str, prefix: bool) -> BTreeSet<&Document> {
+        if !prefix {
+            return self.postings.get(word).into_iter().flatten().collect();
+        }
+        self.postings
+            .range::<str, _>((Bound::Included(word), Bound::Unbounded))
+            .take_while(|(token, _)| token.starts_with(word))
+            .flat_map(|(_, documents)| documents)
+            .collect()
+    }
+}
+
+/// A parsed `search` query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextQuery {
+    /// A word, or every word starting with it.
+    Word(String, bool),
+    Phrase(Vec<String>),
+    And(Box<TextQuery>, Box<TextQuery>),
+    Or(Box<TextQuery>, Box<TextQuery>),
+    Not(Box<TextQuery>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    Open,
+    Close,
+    Minus,
+    End,
+}
+
+fn lex(input: &str) -> Result<Vec<(Token, usize)>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let column = i + 1;
+        let token = match chars[i] {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '-' if chars.get(i + 1).is_some_and(|next| !next.is_whitespace()) => Token::Minus,
+            '"' => {
+                let Some(length) = chars[i + 1..].iter().position(|&c| c == '"') else {
+                    return Err(QueryError::new("unterminated phrase", column));
+                };
+                let phrase = chars[i + 1..i + 1 + length].iter().collect();
+                i += length + 1;
+                Token::Phrase(phrase)
+            }
+            _ => {
+                let start = i;
+                while i + 1 < chars.len()
+                    && !matches!(chars[i + 1], '(' | ')' | '"')
+                    && !chars[i + 1].is_whitespace()
+                {
+                    i += 1;
+                }
+                Token::Word(chars[start..=i].iter().collect())
+            }
+        };
+        i += 1;
+        tokens.push((token, column));
+    }
+    tokens.push((Token::End, chars.len() + 1));
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    depth: usize,
+    terms: usize,
+}
+
+/// Parses a `search` query; operators must be written in capitals.
+pub fn parse(input: &str) -> Result<TextQuery, QueryError> {
+    let mut parser = Parser {
+        tokens: lex(input)?,
+        position: 0,
+        depth: 0,
+        terms: 0,
+    };
+    if parser.peek() == &Token::End {
+        return Err(QueryError::new("empty query", 1));
+    }
+    let query = parser.or()?;
+    match parser.peek() {
+        Token::End => Ok(query),
+        Token::Close => Err(QueryError::new("unmatched `)`", parser.column())),
+        _ => Err(QueryError::new("expected end of query", parser.column())),
+    }
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn column(&self) -> usize {
+        self.tokens[self.position].1
+    }
+
+    fn operator(&mut self, operator: &str) -> bool {
+        if self.peek() == &Token::Word(operator.to_owned()) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Runs `parse` one level deeper, failing past [`MAX_NESTING`].
+    fn nested(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<TextQuery, QueryError>,
+    ) -> Result<TextQuery, QueryError> {
+        if self.depth == MAX_NESTING {
+            return Err(QueryError::new("query nested too deeply", self.column()));
+        }
+        self.depth += 1;
+        let query = parse(self);
+        self.depth -= 1;
+        query
+    }
+
+    /// Counts a word or phrase, failing past [`MAX_CONDITIONS`].
+    fn term(&mut self, column: usize) -> Result<(), QueryError> {
+        if self.terms == MAX_CONDITIONS {
+            return Err(QueryError::new("query has too many terms", column));
+        }
+        self.terms += 1;
+        Ok(())
+    }
+
+    fn or(&mut self) -> Result<TextQuery, QueryError> {
+        let mut query = self.and()?;
+        while self.operator("OR") {
+            query = TextQuery::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<TextQuery, QueryError> {
+        let mut query = self.unary()?;
+        loop {
+            if self.operator("AND") {
+                query = TextQuery::And(Box::new(query), Box::new(self.unary()?));
+                continue;
+            }
+            match self.peek() {
+                Token::End | Token::Close => return Ok(query),
+                Token::Word(word) if word == "OR" => return Ok(query),
+                _ => query = TextQuery::And(Box::new(query), Box::new(self.unary()?)),
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<TextQuery, QueryError> {
+        if self.operator("NOT") || self.peek() == &Token::Minus {
+            if self.peek() == &Token::Minus {
+                self.position += 1;
+            }
+            return Ok(TextQuery::Not(Box::new(self.nested(Self::unary)?)));
+        }
+        let column = self.column();
+        let (token, _) = self.tokens[self.position].clone();
+        match token {
+            Token::Open => {
+                self.position += 1;
+                let query = self.nested(Self::or)?;
+                if self.peek() != &Token::Close {
+                    return Err(QueryError::new("expected `)`", self.column()));
+                }
+                self.position += 1;
+                Ok(query)
+            }
+            Token::Word(word) if !matches!(word.as_str(), "AND" | "OR") => {
+                self.term(column)?;
+                self.position += 1;
+                let (word, prefix) = match word.strip_suffix('*') {
+                    Some(stem) => (stem, true),
+                    None => (word.as_str(), false),
+                };
+                let mut folded: Vec<String> = fold(word).split(' ').map(str::to_owned).collect();
+                match folded.len() {
+                    _ if folded[0].is_empty() => Err(QueryError::new(
+                        format!("nothing to search for in `{word}`"),
+                        column,
+                    )),
+                    1 => Ok(TextQuery::Word(folded.remove(0), prefix)),
+                    _ => Ok(TextQuery::Phrase(folded)),
+                }
+            }
+            Token::Phrase(phrase) => {
+                self.term(column)?;
+                self.position += 1;
+                let folded = fold(&phrase);
+                if folded.is_empty() {
+                    return Err(QueryError::new("empty phrase", column));
+                }
+                Ok(TextQuery::Phrase(
+                    folded.split(' ').map(str::to_owned).collect(),
+                ))
+            }
+            Token::End => Err(QueryError::new(
+                "expected a word or phrase, found end of query",
+                column,
+            )),
+            Token::Close => Err(QueryError::new(
+                "expected a word or phrase, found `)`",
+                column,
+            )),
+            Token::Word(operator) => Err(QueryError::new(
+                format!("expected a word or phrase, found `{operator}`"),
+                column,
+            )),
+            Token::Minus => unreachable!("handled above"),
+        }
+    }
+}
+
+/// A matching text with its matches in `**bold**`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snippet {
+    pub field: TextField,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub record: RecordRef,
+    pub score: f64,
+    pub snippets: Vec<Snippet>,
+}
+
+/// Every text a record matches on: its own and that of the notes and sources
+/// it points at.
+fn record_texts<'a>(store: &'a GedcomStore, record: &RecordRef) -> Texts<'a> {
+    let (mut texts, (notes, sources)) = match record {
+        RecordRef::Individual(id) => match store.get_individual(id) {
+            Some(individual) => (
+                individual_texts(individual),
+                individual_pointers(individual),
+            ),
+            None => return Vec::new(),
+        },
+        RecordRef::Family(id) => match store.get_family(id) {
+            Some(family) => (family_texts(family), family_pointers(family)),
+            None => return Vec::new(),
+        },
+    };
+    for note in notes.into_iter().filter_map(|id| store.get_note(id)) {
+        texts.extend(note_record_texts(note));
+    }
+    for source in sources.into_iter().filter_map(|id| store.get_source(id)) {
+        texts.extend(source_texts(source));
+    }
+    texts
+}
+
+/// Word ranges of `words` matched by `query`.
+fn matches(query: &TextQuery, words: &[(String, Range<usize>)]) -> Vec<Range<usize>> {
+    match query {
+        TextQuery::Word(word, prefix) => words
+            .iter()
+            .enumerate()
+            .filter(|(_, (token, _))| {
+                if *prefix {
+                    token.starts_with(word.as_str())
+                } else {
+                    token == word
+                }
+            })
+            .map(|(idx, _)| idx..idx + 1)
+            .collect(),
+        TextQuery::Phrase(phrase) => words
+            .windows(phrase.len())
+            .enumerate()
+            .filter(|(_, window)| {
+                window
+                    .iter()
+                    .zip(phrase)
+                    .all(|((token, _), word)| token == word)
+            })
+            .map(|(idx, _)| idx..idx + phrase.len())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Words and phrases a record must contain, i.e. those not under `NOT`.
+fn positive_terms<'q>(query: &'q TextQuery, terms: &mut Vec<&'q TextQuery>) {
+    match query {
+        TextQuery::Word(..) | TextQuery::Phrase(_) => terms.push(query),
+        TextQuery::And(a, b) | TextQuery::Or(a, b) => {
+            positive_terms(a, terms);
+            positive_terms(b, terms);
+        }
+        TextQuery::Not(_) => {}
+    }
+}
+
+struct Search<'a> {
+    store: &'a GedcomStore,
+}
+
+impl<'a> Search<'a> {
+    fn referencing(&self, documents: BTreeSet<&Document>) -> BTreeSet<RecordRef> {
+        documents
+            .into_iter()
+            .flat_map(|document| self.store.records_referencing(document))
+            .collect()
+    }
+
+    fn everything(&self) -> BTreeSet<RecordRef> {
+        let individuals = self
+            .store
+            .individuals()
+            .map(|individual| RecordRef::Individual(individual.id.clone()));
+        let families = self
+            .store
+            .families()
+            .map(|family| RecordRef::Family(family.id.clone()));
+        individuals.chain(families).collect()
+    }
+
+    fn records(&self, query: &TextQuery) -> BTreeSet<RecordRef> {
+        let index = self.store.text_index();
+        match query {
+            TextQuery::Word(word, prefix) => self.referencing(index.documents(word, *prefix)),
+            TextQuery::Phrase(phrase) => {
+                let mut candidates: Option<BTreeSet<RecordRef>> = None;
+                for word in phrase {
+                    let found = self.referencing(index.documents(word, false));
+                    candidates = Some(match candidates {
+                        None => found,
+                        Some(candidates) => candidates.intersection(&found).cloned().collect(),
+                    });
+                }
+                candidates
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|record| {
+                        record_texts(self.store, record)
+                            .iter()
+                            .any(|(_, text)| !matches(query, &words(text)).is_empty())
+                    })
+                    .collect()
+            }
+            TextQuery::And(a, b) => match b.as_ref() {
+                TextQuery::Not(excluded) => {
+                    let excluded = self.records(excluded);
+                    self.records(a).difference(&excluded).cloned().collect()
+                }
+                _ => {
+                    let left = self.records(a);
+                    if left.is_empty() {
+                        return left;
+                    }
+                    left.intersection(&self.records(b)).cloned().collect()
+                }
+            },
+            TextQuery::Or(a, b) => {
+                let mut records = self.records(a);
+                records.extend(self.records(b));
+                records
+            }
+            TextQuery::Not(excluded) => {
+                let excluded = self.records(excluded);
+                self.everything().difference(&excluded).cloned().collect()
+            }
+        }
+    }
+
+    /// Scores a matching record by the best field each term appears in, and
+    /// cuts snippets around the matches.
+    fn hit(&self, record: RecordRef, terms: &[&TextQuery]) -> Hit {
+        let texts = record_texts(self.store, &record);
+        let mut best = vec![0.0f64; terms.len()];
+        let mut matched = Vec::new();
+        for (field, text) in texts {
+            let words = words(text);
+            let mut ranges = Vec::new();
+            for (term, best) in terms.iter().zip(&mut best) {
+                let found = matches(term, &words);
+                if !found.is_empty() {
+                    *best = best.max(field.weight());
+                    ranges.extend(found);
+                }
+            }
+            if !ranges.is_empty() {
+                matched.push((field, snippet(text, &words, &ranges)));
+            }
+        }
+        matched.sort_by(|(a, _), (b, _)| b.weight().total_cmp(&a.weight()));
+        matched.dedup();
+        let score: f64 = best.iter().sum();
+        Hit {
+            record,
+            score: (score * 100.0).round() / 100.0,
+            snippets: matched
+                .into_iter()
+                .take(MAX_SNIPPETS)
+                .map(|(field, text)| Snippet { field, text })
+                .collect(),
+        }
+    }
+}
+
+/// Text around the first match, with matched words in `**bold**` and line
+/// breaks flattened.
+fn snippet(text: &str, words: &[(String, Range<usize>)], ranges: &[Range<usize>]) -> String {
+    let hit = |idx: usize| ranges.iter().any(|range| range.contains(&idx));
+    let first = ranges.iter().map(|range| range.start).min().unwrap_or(0);
+    let from = first.saturating_sub(SNIPPET_CONTEXT);
+    let to = (from + SNIPPET_WORDS).min(words.len());
+
+    let mut out = String::new();
+    if from > 0 {
+        out.push('…');
+    }
+    let mut cursor = if from == 0 { 0 } else { words[from].1.start };
+    for (idx, (_, range)) in words.iter().enumerate().take(to).skip(from) {
+        let bold = hit(idx);
+        let open = bold && (idx == from || !hit(idx - 1));
+        let close = bold && (idx + 1 == to || !hit(idx + 1));
+        out.push_str(&text[cursor..range.start]);
+        if open {
+            out.push_str("**");
+        }
+        out.push_str(&text[range.clone()]);
+        if close {
+            out.push_str("**");
+        }
+        cursor = range.end;
+    }
+    if to == words.len() {
+        out.push_str(&text[cursor..]);
+    } else {
+        out.push('…');
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Records matching `query`, best first; ties list individuals before
+/// families, then by ID.
+pub fn search(store: &GedcomStore, query: &TextQuery) -> Vec<Hit> {
+    let search = Search { store };
+    let mut terms = Vec::new();
+    positive_terms(query, &mut terms);
+    let mut hits: Vec<Hit> = search
+        .records(query)
+        .into_iter()
+        .map(|record| search.hit(record, &terms))
+        .collect();
+    let kind = |record: &RecordRef| match record {
+        RecordRef::Individual(id) => (0, id.clone()),
+        RecordRef::Family(id) => (1, id.clone()),
+    };
+    hits.sort_by(|a, b| {
+        let (a_kind, a_id) = kind(&a.record);
+        let (b_kind, b_id) = kind(&b.record);
+        b.score
+            .total_cmp(&a.score)
+            .then(a_kind.cmp(&b_kind))
+            .then_with(|| compare_ids(&a_id, &b_id))
+    });
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> GedcomStore {
+        GedcomStore::from_data(
+            crate::gedcom::parse_gedcom(
+                r#"0 @I1@ INDI
+1 NAME Seán /Ó Briain/
+1 OCCU Fisherman
+2 PLAC Kinsale, Cork, Ireland
+1 NOTE Survived the shipwreck of the Elizabeth off the Old Head
+2 CONT of Kinsale in 1821.
+0 @I2@ INDI
+1 NAME Mary /Walsh/
+1 BIRT
+2 PLAC Cork, Ireland
+2 SOUR @S1@
+3 PAGE Entry 42, baptism of Mary
+1 NOTE @N1@
+0 @I3@ INDI
+1 NAME Thomas /Ship/
+0 @F1@ FAM
+1 HUSB @I1@
+1 WIFE @I2@
+1 MARR
+2 NOTE Married after the wreck.
+0 @N1@ NOTE Emigrated to Boston aboard a famine ship
+0 @S1@ SOUR
+1 TITL Kinsale parish registers
+"#,
+            )
+            .unwrap(),
+        )
+    }
+
+    fn ids(hits: &[Hit]) -> Vec<String> {
+        hits.iter()
+            .map(|hit| match &hit.record {
+                RecordRef::Individual(id) | RecordRef::Family(id) => id.clone(),
+            })
+            .collect()
+    }
+
+    fn run(store: &GedcomStore, query: &str) -> Vec<Hit> {
+        search(store, &parse(query).unwrap())
+    }
+
+    #[test]
+    fn parses_operators_phrases_and_prefixes() {
+        assert_eq!(
+            parse(r#"ship* -"famine ship" OR (cork AND NOT boston)"#).unwrap(),
+            TextQuery::Or(
+                Box::new(TextQuery::And(
+                    Box::new(TextQuery::Word("ship".into(), true)),
+                    Box::new(TextQuery::Not(Box::new(TextQuery::Phrase(vec![
+                        "famine".into(),
+                        "ship".into()
+                    ])))),
+                )),
+                Box::new(TextQuery::And(
+                    Box::new(TextQuery::Word("cork".into(), false)),
+                    Box::new(TextQuery::Not(Box::new(TextQuery::Word(
+                        "boston".into(),
+                        false
+                    )))),
+                )),
+            )
+        );
+        assert_eq!(
+            parse("O'Brien").unwrap(),
+            TextQuery::Phrase(vec!["o".into(), "brien".into()])
+        );
+        assert_eq!(
+            parse("(cork").unwrap_err(),
+            QueryError::new("expected `)`", 6)
+        );
+        assert_eq!(parse("cork)").unwrap_err().column, 5);
+        assert_eq!(parse(r#"cork "ship"#).unwrap_err().column, 6);
+        assert_eq!(parse("cork OR").unwrap_err().column, 8);
+        assert_eq!(parse("  ").unwrap_err().message, "empty query");
+    }
+
+    #[test]
+    fn rejects_deeply_nested_queries() {
+        let nested = |depth: usize| format!("{}cork{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(parse(&nested(MAX_NESTING)).is_ok());
+        assert_eq!(
+            parse(&nested(50_000)).unwrap_err(),
+            QueryError::new("query nested too deeply", MAX_NESTING + 2)
+        );
+        assert_eq!(
+            parse(&"-".repeat(50_000)).unwrap_err().message,
+            "query nested too deeply"
+        );
+        assert!(parse(&"cork ".repeat(MAX_CONDITIONS)).is_ok());
+        assert_eq!(
+            parse(&"cork ".repeat(50_000)).unwrap_err(),
+            QueryError::new("query has too many terms", 5 * MAX_CONDITIONS + 1)
+        );
+    }
+
+    #[test]
+    fn finds_records_through_notes_sources_and_places() {
+        let store = store();
+        let hits = run(&store, "shipwreck");
+        assert_eq!(ids(&hits), vec!["I1"]);
+        assert_eq!(
+            hits[0].snippets,
+            vec![Snippet {
+                field: TextField::Note,
+                text: "Survived the **shipwreck** of the Elizabeth off the Old Head of Kinsale in 1821."
+                    .into(),
+            }]
+        );
+
+        // Shared notes and cited sources match for the records using them.
+        assert_eq!(ids(&run(&store, "boston")), vec!["I2"]);
+        assert_eq!(
+            ids(&run(&store, "\"parish registers\" baptism")),
+            vec!["I2"]
+        );
+
+        // Diacritics, prefixes and boolean operators.
+        assert_eq!(ids(&run(&store, "sean briain")), vec!["I1"]);
+        assert_eq!(ids(&run(&store, "ship*")), vec!["I3", "I1", "I2"]);
+        assert_eq!(ids(&run(&store, "ship* -shipwreck")), vec!["I3", "I2"]);
+        assert_eq!(
+            ids(&run(&store, "kinsale OR wreck")),
+            vec!["I1", "I2", "F1"]
+        );
+        assert_eq!(ids(&run(&store, "NOT cork")), vec!["I3", "F1"]);
+        assert!(run(&store, "\"ship of\"").is_empty());
+    }
+
+    #[test]
+    fn follows_mutations() {
+        let mut store = store();
+        let mut individual = store.get_individual("I3").unwrap().clone();
+        individual.notes.text = vec!["Lighthouse keeper".into()];
+        individual.name = Some("Thomas /Keeper/".into());
+        store.add_record(crate::gedcom::GedcomRecord::Individual(individual));
+
+        assert_eq!(ids(&run(&store, "lighthouse")), vec!["I3"]);
+        assert!(run(&store, "thomas ship").is_empty());
+    }
+}
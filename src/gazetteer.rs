@@ -0,0 +1,469 @@
+use crate::place::Coordinates;
+use crate::text::{edit_distance, fold, trigrams};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use thiserror::Error;
+
+/// One feature of a GeoNames-style gazetteer dump.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GazetteerEntry {
+    pub id: u64,
+    pub name: String,
+    pub coordinates: Coordinates,
+    pub feature_code: String,
+    pub country_code: String,
+    pub admin1_code: String,
+    pub admin2_code: String,
+    pub population: u64,
+    /// ASCII and alternate spellings.
+    pub alternate_names: Vec<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum GazetteerError {
+    #[error("failed to read gazetteer: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid gazetteer record at line {line}: {message}")]
+    InvalidRecord { line: usize, message: String },
+}
+
+/// A proposed canonical form for a place string.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlaceMatch {
+    pub geonameid: u64,
+    pub canonical: String,
+    pub coordinates: Coordinates,
+    /// Between 0 and 1; lowered by fuzzy name matches, qualifiers that do
+    /// not fit the match, and competing candidates.
+    pub confidence: f64,
+}
+
+/// In-memory gazetteer loaded from a tab-separated GeoNames dump
+/// (`geonameid`, `name`, `asciiname`, `alternatenames`, `latitude`,
+/// `longitude`, feature class and code, country and admin codes,
+/// `population`, ...). Region and country names are taken from the `ADM1`,
+/// `ADM2` and `PCL*` features of the same file, so a country extract such as
+/// `US.txt` is self-contained.
+#[derive(Debug, Default)]
+pub struct Gazetteer {
+    entries: Vec<GazetteerEntry>,
+    by_name: HashMap<String, Vec<usize>>,
+    /// Distinct folded names, indexed by their trigrams for fuzzy lookups.
+    names: Vec<String>,
+    by_trigram: HashMap<[char; 3], Vec<usize>>,
+    admin1: HashMap<(String, String), usize>,
+    admin2: HashMap<(String, String, String), usize>,
+    countries: HashMap<String, usize>,
+}
+
+const MIN_COLUMNS: usize = 15;
+
+impl Gazetteer {
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, GazetteerError> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, GazetteerError> {
+        let mut gazetteer = Self::default();
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let entry = parse_entry(&line, idx + 1)?;
+            gazetteer.add(entry);
+        }
+        Ok(gazetteer)
+    }
+
+    fn add(&mut self, entry: GazetteerEntry) {
+        let idx = self.entries.len();
+        let country = entry.country_code.clone();
+        match entry.feature_code.as_str() {
+            "ADM1" => {
+                self.admin1
+                    .insert((country, entry.admin1_code.clone()), idx);
+            }
+            "ADM2" => {
+                self.admin2.insert(
+                    (
+                        country,
+                        entry.admin1_code.clone(),
+                        entry.admin2_code.clone(),
+                    ),
+                    idx,
+                );
+            }
+            "PCLI" => {
+                self.countries.insert(country, idx);
+            }
+            code if code.starts_with("PCL") => {
+                self.countries.entry(country).or_insert(idx);
+            }
+            _ => {}
+        }
+
+        let names: HashSet<String> = std::iter::once(&entry.name)
+            .chain(&entry.alternate_names)
+            .map(|name| fold(name))
+            .filter(|name| !name.is_empty())
+            .collect();
+        for name in names {
+            if !self.by_name.contains_key(&name) {
+                for trigram in trigrams(&name) {
+                    self.by_trigram
+                        .entry(trigram)
+                        .or_default()
+                        .push(self.names.len());
+                }
+                self.names.push(name.clone());
+            }
+            self.by_name.entry(name).or_default().push(idx);
+        }
+        self.entries.push(entry);
+    }
+
+    /// Enclosing second-level division, first-level division and country.
+    fn enclosing(&self, entry: &GazetteerEntry) -> Vec<&GazetteerEntry> {
+        let country = entry.country_code.clone();
+        let admin2 = self.admin2.get(&(
+            country.clone(),
+            entry.admin1_code.clone(),
+            entry.admin2_code.clone(),
+        ));
+        let admin1 = self
+            .admin1
+            .get(&(country.clone(), entry.admin1_code.clone()));
+        let country = self.countries.get(&country);
+        [admin2, admin1, country]
+            .into_iter()
+            .flatten()
+            .map(|&idx| &self.entries[idx])
+            .filter(|enclosing| enclosing.id != entry.id)
+            .collect()
+    }
+
+    /// Full name of an entry, smallest jurisdiction first.
+    fn canonical_name(&self, entry: &GazetteerEntry) -> String {
+        let mut names = vec![entry.name.as_str()];
+        for enclosing in self.enclosing(entry) {
+            if names.last() != Some(&enclosing.name.as_str()) {
+                names.push(&enclosing.name);
+            }
+        }
+        names.join(", ")
+    }
+
+    /// Folded names and codes a qualifier may use to refer to the divisions
+    /// enclosing `entry`.
+    fn qualifier_names(&self, entry: &GazetteerEntry) -> HashSet<String> {
+        let mut names: HashSet<String> = [&entry.country_code, &entry.admin1_code]
+            .into_iter()
+            .map(|code| fold(code))
+            .collect();
+        for enclosing in self.enclosing(entry) {
+            names.extend(
+                std::iter::once(&enclosing.name)
+                    .chain(&enclosing.alternate_names)
+                    .map(|name| fold(name)),
+            );
+        }
+        names.remove("");
+        names
+    }
+
+    /// Entries whose name matches `locality`, with a name score in (0, 1].
+    /// Exact matches on any spelling win; otherwise names sharing the
+    /// initial letter within a small edit distance are considered. Those are
+    /// found through trigrams: `n` edits leave all but at most `3n` of the
+    /// distinct trigrams of `locality` in place, so a single letter only
+    /// matches exactly.
+    fn candidates(&self, locality: &str) -> HashMap<usize, f64> {
+        let mut scores = HashMap::new();
+        if let Some(indices) = self.by_name.get(locality) {
+            for &idx in indices {
+                let score = if fold(&self.entries[idx].name) == locality {
+                    1.0
+                } else {
+                    0.95
+                };
+                let best = scores.entry(idx).or_insert(score);
+                *best = f64::max(*best, score);
+            }
+            return scores;
+        }
+
+        let Some(initial) = locality.chars().next() else {
+            return scores;
+        };
+        let length = locality.chars().count();
+        let max_distance = (length / 4).max(1);
+        let grams = trigrams(locality);
+        let Some(needed) = grams.len().checked_sub(3 * max_distance).filter(|&n| n > 0) else {
+            return scores;
+        };
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        for gram in &grams {
+            for &name in self.by_trigram.get(gram).into_iter().flatten() {
+                *shared.entry(name).or_default() += 1;
+            }
+        }
+        for (name, count) in shared {
+            let name = &self.names[name];
+            let other = name.chars().count();
+            if count < needed || !name.starts_with(initial) || other.abs_diff(length) > max_distance
+            {
+                continue;
+            }
+            let distance = edit_distance(locality, name);
+            if distance > max_distance {
+                continue;
+            }
+            let score = 1.0 - distance as f64 / length.max(other) as f64;
+            for &idx in &self.by_name[name] {
+                let best = scores.entry(idx).or_insert(score);
+                *best = f64::max(*best, score);
+            }
+        }
+        scores
+    }
+
+    /// Best gazetteer match for a GEDCOM place string, if any. The first
+    /// jurisdiction is matched by name; the remaining ones are qualifiers
+    /// checked against the match's enclosing divisions.
+    pub fn lookup(&self, place: &str) -> Option<PlaceMatch> {
+        let components: Vec<String> = place
+            .split(',')
+            .map(fold)
+            .filter(|component| !component.is_empty())
+            .collect();
+        let (locality, qualifiers) = components.split_first()?;
+
+        let mut scored: Vec<(usize, f64)> = self
+            .candidates(locality)
+            .into_iter()
+            .map(|(idx, name_score)| {
+                let known = self.qualifier_names(&self.entries[idx]);
+                let matched = qualifiers.iter().filter(|q| known.contains(*q)).count();
+                let score = name_score * (1 + matched) as f64 / (1 + qualifiers.len()) as f64;
+                (idx, score)
+            })
+            .collect();
+        scored.sort_by(|(a_idx, a), (b_idx, b)| {
+            let (a_entry, b_entry) = (&self.entries[*a_idx], &self.entries[*b_idx]);
+            b.total_cmp(a)
+                .then(b_entry.population.cmp(&a_entry.population))
+                .then(a_entry.id.cmp(&b_entry.id))
+        });
+
+        let (&(best_idx, best), others) = scored.split_first()?;
+        // Competing candidates close to the best score reduce confidence; an
+        // equally good alternative halves it.
+        let competition: f64 = others.iter().map(|(_, s)| (s / best).powi(4)).sum();
+        let confidence = best / (1.0 + competition);
+
+        let entry = &self.entries[best_idx];
+        Some(PlaceMatch {
+            geonameid: entry.id,
+            canonical: self.canonical_name(entry),
+            coordinates: entry.coordinates,
+            confidence: (confidence * 100.0).round() / 100.0,
+        })
+    }
+}
+
+fn parse_entry(line: &str, number: usize) -> Result<GazetteerEntry, GazetteerError> {
+    let invalid = |message: &str| GazetteerError::InvalidRecord {
+        line: number,
+        message: message.to_owned(),
+    };
+    let columns: Vec<&str> = line.split('\t').collect();
+    if columns.len() < MIN_COLUMNS {
+        return Err(invalid(&format!(
+            "expected at least {MIN_COLUMNS} tab-separated columns, found {}",
+            columns.len()
+        )));
+    }
+
+    let id = columns[0]
+        .trim()
+        .parse()
+        .map_err(|_| invalid("invalid geonameid"))?;
+    let coordinates = Coordinates::parse(columns[4], columns[5])
+        .ok_or_else(|| invalid("invalid latitude or longitude"))?;
+    let population = match columns[14].trim() {
+        "" => 0,
+        value => value.parse().map_err(|_| invalid("invalid population"))?,
+    };
+    let name = columns[1].trim().to_owned();
+    let alternate_names = std::iter::once(columns[2])
+        .chain(columns[3].split(','))
+        .map(str::trim)
+        .filter(|alternate| !alternate.is_empty() && *alternate != name)
+        .map(str::to_owned)
+        .collect();
+
+    Ok(GazetteerEntry {
+        id,
+        name,
+        coordinates,
+        feature_code: columns[7].trim().to_owned(),
+        country_code: columns[8].trim().to_owned(),
+        admin1_code: columns[10].trim().to_owned(),
+        admin2_code: columns[11].trim().to_owned(),
+        population,
+        alternate_names,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(
+        id: u64,
+        name: &str,
+        alternates: &str,
+        (lat, long): (f64, f64),
+        (class, code): (&str, &str),
+        (country, admin1, admin2): (&str, &str, &str),
+        population: u64,
+    ) -> String {
+        format!(
+            "{id}\t{name}\t{name}\t{alternates}\t{lat}\t{long}\t{class}\t{code}\t{country}\t\t{admin1}\t{admin2}\t\t\t{population}\t\t\t\t2024-01-01"
+        )
+    }
+
+    fn sample_gazetteer() -> String {
+        [
+            row(
+                6252001,
+                "United States",
+                "USA,US,America",
+                (39.76, -98.5),
+                ("A", "PCLI"),
+                ("US", "00", ""),
+                327167434,
+            ),
+            row(
+                4896861,
+                "Illinois",
+                "IL",
+                (40.0, -89.0),
+                ("A", "ADM1"),
+                ("US", "IL", ""),
+                12830632,
+            ),
+            row(
+                4398678,
+                "Missouri",
+                "MO",
+                (38.25, -92.5),
+                ("A", "ADM1"),
+                ("US", "MO", ""),
+                5988927,
+            ),
+            row(
+                4249993,
+                "Sangamon County",
+                "Sangamon",
+                (39.76, -89.66),
+                ("A", "ADM2"),
+                ("US", "IL", "167"),
+                197465,
+            ),
+            row(
+                4396915,
+                "Greene County",
+                "Greene",
+                (37.26, -93.34),
+                ("A", "ADM2"),
+                ("US", "MO", "077"),
+                275174,
+            ),
+            row(
+                4250542,
+                "Springfield",
+                "",
+                (39.80172, -89.64371),
+                ("P", "PPLA"),
+                ("US", "IL", "167"),
+                116250,
+            ),
+            row(
+                4409896,
+                "Springfield",
+                "",
+                (37.21533, -93.29824),
+                ("P", "PPLA2"),
+                ("US", "MO", "077"),
+                166810,
+            ),
+            row(
+                4887398,
+                "Chicago",
+                "Chicagou",
+                (41.85003, -87.65005),
+                ("P", "PPL"),
+                ("US", "IL", "031"),
+                2720546,
+            ),
+        ]
+        .join("\n")
+    }
+
+    #[test]
+    fn loads_geonames_rows_and_resolves_hierarchy() {
+        let gazetteer = Gazetteer::from_reader(sample_gazetteer().as_bytes()).unwrap();
+        assert_eq!(gazetteer.entries.len(), 8);
+
+        let chicago = gazetteer.lookup("chicago").unwrap();
+        assert_eq!(chicago.canonical, "Chicago, Illinois, United States");
+        assert_eq!(chicago.confidence, 1.0);
+        assert_eq!(chicago.coordinates.latitude, 41.85003);
+
+        let illinois = gazetteer.lookup("Illinois, USA").unwrap();
+        assert_eq!(illinois.canonical, "Illinois, United States");
+        assert_eq!(illinois.confidence, 1.0);
+    }
+
+    #[test]
+    fn scores_qualifiers_fuzzy_names_and_ambiguity() {
+        let gazetteer = Gazetteer::from_reader(sample_gazetteer().as_bytes()).unwrap();
+
+        let full = gazetteer
+            .lookup("Springfield, Sangamon, Illinois, USA")
+            .unwrap();
+        assert_eq!(
+            full.canonical,
+            "Springfield, Sangamon County, Illinois, United States"
+        );
+        assert!(full.confidence > 0.9, "{full:?}");
+
+        let abbreviated = gazetteer.lookup("Springfield, IL").unwrap();
+        assert_eq!(abbreviated.canonical, full.canonical);
+        assert!(abbreviated.confidence > 0.9, "{abbreviated:?}");
+
+        // Without qualifiers both Springfields fit equally well; the larger
+        // one is proposed with halved confidence.
+        let ambiguous = gazetteer.lookup("Springfield").unwrap();
+        assert_eq!(ambiguous.geonameid, 4409896);
+        assert_eq!(ambiguous.confidence, 0.5);
+
+        let misspelt = gazetteer.lookup("Springfld, Illinois").unwrap();
+        assert_eq!(misspelt.canonical, full.canonical);
+        assert!(misspelt.confidence > 0.7 && misspelt.confidence < 0.9);
+
+        assert_eq!(gazetteer.lookup("Atlantis"), None);
+        assert_eq!(gazetteer.lookup(" , "), None);
+    }
+
+    #[test]
+    fn rejects_malformed_rows() {
+        let err = Gazetteer::from_reader("# comment\n1\tShort\n".as_bytes()).unwrap_err();
+        assert!(matches!(err, GazetteerError::InvalidRecord { line: 2, .. }));
+    }
+}
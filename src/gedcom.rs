@@ -1,41 +1,554 @@
+use crate::fulltext::{
+    Document, TextIndex, family_pointers, family_texts, individual_pointers, individual_texts,
+    note_record_texts, source_texts,
+};
+use crate::names::{NameIndex, searchable_names};
+use crate::place::{Coordinates, PlaceIndex, RecordRef, place_key};
+use crate::text::fold;
 use serde::{Deserialize, Serialize};
-use serde_json;
-use std::io::ErrorKind;
+use std::io::{BufRead, BufReader};
 use std::num::ParseIntError;
-use std::{collections::HashMap, fs, path::Path};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::Path,
+};
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Event {
     pub date: Option<String>,
     pub place: Option<String>,
+    /// GEDCOM tag of the event (e.g. `BAPM`, `MARR`, `OCCU`); implied for
+    /// `birth` and `death`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Line value such as the occupation of an `OCCU` or the `Y` of `DEAT Y`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Age of the principal at the event as recorded by an `AGE` tag.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age: Option<String>,
+    /// Coordinates from the `MAP` structure of the event's `PLAC`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coordinates: Option<Coordinates>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<Address>,
+    #[serde(default, skip_serializing_if = "Notes::is_empty")]
+    pub notes: Notes,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub citations: Vec<Citation>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// `NOTE` lines of a record or event: inline text, and pointers to shared
+/// `NOTE` records.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notes {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub text: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub records: Vec<String>,
+}
+
+impl Notes {
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.records.is_empty()
+    }
+
+    fn read(&mut self, node: &Node) {
+        match pointer(&node.value) {
+            Some(id) => self.records.push(id),
+            None => {
+                let text = continued_lines(node).join("\n");
+                if !text.is_empty() {
+                    self.text.push(text);
+                }
+            }
+        }
+    }
+}
+
+/// A `SOUR` citation: a pointer to a source record with the `PAGE` cited and
+/// any `DATA.TEXT` transcription, or the description of an inline source.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Citation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub text: Vec<String>,
+}
+
+impl Citation {
+    fn from_node(node: &Node) -> Self {
+        let mut citation = Citation {
+            source: pointer(&node.value),
+            ..Default::default()
+        };
+        if citation.source.is_none() {
+            citation.text.extend(joined_text(node));
+        }
+        for child in &node.children {
+            match child.tag.as_str() {
+                "PAGE" if citation.page.is_none() => {
+                    citation.page = Some(child.value.clone()).filter(|page| !page.is_empty());
+                }
+                "TEXT" => citation.text.extend(joined_text(child)),
+                "DATA" => citation.text.extend(
+                    child
+                        .children
+                        .iter()
+                        .filter(|data| data.tag == "TEXT")
+                        .filter_map(joined_text),
+                ),
+                _ => {}
+            }
+        }
+        citation
+    }
+}
+
+/// Address structure: `ADDR` with its lines and parts, plus the sibling
+/// `PHON`, `EMAIL`, `FAX` and `WWW` contact lines.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Address {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub postal_code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub phones: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub emails: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub faxes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub websites: Vec<String>,
+}
+
+/// An `ASSO` link to another individual, such as a godparent or witness.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Association {
+    /// ID of the associated individual.
+    pub individual: String,
+    /// `RELA` or `ROLE` (or its `PHRASE`), e.g. `Godfather` or `WITN`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relation: Option<String>,
+    /// Tag of the event the association is attached to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+}
+
+/// A `CHAN` or `CREA` timestamp.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeStamp {
+    pub date: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+}
+
+impl ChangeStamp {
+    /// The current UTC date and time.
+    pub fn now() -> Self {
+        let (jdn, seconds) = crate::date::now_utc();
+        Self {
+            date: crate::date::format_gregorian(jdn),
+            time: Some(format!(
+                "{:02}:{:02}:{:02}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            )),
+        }
+    }
+}
+
+/// An identifier assigned by a user or an external system (`REFN` or `EXID`
+/// with its `TYPE`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalId {
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Restriction {
+    Confidential,
+    Locked,
+    Privacy,
+}
+
+/// `SEX` of an individual: `M`, `F`, `X` (intersex) or `U` (unknown).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sex {
+    Male,
+    Female,
+    Intersex,
+    Unknown,
+}
+
+impl Sex {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "M" => Some(Self::Male),
+            "F" => Some(Self::Female),
+            "X" => Some(Self::Intersex),
+            "U" => Some(Self::Unknown),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Male => "M",
+            Self::Female => "F",
+            Self::Intersex => "X",
+            Self::Unknown => "U",
+        }
+    }
+}
+
+/// Recorded sex, or the role as husband or wife when none is recorded.
+pub fn sex_of(store: &GedcomStore, id: &str) -> Option<Sex> {
+    if let Some(sex @ (Sex::Male | Sex::Female)) = store
+        .get_individual(id)
+        .and_then(|individual| individual.sex)
+    {
+        return Some(sex);
+    }
+    store.families_as_spouse(id).find_map(|family| {
+        if family.husband.as_deref() == Some(id) {
+            Some(Sex::Male)
+        } else if family.wife.as_deref() == Some(id) {
+            Some(Sex::Female)
+        } else {
+            None
+        }
+    })
+}
+
+/// Bookkeeping shared by records: change stamps, identifiers and `RESN`
+/// restriction notices.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changed: Option<ChangeStamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<ChangeStamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rin: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub refns: Vec<ExternalId>,
+    /// `UID` values; a vendor `_UID` is kept under `vendor.uid`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub uids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exids: Vec<ExternalId>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub restrictions: Vec<Restriction>,
+}
+
+impl RecordMetadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether `RESN` marks the record locked against changes.
+    pub fn is_locked(&self) -> bool {
+        self.restrictions.contains(&Restriction::Locked)
+    }
+
+    /// Whether `RESN` marks the record confidential or private.
+    pub fn is_restricted(&self) -> bool {
+        self.restrictions
+            .iter()
+            .any(|restriction| *restriction != Restriction::Locked)
+    }
+
+    /// Handles a metadata tag on a record, returning `false` for other tags.
+    fn read(&mut self, node: &Node) -> bool {
+        let value = || node.value.trim().to_owned();
+        match node.tag.as_str() {
+            "CHAN" => self.changed = change_stamp(node),
+            "CREA" => self.created = change_stamp(node),
+            "RIN" => self.rin = Some(value()).filter(|rin| !rin.is_empty()),
+            "REFN" => self.refns.extend(external_id(node)),
+            "EXID" => self.exids.extend(external_id(node)),
+            "UID" if !node.value.trim().is_empty() => self.uids.push(value()),
+            "RESN" => {
+                for restriction in node.value.split(',') {
+                    let restriction = match restriction.trim().to_ascii_lowercase().as_str() {
+                        "confidential" => Restriction::Confidential,
+                        "locked" => Restriction::Locked,
+                        "privacy" => Restriction::Privacy,
+                        _ => continue,
+                    };
+                    if !self.restrictions.contains(&restriction) {
+                        self.restrictions.push(restriction);
+                    }
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Address {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Where a record came from in the source GEDCOM file.
+///
+/// Lines are 1-based and inclusive; byte offsets are 0-based and the end
+/// offset is exclusive, so `byte_end - byte_start` is the record length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceSpan {
+    pub line_start: usize,
+    pub line_end: usize,
+    pub byte_start: usize,
+    pub byte_end: usize,
+}
+
+/// A vendor-specific (underscore) tag together with all of its
+/// substructures, preserved verbatim.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Extension {
+    pub tag: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub value: String,
+    /// Tag of the standard structure the extension was attached to, or `None`
+    /// when it sits directly on the record.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Extension>,
+}
+
+/// Typed values recognised from common vendor extensions written by
+/// Ancestry, RootsMagic, Legacy and FamilySearch.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VendorFields {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub married_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub familysearch_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub living: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+impl VendorFields {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn from_extensions(extensions: &[Extension]) -> Self {
+        let mut fields = Self::default();
+        for extension in extensions {
+            let value = || Some(extension.value.clone()).filter(|value| !value.is_empty());
+            match extension.tag.as_str() {
+                "_UID" if fields.uid.is_none() => fields.uid = value(),
+                "_MARNM" if fields.married_name.is_none() => fields.married_name = value(),
+                "_FSFTID" | "_FID" if fields.familysearch_id.is_none() => {
+                    fields.familysearch_id = value()
+                }
+                "_LIVING" if fields.living.is_none() => {
+                    fields.living = match extension.value.to_ascii_uppercase().as_str() {
+                        "Y" | "YES" | "TRUE" | "1" => Some(true),
+                        "N" | "NO" | "FALSE" | "0" => Some(false),
+                        _ => None,
+                    }
+                }
+                "_COLOR" | "_COLR" if fields.color.is_none() => fields.color = value(),
+                _ => {}
+            }
+        }
+        fields
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Individual {
     pub id: String,
     pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sex: Option<Sex>,
     pub birth: Option<Event>,
     pub death: Option<Event>,
+    /// Events and attributes other than birth and death, in file order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<Event>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub associations: Vec<Association>,
+    /// IDs of records describing the same person (`ALIA`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(default, skip_serializing_if = "Notes::is_empty")]
+    pub notes: Notes,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub citations: Vec<Citation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_location: Option<SourceSpan>,
+    #[serde(default, skip_serializing_if = "RecordMetadata::is_empty")]
+    pub metadata: RecordMetadata,
+    #[serde(default, skip_serializing_if = "VendorFields::is_empty")]
+    pub vendor: VendorFields,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<Extension>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Family {
     pub id: String,
     pub husband: Option<String>,
     pub wife: Option<String>,
     pub children: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<Event>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub associations: Vec<Association>,
+    #[serde(default, skip_serializing_if = "Notes::is_empty")]
+    pub notes: Notes,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub citations: Vec<Citation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_location: Option<SourceSpan>,
+    #[serde(default, skip_serializing_if = "RecordMetadata::is_empty")]
+    pub metadata: RecordMetadata,
+    #[serde(default, skip_serializing_if = "VendorFields::is_empty")]
+    pub vendor: VendorFields,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<Extension>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Repository {
+    pub id: String,
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_location: Option<SourceSpan>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Submitter {
+    pub id: String,
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_location: Option<SourceSpan>,
+}
+
+/// A shared `NOTE` record.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteRecord {
+    pub id: String,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_location: Option<SourceSpan>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// A `SOUR` record: the source cited by `SOUR` pointers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Source {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publication: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_location: Option<SourceSpan>,
+}
+
+/// File-level information from the `HEAD` record.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Header {
+    /// Jurisdiction levels from `HEAD.PLAC.FORM`, smallest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub place_form: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct GedcomData {
     pub individuals: Vec<Individual>,
     pub families: Vec<Family>,
+    #[serde(default)]
+    pub header: Header,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repositories: Vec<Repository>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub submitters: Vec<Submitter>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<NoteRecord>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<Source>,
 }
 
-#[derive(Debug, Clone)]
+impl GedcomData {
+    /// Writes a JSON snapshot for `load_store` through a temporary file, so
+    /// a crash cannot leave a truncated snapshot behind.
+    pub fn save_to_path(&self, path: impl AsRef<Path>) -> Result<(), std::io::Error> {
+        let path = path.as_ref();
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut file = fs::File::create(&tmp_path)?;
+            serde_json::to_writer_pretty(&mut file, self).map_err(std::io::Error::other)?;
+            file.sync_all()?;
+        }
+        fs::rename(tmp_path, path)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct GedcomStore {
+    header: Header,
     individuals: HashMap<String, Individual>,
     families: HashMap<String, Family>,
+    repositories: HashMap<String, Repository>,
+    submitters: HashMap<String, Submitter>,
+    notes: HashMap<String, NoteRecord>,
+    sources: HashMap<String, Source>,
+    by_familysearch_id: HashMap<String, Vec<String>>,
+    by_uid: HashMap<String, BTreeSet<RecordRef>>,
+    by_refn: HashMap<String, BTreeSet<RecordRef>>,
+    /// Individual and family IDs folded like `query` compares text.
+    by_folded_id: HashMap<String, BTreeSet<RecordRef>>,
+    places: PlaceIndex,
+    names: NameIndex,
+    text: TextIndex,
+    /// Note record ID to the records pointing at it.
+    noted_by: HashMap<String, BTreeSet<RecordRef>>,
+    /// Source ID to the records citing it.
+    cited_by: HashMap<String, BTreeSet<RecordRef>>,
+    /// Individual ID to the records with an `ASSO` pointing at it.
+    associated_by: HashMap<String, BTreeSet<RecordRef>>,
+    /// Individual ID to the individuals listing it as an `ALIA`.
+    aliased_by: HashMap<String, BTreeSet<String>>,
+    /// Individual ID to the families listing it as `CHIL`.
+    child_in: HashMap<String, BTreeSet<String>>,
+    /// Individual ID to the families listing it as `HUSB` or `WIFE`.
+    spouse_in: HashMap<String, BTreeSet<String>>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -48,18 +561,32 @@ pub enum StoreError {
     Persist(#[from] std::io::Error),
 }
 
-#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+#[derive(Debug, Clone, thiserror::Error, PartialEq, Eq)]
 pub enum ParseError {
-    #[error("invalid line level at line {line}: {source}")]
-    InvalidLevel { line: usize, source: ParseIntError },
-    #[error("missing tag at line {line}")]
-    MissingTag { line: usize },
-    #[error("individual at line {line} is missing an ID")]
-    MissingIndividualId { line: usize },
-    #[error("family at line {line} is missing an ID")]
-    MissingFamilyId { line: usize },
-    #[error("orphaned tag {tag} at line {line}")]
-    OrphanTag { line: usize, tag: String },
+    #[error("invalid line level at line {line} (byte {offset}): {source}")]
+    InvalidLevel {
+        line: usize,
+        offset: usize,
+        source: ParseIntError,
+    },
+    #[error("missing tag at line {line} (byte {offset})")]
+    MissingTag { line: usize, offset: usize },
+    #[error("individual at line {line} (byte {offset}) is missing an ID")]
+    MissingIndividualId { line: usize, offset: usize },
+    #[error("family at line {line} (byte {offset}) is missing an ID")]
+    MissingFamilyId { line: usize, offset: usize },
+    #[error("{tag} record at line {line} (byte {offset}) is missing an ID")]
+    MissingRecordId {
+        line: usize,
+        offset: usize,
+        tag: String,
+    },
+    #[error("orphaned tag {tag} at line {line} (byte {offset})")]
+    OrphanTag {
+        line: usize,
+        offset: usize,
+        tag: String,
+    },
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -72,249 +599,1181 @@ pub enum LoadError {
     Deserialize(#[from] serde_json::Error),
 }
 
-pub fn parse_gedcom(input: &str) -> Result<GedcomData, ParseError> {
-    let mut individuals = Vec::new();
-    let mut families = Vec::new();
+/// A top-level GEDCOM record produced by [`GedcomReader`].
+// Records are yielded one at a time and moved straight into the store, so
+// boxing the record variants would only add allocations.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum GedcomRecord {
+    Header(Header),
+    Individual(Individual),
+    Family(Family),
+    Repository(Repository),
+    Submitter(Submitter),
+    Note(NoteRecord),
+    Source(Source),
+}
 
-    // Track current context for level-1 tags.
-    enum IndividualContext {
-        Birth,
-        Death,
+/// A single GEDCOM line split into its components.
+#[derive(Debug)]
+struct Line {
+    number: usize,
+    offset: usize,
+    level: u32,
+    xref: Option<String>,
+    tag: String,
+    value: String,
+}
+
+/// A line together with its subordinate lines, i.e. one GEDCOM structure.
+#[derive(Debug)]
+struct Node {
+    line: usize,
+    offset: usize,
+    xref: Option<String>,
+    tag: String,
+    value: String,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn from_line(line: Line) -> Self {
+        Self {
+            line: line.number,
+            offset: line.offset,
+            xref: line.xref,
+            tag: line.tag,
+            value: line.value,
+            children: Vec::new(),
+        }
     }
 
-    enum Context {
-        Individual {
-            idx: usize,
-            sub: Option<IndividualContext>,
-        },
-        Family(usize),
-        None,
+    fn into_extension(self, parent: Option<&str>) -> Extension {
+        Extension {
+            tag: self.tag,
+            value: self.value,
+            parent: parent.map(str::to_owned),
+            children: self
+                .children
+                .into_iter()
+                .map(|child| child.into_extension(None))
+                .collect(),
+        }
     }
-    let mut context = Context::None;
 
-    for (idx, raw_line) in input.lines().enumerate() {
-        let line_no = idx + 1;
-        let line = raw_line.trim();
-        if line.is_empty() {
-            continue;
+    fn orphan(&self) -> ParseError {
+        ParseError::OrphanTag {
+            line: self.line,
+            offset: self.offset,
+            tag: self.tag.clone(),
         }
+    }
+}
 
-        let mut parts = line.splitn(3, ' ');
-        let level_str = parts.next().unwrap_or_default();
-        let level: u32 = level_str
-            .parse()
-            .map_err(|source| ParseError::InvalidLevel {
-                line: line_no,
-                source,
-            })?;
-
-        let second = parts
-            .next()
-            .ok_or(ParseError::MissingTag { line: line_no })?;
-
-        // GEDCOM allows an optional ID token between level and tag.
-        let (xref, tag, value) = if second.starts_with('@') && second.ends_with('@') {
-            let tag = parts
-                .next()
-                .ok_or(ParseError::MissingTag { line: line_no })?;
-            let value = parts.next().unwrap_or("").trim().to_string();
-            (Some(second.trim_matches('@').to_string()), tag, value)
-        } else {
-            let tag = second;
-            let value = parts.next().unwrap_or("").trim().to_string();
-            (None, tag, value)
+/// Streaming GEDCOM parser that yields one top-level record at a time.
+///
+/// Only the lines of the record currently being assembled are held in memory,
+/// so arbitrarily large files can be processed with bounded memory.
+pub struct GedcomReader<R> {
+    reader: R,
+    buf: String,
+    line_no: usize,
+    offset: usize,
+    pending: Option<Line>,
+    done: bool,
+}
+
+impl<R: BufRead> GedcomReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: String::new(),
+            line_no: 0,
+            offset: 0,
+            pending: None,
+            done: false,
+        }
+    }
+
+    fn next_line(&mut self) -> Result<Option<Line>, LoadError> {
+        loop {
+            self.buf.clear();
+            let len = self.reader.read_line(&mut self.buf)?;
+            if len == 0 {
+                return Ok(None);
+            }
+            self.line_no += 1;
+            let offset = self.offset;
+            self.offset += len;
+            let raw = if self.line_no == 1 {
+                self.buf.trim_start_matches('\u{feff}')
+            } else {
+                &self.buf
+            };
+            let line = raw.trim();
+            if line.is_empty() {
+                continue;
+            }
+            return Ok(Some(split_line(line, self.line_no, offset)?));
+        }
+    }
+
+    fn next_node(&mut self) -> Result<Option<(Node, SourceSpan)>, LoadError> {
+        let first = match self.pending.take() {
+            Some(line) => line,
+            None => match self.next_line()? {
+                Some(line) => line,
+                None => return Ok(None),
+            },
         };
+        if first.level != 0 {
+            return Err(ParseError::OrphanTag {
+                line: first.number,
+                offset: first.offset,
+                tag: first.tag,
+            }
+            .into());
+        }
 
-        match (level, tag) {
-            (0, "INDI") => {
-                let id = xref.ok_or(ParseError::MissingIndividualId { line: line_no })?;
-                individuals.push(Individual {
-                    id,
-                    name: None,
-                    birth: None,
-                    death: None,
-                });
-                context = Context::Individual {
-                    idx: individuals.len() - 1,
-                    sub: None,
-                };
+        let mut span = SourceSpan {
+            line_start: first.number,
+            line_end: first.number,
+            byte_start: first.offset,
+            byte_end: self.offset,
+        };
+        // `stack[i]` is the open structure at level `i`.
+        let mut stack = vec![Node::from_line(first)];
+        while let Some(line) = self.next_line()? {
+            if line.level == 0 {
+                self.pending = Some(line);
+                break;
             }
-            (0, "FAM") => {
-                let id = xref.ok_or(ParseError::MissingFamilyId { line: line_no })?;
-                families.push(Family {
-                    id,
-                    husband: None,
-                    wife: None,
-                    children: Vec::new(),
-                });
-                context = Context::Family(families.len() - 1);
-            }
-            (1, "NAME") => {
-                if let Context::Individual { idx, .. } = context {
-                    individuals[idx].name = Some(value);
-                    context = Context::Individual { idx, sub: None };
-                } else {
-                    return Err(ParseError::OrphanTag {
-                        line: line_no,
-                        tag: tag.to_string(),
-                    });
+            let level = line.level as usize;
+            if level > stack.len() {
+                return Err(ParseError::OrphanTag {
+                    line: line.number,
+                    offset: line.offset,
+                    tag: line.tag,
                 }
+                .into());
             }
-            (1, "BIRT") => {
-                if let Context::Individual { idx, .. } = context {
-                    context = Context::Individual {
-                        idx,
-                        sub: Some(IndividualContext::Birth),
-                    };
-                } else {
-                    return Err(ParseError::OrphanTag {
-                        line: line_no,
-                        tag: tag.to_string(),
-                    });
+            span.line_end = line.number;
+            span.byte_end = self.offset;
+            while stack.len() > level {
+                close_node(&mut stack);
+            }
+            stack.push(Node::from_line(line));
+        }
+        while stack.len() > 1 {
+            close_node(&mut stack);
+        }
+        Ok(stack.pop().map(|node| (node, span)))
+    }
+}
+
+impl<R: BufRead> Iterator for GedcomReader<R> {
+    type Item = Result<GedcomRecord, LoadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let result = self.next_node().and_then(|node| match node {
+                Some((node, span)) => Ok(Some(record_from_node(node, span)?)),
+                None => {
+                    self.done = true;
+                    Ok(None)
+                }
+            });
+            match result {
+                Ok(Some(Some(record))) => return Some(Ok(record)),
+                Ok(Some(None)) => continue,
+                Ok(None) => return None,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
                 }
             }
-            (1, "DEAT") => {
-                if let Context::Individual { idx, .. } = context {
-                    context = Context::Individual {
-                        idx,
-                        sub: Some(IndividualContext::Death),
-                    };
-                } else {
-                    return Err(ParseError::OrphanTag {
-                        line: line_no,
-                        tag: tag.to_string(),
-                    });
+        }
+        None
+    }
+}
+
+fn close_node(stack: &mut Vec<Node>) {
+    if let Some(node) = stack.pop()
+        && let Some(parent) = stack.last_mut()
+    {
+        parent.children.push(node);
+    }
+}
+
+fn split_line(line: &str, line_no: usize, offset: usize) -> Result<Line, ParseError> {
+    let missing_tag = ParseError::MissingTag {
+        line: line_no,
+        offset,
+    };
+    let mut parts = line.splitn(3, ' ');
+    let level_str = parts.next().unwrap_or_default();
+    let level: u32 = level_str
+        .parse()
+        .map_err(|source| ParseError::InvalidLevel {
+            line: line_no,
+            offset,
+            source,
+        })?;
+
+    let second = parts.next().ok_or_else(|| missing_tag.clone())?;
+
+    // GEDCOM allows an optional ID token between level and tag.
+    let (xref, tag, value) = if second.starts_with('@') && second.ends_with('@') {
+        // `0 @N1@ NOTE text` carries a value after the tag too.
+        let mut rest = parts.next().ok_or(missing_tag)?.splitn(2, ' ');
+        let tag = rest.next().unwrap_or_default();
+        let value = rest.next().unwrap_or("").trim().to_string();
+        (Some(second.trim_matches('@').to_string()), tag, value)
+    } else {
+        let tag = second;
+        let value = parts.next().unwrap_or("").trim().to_string();
+        (None, tag, value)
+    };
+
+    Ok(Line {
+        number: line_no,
+        offset,
+        level,
+        xref,
+        tag: tag.to_string(),
+        value,
+    })
+}
+
+/// Converts a level-0 structure into a record, returning `None` for record
+/// types that are not modelled yet.
+fn record_from_node(node: Node, span: SourceSpan) -> Result<Option<GedcomRecord>, ParseError> {
+    match node.tag.as_str() {
+        "HEAD" => Ok(Some(GedcomRecord::Header(header_from_node(&node)))),
+        "INDI" => Ok(Some(GedcomRecord::Individual(individual_from_node(
+            node, span,
+        )?))),
+        "FAM" => Ok(Some(GedcomRecord::Family(family_from_node(node, span)?))),
+        "REPO" => {
+            let (id, name, address) = contact_record_from_node(&node)?;
+            Ok(Some(GedcomRecord::Repository(Repository {
+                id,
+                name,
+                address,
+                source_location: Some(span),
+            })))
+        }
+        "SUBM" => {
+            let (id, name, address) = contact_record_from_node(&node)?;
+            Ok(Some(GedcomRecord::Submitter(Submitter {
+                id,
+                name,
+                address,
+                source_location: Some(span),
+            })))
+        }
+        "NOTE" => Ok(node.xref.clone().map(|id| {
+            GedcomRecord::Note(NoteRecord {
+                id,
+                text: continued_lines(&node).join("\n"),
+                source_location: Some(span),
+            })
+        })),
+        "SOUR" => Ok(node.xref.clone().map(|id| {
+            let text = |tag: &str| {
+                node.children
+                    .iter()
+                    .find(|child| child.tag == tag)
+                    .and_then(joined_text)
+            };
+            GedcomRecord::Source(Source {
+                id,
+                title: text("TITL"),
+                author: text("AUTH"),
+                publication: text("PUBL"),
+                text: text("TEXT"),
+                source_location: Some(span),
+            })
+        })),
+        _ => Ok(None),
+    }
+}
+
+fn header_from_node(node: &Node) -> Header {
+    let place_form = node
+        .children
+        .iter()
+        .filter(|child| child.tag == "PLAC")
+        .flat_map(|plac| &plac.children)
+        .find(|child| child.tag == "FORM")
+        .map(|form| {
+            form.value
+                .split(',')
+                .map(|level| level.trim().to_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    Header { place_form }
+}
+
+/// ID, `NAME` and address of a repository or submitter record.
+fn contact_record_from_node(
+    node: &Node,
+) -> Result<(String, Option<String>, Option<Address>), ParseError> {
+    let id = node
+        .xref
+        .clone()
+        .ok_or_else(|| ParseError::MissingRecordId {
+            line: node.line,
+            offset: node.offset,
+            tag: node.tag.clone(),
+        })?;
+    let name = node
+        .children
+        .iter()
+        .find(|child| child.tag == "NAME")
+        .map(|child| child.value.clone());
+    Ok((id, name, address_from_node(node)))
+}
+
+/// Reads the address structure among the children of `node`.
+fn address_from_node(node: &Node) -> Option<Address> {
+    let mut address = Address::default();
+    let mut numbered_lines = Vec::new();
+    for child in &node.children {
+        let value = || Some(child.value.clone()).filter(|value| !value.is_empty());
+        match child.tag.as_str() {
+            "ADDR" if address.lines.is_empty() => {
+                address.lines = continued_lines(child);
+                for part in &child.children {
+                    let value = Some(part.value.clone()).filter(|value| !value.is_empty());
+                    match part.tag.as_str() {
+                        "ADR1" | "ADR2" | "ADR3" => numbered_lines.extend(value),
+                        "CITY" => address.city = value,
+                        "STAE" => address.state = value,
+                        "POST" => address.postal_code = value,
+                        "CTRY" => address.country = value,
+                        _ => {}
+                    }
                 }
             }
-            (1, "HUSB") => {
-                if let Context::Family(idx) = context {
-                    families[idx].husband = Some(value.trim_matches('@').to_string());
-                    context = Context::Family(idx);
-                } else {
-                    return Err(ParseError::OrphanTag {
-                        line: line_no,
-                        tag: tag.to_string(),
-                    });
+            "PHON" => address.phones.extend(value()),
+            "EMAIL" | "EMAI" => address.emails.extend(value()),
+            "FAX" => address.faxes.extend(value()),
+            "WWW" => address.websites.extend(value()),
+            _ => {}
+        }
+    }
+    if address.lines.is_empty() {
+        address.lines = numbered_lines;
+    }
+    Some(address).filter(|address| !address.is_empty())
+}
+
+/// Joins a value with its `CONT` (new line) and `CONC` (same line)
+/// continuations, dropping empty lines.
+fn continued_lines(node: &Node) -> Vec<String> {
+    let mut lines = vec![node.value.clone()];
+    for child in &node.children {
+        match child.tag.as_str() {
+            "CONT" => lines.push(child.value.clone()),
+            "CONC" => {
+                if let Some(last) = lines.last_mut() {
+                    last.push_str(&child.value);
                 }
             }
-            (1, "WIFE") => {
-                if let Context::Family(idx) = context {
-                    families[idx].wife = Some(value.trim_matches('@').to_string());
-                    context = Context::Family(idx);
-                } else {
-                    return Err(ParseError::OrphanTag {
-                        line: line_no,
-                        tag: tag.to_string(),
-                    });
+            _ => {}
+        }
+    }
+    lines.retain(|line| !line.trim().is_empty());
+    lines
+}
+
+/// A value with its continuations as one text, or `None` if it is empty.
+fn joined_text(node: &Node) -> Option<String> {
+    Some(continued_lines(node).join("\n")).filter(|text| !text.is_empty())
+}
+
+fn individual_from_node(node: Node, span: SourceSpan) -> Result<Individual, ParseError> {
+    let id = node.xref.ok_or(ParseError::MissingIndividualId {
+        line: node.line,
+        offset: node.offset,
+    })?;
+    let mut individual = Individual {
+        id,
+        source_location: Some(span),
+        ..Default::default()
+    };
+
+    for child in node.children {
+        if child.tag.starts_with('_') {
+            individual.extensions.push(child.into_extension(None));
+            continue;
+        }
+        collect_extensions(&child, &mut individual.extensions);
+        if individual.metadata.read(&child) {
+            continue;
+        }
+        match child.tag.as_str() {
+            "NAME" => {
+                check_no_event_details(&child)?;
+                individual.name.get_or_insert(child.value);
+            }
+            "SEX" => {
+                check_no_event_details(&child)?;
+                individual.sex = individual.sex.or(Sex::parse(&child.value));
+            }
+            "BIRT" => {
+                individual.associations.extend(event_associations(&child));
+                if individual.birth.is_none() {
+                    individual.birth = event_from_node(&child);
                 }
             }
-            (1, "CHIL") => {
-                if let Context::Family(idx) = context {
-                    families[idx]
-                        .children
-                        .push(value.trim_matches('@').to_string());
-                    context = Context::Family(idx);
-                } else {
-                    return Err(ParseError::OrphanTag {
-                        line: line_no,
-                        tag: tag.to_string(),
+            "DEAT" => {
+                individual.associations.extend(event_associations(&child));
+                if individual.death.is_none() {
+                    individual.death = event_from_node(&child);
+                }
+            }
+            "HUSB" | "WIFE" | "CHIL" => return Err(child.orphan()),
+            "ASSO" => individual
+                .associations
+                .extend(association_from_node(&child, None)),
+            "ALIA" => individual.aliases.extend(pointer(&child.value)),
+            "NOTE" => individual.notes.read(&child),
+            "SOUR" => individual.citations.push(Citation::from_node(&child)),
+            tag if is_event_tag(tag) => {
+                individual.associations.extend(event_associations(&child));
+                individual.events.extend(tagged_event(&child));
+            }
+            _ => check_no_event_details(&child)?,
+        }
+    }
+    individual.vendor = VendorFields::from_extensions(&individual.extensions);
+
+    Ok(individual)
+}
+
+fn family_from_node(node: Node, span: SourceSpan) -> Result<Family, ParseError> {
+    let id = node.xref.ok_or(ParseError::MissingFamilyId {
+        line: node.line,
+        offset: node.offset,
+    })?;
+    let mut family = Family {
+        id,
+        source_location: Some(span),
+        ..Default::default()
+    };
+
+    for child in node.children {
+        if child.tag.starts_with('_') {
+            family.extensions.push(child.into_extension(None));
+            continue;
+        }
+        collect_extensions(&child, &mut family.extensions);
+        if family.metadata.read(&child) {
+            continue;
+        }
+        match child.tag.as_str() {
+            "HUSB" => family.husband = Some(child.value.trim_matches('@').to_string()),
+            "WIFE" => family.wife = Some(child.value.trim_matches('@').to_string()),
+            "CHIL" => family
+                .children
+                .push(child.value.trim_matches('@').to_string()),
+            "NAME" | "BIRT" | "DEAT" => return Err(child.orphan()),
+            "ASSO" => family
+                .associations
+                .extend(association_from_node(&child, None)),
+            "NOTE" => family.notes.read(&child),
+            "SOUR" => family.citations.push(Citation::from_node(&child)),
+            tag if is_event_tag(tag) => {
+                family.associations.extend(event_associations(&child));
+                family.events.extend(tagged_event(&child));
+            }
+            _ => check_no_event_details(&child)?,
+        }
+    }
+    family.vendor = VendorFields::from_extensions(&family.extensions);
+
+    Ok(family)
+}
+
+fn change_stamp(node: &Node) -> Option<ChangeStamp> {
+    let date = node.children.iter().find(|child| child.tag == "DATE")?;
+    let time = date
+        .children
+        .iter()
+        .find(|child| child.tag == "TIME")
+        .map(|time| time.value.clone());
+    Some(ChangeStamp {
+        date: date.value.clone(),
+        time,
+    })
+}
+
+fn external_id(node: &Node) -> Option<ExternalId> {
+    let value = node.value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    let kind = node
+        .children
+        .iter()
+        .find(|child| child.tag == "TYPE")
+        .map(|kind| kind.value.clone());
+    Some(ExternalId {
+        value: value.to_owned(),
+        kind,
+    })
+}
+
+/// Record ID from a pointer value such as `@I1@`.
+fn pointer(value: &str) -> Option<String> {
+    let id = value.trim().strip_prefix('@')?.strip_suffix('@')?;
+    (!id.is_empty()).then(|| id.to_owned())
+}
+
+/// Reads an `ASSO` pointer with its `RELA` (GEDCOM 5.5) or `ROLE` (GEDCOM 7,
+/// preferring a `PHRASE` describing it).
+fn association_from_node(node: &Node, event: Option<&str>) -> Option<Association> {
+    let individual = pointer(&node.value)?;
+    let relation = node
+        .children
+        .iter()
+        .find_map(|child| match child.tag.as_str() {
+            "RELA" => Some(child.value.clone()),
+            "ROLE" => Some(
+                child
+                    .children
+                    .iter()
+                    .find(|phrase| phrase.tag == "PHRASE")
+                    .unwrap_or(child)
+                    .value
+                    .clone(),
+            ),
+            _ => None,
+        });
+    Some(Association {
+        individual,
+        relation: relation.filter(|relation| !relation.is_empty()),
+        event: event.map(str::to_owned),
+    })
+}
+
+fn event_associations(node: &Node) -> impl Iterator<Item = Association> + '_ {
+    node.children
+        .iter()
+        .filter(|child| child.tag == "ASSO")
+        .filter_map(|child| association_from_node(child, Some(&node.tag)))
+}
+
+/// Collects underscore tags nested anywhere below a standard structure.
+fn collect_extensions(node: &Node, out: &mut Vec<Extension>) {
+    for child in &node.children {
+        if child.tag.starts_with('_') {
+            out.push(clone_node(child).into_extension(Some(&node.tag)));
+        } else {
+            collect_extensions(child, out);
+        }
+    }
+}
+
+fn clone_node(node: &Node) -> Node {
+    Node {
+        line: node.line,
+        offset: node.offset,
+        xref: node.xref.clone(),
+        tag: node.tag.clone(),
+        value: node.value.clone(),
+        children: node.children.iter().map(clone_node).collect(),
+    }
+}
+
+/// Tags whose substructures may carry a DATE or PLAC.
+const DATED_TAGS: &[&str] = &[
+    "ADOP", "ANUL", "BAPL", "BAPM", "BARM", "BASM", "BIRT", "BLES", "BURI", "CAST", "CENS", "CHAN",
+    "CHR", "CHRA", "CONF", "CONL", "CREA", "CREM", "DEAT", "DIV", "DIVF", "DSCR", "EDUC", "EMIG",
+    "ENDL", "ENGA", "EVEN", "FACT", "FCOM", "GRAD", "IDNO", "IMMI", "MARB", "MARC", "MARL", "MARR",
+    "MARS", "NATI", "NATU", "NCHI", "NMR", "OCCU", "ORDN", "PROB", "PROP", "RELI", "RESI", "RETI",
+    "SLGC", "SLGS", "SSN", "TITL", "WILL",
+];
+
+/// Rejects DATE/PLAC lines attached to a structure that is not an event.
+fn check_no_event_details(node: &Node) -> Result<(), ParseError> {
+    if DATED_TAGS.contains(&node.tag.as_str()) || node.tag.starts_with('_') {
+        return Ok(());
+    }
+    match node
+        .children
+        .iter()
+        .find(|child| matches!(child.tag.as_str(), "DATE" | "PLAC"))
+    {
+        Some(child) => Err(child.orphan()),
+        None => Ok(()),
+    }
+}
+
+/// Event and attribute structures, i.e. everything that may carry a DATE or
+/// PLAC except the change/creation stamps.
+fn is_event_tag(tag: &str) -> bool {
+    DATED_TAGS.contains(&tag) && !matches!(tag, "CHAN" | "CREA")
+}
+
+fn tagged_event(node: &Node) -> Option<Event> {
+    let mut event = event_from_node(node)?;
+    event.tag = Some(node.tag.clone());
+    Some(event)
+}
+
+fn event_from_node(node: &Node) -> Option<Event> {
+    let mut event = Event {
+        value: Some(node.value.clone()).filter(|value| !value.is_empty()),
+        ..Default::default()
+    };
+    for child in &node.children {
+        match child.tag.as_str() {
+            "DATE" if event.date.is_none() => event.date = Some(child.value.clone()),
+            "PLAC" if event.place.is_none() => {
+                event.place = Some(child.value.clone());
+                event.coordinates = child
+                    .children
+                    .iter()
+                    .filter(|map| map.tag == "MAP")
+                    .find_map(|map| {
+                        let value = |tag: &str| {
+                            map.children
+                                .iter()
+                                .find(|child| child.tag == tag)
+                                .map(|child| child.value.as_str())
+                        };
+                        Coordinates::parse(value("LATI")?, value("LONG")?)
                     });
+            }
+            "AGE" if event.age.is_none() => event.age = Some(child.value.clone()),
+            "NOTE" => event.notes.read(child),
+            "SOUR" => event.citations.push(Citation::from_node(child)),
+            _ => {}
+        }
+    }
+    event.address = address_from_node(node);
+    if event == Event::default() {
+        None
+    } else {
+        Some(event)
+    }
+}
+
+/// Parses GEDCOM text held in memory; tests build their trees with it.
+#[cfg(test)]
+pub fn parse_gedcom(input: &str) -> Result<GedcomData, ParseError> {
+    let mut data = GedcomData::default();
+    for record in GedcomReader::new(input.as_bytes()) {
+        match record {
+            Ok(GedcomRecord::Header(header)) => data.header = header,
+            Ok(GedcomRecord::Individual(individual)) => data.individuals.push(individual),
+            Ok(GedcomRecord::Family(family)) => data.families.push(family),
+            Ok(GedcomRecord::Repository(repository)) => data.repositories.push(repository),
+            Ok(GedcomRecord::Submitter(submitter)) => data.submitters.push(submitter),
+            Ok(GedcomRecord::Note(note)) => data.notes.push(note),
+            Ok(GedcomRecord::Source(source)) => data.sources.push(source),
+            Err(LoadError::Parse(err)) => return Err(err),
+            Err(err) => unreachable!("reading from memory cannot fail: {err}"),
+        }
+    }
+    Ok(data)
+}
+
+/// Streams a GEDCOM file into a store record by record without first
+/// materializing the whole file.
+pub fn load_gedcom_store(path: impl AsRef<Path>) -> Result<GedcomStore, LoadError> {
+    GedcomStore::from_reader(BufReader::new(fs::File::open(path)?))
+}
+
+pub fn load_store(path: impl AsRef<Path>) -> Result<GedcomStore, LoadError> {
+    let file = fs::File::open(path)?;
+    let data: GedcomData = serde_json::from_reader(file)?;
+    Ok(GedcomStore::from_data(data))
+}
+impl GedcomStore {
+    pub fn from_data(data: GedcomData) -> Self {
+        let mut store = Self {
+            header: data.header,
+            ..Default::default()
+        };
+        for individual in data.individuals {
+            store.add_record(GedcomRecord::Individual(individual));
+        }
+        for family in data.families {
+            store.add_record(GedcomRecord::Family(family));
+        }
+        for repository in data.repositories {
+            store.add_record(GedcomRecord::Repository(repository));
+        }
+        for submitter in data.submitters {
+            store.add_record(GedcomRecord::Submitter(submitter));
+        }
+        for note in data.notes {
+            store.add_record(GedcomRecord::Note(note));
+        }
+        for source in data.sources {
+            store.add_record(GedcomRecord::Source(source));
+        }
+        store
+    }
+
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, LoadError> {
+        let mut store = Self::default();
+        for record in GedcomReader::new(reader) {
+            store.add_record(record?);
+        }
+        Ok(store)
+    }
+
+    /// Adds a parsed record, replacing any existing record with the same ID.
+    pub fn add_record(&mut self, record: GedcomRecord) {
+        match record {
+            GedcomRecord::Header(header) => self.header = header,
+            GedcomRecord::Individual(individual) => self.put_individual(individual),
+            GedcomRecord::Family(family) => self.put_family(family),
+            GedcomRecord::Repository(repository) => {
+                self.repositories.insert(repository.id.clone(), repository);
+            }
+            GedcomRecord::Submitter(submitter) => {
+                self.submitters.insert(submitter.id.clone(), submitter);
+            }
+            GedcomRecord::Note(note) => {
+                let document = Document::Note(note.id.clone());
+                if let Some(previous) = self.notes.remove(&note.id) {
+                    self.text.remove(&document, &note_record_texts(&previous));
+                }
+                self.text.add(&document, &note_record_texts(&note));
+                self.notes.insert(note.id.clone(), note);
+            }
+            GedcomRecord::Source(source) => {
+                let document = Document::Source(source.id.clone());
+                if let Some(previous) = self.sources.remove(&source.id) {
+                    self.text.remove(&document, &source_texts(&previous));
                 }
+                self.text.add(&document, &source_texts(&source));
+                self.sources.insert(source.id.clone(), source);
+            }
+        }
+    }
+
+    fn put_individual(&mut self, individual: Individual) {
+        if let Some(previous) = self.individuals.remove(&individual.id) {
+            self.unindex_individual(&previous);
+        }
+        self.index_individual(&individual);
+        self.individuals.insert(individual.id.clone(), individual);
+    }
+
+    fn put_family(&mut self, family: Family) {
+        if let Some(previous) = self.families.remove(&family.id) {
+            self.unindex_family(&previous);
+        }
+        self.index_family(&family);
+        self.families.insert(family.id.clone(), family);
+    }
+
+    fn index_individual(&mut self, individual: &Individual) {
+        index_value(
+            &mut self.by_familysearch_id,
+            individual.vendor.familysearch_id.as_deref(),
+            &individual.id,
+        );
+        self.index_metadata(
+            &individual.metadata,
+            individual.vendor.uid.as_deref(),
+            RecordRef::Individual(individual.id.clone()),
+        );
+        for (_, event) in individual.tagged_events() {
+            if let Some(place) = &event.place {
+                self.places.add(
+                    place,
+                    event.coordinates,
+                    RecordRef::Individual(individual.id.clone()),
+                );
+            }
+        }
+        for name in searchable_names(individual) {
+            self.names.add(&individual.id, &name);
+        }
+        let record = RecordRef::Individual(individual.id.clone());
+        self.by_folded_id
+            .entry(fold(&individual.id))
+            .or_default()
+            .insert(record.clone());
+        self.text.add(
+            &Document::Record(record.clone()),
+            &individual_texts(individual),
+        );
+        self.index_pointers(individual_pointers(individual), &record);
+        for association in &individual.associations {
+            self.associated_by
+                .entry(association.individual.clone())
+                .or_default()
+                .insert(RecordRef::Individual(individual.id.clone()));
+        }
+        for alias in &individual.aliases {
+            self.aliased_by
+                .entry(alias.clone())
+                .or_default()
+                .insert(individual.id.clone());
+        }
+    }
+
+    fn unindex_individual(&mut self, individual: &Individual) {
+        unindex_value(
+            &mut self.by_familysearch_id,
+            individual.vendor.familysearch_id.as_deref(),
+            &individual.id,
+        );
+        let record = RecordRef::Individual(individual.id.clone());
+        self.unindex_metadata(
+            &individual.metadata,
+            individual.vendor.uid.as_deref(),
+            &record,
+        );
+        for (_, event) in individual.tagged_events() {
+            if let Some(place) = &event.place {
+                self.places.remove(place, &record);
+            }
+        }
+        for name in searchable_names(individual) {
+            self.names.remove(&individual.id, &name);
+        }
+        remove_from_set(&mut self.by_folded_id, &fold(&individual.id), &record);
+        self.text.remove(
+            &Document::Record(record.clone()),
+            &individual_texts(individual),
+        );
+        self.unindex_pointers(individual_pointers(individual), &record);
+        for association in &individual.associations {
+            remove_from_set(&mut self.associated_by, &association.individual, &record);
+        }
+        for alias in &individual.aliases {
+            remove_from_set(&mut self.aliased_by, alias, &individual.id);
+        }
+    }
+
+    fn index_family(&mut self, family: &Family) {
+        for child in &family.children {
+            self.child_in
+                .entry(child.clone())
+                .or_default()
+                .insert(family.id.clone());
+        }
+        for spouse in [&family.husband, &family.wife].into_iter().flatten() {
+            self.spouse_in
+                .entry(spouse.clone())
+                .or_default()
+                .insert(family.id.clone());
+        }
+        for event in &family.events {
+            if let Some(place) = &event.place {
+                self.places.add(
+                    place,
+                    event.coordinates,
+                    RecordRef::Family(family.id.clone()),
+                );
+            }
+        }
+        for association in &family.associations {
+            self.associated_by
+                .entry(association.individual.clone())
+                .or_default()
+                .insert(RecordRef::Family(family.id.clone()));
+        }
+        let record = RecordRef::Family(family.id.clone());
+        self.by_folded_id
+            .entry(fold(&family.id))
+            .or_default()
+            .insert(record.clone());
+        self.text
+            .add(&Document::Record(record.clone()), &family_texts(family));
+        self.index_pointers(family_pointers(family), &record);
+        self.index_metadata(&family.metadata, family.vendor.uid.as_deref(), record);
+    }
+
+    fn index_pointers(
+        &mut self,
+        (notes, sources): (BTreeSet<&str>, BTreeSet<&str>),
+        record: &RecordRef,
+    ) {
+        for note in notes {
+            self.noted_by
+                .entry(note.to_owned())
+                .or_default()
+                .insert(record.clone());
+        }
+        for source in sources {
+            self.cited_by
+                .entry(source.to_owned())
+                .or_default()
+                .insert(record.clone());
+        }
+    }
+
+    fn unindex_pointers(
+        &mut self,
+        (notes, sources): (BTreeSet<&str>, BTreeSet<&str>),
+        record: &RecordRef,
+    ) {
+        for note in notes {
+            remove_from_set(&mut self.noted_by, note, record);
+        }
+        for source in sources {
+            remove_from_set(&mut self.cited_by, source, record);
+        }
+    }
+
+    fn index_metadata(
+        &mut self,
+        metadata: &RecordMetadata,
+        vendor_uid: Option<&str>,
+        record: RecordRef,
+    ) {
+        for uid in metadata.uids.iter().map(String::as_str).chain(vendor_uid) {
+            self.by_uid
+                .entry(normalize_key(uid))
+                .or_default()
+                .insert(record.clone());
+        }
+        for refn in &metadata.refns {
+            self.by_refn
+                .entry(normalize_key(&refn.value))
+                .or_default()
+                .insert(record.clone());
+        }
+    }
+
+    fn unindex_metadata(
+        &mut self,
+        metadata: &RecordMetadata,
+        vendor_uid: Option<&str>,
+        record: &RecordRef,
+    ) {
+        for uid in metadata.uids.iter().map(String::as_str).chain(vendor_uid) {
+            remove_from_set(&mut self.by_uid, &normalize_key(uid), record);
+        }
+        for refn in &metadata.refns {
+            remove_from_set(&mut self.by_refn, &normalize_key(&refn.value), record);
+        }
+    }
+
+    fn unindex_family(&mut self, family: &Family) {
+        for child in &family.children {
+            remove_from_set(&mut self.child_in, child, &family.id);
+        }
+        for spouse in [&family.husband, &family.wife].into_iter().flatten() {
+            remove_from_set(&mut self.spouse_in, spouse, &family.id);
+        }
+        let record = RecordRef::Family(family.id.clone());
+        for event in &family.events {
+            if let Some(place) = &event.place {
+                self.places.remove(place, &record);
+            }
+        }
+        for association in &family.associations {
+            remove_from_set(&mut self.associated_by, &association.individual, &record);
+        }
+        remove_from_set(&mut self.by_folded_id, &fold(&family.id), &record);
+        self.text
+            .remove(&Document::Record(record.clone()), &family_texts(family));
+        self.unindex_pointers(family_pointers(family), &record);
+        self.unindex_metadata(&family.metadata, family.vendor.uid.as_deref(), &record);
+    }
+
+    /// Individuals and families whose ID folds to `folded`, in ID order.
+    pub fn records_by_folded_id(&self, folded: &str) -> impl Iterator<Item = &RecordRef> {
+        self.by_folded_id.get(folded).into_iter().flatten()
+    }
+
+    /// Records with an association pointing at individual `id`, in ID order.
+    pub fn associated_by(&self, id: &str) -> impl Iterator<Item = &RecordRef> {
+        self.associated_by.get(id).into_iter().flatten()
+    }
+
+    /// Individuals listing `id` as an alias, in ID order.
+    pub fn aliased_by(&self, id: &str) -> impl Iterator<Item = &String> {
+        self.aliased_by.get(id).into_iter().flatten()
+    }
+
+    /// Families in which `id` is a child, in ID order.
+    pub fn families_as_child(&self, id: &str) -> impl Iterator<Item = &Family> {
+        self.child_in
+            .get(id)
+            .into_iter()
+            .flatten()
+            .filter_map(|family| self.families.get(family))
+    }
+
+    /// Families in which `id` is a spouse, in ID order.
+    pub fn families_as_spouse(&self, id: &str) -> impl Iterator<Item = &Family> {
+        self.spouse_in
+            .get(id)
+            .into_iter()
+            .flatten()
+            .filter_map(|family| self.families.get(family))
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn places(&self) -> &PlaceIndex {
+        &self.places
+    }
+
+    pub fn names(&self) -> &NameIndex {
+        &self.names
+    }
+
+    pub fn text_index(&self) -> &TextIndex {
+        &self.text
+    }
+
+    pub fn get_note(&self, id: &str) -> Option<&NoteRecord> {
+        self.notes.get(id)
+    }
+
+    pub fn get_source(&self, id: &str) -> Option<&Source> {
+        self.sources.get(id)
+    }
+
+    /// Individuals and families a full-text document stands for: the record
+    /// itself, or those pointing at a shared note or source.
+    pub fn records_referencing(&self, document: &Document) -> Vec<RecordRef> {
+        let referencing = |map: &HashMap<String, BTreeSet<RecordRef>>, id: &str| {
+            map.get(id).into_iter().flatten().cloned().collect()
+        };
+        match document {
+            Document::Record(record) => vec![record.clone()],
+            Document::Note(id) => referencing(&self.noted_by, id),
+            Document::Source(id) => referencing(&self.cited_by, id),
+        }
+    }
+
+    /// Rewrites every event at `place` (compared by place key) to `canonical`,
+    /// filling in `coordinates` where the event has none, and stamps the
+    /// records touched with `changed`. Records locked with `RESN locked` are
+    /// left as they are. Returns the number of events changed.
+    pub fn normalize_place(
+        &mut self,
+        place: &str,
+        canonical: &str,
+        coordinates: Option<Coordinates>,
+        changed: &ChangeStamp,
+    ) -> usize {
+        let key = place_key(place);
+        let Some(entry) = self.places.get(place) else {
+            return 0;
+        };
+        let records: Vec<RecordRef> = entry.records().cloned().collect();
+
+        let mut updated = 0;
+        let mut update = |event: &mut Event| {
+            if event.place.as_deref().map(place_key).as_ref() == Some(&key) {
+                event.place = Some(canonical.to_owned());
+                event.coordinates = event.coordinates.or(coordinates);
+                updated += 1;
             }
-            (2, "DATE") => match &mut context {
-                Context::Individual {
-                    idx,
-                    sub: Some(IndividualContext::Birth),
-                } => {
-                    let event = individuals[*idx].birth.get_or_insert(Event {
-                        date: None,
-                        place: None,
-                    });
-                    event.date = Some(value);
-                }
-                Context::Individual {
-                    idx,
-                    sub: Some(IndividualContext::Death),
-                } => {
-                    let event = individuals[*idx].death.get_or_insert(Event {
-                        date: None,
-                        place: None,
-                    });
-                    event.date = Some(value);
-                }
-                _ => {
-                    return Err(ParseError::OrphanTag {
-                        line: line_no,
-                        tag: tag.to_string(),
-                    });
-                }
-            },
-            (2, "PLAC") => match &mut context {
-                Context::Individual {
-                    idx,
-                    sub: Some(IndividualContext::Birth),
-                } => {
-                    let event = individuals[*idx].birth.get_or_insert(Event {
-                        date: None,
-                        place: None,
-                    });
-                    event.place = Some(value);
-                }
-                Context::Individual {
-                    idx,
-                    sub: Some(IndividualContext::Death),
-                } => {
-                    let event = individuals[*idx].death.get_or_insert(Event {
-                        date: None,
-                        place: None,
-                    });
-                    event.place = Some(value);
+        };
+        for record in records {
+            match record {
+                RecordRef::Individual(id) => {
+                    if let Some(mut individual) = self
+                        .individuals
+                        .get(&id)
+                        .filter(|individual| !individual.metadata.is_locked())
+                        .cloned()
+                    {
+                        individual
+                            .birth
+                            .iter_mut()
+                            .chain(individual.death.iter_mut())
+                            .chain(individual.events.iter_mut())
+                            .for_each(&mut update);
+                        individual.metadata.changed = Some(changed.clone());
+                        self.put_individual(individual);
+                    }
                 }
-                _ => {
-                    return Err(ParseError::OrphanTag {
-                        line: line_no,
-                        tag: tag.to_string(),
-                    });
+                RecordRef::Family(id) => {
+                    if let Some(mut family) = self
+                        .families
+                        .get(&id)
+                        .filter(|family| !family.metadata.is_locked())
+                        .cloned()
+                    {
+                        family.events.iter_mut().for_each(&mut update);
+                        family.metadata.changed = Some(changed.clone());
+                        self.put_family(family);
+                    }
                 }
-            },
-            _ => {
-                // For now ignore other tags/levels.
             }
         }
+        updated
     }
 
-    Ok(GedcomData {
-        individuals,
-        families,
-    })
-}
+    pub fn find_by_familysearch_id(&self, familysearch_id: &str) -> Vec<&Individual> {
+        self.lookup(&self.by_familysearch_id, familysearch_id)
+    }
 
-pub fn load_gedcom(path: impl AsRef<Path>) -> Result<GedcomData, LoadError> {
-    let contents = fs::read_to_string(path)?;
-    Ok(parse_gedcom(&contents)?)
-}
+    pub fn find_by_uid(&self, uid: &str) -> Vec<&Individual> {
+        self.records_by_uid(uid)
+            .filter_map(|record| match record {
+                RecordRef::Individual(id) => self.individuals.get(id),
+                RecordRef::Family(_) => None,
+            })
+            .collect()
+    }
 
-pub fn load_store(path: impl AsRef<Path>) -> Result<GedcomStore, LoadError> {
-    let file = fs::File::open(path)?;
-    let data: GedcomData = serde_json::from_reader(file)?;
-    Ok(GedcomStore::from_data(data))
-}
-impl GedcomStore {
-    pub fn from_data(data: GedcomData) -> Self {
-        let individuals = data
-            .individuals
+    /// Individuals and families carrying `uid` (`UID` or `_UID`), compared
+    /// like vendor IDs.
+    pub fn records_by_uid(&self, uid: &str) -> impl Iterator<Item = &RecordRef> {
+        self.by_uid.get(&normalize_key(uid)).into_iter().flatten()
+    }
+
+    /// Individuals and families with a `REFN` equal to `refn`, compared like
+    /// vendor IDs, optionally only where that `REFN` has the given `TYPE`.
+    pub fn records_by_refn<'a>(
+        &'a self,
+        refn: &str,
+        kind: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a RecordRef> {
+        let key = normalize_key(refn);
+        self.by_refn
+            .get(&key)
             .into_iter()
-            .map(|ind| (ind.id.clone(), ind))
-            .collect();
-        let families = data
-            .families
+            .flatten()
+            .filter(move |record| {
+                let Some(kind) = kind else {
+                    return true;
+                };
+                let metadata = match record {
+                    RecordRef::Individual(id) => self.individuals.get(id).map(|i| &i.metadata),
+                    RecordRef::Family(id) => self.families.get(id).map(|f| &f.metadata),
+                };
+                metadata.is_some_and(|metadata| {
+                    metadata.refns.iter().any(|reference| {
+                        normalize_key(&reference.value) == key
+                            && reference
+                                .kind
+                                .as_deref()
+                                .is_some_and(|k| k.eq_ignore_ascii_case(kind))
+                    })
+                })
+            })
+    }
+
+    fn lookup(&self, index: &HashMap<String, Vec<String>>, key: &str) -> Vec<&Individual> {
+        index
+            .get(&normalize_key(key))
             .into_iter()
-            .map(|fam| (fam.id.clone(), fam))
-            .collect();
-        Self {
-            individuals,
-            families,
-        }
+            .flatten()
+            .filter_map(|id| self.individuals.get(id))
+            .collect()
     }
 
     pub fn get_individual(&self, id: &str) -> Option<&Individual> {
@@ -325,6 +1784,14 @@ impl GedcomStore {
         self.families.get(id)
     }
 
+    pub fn get_repository(&self, id: &str) -> Option<&Repository> {
+        self.repositories.get(id)
+    }
+
+    pub fn get_submitter(&self, id: &str) -> Option<&Submitter> {
+        self.submitters.get(id)
+    }
+
     pub fn families(&self) -> impl Iterator<Item = &Family> {
         self.families.values()
     }
@@ -333,11 +1800,19 @@ impl GedcomStore {
         self.individuals.values()
     }
 
+    pub fn notes(&self) -> impl Iterator<Item = &NoteRecord> {
+        self.notes.values()
+    }
+
+    pub fn sources(&self) -> impl Iterator<Item = &Source> {
+        self.sources.values()
+    }
+
     pub fn insert_individual(&mut self, individual: Individual) -> Result<(), StoreError> {
         if self.individuals.contains_key(&individual.id) {
             return Err(StoreError::DuplicateIndividual(individual.id));
         }
-        self.individuals.insert(individual.id.clone(), individual);
+        self.put_individual(individual);
         Ok(())
     }
 
@@ -345,22 +1820,84 @@ impl GedcomStore {
         if self.families.contains_key(&family.id) {
             return Err(StoreError::DuplicateFamily(family.id));
         }
-        self.families.insert(family.id.clone(), family);
+        self.put_family(family);
         Ok(())
     }
 
+    pub fn remove_individual(&mut self, id: &str) -> Option<Individual> {
+        let individual = self.individuals.remove(id)?;
+        self.unindex_individual(&individual);
+        Some(individual)
+    }
+
+    pub fn remove_family(&mut self, id: &str) -> Option<Family> {
+        let family = self.families.remove(id)?;
+        self.unindex_family(&family);
+        Some(family)
+    }
+
     pub fn to_data(&self) -> GedcomData {
         GedcomData {
             individuals: self.individuals.values().cloned().collect(),
             families: self.families.values().cloned().collect(),
+            header: self.header.clone(),
+            repositories: self.repositories.values().cloned().collect(),
+            submitters: self.submitters.values().cloned().collect(),
+            notes: self.notes.values().cloned().collect(),
+            sources: self.sources.values().cloned().collect(),
         }
     }
+}
 
-    pub fn save_to_path(&self, path: impl AsRef<Path>) -> Result<(), std::io::Error> {
-        let data = self.to_data();
-        let mut file = fs::File::create(path)?;
-        serde_json::to_writer_pretty(&mut file, &data)
-            .map_err(|err| std::io::Error::new(ErrorKind::Other, err))
+impl Individual {
+    /// Birth, death and all other events together with their GEDCOM tag.
+    pub fn tagged_events(&self) -> impl Iterator<Item = (&str, &Event)> {
+        let birth = self.birth.iter().map(|event| ("BIRT", event));
+        let death = self.death.iter().map(|event| ("DEAT", event));
+        let others = self
+            .events
+            .iter()
+            .map(|event| (event.tag.as_deref().unwrap_or("EVEN"), event));
+        birth.chain(death).chain(others)
+    }
+}
+
+/// Vendor IDs are compared case-insensitively and without separators, since
+/// `_UID` values are written both with and without dashes.
+fn normalize_key(key: &str) -> String {
+    key.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+fn index_value(index: &mut HashMap<String, Vec<String>>, key: Option<&str>, id: &str) {
+    if let Some(key) = key {
+        index
+            .entry(normalize_key(key))
+            .or_default()
+            .push(id.to_owned());
+    }
+}
+
+fn remove_from_set<T: Ord>(index: &mut HashMap<String, BTreeSet<T>>, key: &str, value: &T) {
+    if let Some(values) = index.get_mut(key) {
+        values.remove(value);
+        if values.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+fn unindex_value(index: &mut HashMap<String, Vec<String>>, key: Option<&str>, id: &str) {
+    if let Some(key) = key {
+        let key = normalize_key(key);
+        if let Some(ids) = index.get_mut(&key) {
+            ids.retain(|existing| existing != id);
+            if ids.is_empty() {
+                index.remove(&key);
+            }
+        }
     }
 }
 
@@ -368,7 +1905,6 @@ impl GedcomStore {
 mod tests {
     use super::*;
     use std::io::Write;
-    use tempfile;
 
     #[test]
     fn parses_minimal_individuals_and_family() {
@@ -398,9 +1934,17 @@ mod tests {
                     name: Some("John /Doe/".into()),
                     birth: Some(Event {
                         date: Some("1 JAN 1900".into()),
-                        place: Some("Springfield".into())
+                        place: Some("Springfield".into()),
+                        ..Default::default()
+                    }),
+                    death: None,
+                    source_location: Some(SourceSpan {
+                        line_start: 2,
+                        line_end: 6,
+                        byte_start: 1,
+                        byte_end: 115,
                     }),
-                    death: None
+                    ..Default::default()
                 },
                 Individual {
                     id: "I2".into(),
@@ -408,8 +1952,16 @@ mod tests {
                     birth: None,
                     death: Some(Event {
                         date: Some("2 FEB 2000".into()),
-                        place: None
-                    })
+                        place: None,
+                        ..Default::default()
+                    }),
+                    source_location: Some(SourceSpan {
+                        line_start: 7,
+                        line_end: 10,
+                        byte_start: 115,
+                        byte_end: 202,
+                    }),
+                    ..Default::default()
                 }
             ]
         );
@@ -419,7 +1971,14 @@ mod tests {
                 id: "F1".into(),
                 husband: Some("I1".into()),
                 wife: Some("I2".into()),
-                children: vec!["I3".into()]
+                children: vec!["I3".into()],
+                source_location: Some(SourceSpan {
+                    line_start: 11,
+                    line_end: 14,
+                    byte_start: 202,
+                    byte_end: 281,
+                }),
+                ..Default::default()
             }]
         );
     }
@@ -455,6 +2014,22 @@ mod tests {
         assert!(matches!(err, ParseError::InvalidLevel { .. }));
     }
 
+    #[test]
+    fn errors_point_to_line_and_byte_offset() {
+        let input = "0 @I1@ INDI\n1 NAME Ok\n0 @F1@ FAM\n1 NAME Misplaced\n";
+
+        let err = parse_gedcom(input).expect_err("should fail");
+        assert_eq!(
+            err,
+            ParseError::OrphanTag {
+                line: 4,
+                offset: 33,
+                tag: "NAME".into()
+            }
+        );
+        assert_eq!(err.to_string(), "orphaned tag NAME at line 4 (byte 33)");
+    }
+
     #[test]
     fn loads_from_path() {
         let mut tmp = tempfile::NamedTempFile::new().expect("create temp file");
@@ -466,15 +2041,22 @@ mod tests {
         )
         .expect("write temp file");
 
-        let data = load_gedcom(tmp.path()).expect("should load");
+        let store = load_gedcom_store(tmp.path()).expect("should load");
         assert_eq!(
-            data.individuals,
-            vec![Individual {
+            store.get_individual("I1"),
+            Some(&Individual {
                 id: "I1".into(),
                 name: Some("Test /User/".into()),
                 birth: None,
-                death: None
-            }]
+                death: None,
+                source_location: Some(SourceSpan {
+                    line_start: 2,
+                    line_end: 3,
+                    byte_start: 1,
+                    byte_end: 56,
+                }),
+                ..Default::default()
+            })
         );
     }
 
@@ -490,6 +2072,432 @@ mod tests {
         assert!(matches!(err, ParseError::OrphanTag { .. }));
     }
 
+    #[test]
+    fn streams_records_one_at_a_time() {
+        let input = "0 HEAD\n1 CHAR UTF-8\n0 @I1@ INDI\n1 NAME First\n1 CHAN\n2 DATE 1 JAN 2020\n0 @F1@ FAM\n1 CHIL @I1@\n0 @I2@ INDI\n2 NAME Broken\n0 TRLR\n";
+        let mut reader = GedcomReader::new(std::io::BufReader::new(input.as_bytes()));
+
+        match reader.next() {
+            Some(Ok(GedcomRecord::Header(header))) => assert!(header.place_form.is_empty()),
+            other => panic!("expected header, got {other:?}"),
+        }
+        match reader.next() {
+            Some(Ok(GedcomRecord::Individual(individual))) => {
+                assert_eq!(individual.id, "I1");
+                assert_eq!(individual.name.as_deref(), Some("First"));
+            }
+            other => panic!("expected individual, got {other:?}"),
+        }
+        match reader.next() {
+            Some(Ok(GedcomRecord::Family(family))) => assert_eq!(family.children, vec!["I1"]),
+            other => panic!("expected family, got {other:?}"),
+        }
+        match reader.next() {
+            Some(Err(LoadError::Parse(ParseError::OrphanTag { line, .. }))) => assert_eq!(line, 10),
+            other => panic!("expected parse error, got {other:?}"),
+        }
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn builds_store_from_reader() {
+        let input = "\u{feff}0 @I1@ INDI\r\n1 NAME Streamed\r\n0 @F1@ FAM\r\n1 HUSB @I1@\r\n";
+        let store = GedcomStore::from_reader(input.as_bytes()).expect("should load");
+
+        let individual = store.get_individual("I1").expect("individual present");
+        assert_eq!(individual.name.as_deref(), Some("Streamed"));
+        let family = store.get_family("F1").expect("family present");
+        assert_eq!(family.husband.as_deref(), Some("I1"));
+    }
+
+    #[test]
+    fn loads_store_from_gedcom_path() {
+        let mut tmp = tempfile::NamedTempFile::new().expect("create temp file");
+        tmp.write_all(b"0 @I1@ INDI\n1 NAME Test /User/\n")
+            .expect("write temp file");
+
+        let store = load_gedcom_store(tmp.path()).expect("should load");
+        assert_eq!(store.individuals().count(), 1);
+    }
+
+    #[test]
+    fn preserves_vendor_extensions() {
+        let input = r#"
+        0 @I1@ INDI
+        1 NAME Mary /Smith/
+        2 _MARNM Jones
+        1 _UID 0b6a7c4e-1a2b-4c3d-9e8f-001122334455
+        1 _FSFTID KWCB-123
+        1 _COLOR 5
+        1 _LIVING N
+        1 _MILT
+        2 DATE 1917
+        2 PLAC France
+        0 @F1@ FAM
+        1 _UID ABCDEF
+        "#;
+
+        let data = parse_gedcom(input).expect("should parse");
+        let individual = &data.individuals[0];
+        assert_eq!(
+            individual.vendor,
+            VendorFields {
+                uid: Some("0b6a7c4e-1a2b-4c3d-9e8f-001122334455".into()),
+                married_name: Some("Jones".into()),
+                familysearch_id: Some("KWCB-123".into()),
+                living: Some(false),
+                color: Some("5".into()),
+            }
+        );
+        assert_eq!(individual.extensions.len(), 6);
+        assert_eq!(individual.extensions[0].parent.as_deref(), Some("NAME"));
+        let military = individual
+            .extensions
+            .iter()
+            .find(|extension| extension.tag == "_MILT")
+            .expect("_MILT preserved");
+        assert_eq!(military.children.len(), 2);
+        assert_eq!(military.children[1].value, "France");
+        assert_eq!(data.families[0].vendor.uid.as_deref(), Some("ABCDEF"));
+    }
+
+    #[test]
+    fn parses_place_form_and_coordinates() {
+        let input = r#"
+        0 HEAD
+        1 PLAC
+        2 FORM City, County, Country
+        0 @I1@ INDI
+        1 BIRT
+        2 PLAC Leeds, Yorkshire, England
+        3 MAP
+        4 LATI N53.8
+        4 LONG W1.55
+        "#;
+
+        let data = parse_gedcom(input).expect("should parse");
+        assert_eq!(data.header.place_form, vec!["City", "County", "Country"]);
+        let birth = data.individuals[0].birth.as_ref().unwrap();
+        assert_eq!(
+            birth.coordinates,
+            Some(Coordinates {
+                latitude: 53.8,
+                longitude: -1.55
+            })
+        );
+
+        let store = GedcomStore::from_data(data);
+        assert_eq!(store.header().place_form.len(), 3);
+    }
+
+    #[test]
+    fn parses_addresses_and_contact_records() {
+        let input = r#"
+        0 @S1@ SUBM
+        1 NAME Jane Researcher
+        1 ADDR 1 Main St
+        2 CONT Apt 4
+        2 CITY Springfield
+        2 STAE IL
+        2 POST 62701
+        2 CTRY USA
+        1 EMAIL jane@example.com
+        1 WWW https://example.com
+        0 @R1@ REPO
+        1 NAME County Archive
+        1 ADDR
+        2 ADR1 Archive Road
+        2 CITY Cork
+        1 PHON +353 21 000 0000
+        1 FAX +353 21 000 0001
+        0 @I1@ INDI
+        1 RESI
+        2 DATE 1990
+        2 ADDR 12 Elm St
+        3 CONC reet
+        2 PHON 555-0100
+        "#;
+
+        let data = parse_gedcom(input).expect("should parse");
+        let submitter = &data.submitters[0];
+        assert_eq!(submitter.name.as_deref(), Some("Jane Researcher"));
+        assert_eq!(
+            submitter.address,
+            Some(Address {
+                lines: vec!["1 Main St".into(), "Apt 4".into()],
+                city: Some("Springfield".into()),
+                state: Some("IL".into()),
+                postal_code: Some("62701".into()),
+                country: Some("USA".into()),
+                emails: vec!["jane@example.com".into()],
+                websites: vec!["https://example.com".into()],
+                ..Default::default()
+            })
+        );
+
+        let repository = data.repositories[0].address.as_ref().unwrap();
+        assert_eq!(repository.lines, vec!["Archive Road"]);
+        assert_eq!(repository.faxes, vec!["+353 21 000 0001"]);
+
+        let residence = data.individuals[0].events[0].address.as_ref().unwrap();
+        assert_eq!(residence.lines, vec!["12 Elm Street"]);
+        assert_eq!(residence.phones, vec!["555-0100"]);
+
+        let store = GedcomStore::from_data(data);
+        assert!(store.get_repository("R1").is_some());
+        assert!(store.get_submitter("S1").is_some());
+        assert_eq!(store.to_data().repositories.len(), 1);
+
+        assert_eq!(
+            parse_gedcom("0 REPO\n1 NAME Nameless\n"),
+            Err(ParseError::MissingRecordId {
+                line: 1,
+                offset: 0,
+                tag: "REPO".into()
+            })
+        );
+    }
+
+    #[test]
+    fn parses_associations_and_aliases() {
+        let input = r#"
+        0 @I1@ INDI
+        1 ASSO @I2@
+        2 RELA Godfather
+        1 BAPM
+        2 DATE 1900
+        2 ASSO @I3@
+        3 ROLE OTHER
+        4 PHRASE Godmother
+        1 ALIA @I4@
+        1 ALIA John the Smith
+        0 @F1@ FAM
+        1 MARR
+        2 ASSO @I3@
+        3 ROLE WITN
+        "#;
+
+        let data = parse_gedcom(input).expect("should parse");
+        let individual = &data.individuals[0];
+        assert_eq!(
+            individual.associations,
+            vec![
+                Association {
+                    individual: "I2".into(),
+                    relation: Some("Godfather".into()),
+                    event: None,
+                },
+                Association {
+                    individual: "I3".into(),
+                    relation: Some("Godmother".into()),
+                    event: Some("BAPM".into()),
+                },
+            ]
+        );
+        assert_eq!(individual.aliases, vec!["I4"]);
+        assert_eq!(
+            data.families[0].associations[0].event.as_deref(),
+            Some("MARR")
+        );
+
+        let mut store = GedcomStore::from_data(data);
+        let i1 = RecordRef::Individual("I1".into());
+        let f1 = RecordRef::Family("F1".into());
+        assert_eq!(
+            store.associated_by("I3").collect::<Vec<_>>(),
+            vec![&i1, &f1]
+        );
+        assert_eq!(store.aliased_by("I4").collect::<Vec<_>>(), vec!["I1"]);
+
+        store.put_individual(Individual {
+            id: "I1".into(),
+            ..Default::default()
+        });
+        assert_eq!(store.associated_by("I3").collect::<Vec<_>>(), vec![&f1]);
+        assert_eq!(store.associated_by("I2").count(), 0);
+        assert_eq!(store.aliased_by("I4").count(), 0);
+    }
+
+    #[test]
+    fn parses_record_metadata() {
+        let input = r#"
+        0 @I1@ INDI
+        1 RIN 42
+        1 REFN A-17
+        2 TYPE Ancestry
+        1 UID 0f3c2a10-aaaa-bbbb
+        1 _UID 99AA
+        1 EXID 1234
+        2 TYPE https://www.wikitree.com
+        1 RESN confidential, LOCKED
+        1 CHAN
+        2 DATE 5 MAR 2021
+        3 TIME 12:30:00
+        1 CREA
+        2 DATE 1 JAN 2020
+        0 @F1@ FAM
+        1 REFN A-17
+        1 RESN privacy
+        "#;
+
+        let data = parse_gedcom(input).expect("should parse");
+        let metadata = &data.individuals[0].metadata;
+        assert_eq!(
+            metadata,
+            &RecordMetadata {
+                changed: Some(ChangeStamp {
+                    date: "5 MAR 2021".into(),
+                    time: Some("12:30:00".into()),
+                }),
+                created: Some(ChangeStamp {
+                    date: "1 JAN 2020".into(),
+                    time: None,
+                }),
+                rin: Some("42".into()),
+                refns: vec![ExternalId {
+                    value: "A-17".into(),
+                    kind: Some("Ancestry".into()),
+                }],
+                uids: vec!["0f3c2a10-aaaa-bbbb".into()],
+                exids: vec![ExternalId {
+                    value: "1234".into(),
+                    kind: Some("https://www.wikitree.com".into()),
+                }],
+                restrictions: vec![Restriction::Confidential, Restriction::Locked],
+            }
+        );
+        assert!(metadata.is_restricted());
+        assert_eq!(
+            data.families[0].metadata.restrictions,
+            vec![Restriction::Privacy]
+        );
+
+        let store = GedcomStore::from_data(data);
+        let i1 = RecordRef::Individual("I1".into());
+        let f1 = RecordRef::Family("F1".into());
+        assert_eq!(
+            store.records_by_refn("a17", None).collect::<Vec<_>>(),
+            vec![&i1, &f1]
+        );
+        assert_eq!(
+            store
+                .records_by_refn("A-17", Some("ancestry"))
+                .collect::<Vec<_>>(),
+            vec![&i1]
+        );
+        assert_eq!(store.records_by_uid("0F3C2A10AAAABBBB").count(), 1);
+        assert_eq!(store.records_by_uid("99aa").collect::<Vec<_>>(), vec![&i1]);
+    }
+
+    #[test]
+    fn keeps_relationship_indexes_in_sync() {
+        let family = |id: &str, husband: &str, children: &[&str]| Family {
+            id: id.into(),
+            husband: Some(husband.into()),
+            children: children.iter().map(|child| (*child).into()).collect(),
+            ..Default::default()
+        };
+        let ids = |families: Vec<&Family>| {
+            families
+                .into_iter()
+                .map(|family| family.id.clone())
+                .collect::<Vec<_>>()
+        };
+        let mut store = GedcomStore::default();
+        store.insert_family(family("F2", "I1", &["I3"])).unwrap();
+        store.insert_family(family("F1", "I1", &["I2"])).unwrap();
+
+        assert_eq!(
+            ids(store.families_as_spouse("I1").collect()),
+            vec!["F1", "F2"]
+        );
+        assert_eq!(ids(store.families_as_child("I2").collect()), vec!["F1"]);
+
+        store.put_family(family("F1", "I9", &["I3"]));
+        assert_eq!(ids(store.families_as_spouse("I1").collect()), vec!["F2"]);
+        assert_eq!(ids(store.families_as_spouse("I9").collect()), vec!["F1"]);
+        assert!(store.families_as_child("I2").next().is_none());
+        assert_eq!(
+            ids(store.families_as_child("I3").collect()),
+            vec!["F1", "F2"]
+        );
+
+        assert!(store.remove_family("F2").is_some());
+        assert!(store.families_as_spouse("I1").next().is_none());
+        assert_eq!(ids(store.families_as_child("I3").collect()), vec!["F1"]);
+        assert!(store.remove_family("F2").is_none());
+    }
+
+    #[test]
+    fn keeps_place_index_in_sync() {
+        let event = |place: &str| Event {
+            place: Some(place.into()),
+            ..Default::default()
+        };
+        let mut store = GedcomStore::default();
+        store
+            .insert_individual(Individual {
+                id: "I1".into(),
+                birth: Some(event("Cork, Ireland")),
+                death: Some(event("cork,ireland")),
+                ..Default::default()
+            })
+            .unwrap();
+        store
+            .insert_family(Family {
+                id: "F1".into(),
+                events: vec![Event {
+                    tag: Some("MARR".into()),
+                    ..event("Dublin, Ireland")
+                }],
+                ..Default::default()
+            })
+            .unwrap();
+
+        let cork = store.places().get("Cork, Ireland").unwrap();
+        assert_eq!(cork.event_count(), 2);
+        assert_eq!(store.places().matching("Ireland", true).len(), 2);
+
+        store.put_individual(Individual {
+            id: "I1".into(),
+            birth: Some(event("Dublin, Ireland")),
+            ..Default::default()
+        });
+        assert!(store.places().get("Cork, Ireland").is_none());
+        assert_eq!(
+            store.places().get("Dublin, Ireland").unwrap().event_count(),
+            2
+        );
+    }
+
+    #[test]
+    fn finds_individuals_by_vendor_ids() {
+        let mut store = GedcomStore::default();
+        store
+            .insert_individual(Individual {
+                id: "I1".into(),
+                vendor: VendorFields {
+                    uid: Some("0b6a7c4e-1a2b".into()),
+                    familysearch_id: Some("KWCB-123".into()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .expect("insert succeeds");
+
+        let found = store.find_by_familysearch_id("kwcb-123");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, "I1");
+        assert_eq!(store.find_by_uid("0B6A7C4E1A2B").len(), 1);
+        assert!(store.find_by_uid("missing").is_empty());
+
+        store.add_record(GedcomRecord::Individual(Individual {
+            id: "I1".into(),
+            ..Default::default()
+        }));
+        assert!(store.find_by_familysearch_id("KWCB-123").is_empty());
+    }
+
     #[test]
     fn indexes_individuals_and_families() {
         let data = GedcomData {
@@ -498,13 +2506,16 @@ mod tests {
                 name: Some("Indexed".into()),
                 birth: None,
                 death: None,
+                ..Default::default()
             }],
             families: vec![Family {
                 id: "F1".into(),
                 husband: Some("I1".into()),
                 wife: None,
                 children: vec![],
+                ..Default::default()
             }],
+            ..Default::default()
         };
 
         let store = GedcomStore::from_data(data);
@@ -521,6 +2532,7 @@ mod tests {
         let mut store = GedcomStore::from_data(GedcomData {
             individuals: vec![],
             families: vec![],
+            ..Default::default()
         });
 
         store
@@ -529,6 +2541,7 @@ mod tests {
                 name: Some("First".into()),
                 birth: None,
                 death: None,
+                ..Default::default()
             })
             .expect("insert succeeds");
 
@@ -538,6 +2551,7 @@ mod tests {
                 name: Some("Duplicate".into()),
                 birth: None,
                 death: None,
+                ..Default::default()
             })
             .expect_err("should reject duplicate");
         assert!(matches!(err, StoreError::DuplicateIndividual(id) if id == "I1"));
@@ -548,6 +2562,7 @@ mod tests {
         let mut store = GedcomStore::from_data(GedcomData {
             individuals: vec![],
             families: vec![],
+            ..Default::default()
         });
 
         store
@@ -556,6 +2571,7 @@ mod tests {
                 husband: None,
                 wife: None,
                 children: vec![],
+                ..Default::default()
             })
             .expect("insert succeeds");
 
@@ -565,6 +2581,7 @@ mod tests {
                 husband: None,
                 wife: None,
                 children: vec![],
+                ..Default::default()
             })
             .expect_err("should reject duplicate family");
         assert!(matches!(err, StoreError::DuplicateFamily(id) if id == "F1"));
@@ -578,12 +2595,14 @@ mod tests {
                 name: Some("Save".into()),
                 birth: None,
                 death: None,
+                ..Default::default()
             }],
             families: vec![],
+            ..Default::default()
         });
 
         let tmp = tempfile::NamedTempFile::new().expect("temp");
-        store.save_to_path(tmp.path()).expect("save");
+        store.to_data().save_to_path(tmp.path()).expect("save");
 
         let contents = std::fs::read_to_string(tmp.path()).expect("read");
         assert!(contents.contains("\"I1\""));
@@ -598,17 +2617,20 @@ mod tests {
                 name: Some("Saved".into()),
                 birth: None,
                 death: None,
+                ..Default::default()
             }],
             families: vec![Family {
                 id: "F1".into(),
                 husband: Some("I1".into()),
                 wife: None,
                 children: vec![],
+                ..Default::default()
             }],
+            ..Default::default()
         });
 
         let tmp = tempfile::NamedTempFile::new().expect("temp");
-        store.save_to_path(tmp.path()).expect("save");
+        store.to_data().save_to_path(tmp.path()).expect("save");
 
         let loaded = load_store(tmp.path()).expect("load snapshot");
         let indiv = loaded.get_individual("I1").expect("individual present");
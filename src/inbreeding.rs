@@ -0,0 +1,322 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use thiserror::Error;
+
+use crate::gedcom::GedcomStore;
+use crate::pedigree::parents;
+
+/// Upper bound on enumerated ancestor paths (per side) and path pairs, so a
+/// deep cutoff on a heavily intermarried pedigree fails fast instead of
+/// running for minutes.
+const MAX_PATHS: usize = 200_000;
+const MAX_PATH_PAIRS: usize = 2_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum InbreedingError {
+    #[error("pedigree too complex to enumerate its paths; lower max_generations")]
+    TooComplex,
+}
+
+/// What one common ancestor adds to a coefficient.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Contribution {
+    pub ancestor: String,
+    /// Independent path pairs through this ancestor.
+    pub paths: usize,
+    /// Shortest path pair, in generations on both sides together.
+    pub shortest: u32,
+    /// The ancestor's own inbreeding coefficient.
+    pub inbreeding: f64,
+    pub contribution: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Coefficient {
+    pub coefficient: f64,
+    pub contributions: Vec<Contribution>,
+}
+
+type Paths<'a> = BTreeMap<&'a str, Vec<Vec<&'a str>>>;
+
+/// Wright's path method, memoizing inbreeding coefficients of ancestors by
+/// the generations left to them.
+pub struct Calculator<'a> {
+    store: &'a GedcomStore,
+    inbreeding: HashMap<(&'a str, u32), f64>,
+}
+
+impl<'a> Calculator<'a> {
+    pub fn new(store: &'a GedcomStore) -> Self {
+        Self {
+            store,
+            inbreeding: HashMap::new(),
+        }
+    }
+
+    /// Coefficient of inbreeding of `id`: the kinship of their parents,
+    /// counting ancestors at most `max_generations` above `id`.
+    pub fn inbreeding(
+        &mut self,
+        id: &'a str,
+        max_generations: u32,
+    ) -> Result<Coefficient, InbreedingError> {
+        let (Some(father), Some(mother)) = parents(self.store, id) else {
+            return Ok(Coefficient {
+                coefficient: 0.0,
+                contributions: Vec::new(),
+            });
+        };
+        let Some(depth) = max_generations.checked_sub(1) else {
+            return Ok(Coefficient {
+                coefficient: 0.0,
+                contributions: Vec::new(),
+            });
+        };
+        self.kinship(father, mother, depth)
+    }
+
+    /// Coefficient of relationship between `a` and `b`:
+    /// `2 * kinship / sqrt((1 + F_a)(1 + F_b))`.
+    pub fn relationship(
+        &mut self,
+        a: &'a str,
+        b: &'a str,
+        max_generations: u32,
+    ) -> Result<Coefficient, InbreedingError> {
+        let kinship = self.kinship(a, b, max_generations)?;
+        let own_a = self.inbreeding(a, max_generations)?.coefficient;
+        let own_b = self.inbreeding(b, max_generations)?.coefficient;
+        let scale = 2.0 / ((1.0 + own_a) * (1.0 + own_b)).sqrt();
+        Ok(Coefficient {
+            coefficient: kinship.coefficient * scale,
+            contributions: kinship
+                .contributions
+                .into_iter()
+                .map(|contribution| Contribution {
+                    contribution: contribution.contribution * scale,
+                    ..contribution
+                })
+                .collect(),
+        })
+    }
+
+    /// Kinship (coancestry) of `a` and `b`: the sum over common ancestors A
+    /// and pairs of paths meeting only at A of `(1/2)^(n1 + n2 + 1) (1 + F_A)`,
+    /// with paths at most `depth` generations long.
+    fn kinship(
+        &mut self,
+        a: &'a str,
+        b: &'a str,
+        depth: u32,
+    ) -> Result<Coefficient, InbreedingError> {
+        let from_a = upward_paths(self.store, a, depth)?;
+        let from_b = upward_paths(self.store, b, depth)?;
+
+        let mut pairs = 0;
+        let mut contributions = Vec::new();
+        for (&ancestor, left) in &from_a {
+            let Some(right) = from_b.get(ancestor) else {
+                continue;
+            };
+            let mut lengths = Vec::new();
+            for first in left {
+                for second in right {
+                    pairs += 1;
+                    if pairs > MAX_PATH_PAIRS {
+                        return Err(InbreedingError::TooComplex);
+                    }
+                    let meet_only_at_ancestor = first[..first.len() - 1]
+                        .iter()
+                        .all(|person| !second.contains(person));
+                    if meet_only_at_ancestor {
+                        lengths.push(((first.len() - 1) as u32, (second.len() - 1) as u32));
+                    }
+                }
+            }
+            let Some(shortest) = lengths.iter().map(|(n1, n2)| n1 + n2).min() else {
+                continue;
+            };
+            // The ancestor's own inbreeding only counts generations still
+            // inside the window.
+            let deepest = lengths
+                .iter()
+                .map(|(n1, n2)| *n1.max(n2))
+                .max()
+                .unwrap_or(0);
+            let own = self.ancestor_inbreeding(ancestor, depth.saturating_sub(deepest))?;
+            let contribution = lengths
+                .iter()
+                .map(|(n1, n2)| 0.5f64.powi((n1 + n2 + 1) as i32) * (1.0 + own))
+                .sum();
+            contributions.push(Contribution {
+                ancestor: ancestor.to_owned(),
+                paths: lengths.len(),
+                shortest,
+                inbreeding: own,
+                contribution,
+            });
+        }
+
+        Ok(Coefficient {
+            coefficient: contributions.iter().map(|c| c.contribution).sum(),
+            contributions,
+        })
+    }
+
+    fn ancestor_inbreeding(
+        &mut self,
+        id: &'a str,
+        generations: u32,
+    ) -> Result<f64, InbreedingError> {
+        if let Some(&known) = self.inbreeding.get(&(id, generations)) {
+            return Ok(known);
+        }
+        let coefficient = self.inbreeding(id, generations)?.coefficient;
+        self.inbreeding.insert((id, generations), coefficient);
+        Ok(coefficient)
+    }
+}
+
+/// Every upward path from `start` (itself included) of at most `depth`
+/// generations, keyed by the ancestor it ends at. Paths never revisit a
+/// person, so loops in the data end them.
+fn upward_paths<'a>(
+    store: &'a GedcomStore,
+    start: &'a str,
+    depth: u32,
+) -> Result<Paths<'a>, InbreedingError> {
+    let mut paths = Paths::new();
+    let mut stack = vec![vec![start]];
+    let mut count = 0;
+    while let Some(path) = stack.pop() {
+        count += 1;
+        if count > MAX_PATHS {
+            return Err(InbreedingError::TooComplex);
+        }
+        let last = path[path.len() - 1];
+        if path.len() <= depth as usize {
+            let (father, mother) = parents(store, last);
+            for parent in [mother, father].into_iter().flatten() {
+                if !path.contains(&parent) {
+                    let mut longer = path.clone();
+                    longer.push(parent);
+                    stack.push(longer);
+                }
+            }
+        }
+        paths.entry(last).or_default().push(path);
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gedcom::{Family, GedcomData};
+
+    fn store(families: &[(&str, &str, &[&str])]) -> GedcomStore {
+        let families = families
+            .iter()
+            .enumerate()
+            .map(|(idx, (husband, wife, children))| Family {
+                id: format!("F{idx}"),
+                husband: Some((*husband).into()),
+                wife: Some((*wife).into()),
+                children: children.iter().map(|child| (*child).into()).collect(),
+                ..Default::default()
+            })
+            .collect();
+        GedcomStore::from_data(GedcomData {
+            families,
+            ..Default::default()
+        })
+    }
+
+    fn inbreeding(store: &GedcomStore, id: &str, generations: u32) -> f64 {
+        Calculator::new(store)
+            .inbreeding(id, generations)
+            .unwrap()
+            .coefficient
+    }
+
+    fn relationship(store: &GedcomStore, a: &str, b: &str) -> f64 {
+        Calculator::new(store)
+            .relationship(a, b, 10)
+            .unwrap()
+            .coefficient
+    }
+
+    #[test]
+    fn matches_textbook_inbreeding_coefficients() {
+        let full_siblings = store(&[("P1", "P2", &["S", "D"]), ("S", "D", &["X"])]);
+        assert_eq!(inbreeding(&full_siblings, "X", 10), 0.25);
+        assert_eq!(relationship(&full_siblings, "S", "D"), 0.5);
+
+        let half_siblings = store(&[
+            ("P1", "P2", &["S"]),
+            ("P1", "P3", &["D"]),
+            ("S", "D", &["X"]),
+        ]);
+        assert_eq!(inbreeding(&half_siblings, "X", 10), 0.125);
+        assert_eq!(relationship(&half_siblings, "S", "D"), 0.25);
+
+        let first_cousins = store(&[
+            ("G1", "G2", &["A", "B"]),
+            ("A", "W", &["S"]),
+            ("H", "B", &["D"]),
+            ("S", "D", &["X"]),
+        ]);
+        assert_eq!(inbreeding(&first_cousins, "X", 10), 0.0625);
+        assert_eq!(relationship(&first_cousins, "S", "D"), 0.125);
+        // The grandparents of S and D are three generations above X.
+        assert_eq!(inbreeding(&first_cousins, "X", 2), 0.0);
+        assert_eq!(inbreeding(&first_cousins, "X", 3), 0.0625);
+
+        let parent_offspring = store(&[("P", "M", &["D"]), ("P", "D", &["X"])]);
+        assert_eq!(inbreeding(&parent_offspring, "X", 10), 0.25);
+        assert_eq!(relationship(&parent_offspring, "P", "D"), 0.5);
+
+        let double_first_cousins = store(&[
+            ("G1", "G2", &["A", "B"]),
+            ("G3", "G4", &["C", "D"]),
+            ("A", "C", &["S"]),
+            ("B", "D", &["T"]),
+            ("S", "T", &["X"]),
+        ]);
+        assert_eq!(inbreeding(&double_first_cousins, "X", 10), 0.125);
+    }
+
+    #[test]
+    fn weights_inbred_common_ancestors_and_reports_contributions() {
+        // A is the child of full siblings (F_A = 1/4) and the common
+        // ancestor of half siblings S and D.
+        let store = store(&[
+            ("P1", "P2", &["Q1", "Q2"]),
+            ("Q1", "Q2", &["A"]),
+            ("A", "Y", &["S"]),
+            ("A", "Z", &["D"]),
+            ("S", "D", &["X"]),
+        ]);
+        let result = Calculator::new(&store).inbreeding("X", 10).unwrap();
+        assert_eq!(result.coefficient, 0.15625);
+        assert_eq!(
+            result.contributions,
+            vec![Contribution {
+                ancestor: "A".into(),
+                paths: 1,
+                shortest: 2,
+                inbreeding: 0.25,
+                contribution: 0.15625,
+            }]
+        );
+
+        // Relationship to oneself is 1 regardless of inbreeding.
+        assert_eq!(relationship(&store, "A", "A"), 1.0);
+    }
+
+    #[test]
+    fn terminates_on_loops() {
+        let store = store(&[("X", "M", &["F"]), ("F", "M", &["X"])]);
+        assert!(inbreeding(&store, "X", 20) > 0.0);
+    }
+}
@@ -0,0 +1,787 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::gedcom::{GedcomStore, Sex, sex_of};
+use crate::pedigree::other_spouse;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Blood,
+    Spouse,
+    Step,
+    InLaw,
+}
+
+/// Generations from each person up to their common ancestors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Generations {
+    pub from: u32,
+    pub to: u32,
+}
+
+/// One person on a connecting path, with the family linking them to the
+/// previous step.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PathStep {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub family: Option<String>,
+}
+
+/// How the first person relates to the second, e.g. `half-uncle`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Relationship {
+    pub term: String,
+    pub kind: Kind,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub common_ancestors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generations: Option<Generations>,
+    pub path: Vec<PathStep>,
+}
+
+/// Ancestor -> (generations up, child and family it was reached through).
+type Ancestry<'a> = HashMap<&'a str, (u32, Option<(&'a str, &'a str)>)>;
+
+/// Breadth-first ancestry of `id` (including itself at distance 0), up to
+/// `max_depth` generations. Each ancestor is visited once, so loops in the
+/// data terminate.
+fn ancestry<'a>(store: &'a GedcomStore, id: &'a str, max_depth: u32) -> Ancestry<'a> {
+    let mut found = Ancestry::from([(id, (0, None))]);
+    let mut queue = VecDeque::from([(id, 0)]);
+    while let Some((person, depth)) = queue.pop_front() {
+        if depth >= max_depth {
+            continue;
+        }
+        for family in store.families_as_child(person) {
+            for parent in [&family.husband, &family.wife].into_iter().flatten() {
+                if !found.contains_key(parent.as_str()) {
+                    found.insert(parent, (depth + 1, Some((person, &family.id))));
+                    queue.push_back((parent, depth + 1));
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Steps from the person `ancestry` was built for up to `ancestor`.
+fn path_up(ancestry: &Ancestry, ancestor: &str) -> Vec<PathStep> {
+    let mut chain = vec![ancestor];
+    let mut families = Vec::new();
+    let mut current = ancestor;
+    while let Some((_, Some((child, family)))) = ancestry.get(current) {
+        chain.push(child);
+        families.push(*family);
+        current = child;
+    }
+    chain.reverse();
+    families.reverse();
+    chain
+        .iter()
+        .enumerate()
+        .map(|(idx, id)| PathStep {
+            id: (*id).to_owned(),
+            family: idx
+                .checked_sub(1)
+                .map(|previous| families[previous].to_owned()),
+        })
+        .collect()
+}
+
+/// Blood relationships of `from` to `to`, one per group of most recent
+/// common ancestors (a couple counts as one group).
+fn blood(store: &GedcomStore, from: &str, to: &str, max_depth: u32) -> Vec<Relationship> {
+    let up_from = ancestry(store, from, max_depth);
+    let up_to = ancestry(store, to, max_depth);
+    let common = |id: &str| up_from.contains_key(id) && up_to.contains_key(id);
+
+    // Group the most recent common ancestors: a common ancestor none of
+    // whose children is also one. Both spouses reached through the same
+    // family form a full relationship, a lone ancestor a half one.
+    let mut groups: BTreeMap<(u32, u32, u32, &str), Vec<&str>> = BTreeMap::new();
+    for (&ancestor, &(up, link)) in &up_from {
+        let Some(&(down, other_link)) = up_to.get(ancestor) else {
+            continue;
+        };
+        let more_recent = store
+            .families_as_spouse(ancestor)
+            .flat_map(|family| &family.children)
+            .any(|child| common(child));
+        if more_recent {
+            continue;
+        }
+        let family = link.map(|(_, family)| family);
+        let full = up > 0 && down > 0 && family == other_link.map(|(_, family)| family);
+        let key = match (full, family) {
+            (true, Some(family)) => (up + down, up, down, family),
+            _ => (up + down, up, down, ancestor),
+        };
+        groups.entry(key).or_default().push(ancestor);
+    }
+
+    let sex = sex_of(store, from);
+    groups
+        .into_iter()
+        .map(|((_, up, down, _), mut ancestors)| {
+            ancestors.sort_unstable();
+            let half = up > 0 && down > 0 && ancestors.len() == 1 && {
+                let family = up_from[ancestors[0]].1.map(|(_, family)| family);
+                family != up_to[ancestors[0]].1.map(|(_, family)| family)
+            };
+            let mut path = path_up(&up_from, ancestors[0]);
+            let mut down_path = path_up(&up_to, ancestors[0]);
+            down_path.reverse();
+            // Walking down, each step's family links it to the next one.
+            for idx in (1..down_path.len()).rev() {
+                let family = down_path[idx - 1].family.take();
+                down_path[idx].family = family;
+            }
+            path.extend(down_path.into_iter().skip(1));
+            Relationship {
+                term: blood_term(up, down, half, sex),
+                kind: Kind::Blood,
+                common_ancestors: ancestors.into_iter().map(str::to_owned).collect(),
+                generations: Some(Generations { from: up, to: down }),
+                path,
+            }
+        })
+        .collect()
+}
+
+/// Every relationship of `from` to `to` found within `max_depth`
+/// generations: by blood, as spouses, and through one marriage (in-laws,
+/// step-parents and step-children) or two (step-siblings).
+pub fn relationships(
+    store: &GedcomStore,
+    from: &str,
+    to: &str,
+    max_depth: u32,
+) -> Vec<Relationship> {
+    let mut found = blood(store, from, to, max_depth);
+    let is_blood = |up: u32, down: u32| {
+        found
+            .iter()
+            .any(|r| r.generations == Some(Generations { from: up, to: down }))
+    };
+    let (is_parent, is_child) = (is_blood(0, 1), is_blood(1, 0));
+    let sex = sex_of(store, from);
+    let mut extra = Vec::new();
+
+    for (family, spouse) in spouses(store, from) {
+        let step = |id: &str| PathStep {
+            id: id.to_owned(),
+            family: Some(family.to_owned()),
+        };
+        if spouse == to {
+            extra.push(Relationship {
+                term: kin(sex, ["husband", "wife", "spouse"], str::to_owned),
+                kind: Kind::Spouse,
+                common_ancestors: Vec::new(),
+                generations: None,
+                path: vec![from_step(from), step(to)],
+            });
+            continue;
+        }
+        // `from` is married to a blood relative of `to`.
+        for relation in blood(store, spouse, to, max_depth) {
+            let Some(Generations { from: up, to: down }) = relation.generations else {
+                continue;
+            };
+            let (term, kind) = match (up, down) {
+                (0, 1) if is_parent => continue,
+                (0, 1) => (
+                    kin(
+                        sex,
+                        ["step-father", "step-mother", "step-parent"],
+                        str::to_owned,
+                    ),
+                    Kind::Step,
+                ),
+                (1, 0) => (
+                    kin(
+                        sex,
+                        ["son-in-law", "daughter-in-law", "child-in-law"],
+                        str::to_owned,
+                    ),
+                    Kind::InLaw,
+                ),
+                (1, 1) => (sibling_in_law(sex), Kind::InLaw),
+                _ => (
+                    format!(
+                        "{}'s {}",
+                        relation.term,
+                        kin(sex, ["husband", "wife", "spouse"], str::to_owned)
+                    ),
+                    Kind::InLaw,
+                ),
+            };
+            let mut path = relation.path;
+            path[0].family = Some(family.to_owned());
+            path.insert(0, from_step(from));
+            extra.push(Relationship {
+                term,
+                kind,
+                common_ancestors: relation.common_ancestors,
+                generations: None,
+                path,
+            });
+        }
+    }
+
+    for (family, spouse) in spouses(store, to) {
+        if spouse == from {
+            continue;
+        }
+        // `from` is a blood relative of the spouse of `to`.
+        for relation in blood(store, from, spouse, max_depth) {
+            let Some(Generations { from: up, to: down }) = relation.generations else {
+                continue;
+            };
+            let (term, kind) = match (up, down) {
+                (0, 1) => (
+                    kin(
+                        sex,
+                        ["father-in-law", "mother-in-law", "parent-in-law"],
+                        str::to_owned,
+                    ),
+                    Kind::InLaw,
+                ),
+                (1, 0) if is_child => continue,
+                (1, 0) => (
+                    kin(
+                        sex,
+                        ["step-son", "step-daughter", "step-child"],
+                        str::to_owned,
+                    ),
+                    Kind::Step,
+                ),
+                (1, 1) => (sibling_in_law(sex), Kind::InLaw),
+                _ => (format!("spouse's {}", relation.term), Kind::InLaw),
+            };
+            let mut path = relation.path;
+            path.push(PathStep {
+                id: to.to_owned(),
+                family: Some(family.to_owned()),
+            });
+            extra.push(Relationship {
+                term,
+                kind,
+                common_ancestors: relation.common_ancestors,
+                generations: None,
+                path,
+            });
+        }
+    }
+
+    if !is_blood(1, 1) {
+        extra.extend(step_sibling(store, from, to, sex));
+    }
+    extra.sort_by_key(|relation| relation.kind);
+    found.extend(extra);
+    found
+}
+
+fn from_step(id: &str) -> PathStep {
+    PathStep {
+        id: id.to_owned(),
+        family: None,
+    }
+}
+
+/// (family, spouse) pairs of `id`.
+fn spouses<'a>(store: &'a GedcomStore, id: &'a str) -> Vec<(&'a str, &'a str)> {
+    store
+        .families_as_spouse(id)
+        .filter_map(|family| Some((family.id.as_str(), other_spouse(family, id)?)))
+        .collect()
+}
+
+/// Children of two parents married to each other, sharing no parent.
+fn step_sibling(
+    store: &GedcomStore,
+    from: &str,
+    to: &str,
+    sex: Option<Sex>,
+) -> Option<Relationship> {
+    let parents = |id: &str| -> Vec<(String, String)> {
+        store
+            .families_as_child(id)
+            .flat_map(|family| {
+                [&family.husband, &family.wife]
+                    .into_iter()
+                    .flatten()
+                    .map(|parent| (parent.clone(), family.id.clone()))
+            })
+            .collect()
+    };
+    let (own, theirs) = (parents(from), parents(to));
+    if own
+        .iter()
+        .any(|(parent, _)| theirs.iter().any(|(other, _)| other == parent))
+    {
+        return None;
+    }
+    for (parent, family) in &own {
+        for (marriage, spouse) in spouses(store, parent) {
+            let Some((_, other_family)) = theirs.iter().find(|(other, _)| other == spouse) else {
+                continue;
+            };
+            let step = |id: &str, family: &str| PathStep {
+                id: id.to_owned(),
+                family: Some(family.to_owned()),
+            };
+            return Some(Relationship {
+                term: kin(
+                    sex,
+                    ["step-brother", "step-sister", "step-sibling"],
+                    str::to_owned,
+                ),
+                kind: Kind::Step,
+                common_ancestors: Vec::new(),
+                generations: None,
+                path: vec![
+                    from_step(from),
+                    step(parent, family),
+                    step(spouse, marriage),
+                    step(to, other_family),
+                ],
+            });
+        }
+    }
+    None
+}
+
+/// A link followed by `find_connection`: the next person is the previous
+/// one's parent, child, spouse or sibling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Edge {
+    Parent,
+    Child,
+    Spouse,
+    Sibling,
+}
+
+impl Edge {
+    pub const ALL: [Edge; 4] = [Edge::Parent, Edge::Child, Edge::Spouse, Edge::Sibling];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "parent" => Some(Edge::Parent),
+            "child" => Some(Edge::Child),
+            "spouse" => Some(Edge::Spouse),
+            "sibling" => Some(Edge::Sibling),
+            _ => None,
+        }
+    }
+
+    fn inverse(self) -> Self {
+        match self {
+            Edge::Parent => Edge::Child,
+            Edge::Child => Edge::Parent,
+            edge => edge,
+        }
+    }
+
+    /// The term for someone who is the previous person's `self`.
+    pub fn term(self, sex: Option<Sex>) -> String {
+        let forms = match self {
+            Edge::Parent => ["father", "mother", "parent"],
+            Edge::Child => ["son", "daughter", "child"],
+            Edge::Spouse => ["husband", "wife", "spouse"],
+            Edge::Sibling => ["brother", "sister", "sibling"],
+        };
+        kin(sex, forms, str::to_owned)
+    }
+
+    /// People who are `id`'s `self`, with the family linking them.
+    fn neighbors<'a>(self, store: &'a GedcomStore, id: &str) -> Vec<(&'a str, &'a str)> {
+        let mut found = Vec::new();
+        match self {
+            Edge::Parent => {
+                for family in store.families_as_child(id) {
+                    for parent in [&family.husband, &family.wife].into_iter().flatten() {
+                        found.push((parent.as_str(), family.id.as_str()));
+                    }
+                }
+            }
+            Edge::Child => {
+                for family in store.families_as_spouse(id) {
+                    for child in &family.children {
+                        found.push((child.as_str(), family.id.as_str()));
+                    }
+                }
+            }
+            Edge::Spouse => {
+                for family in store.families_as_spouse(id) {
+                    found.extend(
+                        other_spouse(family, id).map(|spouse| (spouse, family.id.as_str())),
+                    );
+                }
+            }
+            Edge::Sibling => {
+                for family in store.families_as_child(id) {
+                    for child in family.children.iter().filter(|child| *child != id) {
+                        found.push((child.as_str(), family.id.as_str()));
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+/// One link of a connection: `id` is the previous person's `edge`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Hop {
+    pub id: String,
+    pub edge: Edge,
+    pub family: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Connection {
+    /// Links from the first person to the second; empty if they are the same.
+    Found(Vec<Hop>),
+    /// One side's whole reachable subtree was searched without meeting the
+    /// other.
+    Disconnected,
+    HopLimit,
+}
+
+/// Person -> (hops from the search origin, neighbor towards the origin,
+/// edge and family of the link between them).
+type Visited<'a> = HashMap<&'a str, (u32, Option<(&'a str, Edge, &'a str)>)>;
+
+/// Shortest chain of `edges` from `from` to `to`, found by breadth-first
+/// search from both ends, always widening the smaller frontier.
+pub fn connection(
+    store: &GedcomStore,
+    from: &str,
+    to: &str,
+    edges: &[Edge],
+    max_hops: u32,
+) -> Connection {
+    if from == to {
+        return Connection::Found(Vec::new());
+    }
+    let mut forward = Visited::from([(from, (0, None))]);
+    let mut backward = Visited::from([(to, (0, None))]);
+    let (mut front, mut back) = (vec![from], vec![to]);
+    let mut hops = 0;
+    loop {
+        if front.is_empty() || back.is_empty() {
+            return Connection::Disconnected;
+        }
+        if hops >= max_hops {
+            return Connection::HopLimit;
+        }
+        let expand_forward = front.len() <= back.len();
+        let (frontier, seen, other) = if expand_forward {
+            (&mut front, &mut forward, &backward)
+        } else {
+            (&mut back, &mut backward, &forward)
+        };
+
+        let mut next = Vec::new();
+        let mut meeting: Option<(u32, &str)> = None;
+        for person in std::mem::take(frontier) {
+            let depth = seen[person].0 + 1;
+            for &edge in edges {
+                // Walking backwards, look for people whose `edge` is `person`.
+                let step = if expand_forward { edge } else { edge.inverse() };
+                for (neighbor, family) in step.neighbors(store, person) {
+                    if seen.contains_key(neighbor) {
+                        continue;
+                    }
+                    seen.insert(neighbor, (depth, Some((person, edge, family))));
+                    next.push(neighbor);
+                    if let Some(&(distance, _)) = other.get(neighbor)
+                        && meeting.is_none_or(|(best, _)| distance < best)
+                    {
+                        meeting = Some((distance, neighbor));
+                    }
+                }
+            }
+        }
+        *frontier = next;
+        hops += 1;
+
+        if let Some((_, middle)) = meeting {
+            return Connection::Found(join(&forward, &backward, middle));
+        }
+    }
+}
+
+/// Joins both halves of a bidirectional search at `middle`.
+fn join(forward: &Visited, backward: &Visited, middle: &str) -> Vec<Hop> {
+    let mut hops = Vec::new();
+    let mut current = middle;
+    while let Some((_, Some((previous, edge, family)))) = forward.get(current) {
+        hops.push(Hop {
+            id: current.to_owned(),
+            edge: *edge,
+            family: (*family).to_owned(),
+        });
+        current = previous;
+    }
+    hops.reverse();
+    let mut current = middle;
+    while let Some((_, Some((next, edge, family)))) = backward.get(current) {
+        hops.push(Hop {
+            id: (*next).to_owned(),
+            edge: *edge,
+            family: (*family).to_owned(),
+        });
+        current = next;
+    }
+    hops
+}
+
+/// Picks the male, female or neutral form, decorating each. Without a
+/// neutral form the unknown case reads "uncle or aunt".
+fn kin(
+    sex: Option<Sex>,
+    [male, female, neutral]: [&str; 3],
+    decorate: impl Fn(&str) -> String,
+) -> String {
+    match sex {
+        Some(Sex::Male) => decorate(male),
+        Some(Sex::Female) => decorate(female),
+        _ if !neutral.is_empty() => decorate(neutral),
+        _ => format!("{} or {}", decorate(male), decorate(female)),
+    }
+}
+
+fn sibling_in_law(sex: Option<Sex>) -> String {
+    kin(
+        sex,
+        ["brother-in-law", "sister-in-law", "sibling-in-law"],
+        str::to_owned,
+    )
+}
+
+/// Canonical English term for the person described, whose common ancestor
+/// with the other person is `up` generations above them and `down`
+/// generations above the other.
+pub fn blood_term(up: u32, down: u32, half: bool, sex: Option<Sex>) -> String {
+    let term = match (up, down) {
+        (0, 0) => "self".to_owned(),
+        (0, 1) => kin(sex, ["father", "mother", "parent"], str::to_owned),
+        (0, n) => kin(sex, ["grandfather", "grandmother", "grandparent"], |base| {
+            greats(n - 2, base)
+        }),
+        (1, 0) => kin(sex, ["son", "daughter", "child"], str::to_owned),
+        (n, 0) => kin(sex, ["grandson", "granddaughter", "grandchild"], |base| {
+            greats(n - 2, base)
+        }),
+        (1, 1) => kin(sex, ["brother", "sister", "sibling"], str::to_owned),
+        (1, n) => kin(sex, ["uncle", "aunt", ""], |base| greats(n - 2, base)),
+        (2, 1) => kin(sex, ["nephew", "niece", ""], str::to_owned),
+        (n, 1) => kin(sex, ["grandnephew", "grandniece", ""], |base| {
+            greats(n - 3, base)
+        }),
+        (up, down) => {
+            let removed = match up.abs_diff(down) {
+                0 => String::new(),
+                1 => " once removed".to_owned(),
+                2 => " twice removed".to_owned(),
+                n => format!(" {n} times removed"),
+            };
+            format!("{} cousin{removed}", ordinal(up.min(down) - 1))
+        }
+    };
+    match (half, up.min(down)) {
+        (false, _) => term,
+        (true, 1) if !term.contains(' ') && !term.starts_with("great") => format!("half-{term}"),
+        (true, _) => format!("half {term}"),
+    }
+}
+
+fn greats(count: u32, base: &str) -> String {
+    match count {
+        0 => base.to_owned(),
+        1 => format!("great-{base}"),
+        n => format!("{} great-{base}", ordinal(n)),
+    }
+}
+
+fn ordinal(n: u32) -> String {
+    const WORDS: [&str; 10] = [
+        "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth",
+        "tenth",
+    ];
+    if let Some(word) = n.checked_sub(1).and_then(|idx| WORDS.get(idx as usize)) {
+        return (*word).to_owned();
+    }
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gedcom::{Family, GedcomData};
+
+    fn store() -> GedcomStore {
+        let family = |id: &str, husband: &str, wife: &str, children: &[&str]| Family {
+            id: id.into(),
+            husband: Some(husband.into()),
+            wife: Some(wife.into()),
+            children: children.iter().map(|child| (*child).into()).collect(),
+            ..Default::default()
+        };
+        GedcomStore::from_data(GedcomData {
+            families: vec![
+                family("F1", "G1", "G2", &["P1", "P2"]),
+                family("F2", "G1", "G3", &["P3"]),
+                family("F3", "P1", "W1", &["C1"]),
+                family("F4", "H2", "P2", &["C2"]),
+                family("F5", "C1", "W3", &["D1"]),
+                family("F6", "P3", "W4", &["C3"]),
+                family("F7", "H9", "W4", &["X1"]),
+                family("F8", "P3", "W5", &["C4"]),
+            ],
+            ..Default::default()
+        })
+    }
+
+    fn terms(from: &str, to: &str) -> Vec<(String, Kind)> {
+        relationships(&store(), from, to, 10)
+            .into_iter()
+            .map(|relationship| (relationship.term, relationship.kind))
+            .collect()
+    }
+
+    #[test]
+    fn names_blood_relationships() {
+        let male = Some(Sex::Male);
+        let female = Some(Sex::Female);
+        assert_eq!(blood_term(0, 0, false, None), "self");
+        assert_eq!(blood_term(0, 4, false, male), "second great-grandfather");
+        assert_eq!(blood_term(2, 0, false, female), "granddaughter");
+        assert_eq!(blood_term(1, 1, true, male), "half-brother");
+        assert_eq!(blood_term(1, 2, true, male), "half-uncle");
+        assert_eq!(blood_term(1, 3, false, None), "great-uncle or great-aunt");
+        assert_eq!(blood_term(3, 1, false, female), "grandniece");
+        assert_eq!(blood_term(2, 2, false, None), "first cousin");
+        assert_eq!(blood_term(4, 3, false, None), "second cousin once removed");
+        assert_eq!(
+            blood_term(3, 5, true, None),
+            "half second cousin twice removed"
+        );
+        assert_eq!(blood_term(13, 13, false, None), "12th cousin");
+    }
+
+    #[test]
+    fn finds_common_ancestors_and_the_connecting_path() {
+        let found = relationships(&store(), "D1", "C2", 10);
+        assert_eq!(found.len(), 1);
+        let cousin = &found[0];
+        assert_eq!(cousin.term, "first cousin once removed");
+        assert_eq!(cousin.common_ancestors, vec!["G1", "G2"]);
+        assert_eq!(cousin.generations, Some(Generations { from: 3, to: 2 }));
+        let path: Vec<(&str, Option<&str>)> = cousin
+            .path
+            .iter()
+            .map(|step| (step.id.as_str(), step.family.as_deref()))
+            .collect();
+        assert_eq!(
+            path,
+            vec![
+                ("D1", None),
+                ("C1", Some("F5")),
+                ("P1", Some("F3")),
+                ("G1", Some("F1")),
+                ("P2", Some("F1")),
+                ("C2", Some("F4")),
+            ]
+        );
+
+        // Out of reach with a shallow search.
+        assert!(relationships(&store(), "D1", "C2", 2).is_empty());
+    }
+
+    #[test]
+    fn names_half_step_and_in_law_relationships() {
+        assert_eq!(terms("P3", "C1"), vec![("half-uncle".into(), Kind::Blood)]);
+        assert_eq!(
+            terms("W1", "G1"),
+            vec![("daughter-in-law".into(), Kind::InLaw)]
+        );
+        assert_eq!(terms("G3", "P1"), vec![("step-mother".into(), Kind::Step)]);
+        assert_eq!(terms("P1", "G3"), vec![("step-son".into(), Kind::Step)]);
+        assert_eq!(
+            terms("G1", "W3"),
+            vec![("spouse's grandfather".into(), Kind::InLaw)]
+        );
+        assert_eq!(
+            terms("H2", "P1"),
+            vec![("brother-in-law".into(), Kind::InLaw)]
+        );
+        assert_eq!(
+            terms("H2", "C1"),
+            vec![("aunt's husband".into(), Kind::InLaw)]
+        );
+        assert_eq!(terms("X1", "C4"), vec![("step-sibling".into(), Kind::Step)]);
+        assert_eq!(terms("W1", "P1"), vec![("wife".into(), Kind::Spouse)]);
+        // A parent married to the other parent is not a step-parent.
+        assert_eq!(terms("G2", "P1"), vec![("mother".into(), Kind::Blood)]);
+    }
+
+    fn chain(from: &str, to: &str, edges: &[Edge], max_hops: u32) -> Option<Vec<(String, Edge)>> {
+        match connection(&store(), from, to, edges, max_hops) {
+            Connection::Found(hops) => {
+                Some(hops.into_iter().map(|hop| (hop.id, hop.edge)).collect())
+            }
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn finds_the_shortest_connection_through_marriages() {
+        assert_eq!(
+            chain("W1", "C2", &Edge::ALL, 10),
+            Some(vec![
+                ("P1".into(), Edge::Spouse),
+                ("P2".into(), Edge::Sibling),
+                ("C2".into(), Edge::Child),
+            ])
+        );
+        assert_eq!(
+            chain("W1", "C2", &[Edge::Parent, Edge::Child], 10).map(|hops| hops.len()),
+            Some(5)
+        );
+
+        // Directed edges are followed the right way round from both ends.
+        assert_eq!(
+            chain("D1", "G1", &[Edge::Parent], 10).map(|hops| hops.len()),
+            Some(3)
+        );
+        assert_eq!(
+            connection(&store(), "G1", "D1", &[Edge::Parent], 10),
+            Connection::Disconnected
+        );
+
+        assert_eq!(
+            connection(&store(), "W1", "C2", &Edge::ALL, 2),
+            Connection::HopLimit
+        );
+        assert_eq!(
+            connection(&store(), "W1", "Z9", &Edge::ALL, 10),
+            Connection::Disconnected
+        );
+        assert_eq!(
+            connection(&store(), "W1", "W1", &Edge::ALL, 0),
+            Connection::Found(Vec::new())
+        );
+    }
+}
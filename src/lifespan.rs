@@ -0,0 +1,404 @@
+use serde::Serialize;
+
+use crate::date::{GedcomDate, completed_years};
+use crate::gedcom::{Event, GedcomStore, Individual};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgeEvidence {
+    Dates,
+    AgeTag,
+    DatesAndAgeTag,
+}
+
+/// An age in completed years. Uncertain dates yield a range rather than a
+/// single number; an open bound is `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AgeRange {
+    pub min_years: Option<i64>,
+    pub max_years: Option<i64>,
+    pub description: String,
+    pub evidence: AgeEvidence,
+    /// Set when the recorded `AGE` disagrees with the dates; the range is
+    /// then computed from the dates alone.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub conflict: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EventAge {
+    pub tag: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub family: Option<String>,
+    pub age: AgeRange,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Lifespan {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age_at_death: Option<AgeRange>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub event_ages: Vec<EventAge>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub father_age_at_birth: Option<AgeRange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mother_age_at_birth: Option<AgeRange>,
+}
+
+impl Lifespan {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+type Bounds = (Option<i64>, Option<i64>);
+
+impl AgeRange {
+    fn new(bounds: Bounds, evidence: AgeEvidence) -> Option<Self> {
+        let description = match bounds {
+            (Some(min), Some(max)) if min == max => min.to_string(),
+            (Some(min), Some(max)) => format!("between {min} and {max}"),
+            (Some(min), None) => format!("at least {min}"),
+            (None, Some(max)) => format!("at most {max}"),
+            (None, None) => return None,
+        };
+        Some(Self {
+            min_years: bounds.0,
+            max_years: bounds.1,
+            description,
+            evidence,
+            conflict: false,
+        })
+    }
+}
+
+fn parsed_date(event: Option<&Event>) -> Option<GedcomDate> {
+    GedcomDate::parse(event?.date.as_deref()?).ok()
+}
+
+/// Age range implied by a birth date and a later date: the youngest possible
+/// age uses the latest birth and earliest event, the oldest the reverse.
+fn bounds_from_dates(birth: &GedcomDate, at: &GedcomDate) -> Bounds {
+    let min = birth
+        .latest_jdn
+        .zip(at.earliest_jdn)
+        .map(|(born, at)| completed_years(born, at));
+    let max = birth
+        .earliest_jdn
+        .zip(at.latest_jdn)
+        .map(|(born, at)| completed_years(born, at));
+    match (min, max) {
+        (Some(min), max) if max.is_none_or(|max| max >= 0) => (Some(min.max(0)), max),
+        other => other,
+    }
+}
+
+/// Parses a GEDCOM `AGE` value such as `72y 3m`, `> 60y`, `< 1y` or the
+/// keywords `CHILD`, `INFANT` and `STILLBORN` into completed-year bounds.
+fn parse_age(value: &str) -> Option<Bounds> {
+    let value = value.trim().to_ascii_uppercase();
+    match value.as_str() {
+        "CHILD" => return Some((Some(0), Some(7))),
+        "INFANT" | "STILLBORN" => return Some((Some(0), Some(0))),
+        _ => {}
+    }
+
+    let (qualifier, rest) = match value.strip_prefix(['<', '>']) {
+        Some(rest) => (value.chars().next(), rest.trim()),
+        None => (None, value.as_str()),
+    };
+    let (mut years, mut remainder) = (0, false);
+    let mut parts = 0;
+    for part in rest.split_whitespace() {
+        let (number, unit) = part.split_at(
+            part.find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(part.len()),
+        );
+        let number: i64 = number.parse().ok()?;
+        match unit {
+            "Y" | "" => years = number,
+            "M" | "D" | "W" => remainder |= number > 0,
+            _ => return None,
+        }
+        parts += 1;
+    }
+    if parts == 0 {
+        return None;
+    }
+
+    Some(match qualifier {
+        Some('>') => (Some(years), None),
+        Some('<') if remainder => (Some(0), Some(years)),
+        Some('<') => (Some(0), Some(years - 1)),
+        _ => (Some(years), Some(years)),
+    })
+}
+
+/// Age at an arbitrary date given the individual's birth event.
+pub fn age_at_date(birth: Option<&Event>, at: &GedcomDate) -> Option<AgeRange> {
+    let birth = parsed_date(birth)?;
+    AgeRange::new(bounds_from_dates(&birth, at), AgeEvidence::Dates)
+}
+
+/// Age at an event, combining the birth and event dates with any `AGE`
+/// recorded on the event.
+pub fn age_at_event(birth: Option<&Event>, event: &Event) -> Option<AgeRange> {
+    let from_dates = parsed_date(birth)
+        .zip(parsed_date(Some(event)))
+        .map(|(birth, at)| bounds_from_dates(&birth, &at))
+        .filter(|bounds| *bounds != (None, None));
+    let from_tag = event.age.as_deref().and_then(parse_age);
+
+    match (from_dates, from_tag) {
+        (Some(dates), Some(tag)) => {
+            let min = dates.0.max(tag.0);
+            let max = match (dates.1, tag.1) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            if min.zip(max).is_none_or(|(min, max)| min <= max) {
+                AgeRange::new((min, max), AgeEvidence::DatesAndAgeTag)
+            } else {
+                AgeRange::new(dates, AgeEvidence::Dates).map(|range| AgeRange {
+                    conflict: true,
+                    ..range
+                })
+            }
+        }
+        (Some(dates), None) => AgeRange::new(dates, AgeEvidence::Dates),
+        (None, Some(tag)) => AgeRange::new(tag, AgeEvidence::AgeTag),
+        (None, None) => None,
+    }
+}
+
+/// Oldest age at which someone with no recorded death is still presumed
+/// living.
+const MAX_PRESUMED_AGE: i64 = 110;
+
+/// Whether an individual is treated as living for privacy purposes: no death,
+/// burial or cremation is recorded, a `_LIVING` flag does not say otherwise,
+/// and their birth could be less than 110 years before `today_jdn`.
+pub fn is_presumed_living(individual: &Individual, today_jdn: i64) -> bool {
+    let died = individual.death.is_some()
+        || individual
+            .events
+            .iter()
+            .any(|event| matches!(event.tag.as_deref(), Some("BURI" | "CREM")));
+    if died {
+        return false;
+    }
+    if let Some(living) = individual.vendor.living {
+        return living;
+    }
+    parsed_date(individual.birth.as_ref())
+        .map(|birth| birth.latest_jdn)
+        .is_none_or(|born| {
+            born.is_none_or(|born| completed_years(born, today_jdn) < MAX_PRESUMED_AGE)
+        })
+}
+
+/// Collects the ages that can be derived for an individual: at death, at
+/// each of their own and their families' events, and of each parent at
+/// their birth.
+pub fn lifespan(store: &GedcomStore, individual: &Individual) -> Lifespan {
+    let birth = individual.birth.as_ref();
+    let mut lifespan = Lifespan {
+        age_at_death: individual
+            .death
+            .as_ref()
+            .and_then(|death| age_at_event(birth, death)),
+        ..Default::default()
+    };
+
+    for event in &individual.events {
+        if let Some(age) = age_at_event(birth, event) {
+            lifespan.event_ages.push(EventAge {
+                tag: event.tag.clone().unwrap_or_default(),
+                date: event.date.clone(),
+                family: None,
+                age,
+            });
+        }
+    }
+
+    for family in store.families_as_spouse(&individual.id) {
+        for event in &family.events {
+            // Family events carry spouse ages on HUSB/WIFE substructures,
+            // so only the dates are used here.
+            let event = Event {
+                age: None,
+                ..event.clone()
+            };
+            if let Some(age) = age_at_event(birth, &event) {
+                lifespan.event_ages.push(EventAge {
+                    tag: event.tag.clone().unwrap_or_default(),
+                    date: event.date.clone(),
+                    family: Some(family.id.clone()),
+                    age,
+                });
+            }
+        }
+    }
+
+    if let Some(child_birth) = parsed_date(birth) {
+        for family in store.families_as_child(&individual.id) {
+            let parent_age = |parent: &Option<String>| {
+                let parent = store.get_individual(parent.as_deref()?)?;
+                age_at_date(parent.birth.as_ref(), &child_birth)
+            };
+            if lifespan.father_age_at_birth.is_none() {
+                lifespan.father_age_at_birth = parent_age(&family.husband);
+            }
+            if lifespan.mother_age_at_birth.is_none() {
+                lifespan.mother_age_at_birth = parent_age(&family.wife);
+            }
+        }
+    }
+
+    lifespan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gedcom::{Family, GedcomData};
+
+    fn event(date: &str) -> Event {
+        Event {
+            date: Some(date.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn computes_exact_and_uncertain_ages() {
+        let birth = event("15 MAR 1900");
+        let age = age_at_event(Some(&birth), &event("20 JUN 1972")).unwrap();
+        assert_eq!((age.min_years, age.max_years), (Some(72), Some(72)));
+        assert_eq!(age.description, "72");
+
+        let age = age_at_event(Some(&event("1900")), &event("1972")).unwrap();
+        assert_eq!((age.min_years, age.max_years), (Some(71), Some(72)));
+        assert_eq!(age.description, "between 71 and 72");
+
+        let age = age_at_event(Some(&event("ABT 1900")), &event("BEF 1950")).unwrap();
+        assert_eq!((age.min_years, age.max_years), (None, Some(49)));
+        assert_eq!(age.description, "at most 49");
+    }
+
+    #[test]
+    fn honours_age_tags() {
+        let with_tag = |date: Option<&str>, age: &str| Event {
+            date: date.map(str::to_owned),
+            age: Some(age.into()),
+            ..Default::default()
+        };
+
+        let age = age_at_event(None, &with_tag(None, "72y 3m")).unwrap();
+        assert_eq!((age.min_years, age.max_years), (Some(72), Some(72)));
+        assert_eq!(age.evidence, AgeEvidence::AgeTag);
+
+        let age = age_at_event(Some(&event("1900")), &with_tag(Some("1972"), "72y")).unwrap();
+        assert_eq!((age.min_years, age.max_years), (Some(72), Some(72)));
+        assert_eq!(age.evidence, AgeEvidence::DatesAndAgeTag);
+
+        let age = age_at_event(Some(&event("1900")), &with_tag(Some("1972"), "40y")).unwrap();
+        assert_eq!((age.min_years, age.max_years), (Some(71), Some(72)));
+        assert!(age.conflict);
+
+        assert_eq!(parse_age("> 60y"), Some((Some(60), None)));
+        assert_eq!(parse_age("< 1y"), Some((Some(0), Some(0))));
+        assert_eq!(parse_age("< 10y 6m"), Some((Some(0), Some(10))));
+        assert_eq!(parse_age("INFANT"), Some((Some(0), Some(0))));
+        assert_eq!(parse_age("old"), None);
+    }
+
+    #[test]
+    fn reports_lifespan_with_parent_ages() {
+        let store = GedcomStore::from_data(GedcomData {
+            individuals: vec![
+                Individual {
+                    id: "I1".into(),
+                    birth: Some(event("1 JAN 1850")),
+                    ..Default::default()
+                },
+                Individual {
+                    id: "I2".into(),
+                    birth: Some(event("ABT 1855")),
+                    ..Default::default()
+                },
+                Individual {
+                    id: "I3".into(),
+                    birth: Some(event("10 JUN 1880")),
+                    death: Some(event("BET 1950 AND 1952")),
+                    events: vec![Event {
+                        tag: Some("OCCU".into()),
+                        value: Some("Farmer".into()),
+                        age: Some("30y".into()),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+            ],
+            families: vec![Family {
+                id: "F1".into(),
+                husband: Some("I1".into()),
+                wife: Some("I2".into()),
+                children: vec!["I3".into()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+
+        let child = store.get_individual("I3").unwrap();
+        let lifespan = lifespan(&store, child);
+        let death = lifespan.age_at_death.unwrap();
+        assert_eq!((death.min_years, death.max_years), (Some(69), Some(72)));
+        assert_eq!(lifespan.event_ages.len(), 1);
+        assert_eq!(lifespan.event_ages[0].tag, "OCCU");
+        assert_eq!(lifespan.father_age_at_birth.unwrap().description, "30");
+        assert_eq!(
+            lifespan.mother_age_at_birth.unwrap().description,
+            "between 24 and 25"
+        );
+    }
+
+    #[test]
+    fn presumes_living_without_evidence_of_death() {
+        let today = crate::date::GedcomDate::parse("1 JAN 2025")
+            .unwrap()
+            .earliest_jdn
+            .unwrap();
+        let person = |birth: Option<&str>| Individual {
+            birth: birth.map(event),
+            ..Default::default()
+        };
+
+        assert!(is_presumed_living(&person(None), today));
+        assert!(is_presumed_living(&person(Some("1990")), today));
+        assert!(is_presumed_living(&person(Some("AFT 1800")), today));
+        assert!(!is_presumed_living(&person(Some("1900")), today));
+        assert!(!is_presumed_living(
+            &Individual {
+                death: Some(Event::default()),
+                ..person(Some("1990"))
+            },
+            today
+        ));
+        assert!(!is_presumed_living(
+            &Individual {
+                events: vec![Event {
+                    tag: Some("BURI".into()),
+                    ..Default::default()
+                }],
+                ..person(None)
+            },
+            today
+        ));
+        let mut flagged = person(None);
+        flagged.vendor.living = Some(false);
+        assert!(!is_presumed_living(&flagged, today));
+    }
+}
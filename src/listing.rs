@@ -0,0 +1,335 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use crate::date::GedcomDate;
+use crate::gedcom::{Family, GedcomStore, Individual, Sex, sex_of};
+use crate::names::{searchable_names, surname};
+use crate::place::place_key;
+use crate::text::fold;
+
+/// Order of `list_individuals` / `list_families`. Families sort by the
+/// husband, or the wife when there is none; ties and records missing the key
+/// (listed last) fall back to the ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    #[default]
+    Id,
+    Surname,
+    Birth,
+}
+
+impl SortKey {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "id" => Some(Self::Id),
+            "surname" | "name" => Some(Self::Surname),
+            "birth" | "birth_date" => Some(Self::Birth),
+            _ => None,
+        }
+    }
+}
+
+/// Filters of the list tools. A family is listed when one of its spouses
+/// matches all of them.
+#[derive(Debug, Clone, Default)]
+pub struct PersonFilter {
+    /// Julian day numbers the birth date must reach, inclusive. Individuals
+    /// without a usable birth date never match a date filter.
+    pub born_after: Option<i64>,
+    pub born_before: Option<i64>,
+    /// Place keys the death place must be one of.
+    pub died_at: Option<HashSet<String>>,
+    /// Only individuals with no known parent.
+    pub no_parents: bool,
+    pub sex: Option<Sex>,
+    /// Folded surname, compared with the birth and married surnames.
+    pub surname: Option<String>,
+}
+
+impl PersonFilter {
+    pub fn is_empty(&self) -> bool {
+        self.born_after.is_none()
+            && self.born_before.is_none()
+            && self.died_at.is_none()
+            && !self.no_parents
+            && self.sex.is_none()
+            && self.surname.is_none()
+    }
+
+    pub fn matches(&self, store: &GedcomStore, individual: &Individual) -> bool {
+        if (self.born_after.is_some() || self.born_before.is_some())
+            && !self.born_in_range(individual)
+        {
+            return false;
+        }
+        if let Some(places) = &self.died_at {
+            let place = individual
+                .death
+                .as_ref()
+                .and_then(|death| death.place.as_deref());
+            if !place.is_some_and(|place| places.contains(&place_key(place))) {
+                return false;
+            }
+        }
+        if self.no_parents
+            && store
+                .families_as_child(&individual.id)
+                .any(|family| family.husband.is_some() || family.wife.is_some())
+        {
+            return false;
+        }
+        if self.sex.is_some() && sex_of(store, &individual.id) != self.sex {
+            return false;
+        }
+        if let Some(wanted) = &self.surname
+            && !searchable_names(individual)
+                .iter()
+                .any(|name| surname(name).as_ref() == Some(wanted))
+        {
+            return false;
+        }
+        true
+    }
+
+    fn born_in_range(&self, individual: &Individual) -> bool {
+        let Some(date) = individual
+            .birth
+            .as_ref()
+            .and_then(|birth| birth.date.as_deref())
+            .and_then(|date| GedcomDate::parse(date).ok())
+        else {
+            return false;
+        };
+        if date.earliest_jdn.is_none() && date.latest_jdn.is_none() {
+            return false;
+        }
+        let after = match (self.born_after, date.latest_jdn) {
+            (Some(after), Some(latest)) => latest >= after,
+            _ => true,
+        };
+        let before = match (self.born_before, date.earliest_jdn) {
+            (Some(before), Some(earliest)) => earliest <= before,
+            _ => true,
+        };
+        after && before
+    }
+}
+
+/// Sort value of one record; `Missing` orders after every present value.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Key {
+    Name(String, String),
+    Day(i64),
+    Missing,
+}
+
+fn key(individual: Option<&Individual>, sort: SortKey) -> Key {
+    let name = individual.and_then(|individual| individual.name.as_deref());
+    match sort {
+        SortKey::Id => Key::Missing,
+        SortKey::Surname => match name.and_then(surname) {
+            Some(surname) => Key::Name(surname, name.map(fold).unwrap_or_default()),
+            None => Key::Missing,
+        },
+        SortKey::Birth => individual
+            .and_then(|individual| individual.birth.as_ref())
+            .and_then(|birth| birth.date.as_deref())
+            .and_then(|date| GedcomDate::parse(date).ok())
+            .and_then(|date| date.earliest_jdn.or(date.latest_jdn))
+            .map_or(Key::Missing, Key::Day),
+    }
+}
+
+/// Individuals matching `filter`, in `sort` order.
+pub fn individuals<'a>(
+    store: &'a GedcomStore,
+    filter: &PersonFilter,
+    sort: SortKey,
+) -> Vec<&'a Individual> {
+    let mut found: Vec<(Key, &Individual)> = store
+        .individuals()
+        .filter(|individual| filter.matches(store, individual))
+        .map(|individual| (key(Some(individual), sort), individual))
+        .collect();
+    found
+        .sort_by(|(a_key, a), (b_key, b)| a_key.cmp(b_key).then_with(|| compare_ids(&a.id, &b.id)));
+    found
+        .into_iter()
+        .map(|(_, individual)| individual)
+        .collect()
+}
+
+/// Families with a spouse matching `filter`, in `sort` order.
+pub fn families<'a>(
+    store: &'a GedcomStore,
+    filter: &PersonFilter,
+    sort: SortKey,
+) -> Vec<&'a Family> {
+    let spouse = |id: &Option<String>| id.as_deref().and_then(|id| store.get_individual(id));
+    let mut found: Vec<(Key, &Family)> = store
+        .families()
+        .filter(|family| {
+            filter.is_empty()
+                || [&family.husband, &family.wife]
+                    .into_iter()
+                    .filter_map(spouse)
+                    .any(|individual| filter.matches(store, individual))
+        })
+        .map(|family| {
+            let person = spouse(&family.husband).or_else(|| spouse(&family.wife));
+            (key(person, sort), family)
+        })
+        .collect();
+    found
+        .sort_by(|(a_key, a), (b_key, b)| a_key.cmp(b_key).then_with(|| compare_ids(&a.id, &b.id)));
+    found.into_iter().map(|(_, family)| family).collect()
+}
+
+/// Compares record IDs with runs of digits by value, so `I2` sorts before
+/// `I10`.
+pub fn compare_ids(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(x), Some(y)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+        if x.is_ascii_digit() && y.is_ascii_digit() {
+            let a_end = a.find(|c: char| !c.is_ascii_digit()).unwrap_or(a.len());
+            let b_end = b.find(|c: char| !c.is_ascii_digit()).unwrap_or(b.len());
+            let (a_digits, b_digits) = (
+                a[..a_end].trim_start_matches('0'),
+                b[..b_end].trim_start_matches('0'),
+            );
+            let order = a_digits
+                .len()
+                .cmp(&b_digits.len())
+                .then_with(|| a_digits.cmp(b_digits))
+                .then_with(|| a_end.cmp(&b_end));
+            if order != Ordering::Equal {
+                return order;
+            }
+            (a, b) = (&a[a_end..], &b[b_end..]);
+        } else {
+            if x != y {
+                return x.cmp(&y);
+            }
+            (a, b) = (&a[x.len_utf8()..], &b[y.len_utf8()..]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> GedcomStore {
+        GedcomStore::from_data(
+            crate::gedcom::parse_gedcom(
+                r#"0 @I10@ INDI
+1 NAME Karl /Zimmer/
+1 SEX M
+1 BIRT
+2 DATE 1890
+0 @I2@ INDI
+1 NAME Eva /Adler/
+1 SEX F
+1 BIRT
+2 DATE ABT 1920
+1 DEAT
+2 PLAC Graz, Steiermark, Austria
+0 @I3@ INDI
+1 NAME Otto /Adler/
+1 DEAT
+2 PLAC Wien, Austria
+0 @I4@ INDI
+1 NAME Anna /Berg/
+1 SEX F
+1 BIRT
+2 DATE 1850
+1 _MARNM Zimmer
+0 @F1@ FAM
+1 HUSB @I10@
+1 WIFE @I4@
+1 CHIL @I2@
+"#,
+            )
+            .unwrap(),
+        )
+    }
+
+    fn ids(individuals: Vec<&Individual>) -> Vec<&str> {
+        individuals
+            .into_iter()
+            .map(|individual| individual.id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn orders_ids_naturally() {
+        assert_eq!(compare_ids("I2", "I10"), Ordering::Less);
+        assert_eq!(compare_ids("I10", "I10"), Ordering::Equal);
+        assert_eq!(compare_ids("F1", "I1"), Ordering::Less);
+        assert_eq!(compare_ids("I01", "I1"), Ordering::Greater);
+        assert_eq!(compare_ids("I1", "I1a"), Ordering::Less);
+    }
+
+    #[test]
+    fn sorts_and_filters_individuals() {
+        let store = store();
+        let all = PersonFilter::default();
+        assert_eq!(
+            ids(individuals(&store, &all, SortKey::Id)),
+            vec!["I2", "I3", "I4", "I10"]
+        );
+        assert_eq!(
+            ids(individuals(&store, &all, SortKey::Surname)),
+            vec!["I2", "I3", "I4", "I10"]
+        );
+        assert_eq!(
+            ids(individuals(&store, &all, SortKey::Birth)),
+            vec!["I4", "I10", "I2", "I3"]
+        );
+
+        let born = PersonFilter {
+            born_after: GedcomDate::parse("1880").unwrap().earliest_jdn,
+            born_before: GedcomDate::parse("1920").unwrap().latest_jdn,
+            ..Default::default()
+        };
+        // ABT 1920 reaches into the range.
+        assert_eq!(
+            ids(individuals(&store, &born, SortKey::Id)),
+            vec!["I2", "I10"]
+        );
+
+        let austria: HashSet<String> = store
+            .places()
+            .matching("Austria", true)
+            .into_iter()
+            .map(|entry| place_key(&entry.name))
+            .collect();
+        let died = PersonFilter {
+            died_at: Some(austria),
+            sex: Some(Sex::Female),
+            ..Default::default()
+        };
+        assert_eq!(ids(individuals(&store, &died, SortKey::Id)), vec!["I2"]);
+
+        let roots = PersonFilter {
+            no_parents: true,
+            surname: Some("zimmer".into()),
+            ..Default::default()
+        };
+        // Anna is a Zimmer by marriage.
+        assert_eq!(
+            ids(individuals(&store, &roots, SortKey::Id)),
+            vec!["I4", "I10"]
+        );
+
+        let adler = PersonFilter {
+            surname: Some("adler".into()),
+            ..Default::default()
+        };
+        assert!(families(&store, &adler, SortKey::Id).is_empty());
+        assert_eq!(families(&store, &roots, SortKey::Surname).len(), 1);
+    }
+}
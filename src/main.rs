@@ -1,12 +1,30 @@
+mod audit;
 mod config;
+mod date;
+mod duplicates;
+mod fulltext;
+mod gazetteer;
 mod gedcom;
+mod inbreeding;
+mod kinship;
+mod lifespan;
+mod listing;
 mod mcp;
+mod names;
+mod pedigree;
+mod phonetic;
+mod place;
+mod query;
+mod statistics;
+mod text;
 
 use std::{env, process};
 
 use crate::config::Config;
-use crate::gedcom::{GedcomStore, load_gedcom, load_store};
+use crate::gazetteer::Gazetteer;
+use crate::gedcom::{GedcomStore, load_gedcom_store, load_store};
 use crate::mcp::Server;
+use crate::pedigree::ancestor_cycles;
 
 fn main() {
     let subscriber = tracing_subscriber::fmt()
@@ -50,17 +68,17 @@ fn main() {
                         store_path.display(),
                         ged_path.display()
                     );
-                    let gedcom_data = load_gedcom(ged_path).unwrap_or_else(|load_err| {
+                    load_gedcom_store(ged_path).unwrap_or_else(|load_err| {
                         eprintln!(
                             "Failed to load GEDCOM data from {}: {load_err}",
                             ged_path.display()
                         );
                         process::exit(1);
-                    });
-                    GedcomStore::from_data(gedcom_data)
+                    })
                 }
             };
 
+            validate(&server_store);
             Server::with_storage(server_store, store_path.clone())
         }
         (ged_path, None) => {
@@ -68,16 +86,28 @@ fn main() {
                 "Loading GEDCOM from {} (persistence disabled)",
                 ged_path.display()
             );
-            let gedcom_data = load_gedcom(ged_path).unwrap_or_else(|err| {
+            let store = load_gedcom_store(ged_path).unwrap_or_else(|err| {
                 eprintln!(
                     "Failed to load GEDCOM data from {}: {err}",
                     ged_path.display()
                 );
                 process::exit(1);
             });
-            Server::new(Some(GedcomStore::from_data(gedcom_data)))
+            validate(&store);
+            Server::new(Some(store))
         }
     };
+    let server = match &config.gazetteer_path {
+        Some(path) => {
+            let gazetteer = Gazetteer::from_path(path).unwrap_or_else(|err| {
+                eprintln!("Failed to load gazetteer from {}: {err}", path.display());
+                process::exit(1);
+            });
+            tracing::info!("Loaded gazetteer from {}", path.display());
+            server.with_gazetteer(gazetteer)
+        }
+        None => server,
+    };
     tracing::info!(
         "Server initialized with GEDCOM data: listening for MCP messages on {} (stdin/stdout)",
         config.bind_addr
@@ -88,3 +118,11 @@ fn main() {
         process::exit(1);
     }
 }
+
+/// Load-time validation: logs problems in the data that the tools work
+/// around but that should be fixed at the source.
+fn validate(store: &GedcomStore) {
+    for cycle in ancestor_cycles(store) {
+        tracing::warn!("{}", cycle.describe());
+    }
+}
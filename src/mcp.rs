@@ -1,10 +1,27 @@
-use crate::gedcom::GedcomStore;
+use crate::audit::{self, Rule};
+use crate::date::{GedcomDate, today_jdn};
+use crate::duplicates;
+use crate::fulltext;
+use crate::gazetteer::Gazetteer;
+use crate::gedcom::{
+    Association, ChangeStamp, Event, Family, GedcomStore, Individual, RecordMetadata, Sex, sex_of,
+};
+use crate::inbreeding::{self, Calculator};
+use crate::kinship::{self, Edge};
+use crate::lifespan;
+use crate::listing::{self, PersonFilter, SortKey};
+use crate::names::NameQuery;
+use crate::pedigree::{self, Lineage, Relation, TraversalOptions, TreeNode, other_spouse};
+use crate::place::{self, RecordRef};
+use crate::query;
+use crate::statistics;
+use crate::text;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::fs;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::io::{BufRead, Write};
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
 use tracing::{info, warn};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -42,10 +59,63 @@ pub enum OutboundMessage {
     Error(ErrorResponse),
 }
 
-#[derive(Debug, Default, Clone)]
+/// Proposals below this confidence are previewed but not applied unless the
+/// caller lowers `min_confidence`.
+const DEFAULT_MIN_CONFIDENCE: f64 = 0.75;
+
+/// Generations walked by `get_ancestors` / `get_descendants` unless the
+/// caller passes `max_generations`.
+const DEFAULT_MAX_GENERATIONS: u32 = 4;
+
+/// Generations searched for common ancestors by `get_relationship`.
+const DEFAULT_RELATIONSHIP_DEPTH: u32 = 12;
+
+/// Links followed by `find_connection` before giving up.
+const DEFAULT_MAX_HOPS: u32 = 12;
+
+/// Generations examined by `analyze_pedigree`.
+const DEFAULT_ANALYSIS_GENERATIONS: u32 = 10;
+
+/// Largest `max_generations` honoured by `analyze_pedigree`.
+const MAX_ANALYSIS_GENERATIONS: u32 = 100;
+
+/// Generation cutoff of `get_kinship_coefficients`; paths are enumerated,
+/// so this is kept shallower than the collapse analysis.
+const DEFAULT_KINSHIP_GENERATIONS: u32 = 8;
+
+/// Results per page of `search_individuals` and `search` unless the caller
+/// passes `limit`.
+const DEFAULT_SEARCH_LIMIT: u32 = 20;
+
+/// Records per page of `list_individuals` / `list_families`.
+const DEFAULT_LIST_LIMIT: u32 = 50;
+
+/// Lowest score of pairs returned by `find_duplicates` unless the caller
+/// passes `min_score`.
+const DEFAULT_DUPLICATE_SCORE: f64 = 0.5;
+
+/// Names and places listed by `get_statistics` unless the caller passes `top`.
+const DEFAULT_STATISTICS_TOP: u32 = 10;
+
+/// URI of the resource serving `get_statistics` with its defaults.
+const SUMMARY_RESOURCE: &str = "gedcom://tree/summary";
+
+/// Largest `limit` honoured by paginated tools.
+const MAX_PAGE_LIMIT: u32 = 100;
+
+#[derive(Debug, Clone)]
 pub struct Server {
     store: Option<Arc<Mutex<GedcomStore>>>,
     storage_path: Option<PathBuf>,
+    gazetteer: Option<Arc<Gazetteer>>,
+    /// Source of the `CHAN`/`CREA` stamps put on records by mutations.
+    clock: fn() -> ChangeStamp,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new(None)
+    }
 }
 
 impl Server {
@@ -53,6 +123,8 @@ impl Server {
         Self {
             store: store.map(|s| Arc::new(Mutex::new(s))),
             storage_path: None,
+            gazetteer: None,
+            clock: ChangeStamp::now,
         }
     }
 
@@ -60,9 +132,22 @@ impl Server {
         Self {
             store: Some(Arc::new(Mutex::new(store))),
             storage_path: Some(storage_path),
+            gazetteer: None,
+            clock: ChangeStamp::now,
         }
     }
 
+    pub fn with_gazetteer(mut self, gazetteer: Gazetteer) -> Self {
+        self.gazetteer = Some(Arc::new(gazetteer));
+        self
+    }
+
+    #[cfg(test)]
+    fn with_clock(mut self, clock: fn() -> ChangeStamp) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn handle_request(&self, request: Request) -> OutboundMessage {
         info!(
             "handling request id={} method={}",
@@ -79,6 +164,38 @@ impl Server {
             "list_families" => self.handle_list_families(request),
             "create_individual" => self.handle_create_individual(request),
             "create_family" => self.handle_create_family(request),
+            "delete_individual" => self.handle_delete_individual(request),
+            "delete_family" => self.handle_delete_family(request),
+            "find_by_vendor_id" => self.handle_find_by_vendor_id(request),
+            "get_age" => self.handle_get_age(request),
+            "list_places" => self.handle_list_places(request),
+            "find_events_at_place" => self.handle_find_events_at_place(request),
+            "normalize_places" => self.handle_normalize_places(request),
+            "get_repository" => self.handle_get_repository(request),
+            "get_submitter" => self.handle_get_submitter(request),
+            "get_associates" => self.handle_get_associates(request),
+            "get_aliases" => self.handle_get_aliases(request),
+            "find_by_reference" => self.handle_find_by_reference(request),
+            "get_parents" => self.handle_get_parents(request),
+            "get_children" => self.handle_get_children(request),
+            "get_spouses" => self.handle_get_spouses(request),
+            "get_siblings" => self.handle_get_siblings(request),
+            "get_ancestors" => self.handle_traversal(request, pedigree::ancestors),
+            "get_descendants" => self.handle_traversal(request, pedigree::descendants),
+            "get_relationship" => self.handle_get_relationship(request),
+            "find_connection" => self.handle_find_connection(request),
+            "analyze_pedigree" => self.handle_analyze_pedigree(request),
+            "get_kinship_coefficients" => self.handle_get_kinship_coefficients(request),
+            "get_dna_line" => self.handle_get_dna_line(request),
+            "find_dna_carriers" => self.handle_find_dna_carriers(request),
+            "search_individuals" => self.handle_search_individuals(request),
+            "query" => self.handle_query(request),
+            "search" => self.handle_search(request),
+            "get_statistics" => self.handle_get_statistics(request),
+            "audit" => self.handle_audit(request),
+            "find_duplicates" => self.handle_find_duplicates(request),
+            "resources/list" => self.handle_list_resources(request),
+            "resources/read" => self.handle_read_resource(request),
             other => {
                 warn!("method not found: {}", other);
                 OutboundMessage::Error(ErrorResponse::method_not_found(request.id, other))
@@ -133,6 +250,22 @@ impl Server {
         Ok(())
     }
 
+    fn lock_store(&self, request_id: &str) -> Result<MutexGuard<'_, GedcomStore>, OutboundMessage> {
+        let Some(store) = &self.store else {
+            return Err(OutboundMessage::Error(ErrorResponse::server_error(
+                request_id.to_owned(),
+                "server not initialized with GEDCOM data",
+            )));
+        };
+
+        store.lock().map_err(|_| {
+            OutboundMessage::Error(ErrorResponse::server_error(
+                request_id.to_owned(),
+                "store lock poisoned",
+            ))
+        })
+    }
+
     fn handle_get_individual(&self, request: Request) -> OutboundMessage {
         let id = request
             .params
@@ -165,17 +298,17 @@ impl Server {
         };
 
         match guard.get_individual(&id) {
-            Some(individual) => OutboundMessage::Response(Response {
-                id: request.id,
-                result: serde_json::to_value(individual).unwrap_or_else(|_| {
-                    serde_json::json!({
-                        "id": individual.id,
-                        "name": individual.name,
-                        "birth": individual.birth,
-                        "death": individual.death
-                    })
-                }),
-            }),
+            Some(individual) => {
+                let mut result = individual_json(individual, include_private(&request));
+                let lifespan = lifespan::lifespan(&guard, individual);
+                if !lifespan.is_empty() {
+                    result["lifespan"] = serde_json::to_value(lifespan).unwrap_or(Value::Null);
+                }
+                OutboundMessage::Response(Response {
+                    id: request.id,
+                    result,
+                })
+            }
             None => OutboundMessage::Error(ErrorResponse::not_found(
                 request.id,
                 format!("individual {id} not found"),
@@ -213,32 +346,47 @@ impl Server {
             .get("name")
             .and_then(Value::as_str)
             .map(str::to_owned);
+        let sex = match request.params.get("sex") {
+            None => None,
+            Some(value) => match value.as_str().and_then(Sex::parse) {
+                Some(sex) => Some(sex),
+                None => {
+                    return OutboundMessage::Error(ErrorResponse::invalid_params(
+                        request.id,
+                        "sex must be one of M, F, X or U",
+                    ));
+                }
+            },
+        };
         let birth = parse_event(request.params.get("birth"));
         let death = parse_event(request.params.get("death"));
 
-        let individual = crate::gedcom::Individual {
+        let individual = Individual {
             id: id.to_owned(),
             name,
+            sex,
             birth,
             death,
+            metadata: created_metadata((self.clock)()),
+            ..Default::default()
         };
 
         match guard.insert_individual(individual.clone()) {
             Ok(_) => {
                 let snapshot = guard.to_data();
                 drop(guard);
-                if let Some(path) = &self.storage_path {
-                    if let Err(err) = persist_snapshot(path, &snapshot) {
-                        return OutboundMessage::Error(ErrorResponse::server_error(
-                            request.id,
-                            format!("failed to persist data: {err}"),
-                        ));
-                    }
+                if let Some(path) = &self.storage_path
+                    && let Err(err) = snapshot.save_to_path(path)
+                {
+                    return OutboundMessage::Error(ErrorResponse::server_error(
+                        request.id,
+                        format!("failed to persist data: {err}"),
+                    ));
                 }
 
                 OutboundMessage::Response(Response {
                     id: request.id,
-                    result: serde_json::to_value(individual).unwrap_or_else(|_| Value::Null),
+                    result: individual_json(&individual, false),
                 })
             }
             Err(crate::gedcom::StoreError::DuplicateIndividual(existing)) => {
@@ -255,54 +403,58 @@ impl Server {
     }
 
     fn handle_list_individuals(&self, request: Request) -> OutboundMessage {
-        let Some(store) = &self.store else {
-            return OutboundMessage::Error(ErrorResponse::server_error(
-                request.id,
-                "server not initialized with GEDCOM data",
-            ));
+        let (offset, limit) = match page_params(&request, DEFAULT_LIST_LIMIT) {
+            Ok(page) => page,
+            Err(err) => return err,
         };
-
-        let guard = match store.lock() {
+        let guard = match self.lock_store(&request.id) {
             Ok(guard) => guard,
-            Err(_) => {
-                return OutboundMessage::Error(ErrorResponse::server_error(
-                    request.id,
-                    "store lock poisoned",
-                ));
-            }
+            Err(err) => return err,
+        };
+        let (filter, sort) = match list_params(&request, &guard) {
+            Ok(params) => params,
+            Err(err) => return err,
         };
 
-        let items: Vec<_> = guard.individuals().cloned().collect();
+        let found = listing::individuals(&guard, &filter, sort);
+        let items: Vec<_> = found
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|individual| individual_json(individual, false))
+            .collect();
 
         OutboundMessage::Response(Response {
             id: request.id,
-            result: serde_json::to_value(items).unwrap_or_else(|_| Value::Null),
+            result: page_json("individuals", items, offset, found.len()),
         })
     }
 
     fn handle_list_families(&self, request: Request) -> OutboundMessage {
-        let Some(store) = &self.store else {
-            return OutboundMessage::Error(ErrorResponse::server_error(
-                request.id,
-                "server not initialized with GEDCOM data",
-            ));
+        let (offset, limit) = match page_params(&request, DEFAULT_LIST_LIMIT) {
+            Ok(page) => page,
+            Err(err) => return err,
         };
-
-        let guard = match store.lock() {
+        let guard = match self.lock_store(&request.id) {
             Ok(guard) => guard,
-            Err(_) => {
-                return OutboundMessage::Error(ErrorResponse::server_error(
-                    request.id,
-                    "store lock poisoned",
-                ));
-            }
+            Err(err) => return err,
+        };
+        let (filter, sort) = match list_params(&request, &guard) {
+            Ok(params) => params,
+            Err(err) => return err,
         };
 
-        let items: Vec<_> = guard.families().cloned().collect();
+        let found = listing::families(&guard, &filter, sort);
+        let items: Vec<_> = found
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|family| family_json(&guard, family, include_private(&request)))
+            .collect();
 
         OutboundMessage::Response(Response {
             id: request.id,
-            result: serde_json::to_value(items).unwrap_or_else(|_| Value::Null),
+            result: page_json("families", items, offset, found.len()),
         })
     }
 
@@ -338,17 +490,13 @@ impl Server {
         };
 
         match guard.get_family(&id) {
-            Some(family) => OutboundMessage::Response(Response {
-                id: request.id,
-                result: serde_json::to_value(family).unwrap_or_else(|_| {
-                    serde_json::json!({
-                        "id": family.id,
-                        "husband": family.husband,
-                        "wife": family.wife,
-                        "children": family.children
-                    })
-                }),
-            }),
+            Some(family) => {
+                let result = family_json(&guard, family, include_private(&request));
+                OutboundMessage::Response(Response {
+                    id: request.id,
+                    result,
+                })
+            }
             None => OutboundMessage::Error(ErrorResponse::not_found(
                 request.id,
                 format!("family {id} not found"),
@@ -421,24 +569,38 @@ impl Server {
             husband,
             wife,
             children,
+            metadata: created_metadata((self.clock)()),
+            ..Default::default()
         };
 
         match guard.insert_family(family.clone()) {
             Ok(_) => {
+                let warnings: Vec<String> = pedigree::cycles_through(&guard, id)
+                    .iter()
+                    .map(|cycle| {
+                        let warning = cycle.describe();
+                        tracing::warn!("{warning}");
+                        warning
+                    })
+                    .collect();
                 let snapshot = guard.to_data();
                 drop(guard);
-                if let Some(path) = &self.storage_path {
-                    if let Err(err) = persist_snapshot(path, &snapshot) {
-                        return OutboundMessage::Error(ErrorResponse::server_error(
-                            request.id,
-                            format!("failed to persist data: {err}"),
-                        ));
-                    }
+                if let Some(path) = &self.storage_path
+                    && let Err(err) = snapshot.save_to_path(path)
+                {
+                    return OutboundMessage::Error(ErrorResponse::server_error(
+                        request.id,
+                        format!("failed to persist data: {err}"),
+                    ));
                 }
 
+                let mut result = serde_json::to_value(family).unwrap_or(Value::Null);
+                if !warnings.is_empty() {
+                    result["warnings"] = Value::from(warnings);
+                }
                 OutboundMessage::Response(Response {
                     id: request.id,
-                    result: serde_json::to_value(family).unwrap_or_else(|_| Value::Null),
+                    result,
                 })
             }
             Err(crate::gedcom::StoreError::DuplicateFamily(existing)) => OutboundMessage::Error(
@@ -450,660 +612,4393 @@ impl Server {
             )),
         }
     }
-}
 
-fn parse_event(value: Option<&Value>) -> Option<crate::gedcom::Event> {
-    let Value::Object(map) = value? else {
-        return None;
-    };
+    /// Deletes an individual who is no longer a spouse or child in any
+    /// family, so no family is left pointing at a missing record.
+    fn handle_delete_individual(&self, request: Request) -> OutboundMessage {
+        let Some(id) = request.params.get("id").and_then(Value::as_str) else {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "missing required param: id",
+            ));
+        };
+        let mut guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+        let families: Vec<&str> = guard
+            .families_as_spouse(id)
+            .chain(guard.families_as_child(id))
+            .map(|family| family.id.as_str())
+            .collect();
+        if !families.is_empty() {
+            return OutboundMessage::Error(ErrorResponse::conflict(
+                request.id,
+                format!(
+                    "individual {id} is still a member of {}",
+                    families.join(", ")
+                ),
+            ));
+        }
+        let Some(individual) = guard.remove_individual(id) else {
+            return OutboundMessage::Error(ErrorResponse::not_found(
+                request.id,
+                format!("individual {id} not found"),
+            ));
+        };
 
-    let date = map.get("date").and_then(Value::as_str).map(str::to_owned);
-    let place = map.get("place").and_then(Value::as_str).map(str::to_owned);
+        let snapshot = guard.to_data();
+        drop(guard);
+        if let Some(path) = &self.storage_path
+            && let Err(err) = snapshot.save_to_path(path)
+        {
+            return OutboundMessage::Error(ErrorResponse::server_error(
+                request.id,
+                format!("failed to persist data: {err}"),
+            ));
+        }
 
-    if date.is_none() && place.is_none() {
-        None
-    } else {
-        Some(crate::gedcom::Event { date, place })
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: serde_json::to_value(individual).unwrap_or(Value::Null),
+        })
     }
-}
 
-fn persist_snapshot(
-    path: &PathBuf,
-    data: &crate::gedcom::GedcomData,
-) -> Result<(), std::io::Error> {
-    let tmp_path = path.with_extension("tmp");
-    {
-        let mut file = fs::File::create(&tmp_path)?;
-        serde_json::to_writer_pretty(&mut file, data)
-            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
-        file.sync_all()?;
-    }
-    fs::rename(tmp_path, path)?;
-    Ok(())
-}
+    fn handle_delete_family(&self, request: Request) -> OutboundMessage {
+        let Some(id) = request.params.get("id").and_then(Value::as_str) else {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "missing required param: id",
+            ));
+        };
+        let mut guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+        let Some(family) = guard.remove_family(id) else {
+            return OutboundMessage::Error(ErrorResponse::not_found(
+                request.id,
+                format!("family {id} not found"),
+            ));
+        };
 
-impl ErrorResponse {
-    pub fn method_not_found(id: String, method: impl Into<String>) -> Self {
-        Self {
-            id,
-            error: ErrorObject {
-                code: -32601, // JSON-RPC method not found
-                message: format!("method not found: {}", method.into()),
-                data: None,
-            },
+        let snapshot = guard.to_data();
+        drop(guard);
+        if let Some(path) = &self.storage_path
+            && let Err(err) = snapshot.save_to_path(path)
+        {
+            return OutboundMessage::Error(ErrorResponse::server_error(
+                request.id,
+                format!("failed to persist data: {err}"),
+            ));
         }
-    }
 
-    pub fn parse_error(message: impl Into<String>) -> Self {
-        Self {
-            id: "null".into(),
-            error: ErrorObject {
-                code: -32700, // JSON-RPC parse error
-                message: message.into(),
-                data: None,
-            },
-        }
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: serde_json::to_value(family).unwrap_or(Value::Null),
+        })
     }
 
-    pub fn invalid_params(id: String, message: impl Into<String>) -> Self {
-        Self {
-            id,
-            error: ErrorObject {
-                code: -32602,
-                message: message.into(),
-                data: None,
+    fn handle_get_age(&self, request: Request) -> OutboundMessage {
+        let Some(id) = request.params.get("id").and_then(Value::as_str) else {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "missing required param: id",
+            ));
+        };
+        let date = match request.params.get("date").and_then(Value::as_str) {
+            Some(date) => match GedcomDate::parse(date) {
+                Ok(date) => Some(date),
+                Err(err) => {
+                    return OutboundMessage::Error(ErrorResponse::invalid_params(
+                        request.id,
+                        err.to_string(),
+                    ));
+                }
             },
-        }
-    }
+            None => None,
+        };
+        let event_tag = request.params.get("event").and_then(Value::as_str);
 
-    pub fn server_error(id: String, message: impl Into<String>) -> Self {
-        Self {
-            id,
-            error: ErrorObject {
-                code: -32000,
-                message: message.into(),
-                data: None,
-            },
-        }
-    }
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+        let Some(individual) = guard.get_individual(id) else {
+            return OutboundMessage::Error(ErrorResponse::not_found(
+                request.id,
+                format!("individual {id} not found"),
+            ));
+        };
+        let birth = individual.birth.as_ref();
 
-    pub fn not_found(id: String, message: impl Into<String>) -> Self {
-        Self {
-            id,
-            error: ErrorObject {
-                code: -32004,
-                message: message.into(),
-                data: None,
-            },
-        }
+        let result = match (date, event_tag) {
+            (Some(date), _) => serde_json::json!({
+                "id": id,
+                "date": request.params["date"],
+                "age": lifespan::age_at_date(birth, &date),
+            }),
+            (None, Some(tag)) => {
+                let tag = tag.to_ascii_uppercase();
+                let family_event = || {
+                    guard
+                        .families_as_spouse(id)
+                        .flat_map(|family| &family.events)
+                        .find(|event| event.tag.as_deref() == Some(tag.as_str()))
+                        .map(|event| Event {
+                            age: None,
+                            ..event.clone()
+                        })
+                };
+                let event = match tag.as_str() {
+                    "BIRT" => individual.birth.clone(),
+                    "DEAT" => individual.death.clone(),
+                    _ => individual
+                        .events
+                        .iter()
+                        .find(|event| event.tag.as_deref() == Some(tag.as_str()))
+                        .cloned()
+                        .or_else(family_event),
+                };
+                let Some(event) = event else {
+                    return OutboundMessage::Error(ErrorResponse::not_found(
+                        request.id,
+                        format!("individual {id} has no {tag} event"),
+                    ));
+                };
+                serde_json::json!({
+                    "id": id,
+                    "event": tag,
+                    "date": event.date,
+                    "age": lifespan::age_at_event(birth, &event),
+                })
+            }
+            (None, None) => serde_json::json!({
+                "id": id,
+                "lifespan": lifespan::lifespan(&guard, individual),
+            }),
+        };
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result,
+        })
     }
 
-    pub fn conflict(id: String, message: impl Into<String>) -> Self {
-        Self {
-            id,
-            error: ErrorObject {
-                code: -32001,
-                message: message.into(),
-                data: None,
-            },
+    fn handle_find_by_vendor_id(&self, request: Request) -> OutboundMessage {
+        let familysearch_id = request
+            .params
+            .get("familysearch_id")
+            .and_then(Value::as_str);
+        let uid = request.params.get("uid").and_then(Value::as_str);
+        if familysearch_id.is_none() && uid.is_none() {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "missing required param: familysearch_id or uid",
+            ));
         }
+
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+
+        let mut items = match (familysearch_id, uid) {
+            (Some(familysearch_id), uid) => {
+                let with_uid: Option<HashSet<&str>> = uid.map(|uid| {
+                    guard
+                        .find_by_uid(uid)
+                        .into_iter()
+                        .map(|individual| individual.id.as_str())
+                        .collect()
+                });
+                guard
+                    .find_by_familysearch_id(familysearch_id)
+                    .into_iter()
+                    .filter(|individual| {
+                        with_uid
+                            .as_ref()
+                            .is_none_or(|ids| ids.contains(individual.id.as_str()))
+                    })
+                    .collect()
+            }
+            (None, Some(uid)) => guard.find_by_uid(uid),
+            (None, None) => Vec::new(),
+        };
+        items.sort_by(|a, b| a.id.cmp(&b.id));
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: Value::Array(
+                items
+                    .into_iter()
+                    .map(|individual| individual_json(individual, false))
+                    .collect(),
+            ),
+        })
     }
-}
 
-pub fn parse_request(input: &str) -> Result<Request, serde_json::Error> {
-    serde_json::from_str(input)
-}
+    fn handle_list_places(&self, request: Request) -> OutboundMessage {
+        let within = request.params.get("within").and_then(Value::as_str);
 
-pub fn serialize_message(message: &OutboundMessage) -> Result<String, serde_json::Error> {
-    serde_json::to_string(message)
-}
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::gedcom::{Family, GedcomData, GedcomStore, Individual};
-    use tempfile;
+        let places = guard.places();
+        let entries = match within {
+            Some(within) => places.matching(within, true),
+            None => places.entries().collect(),
+        };
+        let form = &guard.header().place_form;
+        let items = entries
+            .into_iter()
+            .map(|entry| {
+                let mut value = serde_json::json!({
+                    "name": entry.name,
+                    "jurisdictions": place::hierarchy(&entry.name, form),
+                    "event_count": entry.event_count(),
+                });
+                if let Some(coordinates) = entry.coordinates {
+                    value["coordinates"] = serde_json::to_value(coordinates).unwrap_or(Value::Null);
+                }
+                value
+            })
+            .collect();
 
-    #[test]
-    fn round_trips_request_json() {
-        let json = r#"{"id":"1","method":"ping","params":{"echo":"hi"}}"#;
-        let request = parse_request(json).expect("should parse");
-        assert_eq!(
-            request,
-            Request {
-                id: "1".into(),
-                method: "ping".into(),
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: Value::Array(items),
+        })
+    }
+
+    fn handle_find_events_at_place(&self, request: Request) -> OutboundMessage {
+        let Some(query) = request.params.get("place").and_then(Value::as_str) else {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "missing required param: place",
+            ));
+        };
+        let include_subplaces = request
+            .params
+            .get("include_subplaces")
+            .and_then(Value::as_bool)
+            .unwrap_or(true);
+
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+
+        let entries = guard.places().matching(query, include_subplaces);
+        let keys: HashSet<String> = entries
+            .iter()
+            .map(|entry| place::place_key(&entry.name))
+            .collect();
+        let records: BTreeSet<&RecordRef> =
+            entries.iter().flat_map(|entry| entry.records()).collect();
+
+        let mut items = Vec::new();
+        let mut push = |record_type: &str, record_id: &str, tag: &str, event: &Event| {
+            if let Some(place) = &event.place
+                && keys.contains(&place::place_key(place))
+            {
+                items.push(serde_json::json!({
+                    "record_type": record_type,
+                    "record_id": record_id,
+                    "tag": tag,
+                    "date": event.date,
+                    "place": place,
+                }));
+            }
+        };
+        for record in records {
+            match record {
+                RecordRef::Individual(id) => {
+                    if let Some(individual) = guard.get_individual(id) {
+                        for (tag, event) in individual.tagged_events() {
+                            push("individual", id, tag, event);
+                        }
+                    }
+                }
+                RecordRef::Family(id) => {
+                    if let Some(family) = guard.get_family(id) {
+                        for event in &family.events {
+                            push("family", id, event.tag.as_deref().unwrap_or("EVEN"), event);
+                        }
+                    }
+                }
+            }
+        }
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: Value::Array(items),
+        })
+    }
+
+    fn handle_normalize_places(&self, request: Request) -> OutboundMessage {
+        let Some(gazetteer) = &self.gazetteer else {
+            return OutboundMessage::Error(ErrorResponse::server_error(
+                request.id,
+                "no gazetteer configured",
+            ));
+        };
+        let apply = request
+            .params
+            .get("apply")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let min_confidence = match request.params.get("min_confidence") {
+            None => DEFAULT_MIN_CONFIDENCE,
+            Some(value) => match value.as_f64() {
+                Some(confidence) if (0.0..=1.0).contains(&confidence) => confidence,
+                _ => {
+                    return OutboundMessage::Error(ErrorResponse::invalid_params(
+                        request.id,
+                        "min_confidence must be a number between 0 and 1",
+                    ));
+                }
+            },
+        };
+        let only: Option<HashSet<String>> = match request.params.get("places") {
+            None => None,
+            Some(Value::Array(places)) => Some(
+                places
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(place::place_key)
+                    .collect(),
+            ),
+            Some(_) => {
+                return OutboundMessage::Error(ErrorResponse::invalid_params(
+                    request.id,
+                    "places must be an array of strings",
+                ));
+            }
+        };
+
+        let mut guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+
+        let mut proposals = Vec::new();
+        let mut unmatched = Vec::new();
+        for entry in guard.places().entries() {
+            if only
+                .as_ref()
+                .is_some_and(|only| !only.contains(&place::place_key(&entry.name)))
+            {
+                continue;
+            }
+            match gazetteer.lookup(&entry.name) {
+                Some(found) if found.canonical != entry.name || entry.coordinates.is_none() => {
+                    let accepted = found.confidence >= min_confidence;
+                    proposals.push((entry.name.clone(), entry.event_count(), found, accepted));
+                }
+                Some(_) => {}
+                None => unmatched.push(entry.name.clone()),
+            }
+        }
+
+        let mut updated_events = 0;
+        if apply {
+            let changed = (self.clock)();
+            for (place, _, found, accepted) in &proposals {
+                if *accepted {
+                    updated_events += guard.normalize_place(
+                        place,
+                        &found.canonical,
+                        Some(found.coordinates),
+                        &changed,
+                    );
+                }
+            }
+            if updated_events > 0 {
+                let snapshot = guard.to_data();
+                drop(guard);
+                if let Some(path) = &self.storage_path
+                    && let Err(err) = snapshot.save_to_path(path)
+                {
+                    return OutboundMessage::Error(ErrorResponse::server_error(
+                        request.id,
+                        format!("failed to persist data: {err}"),
+                    ));
+                }
+            }
+        }
+
+        let proposals: Vec<Value> = proposals
+            .into_iter()
+            .map(|(place, event_count, found, accepted)| {
+                let mut value = serde_json::to_value(found).unwrap_or(Value::Null);
+                value["place"] = Value::from(place);
+                value["event_count"] = Value::from(event_count);
+                value["accepted"] = Value::from(accepted);
+                value
+            })
+            .collect();
+        let mut result = serde_json::json!({
+            "applied": apply,
+            "proposals": proposals,
+            "unmatched": unmatched,
+        });
+        if apply {
+            result["updated_events"] = Value::from(updated_events);
+        }
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result,
+        })
+    }
+
+    fn handle_get_repository(&self, request: Request) -> OutboundMessage {
+        let Some(id) = request.params.get("id").and_then(Value::as_str) else {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "missing required param: id",
+            ));
+        };
+
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+
+        match guard.get_repository(id) {
+            Some(repository) => OutboundMessage::Response(Response {
+                id: request.id,
+                result: serde_json::to_value(repository).unwrap_or(Value::Null),
+            }),
+            None => OutboundMessage::Error(ErrorResponse::not_found(
+                request.id,
+                format!("repository {id} not found"),
+            )),
+        }
+    }
+
+    fn handle_get_submitter(&self, request: Request) -> OutboundMessage {
+        let Some(id) = request.params.get("id").and_then(Value::as_str) else {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "missing required param: id",
+            ));
+        };
+
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+
+        match guard.get_submitter(id) {
+            Some(submitter) => OutboundMessage::Response(Response {
+                id: request.id,
+                result: serde_json::to_value(submitter).unwrap_or(Value::Null),
+            }),
+            None => OutboundMessage::Error(ErrorResponse::not_found(
+                request.id,
+                format!("submitter {id} not found"),
+            )),
+        }
+    }
+
+    fn handle_get_associates(&self, request: Request) -> OutboundMessage {
+        let Some(id) = request.params.get("id").and_then(Value::as_str) else {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "missing required param: id",
+            ));
+        };
+        let relation = request
+            .params
+            .get("relation")
+            .and_then(Value::as_str)
+            .map(str::to_lowercase);
+        let matches_relation = |association: &Association| {
+            relation.as_ref().is_none_or(|wanted| {
+                association
+                    .relation
+                    .as_ref()
+                    .is_some_and(|relation| relation.to_lowercase().contains(wanted))
+            })
+        };
+
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+        let Some(individual) = guard.get_individual(id) else {
+            return OutboundMessage::Error(ErrorResponse::not_found(
+                request.id,
+                format!("individual {id} not found"),
+            ));
+        };
+        let name_of = |id: &str| guard.get_individual(id).and_then(|i| i.name.clone());
+
+        let associates: Vec<Value> = individual
+            .associations
+            .iter()
+            .filter(|association| matches_relation(association))
+            .map(|association| {
+                serde_json::json!({
+                    "individual": association.individual,
+                    "name": name_of(&association.individual),
+                    "relation": association.relation,
+                    "event": association.event,
+                })
+            })
+            .collect();
+
+        let mut associated_with = Vec::new();
+        for record in guard.associated_by(id) {
+            let (record_type, record_id, name, associations) = match record {
+                RecordRef::Individual(other) => match guard.get_individual(other) {
+                    Some(other) => (
+                        "individual",
+                        &other.id,
+                        other.name.clone(),
+                        &other.associations,
+                    ),
+                    None => continue,
+                },
+                RecordRef::Family(family) => match guard.get_family(family) {
+                    Some(family) => ("family", &family.id, None, &family.associations),
+                    None => continue,
+                },
+            };
+            for association in associations {
+                if association.individual == id && matches_relation(association) {
+                    associated_with.push(serde_json::json!({
+                        "record_type": record_type,
+                        "record_id": record_id,
+                        "name": name,
+                        "relation": association.relation,
+                        "event": association.event,
+                    }));
+                }
+            }
+        }
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: serde_json::json!({
+                "id": id,
+                "associates": associates,
+                "associated_with": associated_with,
+            }),
+        })
+    }
+
+    fn handle_get_aliases(&self, request: Request) -> OutboundMessage {
+        let Some(id) = request.params.get("id").and_then(Value::as_str) else {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "missing required param: id",
+            ));
+        };
+
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+        let Some(individual) = guard.get_individual(id) else {
+            return OutboundMessage::Error(ErrorResponse::not_found(
+                request.id,
+                format!("individual {id} not found"),
+            ));
+        };
+
+        // Aliases are symmetric for identity purposes, so follow links in
+        // both directions to collect every record describing this person.
+        let mut same_person = BTreeSet::new();
+        let mut queue = VecDeque::from([id.to_owned()]);
+        while let Some(current) = queue.pop_front() {
+            let linked = guard
+                .get_individual(&current)
+                .map(|individual| individual.aliases.clone())
+                .unwrap_or_default()
+                .into_iter()
+                .chain(guard.aliased_by(&current).cloned());
+            for other in linked {
+                if other != id && same_person.insert(other.clone()) {
+                    queue.push_back(other);
+                }
+            }
+        }
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: serde_json::json!({
+                "id": id,
+                "aliases": individual.aliases,
+                "aliased_by": guard.aliased_by(id).collect::<Vec<_>>(),
+                "same_person": same_person,
+            }),
+        })
+    }
+
+    fn handle_find_by_reference(&self, request: Request) -> OutboundMessage {
+        let refn = request.params.get("refn").and_then(Value::as_str);
+        let uid = request.params.get("uid").and_then(Value::as_str);
+        let kind = request.params.get("type").and_then(Value::as_str);
+        if refn.is_none() && uid.is_none() {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "missing required param: refn or uid",
+            ));
+        }
+
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+
+        let by_refn: Option<BTreeSet<&RecordRef>> =
+            refn.map(|refn| guard.records_by_refn(refn, kind).collect());
+        let by_uid: Option<BTreeSet<&RecordRef>> =
+            uid.map(|uid| guard.records_by_uid(uid).collect());
+        let records: BTreeSet<&RecordRef> = match (by_refn, by_uid) {
+            (Some(refn), Some(uid)) => refn.intersection(&uid).copied().collect(),
+            (Some(records), None) | (None, Some(records)) => records,
+            (None, None) => BTreeSet::new(),
+        };
+
+        let mut individuals = Vec::new();
+        let mut families = Vec::new();
+        for record in records {
+            match record {
+                RecordRef::Individual(id) => {
+                    if let Some(individual) = guard.get_individual(id) {
+                        individuals.push(individual_json(individual, false));
+                    }
+                }
+                RecordRef::Family(id) => {
+                    if let Some(family) = guard.get_family(id) {
+                        families.push(family_json(&guard, family, false));
+                    }
+                }
+            }
+        }
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: serde_json::json!({
+                "individuals": individuals,
+                "families": families,
+            }),
+        })
+    }
+
+    /// Shared preamble of the relationship tools: reads `id`, locks the store
+    /// and checks that the individual exists.
+    fn relative_query(
+        &self,
+        request: &Request,
+    ) -> Result<(String, MutexGuard<'_, GedcomStore>), OutboundMessage> {
+        let Some(id) = request.params.get("id").and_then(Value::as_str) else {
+            return Err(OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id.clone(),
+                "missing required param: id",
+            )));
+        };
+        let guard = self.lock_store(&request.id)?;
+        if guard.get_individual(id).is_none() {
+            return Err(OutboundMessage::Error(ErrorResponse::not_found(
+                request.id.clone(),
+                format!("individual {id} not found"),
+            )));
+        }
+        Ok((id.to_owned(), guard))
+    }
+
+    fn handle_get_parents(&self, request: Request) -> OutboundMessage {
+        let (id, guard) = match self.relative_query(&request) {
+            Ok(query) => query,
+            Err(err) => return err,
+        };
+
+        let items: Vec<Value> = guard
+            .families_as_child(&id)
+            .map(|family| {
+                serde_json::json!({
+                    "family": family.id,
+                    "father": family.husband.as_deref().map(|id| person_json(&guard, id)),
+                    "mother": family.wife.as_deref().map(|id| person_json(&guard, id)),
+                })
+            })
+            .collect();
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: Value::Array(items),
+        })
+    }
+
+    fn handle_get_children(&self, request: Request) -> OutboundMessage {
+        let (id, guard) = match self.relative_query(&request) {
+            Ok(query) => query,
+            Err(err) => return err,
+        };
+
+        let mut items = Vec::new();
+        for family in guard.families_as_spouse(&id) {
+            let other_parent = other_spouse(family, &id);
+            for child in &family.children {
+                let mut value = person_json(&guard, child);
+                value["family"] = Value::from(family.id.as_str());
+                value["other_parent"] = Value::from(other_parent);
+                items.push(value);
+            }
+        }
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: Value::Array(items),
+        })
+    }
+
+    fn handle_get_spouses(&self, request: Request) -> OutboundMessage {
+        let (id, guard) = match self.relative_query(&request) {
+            Ok(query) => query,
+            Err(err) => return err,
+        };
+
+        let items: Vec<Value> = guard
+            .families_as_spouse(&id)
+            .filter_map(|family| {
+                let mut value = person_json(&guard, other_spouse(family, &id)?);
+                value["family"] = Value::from(family.id.as_str());
+                Some(value)
+            })
+            .collect();
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: Value::Array(items),
+        })
+    }
+
+    /// Full siblings share a family as child (or both parents), half siblings
+    /// share one parent, and step siblings are children of a parent's spouse
+    /// with no parent in common.
+    fn handle_get_siblings(&self, request: Request) -> OutboundMessage {
+        let (id, guard) = match self.relative_query(&request) {
+            Ok(query) => query,
+            Err(err) => return err,
+        };
+        let parents_of = |child: &str| -> BTreeSet<String> {
+            guard
+                .families_as_child(child)
+                .flat_map(|family| [&family.husband, &family.wife])
+                .flatten()
+                .cloned()
+                .collect()
+        };
+        let own_families: BTreeSet<&str> = guard
+            .families_as_child(&id)
+            .map(|family| family.id.as_str())
+            .collect();
+        let own_parents = parents_of(&id);
+
+        let mut full = BTreeSet::new();
+        let mut half = BTreeMap::new();
+        let mut step = BTreeMap::new();
+        for parent in &own_parents {
+            for family in guard.families_as_spouse(parent) {
+                for child in family.children.iter().filter(|child| **child != id) {
+                    let shared: Vec<String> = parents_of(child)
+                        .intersection(&own_parents)
+                        .cloned()
+                        .collect();
+                    if own_families.contains(family.id.as_str()) || shared.len() >= 2 {
+                        full.insert(child.clone());
+                    } else {
+                        half.insert(child.clone(), shared);
+                    }
+                }
+
+                // Children the parent's spouse had with someone else.
+                let Some(step_parent) = other_spouse(family, parent) else {
+                    continue;
+                };
+                if own_parents.contains(step_parent) {
+                    continue;
+                }
+                for other in guard.families_as_spouse(step_parent) {
+                    if [&other.husband, &other.wife]
+                        .into_iter()
+                        .flatten()
+                        .any(|spouse| own_parents.contains(spouse))
+                    {
+                        continue;
+                    }
+                    for child in other.children.iter().filter(|child| **child != id) {
+                        if parents_of(child).is_disjoint(&own_parents) {
+                            step.entry(child.clone())
+                                .or_insert_with(|| step_parent.to_owned());
+                        }
+                    }
+                }
+            }
+        }
+        half.retain(|child, _| !full.contains(child));
+
+        let full: Vec<Value> = full
+            .iter()
+            .map(|child| person_json(&guard, child))
+            .collect();
+        let half: Vec<Value> = half
+            .iter()
+            .map(|(child, shared)| {
+                let mut value = person_json(&guard, child);
+                value["shared_parents"] = serde_json::json!(shared);
+                value
+            })
+            .collect();
+        let step: Vec<Value> = step
+            .iter()
+            .map(|(child, step_parent)| {
+                let mut value = person_json(&guard, child);
+                value["step_parent"] = Value::from(step_parent.as_str());
+                value
+            })
+            .collect();
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: serde_json::json!({
+                "id": id,
+                "full": full,
+                "half": half,
+                "step": step,
+            }),
+        })
+    }
+
+    /// `get_ancestors` and `get_descendants`: reads the traversal options,
+    /// walks the tree and returns it as a flat list or nested under the root.
+    fn handle_traversal(
+        &self,
+        request: Request,
+        traverse: for<'a> fn(&'a GedcomStore, &'a str, &TraversalOptions) -> Vec<TreeNode>,
+    ) -> OutboundMessage {
+        let params = &request.params;
+        let max_generations =
+            match count_param(&request, "max_generations", DEFAULT_MAX_GENERATIONS) {
+                Ok(count) => count,
+                Err(err) => return err,
+            };
+        let tree = match params.get("format").and_then(Value::as_str) {
+            None | Some("list") => false,
+            Some("tree") => true,
+            Some(other) => {
+                return OutboundMessage::Error(ErrorResponse::invalid_params(
+                    request.id,
+                    format!("unknown format {other}; expected list or tree"),
+                ));
+            }
+        };
+        let options = TraversalOptions {
+            max_generations,
+            direct_line: params
+                .get("direct_line")
+                .and_then(Value::as_bool)
+                .unwrap_or(true),
+            include_spouses: params
+                .get("include_spouses")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+        };
+        let (id, guard) = match self.relative_query(&request) {
+            Ok(query) => query,
+            Err(err) => return err,
+        };
+
+        let nodes = traverse(&guard, &id, &options);
+        let result = if tree {
+            serde_json::json!({ "id": id, "tree": nest_tree(&guard, &nodes) })
+        } else {
+            let items: Vec<Value> = nodes.iter().map(|node| node_json(&guard, node)).collect();
+            serde_json::json!({ "id": id, "individuals": items })
+        };
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result,
+        })
+    }
+
+    /// Describes how `from` is related to `to`: by blood through their most
+    /// recent common ancestors, as spouses, or through a marriage.
+    fn handle_get_relationship(&self, request: Request) -> OutboundMessage {
+        let param = |name: &str| request.params.get(name).and_then(Value::as_str);
+        let (Some(from), Some(to)) = (param("from"), param("to")) else {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "missing required params: from, to",
+            ));
+        };
+        let max_depth = match count_param(&request, "max_depth", DEFAULT_RELATIONSHIP_DEPTH) {
+            Ok(count) => count,
+            Err(err) => return err,
+        };
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+        if let Some(missing) = [from, to]
+            .into_iter()
+            .find(|id| guard.get_individual(id).is_none())
+        {
+            return OutboundMessage::Error(ErrorResponse::not_found(
+                request.id,
+                format!("individual {missing} not found"),
+            ));
+        }
+
+        let label = |id: &str| {
+            guard
+                .get_individual(id)
+                .and_then(|individual| individual.name.clone())
+                .unwrap_or_else(|| id.to_owned())
+        };
+        let relationships: Vec<Value> = kinship::relationships(&guard, from, to, max_depth)
+            .into_iter()
+            .map(|relationship| {
+                let description =
+                    format!("{} is {}'s {}", label(from), label(to), relationship.term);
+                let mut value = serde_json::to_value(&relationship).unwrap_or(Value::Null);
+                value["description"] = Value::from(description);
+                for step in value["path"].as_array_mut().into_iter().flatten() {
+                    let id = step["id"].as_str().unwrap_or_default().to_owned();
+                    step["name"] = person_json(&guard, &id)["name"].take();
+                }
+                value
+            })
+            .collect();
+
+        let result = serde_json::json!({
+            "from": person_json(&guard, from),
+            "to": person_json(&guard, to),
+            "related": !relationships.is_empty(),
+            "relationships": relationships,
+        });
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result,
+        })
+    }
+
+    /// Shortest chain of family links from `from` to `to`, e.g. "A's wife's
+    /// brother's son is B".
+    fn handle_find_connection(&self, request: Request) -> OutboundMessage {
+        let param = |name: &str| request.params.get(name).and_then(Value::as_str);
+        let (Some(from), Some(to)) = (param("from"), param("to")) else {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "missing required params: from, to",
+            ));
+        };
+        let edges: Vec<Edge> = match request.params.get("edges") {
+            None => Edge::ALL.to_vec(),
+            Some(Value::Array(values)) => {
+                match values
+                    .iter()
+                    .map(|value| value.as_str().and_then(Edge::parse))
+                    .collect::<Option<Vec<_>>>()
+                {
+                    Some(edges) if !edges.is_empty() => edges,
+                    _ => {
+                        return OutboundMessage::Error(ErrorResponse::invalid_params(
+                            request.id,
+                            "edges must be a non-empty array of parent, child, spouse or sibling",
+                        ));
+                    }
+                }
+            }
+            Some(_) => {
+                return OutboundMessage::Error(ErrorResponse::invalid_params(
+                    request.id,
+                    "edges must be a non-empty array of parent, child, spouse or sibling",
+                ));
+            }
+        };
+        let max_hops = match count_param(&request, "max_hops", DEFAULT_MAX_HOPS) {
+            Ok(count) => count,
+            Err(err) => return err,
+        };
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+        if let Some(missing) = [from, to]
+            .into_iter()
+            .find(|id| guard.get_individual(id).is_none())
+        {
+            return OutboundMessage::Error(ErrorResponse::not_found(
+                request.id,
+                format!("individual {missing} not found"),
+            ));
+        }
+
+        let label = |id: &str| {
+            guard
+                .get_individual(id)
+                .and_then(|individual| individual.name.clone())
+                .unwrap_or_else(|| id.to_owned())
+        };
+        let mut result = serde_json::json!({
+            "from": person_json(&guard, from),
+            "to": person_json(&guard, to),
+        });
+        match kinship::connection(&guard, from, to, &edges, max_hops) {
+            kinship::Connection::Found(hops) => {
+                let mut description = label(from);
+                let mut path = vec![person_json(&guard, from)];
+                for hop in &hops {
+                    let term = hop.edge.term(sex_of(&guard, &hop.id));
+                    description.push_str(&format!("'s {term}"));
+                    let mut step = person_json(&guard, &hop.id);
+                    step["relation"] = Value::from(term);
+                    step["edge"] = serde_json::to_value(hop.edge).unwrap_or(Value::Null);
+                    step["family"] = Value::from(hop.family.as_str());
+                    path.push(step);
+                }
+                result["connected"] = Value::Bool(true);
+                result["hops"] = Value::from(hops.len());
+                result["description"] = if hops.is_empty() {
+                    Value::from(format!("{description} is the same person"))
+                } else {
+                    Value::from(format!("{description} is {}", label(to)))
+                };
+                result["path"] = Value::Array(path);
+            }
+            kinship::Connection::Disconnected => {
+                result["connected"] = Value::Bool(false);
+                result["reason"] = Value::from("disconnected");
+            }
+            kinship::Connection::HopLimit => {
+                result["connected"] = Value::Bool(false);
+                result["reason"] = Value::from("hop_limit");
+            }
+        }
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result,
+        })
+    }
+
+    /// Pedigree collapse of `id`: repeated ancestors and distinct versus
+    /// theoretical ancestors per generation, plus the ancestors shared with
+    /// `other` when given.
+    fn handle_analyze_pedigree(&self, request: Request) -> OutboundMessage {
+        let max_generations =
+            match count_param(&request, "max_generations", DEFAULT_ANALYSIS_GENERATIONS) {
+                Ok(count) => count.min(MAX_ANALYSIS_GENERATIONS),
+                Err(err) => return err,
+            };
+        let (id, guard) = match self.relative_query(&request) {
+            Ok(query) => query,
+            Err(err) => return err,
+        };
+        let other = request.params.get("other").and_then(Value::as_str);
+        if let Some(other) = other
+            && guard.get_individual(other).is_none()
+        {
+            return OutboundMessage::Error(ErrorResponse::not_found(
+                request.id,
+                format!("individual {other} not found"),
+            ));
+        }
+
+        let pedigree = pedigree::appearances(&guard, &id, max_generations);
+        let ancestors = &pedigree[1..];
+        let known = ancestors
+            .iter()
+            .flat_map(HashMap::values)
+            .fold(0u64, |sum, count| sum.saturating_add(*count));
+        let distinct = ancestors
+            .iter()
+            .flat_map(HashMap::keys)
+            .collect::<HashSet<_>>()
+            .len();
+        let with_name = |value: Value, id: &str| {
+            let mut value = value;
+            value["name"] = person_json(&guard, id)["name"].take();
+            value
+        };
+        let repeated: Vec<Value> = pedigree::repeated_ancestors(&pedigree)
+            .iter()
+            .map(|ancestor| {
+                with_name(
+                    serde_json::to_value(ancestor).unwrap_or(Value::Null),
+                    &ancestor.id,
+                )
+            })
+            .collect();
+
+        let mut result = serde_json::json!({
+            "id": id,
+            "generations": pedigree::implex(&pedigree),
+            "totals": {
+                "known": known,
+                "distinct": distinct,
+                "collapse": pedigree::collapse(distinct, known),
+            },
+            "repeated_ancestors": repeated,
+        });
+        if let Some(other) = other {
+            let theirs = pedigree::appearances(&guard, other, max_generations);
+            let common: Vec<Value> = pedigree::common_ancestors(&guard, &pedigree, &theirs)
+                .iter()
+                .map(|ancestor| {
+                    with_name(
+                        serde_json::to_value(ancestor).unwrap_or(Value::Null),
+                        &ancestor.id,
+                    )
+                })
+                .collect();
+            result["other"] = Value::from(other);
+            result["common_ancestors"] = Value::Array(common);
+        }
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result,
+        })
+    }
+
+    /// Wright's coefficient of inbreeding of `id` and, with `other`, the
+    /// coefficient of relationship between them, each broken down by common
+    /// ancestor.
+    fn handle_get_kinship_coefficients(&self, request: Request) -> OutboundMessage {
+        let max_generations =
+            match count_param(&request, "max_generations", DEFAULT_KINSHIP_GENERATIONS) {
+                Ok(count) => count,
+                Err(err) => return err,
+            };
+        let (id, guard) = match self.relative_query(&request) {
+            Ok(query) => query,
+            Err(err) => return err,
+        };
+        let other = request.params.get("other").and_then(Value::as_str);
+        if let Some(other) = other
+            && guard.get_individual(other).is_none()
+        {
+            return OutboundMessage::Error(ErrorResponse::not_found(
+                request.id,
+                format!("individual {other} not found"),
+            ));
+        }
+
+        let coefficient_json = |coefficient: inbreeding::Coefficient| {
+            let mut value = serde_json::to_value(&coefficient).unwrap_or(Value::Null);
+            for contribution in value["contributions"].as_array_mut().into_iter().flatten() {
+                let ancestor = contribution["ancestor"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned();
+                contribution["name"] = person_json(&guard, &ancestor)["name"].take();
+            }
+            value
+        };
+        let mut calculator = Calculator::new(&guard);
+        let computed = calculator.inbreeding(&id, max_generations).and_then(|own| {
+            let relationship = other
+                .map(|other| calculator.relationship(&id, other, max_generations))
+                .transpose()?;
+            Ok((own, relationship))
+        });
+        let (own, relationship) = match computed {
+            Ok(computed) => computed,
+            Err(err) => {
+                return OutboundMessage::Error(ErrorResponse::invalid_params(
+                    request.id,
+                    err.to_string(),
+                ));
+            }
+        };
+
+        let mut result = serde_json::json!({
+            "id": id,
+            "max_generations": max_generations,
+            "inbreeding": coefficient_json(own),
+        });
+        if let (Some(other), Some(relationship)) = (other, relationship) {
+            result["other"] = Value::from(other);
+            result["relationship"] = coefficient_json(relationship);
+        }
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result,
+        })
+    }
+
+    /// Strict paternal (Y-DNA) or maternal (mtDNA) line above `id`.
+    fn handle_get_dna_line(&self, request: Request) -> OutboundMessage {
+        let lineage = match lineage_param(&request) {
+            Ok(lineage) => lineage,
+            Err(err) => return err,
+        };
+        let (id, guard) = match self.relative_query(&request) {
+            Ok(query) => query,
+            Err(err) => return err,
+        };
+
+        let line = pedigree::line(&guard, &id, lineage);
+        let ancestors: Vec<Value> = line
+            .iter()
+            .zip(0u32..)
+            .skip(1)
+            .map(|(ancestor, generation)| {
+                let mut value = person_json(&guard, ancestor);
+                value["generation"] = Value::from(generation);
+                value
+            })
+            .collect();
+        // Everyone carries their mother's mtDNA; only men carry a Y line.
+        let carries = match (lineage, sex_of(&guard, &id)) {
+            (Lineage::Maternal, _) => Some(true),
+            (Lineage::Paternal, Some(Sex::Male)) => Some(true),
+            (Lineage::Paternal, Some(Sex::Female)) => Some(false),
+            (Lineage::Paternal, _) => None,
+        };
+
+        let result = serde_json::json!({
+            "id": id,
+            "line": lineage,
+            "carries": carries,
+            "ancestors": ancestors,
+            "most_distant": line.last().map(|ancestor| person_json(&guard, ancestor)),
+        });
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result,
+        })
+    }
+
+    /// Descendants of the most distant ancestor on `id`'s Y or mtDNA line who
+    /// carry that line, optionally only those presumed living or born after
+    /// `born_after`.
+    fn handle_find_dna_carriers(&self, request: Request) -> OutboundMessage {
+        let lineage = match lineage_param(&request) {
+            Ok(lineage) => lineage,
+            Err(err) => return err,
+        };
+        let living_only = request
+            .params
+            .get("living_only")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let born_after = match date_bound(&request, "born_after", true) {
+            Ok(bound) => bound,
+            Err(err) => return err,
+        };
+        let (id, guard) = match self.relative_query(&request) {
+            Ok(query) => query,
+            Err(err) => return err,
+        };
+
+        let line = pedigree::line(&guard, &id, lineage);
+        let ancestor = line[line.len() - 1];
+        let today = today_jdn();
+        let carriers: Vec<Value> = pedigree::carriers(&guard, ancestor, lineage)
+            .into_iter()
+            .filter_map(|(carrier, generation)| {
+                let individual = guard.get_individual(carrier);
+                let living = individual
+                    .is_some_and(|individual| lifespan::is_presumed_living(individual, today));
+                let birth = individual
+                    .and_then(|individual| individual.birth.as_ref())
+                    .and_then(|birth| birth.date.as_deref());
+                let born_late_enough = match (born_after, birth) {
+                    (Some(cutoff), Some(birth)) => GedcomDate::parse(birth)
+                        .ok()
+                        .and_then(|date| date.latest_jdn)
+                        .is_none_or(|latest| latest >= cutoff),
+                    _ => true,
+                };
+                if (living_only && !living) || !born_late_enough {
+                    return None;
+                }
+                let mut value = person_json(&guard, carrier);
+                value["generation"] = Value::from(generation);
+                value["birth"] = Value::from(birth);
+                value["presumed_living"] = Value::Bool(living);
+                Some(value)
+            })
+            .collect();
+
+        let result = serde_json::json!({
+            "id": id,
+            "line": lineage,
+            "ancestor": person_json(&guard, ancestor),
+            "count": carriers.len(),
+            "carriers": carriers,
+        });
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result,
+        })
+    }
+
+    /// Ranked name search over the store's name index. `query` terms match any
+    /// part of a name, `given` and `surname` terms only that part; every term
+    /// must match.
+    fn handle_search_individuals(&self, request: Request) -> OutboundMessage {
+        let terms = |name: &str| -> Vec<String> {
+            request
+                .params
+                .get(name)
+                .and_then(Value::as_str)
+                .map(|value| {
+                    text::fold(value)
+                        .split(' ')
+                        .filter(|t| !t.is_empty())
+                        .map(str::to_owned)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let flag = |name: &str| {
+            request
+                .params
+                .get(name)
+                .and_then(Value::as_bool)
+                .unwrap_or(true)
+        };
+        let query = NameQuery {
+            any: terms("query"),
+            given: terms("given"),
+            surname: terms("surname"),
+            fuzzy: flag("fuzzy"),
+            phonetic: flag("phonetic"),
+        };
+        if query.is_empty() {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "at least one of query, given or surname is required",
+            ));
+        }
+        let (offset, limit) = match page_params(&request, DEFAULT_SEARCH_LIMIT) {
+            Ok(page) => page,
+            Err(err) => return err,
+        };
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+
+        let matches = guard.names().search(&query);
+        let total = matches.len();
+        let results: Vec<Value> = matches
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|found| {
+                let individual = guard.get_individual(&found.id);
+                let date = |event: Option<&Event>| event.and_then(|event| event.date.clone());
+                let mut value = person_json(&guard, &found.id);
+                value["birth"] = Value::from(date(individual.and_then(|i| i.birth.as_ref())));
+                value["death"] = Value::from(date(individual.and_then(|i| i.death.as_ref())));
+                value["score"] = Value::from(found.score);
+                value["matches"] = serde_json::to_value(&found.matches).unwrap_or(Value::Null);
+                value
+            })
+            .collect();
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: page_json("results", results, offset, total),
+        })
+    }
+    /// Runs a query-language `query`, paginated like the list tools, and
+    /// reports the `plan` it was run with.
+    fn handle_query(&self, request: Request) -> OutboundMessage {
+        let Some(source) = request.params.get("query").and_then(Value::as_str) else {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "missing required param: query",
+            ));
+        };
+        let query = match query::parse(source) {
+            Ok(query) => query,
+            Err(err) => {
+                let mut error = ErrorResponse::invalid_params(request.id, err.to_string());
+                error.error.data = Some(serde_json::json!({"column": err.column}));
+                return OutboundMessage::Error(error);
+            }
+        };
+        let (offset, limit) = match page_params(&request, DEFAULT_LIST_LIMIT) {
+            Ok(page) => page,
+            Err(err) => return err,
+        };
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+
+        let (ids, plan) = query.run(&guard);
+        let items: Vec<Value> = ids
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|id| query.project(&guard, id))
+            .collect();
+        let mut result = page_json("results", items, offset, ids.len());
+        result["plan"] = serde_json::to_value(plan).unwrap_or(Value::Null);
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result,
+        })
+    }
+
+    /// Full-text `search` over names, places, occupations, notes and
+    /// citations; results carry a score and highlighted snippets.
+    fn handle_search(&self, request: Request) -> OutboundMessage {
+        let Some(source) = request.params.get("query").and_then(Value::as_str) else {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "missing required param: query",
+            ));
+        };
+        let query = match fulltext::parse(source) {
+            Ok(query) => query,
+            Err(err) => {
+                let mut error = ErrorResponse::invalid_params(request.id, err.to_string());
+                error.error.data = Some(serde_json::json!({"column": err.column}));
+                return OutboundMessage::Error(error);
+            }
+        };
+        let (offset, limit) = match page_params(&request, DEFAULT_SEARCH_LIMIT) {
+            Ok(page) => page,
+            Err(err) => return err,
+        };
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+
+        let hits = fulltext::search(&guard, &query);
+        let total = hits.len();
+        let results: Vec<Value> = hits
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|hit| {
+                let mut value = match &hit.record {
+                    RecordRef::Individual(id) => {
+                        let mut value = person_json(&guard, id);
+                        value["type"] = Value::from("individual");
+                        value
+                    }
+                    RecordRef::Family(id) => {
+                        let family = guard.get_family(id);
+                        let spouse = |spouse: Option<&String>| {
+                            spouse.map_or(Value::Null, |id| person_json(&guard, id))
+                        };
+                        serde_json::json!({
+                            "type": "family",
+                            "id": id,
+                            "husband": spouse(family.and_then(|f| f.husband.as_ref())),
+                            "wife": spouse(family.and_then(|f| f.wife.as_ref())),
+                        })
+                    }
+                };
+                value["score"] = Value::from(hit.score);
+                value["snippets"] = serde_json::to_value(&hit.snippets).unwrap_or(Value::Null);
+                value
+            })
+            .collect();
+
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: page_json("results", results, offset, total),
+        })
+    }
+
+    fn handle_get_statistics(&self, request: Request) -> OutboundMessage {
+        let top = match count_param(&request, "top", DEFAULT_STATISTICS_TOP) {
+            Ok(top) => top.clamp(1, MAX_PAGE_LIMIT),
+            Err(err) => return err,
+        };
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+        let stats = statistics::statistics(&guard, top as usize);
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: serde_json::to_value(stats).unwrap_or(Value::Null),
+        })
+    }
+
+    fn handle_list_resources(&self, request: Request) -> OutboundMessage {
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: serde_json::json!({
+                "resources": [{
+                    "uri": SUMMARY_RESOURCE,
+                    "name": "Tree summary",
+                    "description": "Record counts, common names, births and deaths per decade, lifespans, top places and completeness of the loaded tree.",
+                    "mimeType": "application/json",
+                }],
+            }),
+        })
+    }
+
+    /// Reads a resource from `resources/list`; the contents are returned as
+    /// JSON text.
+    fn handle_read_resource(&self, request: Request) -> OutboundMessage {
+        let Some(uri) = request.params.get("uri").and_then(Value::as_str) else {
+            return OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id,
+                "missing required param: uri",
+            ));
+        };
+        if uri != SUMMARY_RESOURCE {
+            return OutboundMessage::Error(ErrorResponse::not_found(
+                request.id,
+                format!("resource {uri} not found"),
+            ));
+        }
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+        let stats = statistics::statistics(&guard, DEFAULT_STATISTICS_TOP as usize);
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: serde_json::json!({
+                "contents": [{
+                    "uri": SUMMARY_RESOURCE,
+                    "mimeType": "application/json",
+                    "text": serde_json::to_string(&stats).unwrap_or_default(),
+                }],
+            }),
+        })
+    }
+
+    /// Runs the consistency rules named in `rules` (all by default) except
+    /// those in `disable`, paginated like the list tools.
+    fn handle_audit(&self, request: Request) -> OutboundMessage {
+        let rule_names = |name: &str| -> Result<Option<Vec<Rule>>, OutboundMessage> {
+            let Some(value) = request.params.get(name) else {
+                return Ok(None);
+            };
+            let names = value.as_array().ok_or_else(|| {
+                OutboundMessage::Error(ErrorResponse::invalid_params(
+                    request.id.clone(),
+                    format!("{name} must be an array of rule names"),
+                ))
+            })?;
+            names
+                .iter()
+                .map(|name| {
+                    name.as_str().and_then(Rule::parse).ok_or_else(|| {
+                        let known: Vec<&str> = Rule::ALL.iter().map(|rule| rule.name()).collect();
+                        OutboundMessage::Error(ErrorResponse::invalid_params(
+                            request.id.clone(),
+                            format!("unknown rule {name}; expected one of {}", known.join(", ")),
+                        ))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(Some)
+        };
+        let mut rules: BTreeSet<Rule> = match rule_names("rules") {
+            Ok(Some(rules)) => rules.into_iter().collect(),
+            Ok(None) => Rule::ALL.into_iter().collect(),
+            Err(err) => return err,
+        };
+        match rule_names("disable") {
+            Ok(disabled) => {
+                for rule in disabled.into_iter().flatten() {
+                    rules.remove(&rule);
+                }
+            }
+            Err(err) => return err,
+        }
+        let (offset, limit) = match page_params(&request, DEFAULT_LIST_LIMIT) {
+            Ok(page) => page,
+            Err(err) => return err,
+        };
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+
+        let findings = audit::audit(&guard, &rules);
+        let total = findings.len();
+        let items: Vec<Value> = findings
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|finding| serde_json::to_value(finding).unwrap_or(Value::Null))
+            .collect();
+        let mut result = page_json("findings", items, offset, total);
+        result["rules"] = rules.iter().map(|rule| rule.name()).collect();
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result,
+        })
+    }
+
+    /// Likely duplicate individuals, ranked, optionally only those of `id`.
+    fn handle_find_duplicates(&self, request: Request) -> OutboundMessage {
+        let min_score = match request.params.get("min_score") {
+            None => DEFAULT_DUPLICATE_SCORE,
+            Some(value) => match value.as_f64() {
+                Some(score) if (0.0..=1.0).contains(&score) => score,
+                _ => {
+                    return OutboundMessage::Error(ErrorResponse::invalid_params(
+                        request.id,
+                        "min_score must be a number between 0 and 1",
+                    ));
+                }
+            },
+        };
+        let (offset, limit) = match page_params(&request, DEFAULT_SEARCH_LIMIT) {
+            Ok(page) => page,
+            Err(err) => return err,
+        };
+        let only = match request.params.get("id") {
+            None => None,
+            Some(Value::String(id)) => Some(id.as_str()),
+            Some(_) => {
+                return OutboundMessage::Error(ErrorResponse::invalid_params(
+                    request.id,
+                    "id must be a string",
+                ));
+            }
+        };
+        let guard = match self.lock_store(&request.id) {
+            Ok(guard) => guard,
+            Err(err) => return err,
+        };
+        if let Some(id) = only
+            && guard.get_individual(id).is_none()
+        {
+            return OutboundMessage::Error(ErrorResponse::not_found(
+                request.id,
+                format!("individual {id} not found"),
+            ));
+        }
+
+        let found = duplicates::find_duplicates(&guard, min_score, only);
+        let total = found.len();
+        let items: Vec<Value> = found
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|pair| {
+                serde_json::json!({
+                    "a": person_json(&guard, &pair.a),
+                    "b": person_json(&guard, &pair.b),
+                    "score": pair.score,
+                    "matches": pair.matches,
+                    "conflicts": pair.conflicts,
+                })
+            })
+            .collect();
+        OutboundMessage::Response(Response {
+            id: request.id,
+            result: page_json("duplicates", items, offset, total),
+        })
+    }
+}
+
+/// Compact reference to an individual: ID and name, if the record exists.
+fn person_json(store: &GedcomStore, id: &str) -> Value {
+    serde_json::json!({
+        "id": id,
+        "name": store.get_individual(id).and_then(|individual| individual.name.clone()),
+    })
+}
+
+/// Nests a traversal under its root: parents as `father` / `mother`, and
+/// children, spouses, siblings and step-children as arrays. Built bottom-up
+/// so deep trees do not recurse.
+fn nest_tree(store: &GedcomStore, nodes: &[TreeNode]) -> Value {
+    let mut attached: Vec<Vec<(Relation, Value)>> = vec![Vec::new(); nodes.len()];
+    let mut root = Value::Null;
+    for (idx, node) in nodes.iter().enumerate().rev() {
+        let mut value = node_json(store, node);
+        for (relation, related) in std::mem::take(&mut attached[idx]).into_iter().rev() {
+            let key = match relation {
+                Relation::Father => "father",
+                Relation::Mother => "mother",
+                Relation::Child => "children",
+                Relation::Spouse => "spouses",
+                Relation::Sibling => "siblings",
+                Relation::StepChild => "step_children",
+                Relation::Root => continue,
+            };
+            if matches!(relation, Relation::Father | Relation::Mother) {
+                value[key] = related;
+            } else if let Some(items) = value[key].as_array_mut() {
+                items.push(related);
+            } else {
+                value[key] = Value::Array(vec![related]);
+            }
+        }
+        match node.parent {
+            Some(parent) => attached[parent].push((node.relation, value)),
+            None => root = value,
+        }
+    }
+    root
+}
+
+fn node_json(store: &GedcomStore, node: &TreeNode) -> Value {
+    let mut value = serde_json::to_value(node).unwrap_or(Value::Null);
+    value["name"] = person_json(store, &node.id)["name"].take();
+    value
+}
+
+/// Serializes an individual, adding the parsed form of each event date.
+/// Addresses of individuals presumed living or under a confidential or
+/// privacy restriction are left out unless `include_private` is set.
+fn individual_json(individual: &Individual, include_private: bool) -> Value {
+    let hide_addresses = !include_private
+        && (individual.metadata.is_restricted()
+            || lifespan::is_presumed_living(individual, today_jdn()));
+    let mut value = serde_json::to_value(individual).unwrap_or(Value::Null);
+    for (key, event) in [("birth", &individual.birth), ("death", &individual.death)] {
+        if let Some(event) = event {
+            value[key] = event_json(event, hide_addresses);
+        }
+    }
+    if !individual.events.is_empty() {
+        value["events"] = individual
+            .events
+            .iter()
+            .map(|event| event_json(event, hide_addresses))
+            .collect();
+    }
+    if hide_addresses
+        && individual
+            .tagged_events()
+            .any(|(_, event)| event.address.is_some())
+    {
+        value["addresses_hidden"] = Value::Bool(true);
+    }
+    value
+}
+
+/// Serializes a family, leaving out event addresses while it is restricted or
+/// either spouse is presumed living unless `include_private` is set.
+fn family_json(store: &GedcomStore, family: &Family, include_private: bool) -> Value {
+    let today = today_jdn();
+    let hide_addresses = !include_private
+        && (family.metadata.is_restricted()
+            || [&family.husband, &family.wife]
+                .into_iter()
+                .flatten()
+                .filter_map(|id| store.get_individual(id))
+                .any(|spouse| lifespan::is_presumed_living(spouse, today)));
+    let mut value = serde_json::to_value(family).unwrap_or(Value::Null);
+    if !family.events.is_empty() {
+        value["events"] = family
+            .events
+            .iter()
+            .map(|event| event_json(event, hide_addresses))
+            .collect();
+    }
+    if hide_addresses && family.events.iter().any(|event| event.address.is_some()) {
+        value["addresses_hidden"] = Value::Bool(true);
+    }
+    value
+}
+
+fn event_json(event: &Event, hide_address: bool) -> Value {
+    let mut value = serde_json::to_value(event).unwrap_or(Value::Null);
+    if hide_address && let Value::Object(map) = &mut value {
+        map.remove("address");
+    }
+    if let Some(date) = event
+        .date
+        .as_deref()
+        .and_then(|date| GedcomDate::parse(date).ok())
+    {
+        value["parsed_date"] = serde_json::to_value(date).unwrap_or(Value::Null);
+    }
+    value
+}
+
+/// Metadata for a record created through the API, stamped with `now` as both
+/// its creation and last change.
+fn created_metadata(now: ChangeStamp) -> RecordMetadata {
+    RecordMetadata {
+        changed: Some(now.clone()),
+        created: Some(now),
+        ..Default::default()
+    }
+}
+
+/// Optional non-negative integer param, saturating at `u32::MAX`.
+fn count_param(request: &Request, name: &str, default: u32) -> Result<u32, OutboundMessage> {
+    match request.params.get(name) {
+        None => Ok(default),
+        Some(value) => match value.as_u64() {
+            Some(count) => Ok(u32::try_from(count).unwrap_or(u32::MAX)),
+            None => Err(OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id.clone(),
+                format!("{name} must be a non-negative integer"),
+            ))),
+        },
+    }
+}
+
+/// `cursor` and `limit` params as an offset and page size. Cursors are the
+/// `nextCursor` of a previous page and opaque to clients.
+fn page_params(request: &Request, default_limit: u32) -> Result<(usize, usize), OutboundMessage> {
+    let limit = count_param(request, "limit", default_limit)?.clamp(1, MAX_PAGE_LIMIT);
+    let offset = match request.params.get("cursor") {
+        None | Some(Value::Null) => 0,
+        Some(cursor) => match cursor.as_str().and_then(|cursor| cursor.parse().ok()) {
+            Some(offset) => offset,
+            None => {
+                return Err(OutboundMessage::Error(ErrorResponse::invalid_params(
+                    request.id.clone(),
+                    "invalid cursor",
+                )));
+            }
+        },
+    };
+    Ok((offset, limit as usize))
+}
+
+/// One page of a paginated list: the `items` under `key`, the `total` number
+/// of matches and the `nextCursor` of the following page, if any.
+fn page_json(key: &str, items: Vec<Value>, offset: usize, total: usize) -> Value {
+    let next = offset + items.len();
+    let mut page = serde_json::json!({
+        "total": total,
+        "nextCursor": (next < total).then(|| next.to_string()),
+    });
+    page[key] = Value::Array(items);
+    page
+}
+
+/// Optional GEDCOM date param as the earliest (or latest) Julian day number
+/// it covers.
+fn date_bound(
+    request: &Request,
+    name: &str,
+    earliest: bool,
+) -> Result<Option<i64>, OutboundMessage> {
+    let Some(date) = request.params.get(name).and_then(Value::as_str) else {
+        return Ok(None);
+    };
+    let bound = GedcomDate::parse(date).ok().and_then(|date| {
+        if earliest {
+            date.earliest_jdn
+        } else {
+            date.latest_jdn
+        }
+    });
+    match bound {
+        Some(bound) => Ok(Some(bound)),
+        None => Err(OutboundMessage::Error(ErrorResponse::invalid_params(
+            request.id.clone(),
+            format!("invalid {name} date: {date}"),
+        ))),
+    }
+}
+
+/// Filters and sort order shared by `list_individuals` and `list_families`.
+fn list_params(
+    request: &Request,
+    store: &GedcomStore,
+) -> Result<(PersonFilter, SortKey), OutboundMessage> {
+    let invalid = |message: String| {
+        OutboundMessage::Error(ErrorResponse::invalid_params(request.id.clone(), message))
+    };
+    let text = |name: &str| request.params.get(name).and_then(Value::as_str);
+
+    let sort = match text("sort") {
+        None => SortKey::Id,
+        Some(sort) => SortKey::parse(sort)
+            .ok_or_else(|| invalid(format!("sort must be id, surname or birth, not {sort}")))?,
+    };
+    let sex = match text("sex") {
+        None => None,
+        Some(sex) => Some(
+            Sex::parse(sex)
+                .ok_or_else(|| invalid(format!("sex must be M, F, X or U, not {sex}")))?,
+        ),
+    };
+    let died_at = text("died_at").map(|place| {
+        store
+            .places()
+            .matching(place, true)
+            .into_iter()
+            .map(|entry| place::place_key(&entry.name))
+            .collect()
+    });
+    let filter = PersonFilter {
+        born_after: date_bound(request, "born_after", true)?,
+        born_before: date_bound(request, "born_before", false)?,
+        died_at,
+        no_parents: request
+            .params
+            .get("has_no_parents")
+            .and_then(Value::as_bool)
+            .unwrap_or(false),
+        sex,
+        surname: text("surname").map(text::fold),
+    };
+    Ok((filter, sort))
+}
+
+/// Required `line` param: `y` / `paternal` or `mt` / `maternal`.
+fn lineage_param(request: &Request) -> Result<Lineage, OutboundMessage> {
+    request
+        .params
+        .get("line")
+        .and_then(Value::as_str)
+        .and_then(Lineage::parse)
+        .ok_or_else(|| {
+            OutboundMessage::Error(ErrorResponse::invalid_params(
+                request.id.clone(),
+                "line must be y (paternal) or mt (maternal)",
+            ))
+        })
+}
+
+fn include_private(request: &Request) -> bool {
+    request
+        .params
+        .get("include_private")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+fn parse_event(value: Option<&Value>) -> Option<Event> {
+    let Value::Object(map) = value? else {
+        return None;
+    };
+
+    let date = map.get("date").and_then(Value::as_str).map(str::to_owned);
+    let place = map.get("place").and_then(Value::as_str).map(str::to_owned);
+    let age = map.get("age").and_then(Value::as_str).map(str::to_owned);
+
+    if date.is_none() && place.is_none() && age.is_none() {
+        None
+    } else {
+        Some(Event {
+            date,
+            place,
+            age,
+            ..Default::default()
+        })
+    }
+}
+
+impl ErrorResponse {
+    pub fn method_not_found(id: String, method: impl Into<String>) -> Self {
+        Self {
+            id,
+            error: ErrorObject {
+                code: -32601, // JSON-RPC method not found
+                message: format!("method not found: {}", method.into()),
+                data: None,
+            },
+        }
+    }
+
+    pub fn parse_error(message: impl Into<String>) -> Self {
+        Self {
+            id: "null".into(),
+            error: ErrorObject {
+                code: -32700, // JSON-RPC parse error
+                message: message.into(),
+                data: None,
+            },
+        }
+    }
+
+    pub fn invalid_params(id: String, message: impl Into<String>) -> Self {
+        Self {
+            id,
+            error: ErrorObject {
+                code: -32602,
+                message: message.into(),
+                data: None,
+            },
+        }
+    }
+
+    pub fn server_error(id: String, message: impl Into<String>) -> Self {
+        Self {
+            id,
+            error: ErrorObject {
+                code: -32000,
+                message: message.into(),
+                data: None,
+            },
+        }
+    }
+
+    pub fn not_found(id: String, message: impl Into<String>) -> Self {
+        Self {
+            id,
+            error: ErrorObject {
+                code: -32004,
+                message: message.into(),
+                data: None,
+            },
+        }
+    }
+
+    pub fn conflict(id: String, message: impl Into<String>) -> Self {
+        Self {
+            id,
+            error: ErrorObject {
+                code: -32001,
+                message: message.into(),
+                data: None,
+            },
+        }
+    }
+}
+
+pub fn parse_request(input: &str) -> Result<Request, serde_json::Error> {
+    serde_json::from_str(input)
+}
+
+pub fn serialize_message(message: &OutboundMessage) -> Result<String, serde_json::Error> {
+    serde_json::to_string(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gedcom::{Family, GedcomData, GedcomStore, Individual};
+
+    #[test]
+    fn round_trips_request_json() {
+        let json = r#"{"id":"1","method":"ping","params":{"echo":"hi"}}"#;
+        let request = parse_request(json).expect("should parse");
+        assert_eq!(
+            request,
+            Request {
+                id: "1".into(),
+                method: "ping".into(),
                 params: serde_json::json!({"echo": "hi"})
             }
-        );
-        let serialized = serde_json::to_string(&request).expect("should serialize");
-        assert_eq!(
-            serde_json::from_str::<Request>(&serialized).unwrap(),
-            request
-        );
+        );
+        let serialized = serde_json::to_string(&request).expect("should serialize");
+        assert_eq!(
+            serde_json::from_str::<Request>(&serialized).unwrap(),
+            request
+        );
+    }
+
+    #[test]
+    fn handles_ping_request() {
+        let server = Server::default();
+        let response = server.handle_request(Request {
+            id: "1".into(),
+            method: "ping".into(),
+            params: Value::Null,
+        });
+
+        assert_eq!(
+            response,
+            OutboundMessage::Response(Response {
+                id: "1".into(),
+                result: serde_json::json!({ "status": "ok" })
+            })
+        );
+    }
+
+    #[test]
+    fn returns_error_for_unknown_method() {
+        let server = Server::default();
+        let response = server.handle_request(Request {
+            id: "2".into(),
+            method: "unknown".into(),
+            params: Value::Null,
+        });
+
+        match response {
+            OutboundMessage::Error(error) => {
+                assert_eq!(error.id, "2");
+                assert_eq!(error.error.code, -32601);
+                assert!(error.error.message.contains("method not found"));
+            }
+            other => panic!("expected error response, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn serializes_outbound_message() {
+        let message = OutboundMessage::Response(Response {
+            id: "3".into(),
+            result: serde_json::json!({"status": "ok"}),
+        });
+
+        let json = serialize_message(&message).expect("should serialize");
+        assert!(json.contains("\"status\":\"ok\""));
+        let deserialized: OutboundMessage =
+            serde_json::from_str(&json).expect("should deserialize");
+        assert_eq!(deserialized, message);
+    }
+
+    #[test]
+    fn returns_parse_error_for_invalid_json() {
+        let server = Server::default();
+        let response = server.handle_raw_message("{ invalid json");
+
+        match response {
+            OutboundMessage::Error(error) => {
+                assert_eq!(error.error.code, -32700);
+                assert!(
+                    !error.error.message.is_empty(),
+                    "parse error message should be present"
+                );
+            }
+            other => panic!("expected parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn processes_json_line_happy_path() {
+        let server = Server::default();
+        let raw = r#"{"id":"1","method":"ping","params":{}}"#;
+        let output = server.handle_json_line(raw).expect("should serialize");
+        let message: OutboundMessage =
+            serde_json::from_str(&output).expect("should deserialize outbound");
+
+        match message {
+            OutboundMessage::Response(resp) => {
+                assert_eq!(resp.id, "1");
+                assert_eq!(resp.result, serde_json::json!({"status": "ok"}));
+            }
+            other => panic!("expected response, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn processes_json_line_with_parse_error() {
+        let server = Server::default();
+        let output = server
+            .handle_json_line("{ invalid json")
+            .expect("serialize error response");
+
+        let message: OutboundMessage =
+            serde_json::from_str(&output).expect("should deserialize error");
+
+        match message {
+            OutboundMessage::Error(err) => {
+                assert_eq!(err.error.code, -32700);
+                assert!(
+                    !err.error.message.is_empty(),
+                    "error message should be present"
+                );
+            }
+            other => panic!("expected error, got {other:?}"),
+        }
+    }
+
+    fn build_store() -> GedcomStore {
+        let data = GedcomData {
+            individuals: vec![Individual {
+                id: "I1".into(),
+                name: Some("Indexed".into()),
+                birth: Some(crate::gedcom::Event {
+                    date: Some("1 JAN 1900".into()),
+                    place: None,
+                    ..Default::default()
+                }),
+                death: None,
+                ..Default::default()
+            }],
+            families: vec![],
+            ..Default::default()
+        };
+        GedcomStore::from_data(data)
+    }
+
+    #[test]
+    fn returns_individual_details() {
+        let server = Server::new(Some(build_store()));
+        let response = server.handle_request(Request {
+            id: "42".into(),
+            method: "get_individual".into(),
+            params: serde_json::json!({"id": "I1"}),
+        });
+
+        match response {
+            OutboundMessage::Response(resp) => {
+                assert_eq!(resp.id, "42");
+                assert_eq!(
+                    resp.result,
+                    serde_json::json!({
+                        "id": "I1",
+                        "name": "Indexed",
+                        "birth": {
+                            "date": "1 JAN 1900",
+                            "place": null,
+                            "parsed_date": {
+                                "kind": "exact",
+                                "date": {
+                                    "calendar": "gregorian",
+                                    "year": 1900,
+                                    "month": 1,
+                                    "day": 1
+                                },
+                                "earliest_jdn": 2415021,
+                                "latest_jdn": 2415021
+                            }
+                        },
+                        "death": null
+                    })
+                );
+            }
+            other => panic!("expected response, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn returns_source_location_for_parsed_records() {
+        let data = crate::gedcom::parse_gedcom("0 HEAD\n0 @I1@ INDI\n1 NAME Located\n0 TRLR\n")
+            .expect("should parse");
+        let server = Server::new(Some(GedcomStore::from_data(data)));
+        let response = server.handle_request(Request {
+            id: "46".into(),
+            method: "get_individual".into(),
+            params: serde_json::json!({"id": "I1"}),
+        });
+
+        match response {
+            OutboundMessage::Response(resp) => {
+                assert_eq!(
+                    resp.result["source_location"],
+                    serde_json::json!({
+                        "line_start": 2,
+                        "line_end": 3,
+                        "byte_start": 7,
+                        "byte_end": 34
+                    })
+                );
+            }
+            other => panic!("expected response, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn errors_when_id_missing() {
+        let server = Server::new(Some(build_store()));
+        let response = server.handle_request(Request {
+            id: "43".into(),
+            method: "get_individual".into(),
+            params: serde_json::json!({}),
+        });
+
+        match response {
+            OutboundMessage::Error(err) => {
+                assert_eq!(err.id, "43");
+                assert_eq!(err.error.code, -32602);
+            }
+            other => panic!("expected error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn errors_when_individual_not_found() {
+        let server = Server::new(Some(build_store()));
+        let response = server.handle_request(Request {
+            id: "44".into(),
+            method: "get_individual".into(),
+            params: serde_json::json!({"id": "missing"}),
+        });
+
+        match response {
+            OutboundMessage::Error(err) => {
+                assert_eq!(err.id, "44");
+                assert_eq!(err.error.code, -32004);
+            }
+            other => panic!("expected error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn errors_when_store_missing() {
+        let server = Server::default();
+        let response = server.handle_request(Request {
+            id: "45".into(),
+            method: "get_individual".into(),
+            params: serde_json::json!({"id": "I1"}),
+        });
+
+        match response {
+            OutboundMessage::Error(err) => {
+                assert_eq!(err.id, "45");
+                assert_eq!(err.error.code, -32000);
+            }
+            other => panic!("expected error, got {other:?}"),
+        }
+    }
+
+    fn build_family_store() -> GedcomStore {
+        let data = GedcomData {
+            individuals: vec![],
+            families: vec![Family {
+                id: "F1".into(),
+                husband: Some("I1".into()),
+                wife: Some("I2".into()),
+                children: vec!["I3".into()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        GedcomStore::from_data(data)
+    }
+
+    fn empty_store() -> GedcomStore {
+        GedcomStore::from_data(GedcomData {
+            individuals: vec![],
+            families: vec![],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn lists_individuals() {
+        let server = Server::new(Some(build_store()));
+        let response = server.handle_request(Request {
+            id: "200".into(),
+            method: "list_individuals".into(),
+            params: Value::Null,
+        });
+
+        match response {
+            OutboundMessage::Response(resp) => {
+                assert_eq!(resp.id, "200");
+                assert_eq!(resp.result["nextCursor"], Value::Null);
+                let arr = resp.result["individuals"].as_array().unwrap();
+                assert_eq!(arr.len(), 1);
+                assert_eq!(arr[0]["id"], "I1");
+            }
+            other => panic!("expected response, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn lists_families() {
+        let server = Server::new(Some(build_family_store()));
+        let response = server.handle_request(Request {
+            id: "201".into(),
+            method: "list_families".into(),
+            params: Value::Null,
+        });
+
+        match response {
+            OutboundMessage::Response(resp) => {
+                assert_eq!(resp.id, "201");
+                assert_eq!(resp.result["nextCursor"], Value::Null);
+                let arr = resp.result["families"].as_array().unwrap();
+                assert_eq!(arr.len(), 1);
+                assert_eq!(arr[0]["id"], "F1");
+            }
+            other => panic!("expected response, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn returns_family_details() {
+        let server = Server::new(Some(build_family_store()));
+        let response = server.handle_request(Request {
+            id: "100".into(),
+            method: "get_family".into(),
+            params: serde_json::json!({"id": "F1"}),
+        });
+
+        match response {
+            OutboundMessage::Response(resp) => {
+                assert_eq!(resp.id, "100");
+                assert_eq!(
+                    resp.result,
+                    serde_json::json!({
+                        "id": "F1",
+                        "husband": "I1",
+                        "wife": "I2",
+                        "children": ["I3"]
+                    })
+                );
+            }
+            other => panic!("expected response, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn errors_when_family_missing() {
+        let server = Server::new(Some(build_family_store()));
+        let response = server.handle_request(Request {
+            id: "101".into(),
+            method: "get_family".into(),
+            params: serde_json::json!({"id": "missing"}),
+        });
+
+        match response {
+            OutboundMessage::Error(err) => {
+                assert_eq!(err.id, "101");
+                assert_eq!(err.error.code, -32004);
+            }
+            other => panic!("expected error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn errors_when_family_param_missing() {
+        let server = Server::new(Some(build_family_store()));
+        let response = server.handle_request(Request {
+            id: "102".into(),
+            method: "get_family".into(),
+            params: serde_json::json!({}),
+        });
+
+        match response {
+            OutboundMessage::Error(err) => {
+                assert_eq!(err.id, "102");
+                assert_eq!(err.error.code, -32602);
+            }
+            other => panic!("expected error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn errors_when_store_missing_for_family() {
+        let server = Server::default();
+        let response = server.handle_request(Request {
+            id: "103".into(),
+            method: "get_family".into(),
+            params: serde_json::json!({"id": "F1"}),
+        });
+
+        match response {
+            OutboundMessage::Error(err) => {
+                assert_eq!(err.id, "103");
+                assert_eq!(err.error.code, -32000);
+            }
+            other => panic!("expected error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn creates_individual() {
+        let server = Server::new(Some(empty_store()));
+        let response = server.handle_request(Request {
+            id: "300".into(),
+            method: "create_individual".into(),
+            params: serde_json::json!({
+                "id": "I99",
+                "name": "New Person",
+                "birth": { "date": "1 JAN 1990", "place": "Town" }
+            }),
+        });
+
+        match response {
+            OutboundMessage::Response(resp) => {
+                assert_eq!(resp.id, "300");
+                assert_eq!(resp.result["id"], "I99");
+                assert_eq!(resp.result["birth"]["date"], "1 JAN 1990");
+                assert_eq!(resp.result["birth"]["place"], "Town");
+            }
+            other => panic!("expected response, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn create_individual_conflict() {
+        let mut base = empty_store();
+        base.insert_individual(Individual {
+            id: "I1".into(),
+            name: None,
+            birth: None,
+            death: None,
+            ..Default::default()
+        })
+        .unwrap();
+        let server = Server::new(Some(base));
+
+        let response = server.handle_request(Request {
+            id: "301".into(),
+            method: "create_individual".into(),
+            params: serde_json::json!({
+                "id": "I1",
+                "name": "Dup"
+            }),
+        });
+
+        match response {
+            OutboundMessage::Error(err) => {
+                assert_eq!(err.id, "301");
+                assert_eq!(err.error.code, -32001);
+            }
+            other => panic!("expected error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn creates_family() {
+        let server = Server::new(Some(empty_store()));
+        let response = server.handle_request(Request {
+            id: "400".into(),
+            method: "create_family".into(),
+            params: serde_json::json!({
+                "id": "F9",
+                "husband": "I1",
+                "wife": "I2",
+                "children": ["I3", "I4"]
+            }),
+        });
+
+        match response {
+            OutboundMessage::Response(resp) => {
+                assert_eq!(resp.id, "400");
+                assert_eq!(resp.result["id"], "F9");
+                assert_eq!(resp.result["children"], serde_json::json!(["I3", "I4"]));
+            }
+            other => panic!("expected response, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn create_family_warns_about_loops() {
+        let mut base = empty_store();
+        base.insert_family(Family {
+            id: "F1".into(),
+            husband: Some("I1".into()),
+            children: vec!["I2".into()],
+            ..Default::default()
+        })
+        .unwrap();
+        let server = Server::new(Some(base));
+        let create = |id: &str, husband: &str, child: &str| match server.handle_request(Request {
+            id: "403".into(),
+            method: "create_family".into(),
+            params: serde_json::json!({"id": id, "husband": husband, "children": [child]}),
+        }) {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
+
+        assert!(create("F2", "I2", "I3").get("warnings").is_none());
+        let result = create("F3", "I3", "I1");
+        assert_eq!(result["id"], "F3");
+        assert_eq!(
+            result["warnings"],
+            serde_json::json!([
+                "parent/child loop: I1 is their own ancestor (I1 -> I3 -> I2 -> I1); 3 individuals affected"
+            ])
+        );
+    }
+
+    #[test]
+    fn create_family_conflict() {
+        let mut base = empty_store();
+        base.insert_family(Family {
+            id: "F1".into(),
+            husband: None,
+            wife: None,
+            children: vec![],
+            ..Default::default()
+        })
+        .unwrap();
+        let server = Server::new(Some(base));
+
+        let response = server.handle_request(Request {
+            id: "401".into(),
+            method: "create_family".into(),
+            params: serde_json::json!({
+                "id": "F1"
+            }),
+        });
+
+        match response {
+            OutboundMessage::Error(err) => {
+                assert_eq!(err.id, "401");
+                assert_eq!(err.error.code, -32001);
+            }
+            other => panic!("expected error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn create_family_validates_children() {
+        let server = Server::new(Some(empty_store()));
+        let response = server.handle_request(Request {
+            id: "402".into(),
+            method: "create_family".into(),
+            params: serde_json::json!({
+                "id": "F2",
+                "children": ["I1", 2]
+            }),
+        });
+
+        match response {
+            OutboundMessage::Error(err) => {
+                assert_eq!(err.id, "402");
+                assert_eq!(err.error.code, -32602);
+            }
+            other => panic!("expected error, got {other:?}"),
+        }
     }
 
     #[test]
-    fn handles_ping_request() {
+    fn create_handlers_require_store() {
         let server = Server::default();
+
+        let resp_individual = server.handle_request(Request {
+            id: "500".into(),
+            method: "create_individual".into(),
+            params: serde_json::json!({"id": "I1"}),
+        });
+        match resp_individual {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32000),
+            _ => panic!("expected server error"),
+        }
+
+        let resp_family = server.handle_request(Request {
+            id: "501".into(),
+            method: "create_family".into(),
+            params: serde_json::json!({"id": "F1"}),
+        });
+        match resp_family {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32000),
+            _ => panic!("expected server error"),
+        }
+    }
+
+    #[test]
+    fn create_individual_persists_to_storage() {
+        let tmp = tempfile::NamedTempFile::new().expect("temp file");
+        let server = Server::with_storage(empty_store(), tmp.path().to_path_buf());
+
         let response = server.handle_request(Request {
-            id: "1".into(),
-            method: "ping".into(),
-            params: Value::Null,
+            id: "600".into(),
+            method: "create_individual".into(),
+            params: serde_json::json!({"id": "I1", "name": "Persisted"}),
         });
 
-        assert_eq!(
-            response,
-            OutboundMessage::Response(Response {
-                id: "1".into(),
-                result: serde_json::json!({ "status": "ok" })
-            })
-        );
+        match response {
+            OutboundMessage::Response(_) => {}
+            other => panic!("expected response, got {other:?}"),
+        }
+
+        let contents = std::fs::read_to_string(tmp.path()).expect("read persisted file");
+        assert!(contents.contains("I1"));
+        assert!(contents.contains("Persisted"));
     }
 
     #[test]
-    fn returns_error_for_unknown_method() {
-        let server = Server::default();
+    fn finds_individual_by_familysearch_id() {
+        let data = crate::gedcom::parse_gedcom(
+            "0 @I1@ INDI\n1 NAME Found\n1 _FSFTID KWCB-123\n1 _UID 1234\n0 @I2@ INDI\n1 NAME Other\n",
+        )
+        .expect("should parse");
+        let server = Server::new(Some(GedcomStore::from_data(data)));
+
         let response = server.handle_request(Request {
-            id: "2".into(),
-            method: "unknown".into(),
-            params: Value::Null,
+            id: "700".into(),
+            method: "find_by_vendor_id".into(),
+            params: serde_json::json!({"familysearch_id": "KWCB-123"}),
         });
+        match response {
+            OutboundMessage::Response(resp) => {
+                let arr = resp.result.as_array().expect("array result");
+                assert_eq!(arr.len(), 1);
+                assert_eq!(arr[0]["id"], "I1");
+                assert_eq!(arr[0]["vendor"]["familysearch_id"], "KWCB-123");
+            }
+            other => panic!("expected response, got {other:?}"),
+        }
 
+        let response = server.handle_request(Request {
+            id: "701".into(),
+            method: "find_by_vendor_id".into(),
+            params: serde_json::json!({"familysearch_id": "KWCB-123", "uid": "9999"}),
+        });
         match response {
-            OutboundMessage::Error(error) => {
-                assert_eq!(error.id, "2");
-                assert_eq!(error.error.code, -32601);
-                assert!(error.error.message.contains("method not found"));
+            OutboundMessage::Response(resp) => assert_eq!(resp.result, serde_json::json!([])),
+            other => panic!("expected response, got {other:?}"),
+        }
+
+        let response = server.handle_request(Request {
+            id: "702".into(),
+            method: "find_by_vendor_id".into(),
+            params: serde_json::json!({}),
+        });
+        match response {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32602),
+            other => panic!("expected error, got {other:?}"),
+        }
+    }
+
+    fn lifespan_store() -> GedcomStore {
+        let data = crate::gedcom::parse_gedcom(
+            r#"
+            0 @I1@ INDI
+            1 NAME Father
+            1 BIRT
+            2 DATE 1850
+            0 @I2@ INDI
+            1 NAME Child
+            1 BIRT
+            2 DATE 10 JUN 1880
+            1 DEAT
+            2 DATE BET 1950 AND 1952
+            0 @I3@ INDI
+            1 NAME Spouse
+            0 @F1@ FAM
+            1 HUSB @I1@
+            1 CHIL @I2@
+            0 @F2@ FAM
+            1 HUSB @I2@
+            1 WIFE @I3@
+            1 MARR
+            2 DATE 3 MAY 1905
+            "#,
+        )
+        .expect("should parse");
+        GedcomStore::from_data(data)
+    }
+
+    #[test]
+    fn get_individual_includes_lifespan() {
+        let server = Server::new(Some(lifespan_store()));
+        let response = server.handle_request(Request {
+            id: "800".into(),
+            method: "get_individual".into(),
+            params: serde_json::json!({"id": "I2"}),
+        });
+
+        match response {
+            OutboundMessage::Response(resp) => {
+                let lifespan = &resp.result["lifespan"];
+                assert_eq!(lifespan["age_at_death"]["description"], "between 69 and 72");
+                assert_eq!(
+                    lifespan["father_age_at_birth"]["description"],
+                    "between 29 and 30"
+                );
+                assert_eq!(lifespan["event_ages"][0]["tag"], "MARR");
+                assert_eq!(lifespan["event_ages"][0]["family"], "F2");
+                assert_eq!(lifespan["event_ages"][0]["age"]["min_years"], 24);
             }
-            other => panic!("expected error response, got {other:?}"),
+            other => panic!("expected response, got {other:?}"),
         }
     }
 
     #[test]
-    fn serializes_outbound_message() {
-        let message = OutboundMessage::Response(Response {
-            id: "3".into(),
-            result: serde_json::json!({"status": "ok"}),
+    fn gets_age_at_date_and_event() {
+        let server = Server::new(Some(lifespan_store()));
+
+        let response = server.handle_request(Request {
+            id: "801".into(),
+            method: "get_age".into(),
+            params: serde_json::json!({"id": "I2", "date": "1 JAN 1900"}),
         });
+        match response {
+            OutboundMessage::Response(resp) => {
+                assert_eq!(resp.result["age"]["min_years"], 19);
+                assert_eq!(resp.result["age"]["max_years"], 19);
+            }
+            other => panic!("expected response, got {other:?}"),
+        }
 
-        let json = serialize_message(&message).expect("should serialize");
-        assert!(json.contains("\"status\":\"ok\""));
-        let deserialized: OutboundMessage =
-            serde_json::from_str(&json).expect("should deserialize");
-        assert_eq!(deserialized, message);
+        let response = server.handle_request(Request {
+            id: "802".into(),
+            method: "get_age".into(),
+            params: serde_json::json!({"id": "I2", "event": "marr"}),
+        });
+        match response {
+            OutboundMessage::Response(resp) => {
+                assert_eq!(resp.result["event"], "MARR");
+                assert_eq!(resp.result["age"]["description"], "24");
+            }
+            other => panic!("expected response, got {other:?}"),
+        }
+
+        for (id, params, code) in [
+            (
+                "803",
+                serde_json::json!({"id": "I2", "event": "BURI"}),
+                -32004,
+            ),
+            (
+                "804",
+                serde_json::json!({"id": "I2", "date": "not a date"}),
+                -32602,
+            ),
+            ("805", serde_json::json!({}), -32602),
+        ] {
+            let response = server.handle_request(Request {
+                id: id.into(),
+                method: "get_age".into(),
+                params,
+            });
+            match response {
+                OutboundMessage::Error(err) => assert_eq!(err.error.code, code),
+                other => panic!("expected error, got {other:?}"),
+            }
+        }
+    }
+
+    fn place_store() -> GedcomStore {
+        let data = crate::gedcom::parse_gedcom(
+            r#"
+            0 HEAD
+            1 PLAC
+            2 FORM City, County, Country
+            0 @I1@ INDI
+            1 BIRT
+            2 DATE 1850
+            2 PLAC Leeds, Yorkshire, England
+            3 MAP
+            4 LATI N53.8
+            4 LONG W1.55
+            1 RESI
+            2 PLAC York, Yorkshire, England
+            0 @I2@ INDI
+            1 BIRT
+            2 PLAC leeds, yorkshire, england
+            0 @F1@ FAM
+            1 HUSB @I1@
+            1 MARR
+            2 PLAC Cork, Ireland
+            "#,
+        )
+        .expect("should parse");
+        GedcomStore::from_data(data)
     }
 
     #[test]
-    fn returns_parse_error_for_invalid_json() {
-        let server = Server::default();
-        let response = server.handle_raw_message("{ invalid json");
+    fn lists_deduplicated_places() {
+        let server = Server::new(Some(place_store()));
+        let response = server.handle_request(Request {
+            id: "900".into(),
+            method: "list_places".into(),
+            params: serde_json::json!({}),
+        });
 
         match response {
-            OutboundMessage::Error(error) => {
-                assert_eq!(error.error.code, -32700);
-                assert!(
-                    !error.error.message.is_empty(),
-                    "parse error message should be present"
+            OutboundMessage::Response(resp) => {
+                let places = resp.result.as_array().unwrap();
+                assert_eq!(places.len(), 3);
+                assert_eq!(places[1]["name"], "Leeds, Yorkshire, England");
+                assert_eq!(places[1]["event_count"], 2);
+                assert_eq!(places[1]["coordinates"]["latitude"], 53.8);
+                assert_eq!(
+                    places[1]["jurisdictions"][1],
+                    serde_json::json!({"name": "Yorkshire", "level": "County"})
                 );
             }
-            other => panic!("expected parse error, got {other:?}"),
+            other => panic!("expected response, got {other:?}"),
+        }
+
+        let response = server.handle_request(Request {
+            id: "901".into(),
+            method: "list_places".into(),
+            params: serde_json::json!({"within": "Ireland"}),
+        });
+        match response {
+            OutboundMessage::Response(resp) => {
+                assert_eq!(resp.result.as_array().unwrap().len(), 1);
+                assert_eq!(resp.result[0]["name"], "Cork, Ireland");
+            }
+            other => panic!("expected response, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn finds_events_at_place_and_sub_places() {
+        let server = Server::new(Some(place_store()));
+        let events_at = |params: Value| match server.handle_request(Request {
+            id: "902".into(),
+            method: "find_events_at_place".into(),
+            params,
+        }) {
+            OutboundMessage::Response(resp) => resp
+                .result
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|event| {
+                    format!(
+                        "{} {}",
+                        event["record_id"].as_str().unwrap(),
+                        event["tag"].as_str().unwrap()
+                    )
+                })
+                .collect::<Vec<_>>(),
+            other => panic!("expected response, got {other:?}"),
+        };
+
+        assert_eq!(
+            events_at(serde_json::json!({"place": "Yorkshire, England"})),
+            vec!["I1 BIRT", "I1 RESI", "I2 BIRT"]
+        );
+        assert_eq!(
+            events_at(
+                serde_json::json!({"place": "LEEDS, Yorkshire, England", "include_subplaces": false})
+            ),
+            vec!["I1 BIRT", "I2 BIRT"]
+        );
+        assert_eq!(
+            events_at(serde_json::json!({"place": "Ireland"})),
+            vec!["F1 MARR"]
+        );
+
+        match server.handle_request(Request {
+            id: "903".into(),
+            method: "find_events_at_place".into(),
+            params: serde_json::json!({}),
+        }) {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32602),
+            other => panic!("expected error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn previews_and_applies_place_normalization() {
+        let gazetteer = [
+            "6252001\tUnited States\tUnited States\tUSA\t39.76\t-98.5\tA\tPCLI\tUS\t\t00\t\t\t\t327167434",
+            "4896861\tIllinois\tIllinois\tIL\t40.0\t-89.0\tA\tADM1\tUS\t\tIL\t\t\t\t12830632",
+            "4250542\tSpringfield\tSpringfield\t\t39.80172\t-89.64371\tP\tPPLA\tUS\t\tIL\t167\t\t\t116250",
+        ]
+        .join("\n");
+        let gazetteer = Gazetteer::from_reader(gazetteer.as_bytes()).unwrap();
+        let data = crate::gedcom::parse_gedcom(
+            r#"
+            0 @I1@ INDI
+            1 BIRT
+            2 PLAC Springfld, IL
+            1 DEAT
+            2 PLAC Springfield, Illinois, USA
+            0 @I2@ INDI
+            1 BIRT
+            2 PLAC Springfield
+            1 RESI
+            2 PLAC Atlantis
+            0 @I3@ INDI
+            1 RESN locked
+            1 BIRT
+            2 PLAC Springfield, Illinois, USA
+            "#,
+        )
+        .unwrap();
+        let server = Server::new(Some(GedcomStore::from_data(data))).with_gazetteer(gazetteer);
+        let normalize = |params: Value| match server.handle_request(Request {
+            id: "910".into(),
+            method: "normalize_places".into(),
+            params,
+        }) {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
+
+        let preview = normalize(serde_json::json!({}));
+        assert_eq!(preview["applied"], false);
+        assert_eq!(preview["unmatched"], serde_json::json!(["Atlantis"]));
+        let proposals = preview["proposals"].as_array().unwrap();
+        assert_eq!(proposals.len(), 3);
+        assert!(proposals.iter().all(|proposal| {
+            proposal["canonical"] == "Springfield, Illinois, United States"
+                && proposal["geonameid"] == 4250542
+        }));
+
+        let applied = normalize(serde_json::json!({
+            "apply": true,
+            "places": ["springfld, il", "Springfield, Illinois, USA"]
+        }));
+        assert_eq!(applied["applied"], true);
+        assert_eq!(applied["updated_events"], 2);
+
+        let store = server.store.as_ref().unwrap().lock().unwrap();
+        let birth = store.get_individual("I1").unwrap().birth.clone().unwrap();
+        assert_eq!(
+            birth.place.as_deref(),
+            Some("Springfield, Illinois, United States")
+        );
+        assert_eq!(birth.coordinates.unwrap().latitude, 39.80172);
+        let canonical = store
+            .places()
+            .get("Springfield, Illinois, United States")
+            .unwrap();
+        assert_eq!(canonical.event_count(), 2);
+        assert!(store.places().get("Springfield").is_some());
+        let locked = store.get_individual("I3").unwrap();
+        assert_eq!(
+            locked.birth.as_ref().unwrap().place.as_deref(),
+            Some("Springfield, Illinois, USA")
+        );
+        assert!(locked.metadata.changed.is_none());
+        drop(store);
+
+        for min_confidence in [
+            serde_json::json!(-0.1),
+            serde_json::json!(1.5),
+            "high".into(),
+        ] {
+            match server.handle_request(Request {
+                id: "912".into(),
+                method: "normalize_places".into(),
+                params: serde_json::json!({"min_confidence": min_confidence}),
+            }) {
+                OutboundMessage::Error(err) => assert_eq!(err.error.code, -32602),
+                other => panic!("expected error, got {other:?}"),
+            }
+        }
+
+        let unconfigured = Server::new(Some(GedcomStore::default()));
+        match unconfigured.handle_request(Request {
+            id: "911".into(),
+            method: "normalize_places".into(),
+            params: serde_json::json!({}),
+        }) {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32000),
+            other => panic!("expected error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn hides_addresses_of_living_people_by_default() {
+        let data = crate::gedcom::parse_gedcom(
+            r#"
+            0 @I1@ INDI
+            1 NAME Living
+            1 BIRT
+            2 DATE 1990
+            1 RESI
+            2 ADDR 12 Elm Street
+            0 @I2@ INDI
+            1 NAME Deceased
+            1 DEAT Y
+            1 RESI
+            2 ADDR 1 Old Lane
+            0 @F1@ FAM
+            1 HUSB @I1@
+            1 RESI
+            2 ADDR 12 Elm Street
+            1 MARR
+            2 ADDR Town Hall
+            0 @R1@ REPO
+            1 NAME Archive
+            1 ADDR Archive Road
+            "#,
+        )
+        .unwrap();
+        let server = Server::new(Some(GedcomStore::from_data(data)));
+        let get = |method: &str, params: Value| match server.handle_request(Request {
+            id: "920".into(),
+            method: method.into(),
+            params,
+        }) {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
+
+        let living = get("get_individual", serde_json::json!({"id": "I1"}));
+        assert_eq!(living["events"][0].get("address"), None);
+        assert_eq!(living["addresses_hidden"], true);
+
+        let revealed = get(
+            "get_individual",
+            serde_json::json!({"id": "I1", "include_private": true}),
+        );
+        assert_eq!(
+            revealed["events"][0]["address"]["lines"],
+            serde_json::json!(["12 Elm Street"])
+        );
+        assert_eq!(revealed.get("addresses_hidden"), None);
+
+        let deceased = get("get_individual", serde_json::json!({"id": "I2"}));
+        assert_eq!(
+            deceased["events"][0]["address"]["lines"],
+            serde_json::json!(["1 Old Lane"])
+        );
+
+        let family = get("get_family", serde_json::json!({"id": "F1"}));
+        assert_eq!(family["events"][0].get("address"), None);
+        assert_eq!(family["addresses_hidden"], true);
+
+        let listed = get("list_families", serde_json::json!({}));
+        let marriage = &listed["families"][0]["events"][1];
+        assert_eq!(marriage["tag"], "MARR");
+        assert_eq!(marriage.get("address"), None);
+        assert_eq!(listed["families"][0]["addresses_hidden"], true);
+        let listed = get(
+            "list_families",
+            serde_json::json!({"include_private": true}),
+        );
+        assert_eq!(
+            listed["families"][0]["events"][1]["address"]["lines"],
+            serde_json::json!(["Town Hall"])
+        );
+
+        let repository = get("get_repository", serde_json::json!({"id": "R1"}));
+        assert_eq!(repository["name"], "Archive");
+        assert_eq!(
+            repository["address"]["lines"],
+            serde_json::json!(["Archive Road"])
+        );
+
+        match server.handle_request(Request {
+            id: "921".into(),
+            method: "get_submitter".into(),
+            params: serde_json::json!({"id": "S9"}),
+        }) {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32004),
+            other => panic!("expected error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn lists_associates_and_aliases() {
+        let data = crate::gedcom::parse_gedcom(
+            r#"
+            0 @I1@ INDI
+            1 NAME Child
+            1 BAPM
+            2 ASSO @I2@
+            3 RELA Godfather
+            1 ALIA @I3@
+            0 @I2@ INDI
+            1 NAME Godfather
+            0 @I3@ INDI
+            1 NAME Same Child
+            1 ALIA @I4@
+            0 @I4@ INDI
+            1 NAME Also Same
+            0 @F1@ FAM
+            1 MARR
+            2 ASSO @I2@
+            3 RELA Witness
+            "#,
+        )
+        .unwrap();
+        let server = Server::new(Some(GedcomStore::from_data(data)));
+        let call = |method: &str, params: Value| match server.handle_request(Request {
+            id: "930".into(),
+            method: method.into(),
+            params,
+        }) {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
+
+        let child = call("get_associates", serde_json::json!({"id": "I1"}));
+        assert_eq!(
+            child["associates"],
+            serde_json::json!([{
+                "individual": "I2",
+                "name": "Godfather",
+                "relation": "Godfather",
+                "event": "BAPM",
+            }])
+        );
+
+        let witness = call(
+            "get_associates",
+            serde_json::json!({"id": "I2", "relation": "witness"}),
+        );
+        assert_eq!(
+            witness["associated_with"],
+            serde_json::json!([{
+                "record_type": "family",
+                "record_id": "F1",
+                "name": null,
+                "relation": "Witness",
+                "event": "MARR",
+            }])
+        );
+
+        let aliases = call("get_aliases", serde_json::json!({"id": "I4"}));
+        assert_eq!(aliases["aliases"], serde_json::json!([]));
+        assert_eq!(aliases["aliased_by"], serde_json::json!(["I3"]));
+        assert_eq!(aliases["same_person"], serde_json::json!(["I1", "I3"]));
+
+        match server.handle_request(Request {
+            id: "931".into(),
+            method: "get_associates".into(),
+            params: serde_json::json!({"id": "I9"}),
+        }) {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32004),
+            other => panic!("expected error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn stamps_created_records_and_finds_by_reference() {
+        let data = crate::gedcom::parse_gedcom(
+            r#"
+            0 @I1@ INDI
+            1 NAME Referenced
+            1 REFN 17
+            2 TYPE Card index
+            1 UID 0f3c2a10
+            0 @F1@ FAM
+            1 REFN 17
+            "#,
+        )
+        .unwrap();
+        let server = Server::new(Some(GedcomStore::from_data(data))).with_clock(|| ChangeStamp {
+            date: "18 OCT 2026".into(),
+            time: Some("09:30:00".into()),
+        });
+        let call = |method: &str, params: Value| match server.handle_request(Request {
+            id: "940".into(),
+            method: method.into(),
+            params,
+        }) {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
+
+        let created = call("create_individual", serde_json::json!({"id": "I2"}));
+        let changed = &created["metadata"]["changed"];
+        assert_eq!(changed["date"], "18 OCT 2026");
+        assert_eq!(changed["time"], "09:30:00");
+        assert_eq!(created["metadata"]["created"], *changed);
+
+        let found = call("find_by_reference", serde_json::json!({"refn": "17"}));
+        assert_eq!(found["individuals"][0]["id"], "I1");
+        assert_eq!(found["families"][0]["id"], "F1");
+
+        let typed = call(
+            "find_by_reference",
+            serde_json::json!({"refn": "17", "type": "card index"}),
+        );
+        assert_eq!(typed["individuals"].as_array().unwrap().len(), 1);
+        assert_eq!(typed["families"], serde_json::json!([]));
+
+        let by_uid = call("find_by_reference", serde_json::json!({"uid": "0F3C2A10"}));
+        assert_eq!(by_uid["individuals"][0]["name"], "Referenced");
+
+        match server.handle_request(Request {
+            id: "941".into(),
+            method: "find_by_reference".into(),
+            params: serde_json::json!({}),
+        }) {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32602),
+            other => panic!("expected error, got {other:?}"),
         }
     }
 
-    #[test]
-    fn processes_json_line_happy_path() {
-        let server = Server::default();
-        let raw = r#"{"id":"1","method":"ping","params":{}}"#;
-        let output = server.handle_json_line(raw).expect("should serialize");
-        let message: OutboundMessage =
-            serde_json::from_str(&output).expect("should deserialize outbound");
-
-        match message {
-            OutboundMessage::Response(resp) => {
-                assert_eq!(resp.id, "1");
-                assert_eq!(resp.result, serde_json::json!({"status": "ok"}));
-            }
-            other => panic!("expected response, got {other:?}"),
+    fn blended_family_store() -> GedcomStore {
+        let mut gedcom = String::new();
+        for id in 1..=12 {
+            gedcom.push_str(&format!("0 @I{id}@ INDI\n1 NAME Person {id}\n"));
+        }
+        for (family, husband, wife, children) in [
+            ("F1", "I1", "I2", "1 CHIL @I3@\n1 CHIL @I4@\n"),
+            ("F2", "I1", "I5", "1 CHIL @I6@\n"),
+            ("F3", "I7", "I2", "1 CHIL @I8@\n"),
+            ("F4", "I7", "I9", "1 CHIL @I10@\n"),
+            ("F5", "I11", "I5", "1 CHIL @I12@\n"),
+        ] {
+            gedcom.push_str(&format!(
+                "0 @{family}@ FAM\n1 HUSB @{husband}@\n1 WIFE @{wife}@\n{children}"
+            ));
         }
+        GedcomStore::from_data(crate::gedcom::parse_gedcom(&gedcom).unwrap())
     }
 
     #[test]
-    fn processes_json_line_with_parse_error() {
-        let server = Server::default();
-        let output = server
-            .handle_json_line("{ invalid json")
-            .expect("serialize error response");
+    fn answers_parent_child_and_spouse_queries() {
+        let server = Server::new(Some(blended_family_store()));
+        let call = |method: &str, id: &str| match server.handle_request(Request {
+            id: "950".into(),
+            method: method.into(),
+            params: serde_json::json!({ "id": id }),
+        }) {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
 
-        let message: OutboundMessage =
-            serde_json::from_str(&output).expect("should deserialize error");
+        assert_eq!(
+            call("get_parents", "I3"),
+            serde_json::json!([{
+                "family": "F1",
+                "father": {"id": "I1", "name": "Person 1"},
+                "mother": {"id": "I2", "name": "Person 2"},
+            }])
+        );
 
-        match message {
-            OutboundMessage::Error(err) => {
-                assert_eq!(err.error.code, -32700);
-                assert!(
-                    !err.error.message.is_empty(),
-                    "error message should be present"
-                );
-            }
+        let children = call("get_children", "I1");
+        let children: Vec<_> = children
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|child| {
+                (
+                    child["id"].as_str().unwrap(),
+                    child["other_parent"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(children, vec![("I3", "I2"), ("I4", "I2"), ("I6", "I5")]);
+
+        assert_eq!(
+            call("get_spouses", "I2"),
+            serde_json::json!([
+                {"id": "I1", "name": "Person 1", "family": "F1"},
+                {"id": "I7", "name": "Person 7", "family": "F3"},
+            ])
+        );
+
+        match server.handle_request(Request {
+            id: "951".into(),
+            method: "get_parents".into(),
+            params: serde_json::json!({"id": "I99"}),
+        }) {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32004),
             other => panic!("expected error, got {other:?}"),
         }
     }
 
-    fn build_store() -> GedcomStore {
-        let data = GedcomData {
-            individuals: vec![Individual {
-                id: "I1".into(),
-                name: Some("Indexed".into()),
-                birth: Some(crate::gedcom::Event {
-                    date: Some("1 JAN 1900".into()),
-                    place: None,
-                }),
-                death: None,
-            }],
-            families: vec![],
+    #[test]
+    fn deletes_records_and_their_relationships() {
+        let server = Server::new(Some(blended_family_store()));
+        let call = |method: &str, id: &str| match server.handle_request(Request {
+            id: "955".into(),
+            method: method.into(),
+            params: serde_json::json!({ "id": id }),
+        }) {
+            OutboundMessage::Response(resp) => Ok(resp.result),
+            OutboundMessage::Error(err) => Err(err.error.code),
         };
-        GedcomStore::from_data(data)
+
+        assert_eq!(call("delete_individual", "I12"), Err(-32001));
+        assert_eq!(call("delete_family", "F5").unwrap()["id"], "F5");
+        assert_eq!(
+            call("get_spouses", "I5"),
+            Ok(serde_json::json!([{"id": "I1", "name": "Person 1", "family": "F2"}]))
+        );
+        assert_eq!(call("get_parents", "I12"), Ok(serde_json::json!([])));
+        let store = server.store.as_ref().unwrap().lock().unwrap();
+        assert!(store.families_as_spouse("I11").next().is_none());
+        assert!(store.families_as_child("I12").next().is_none());
+        drop(store);
+
+        assert_eq!(call("delete_individual", "I12").unwrap()["id"], "I12");
+        assert_eq!(call("get_parents", "I12"), Err(-32004));
+        assert_eq!(call("delete_family", "F5"), Err(-32004));
+        assert_eq!(call("delete_individual", "I12"), Err(-32004));
     }
 
     #[test]
-    fn returns_individual_details() {
-        let server = Server::new(Some(build_store()));
-        let response = server.handle_request(Request {
-            id: "42".into(),
-            method: "get_individual".into(),
-            params: serde_json::json!({"id": "I1"}),
-        });
-
-        match response {
-            OutboundMessage::Response(resp) => {
-                assert_eq!(resp.id, "42");
-                assert_eq!(
-                    resp.result,
-                    serde_json::json!({
-                        "id": "I1",
-                        "name": "Indexed",
-                        "birth": {
-                            "date": "1 JAN 1900",
-                            "place": null
-                        },
-                        "death": null
-                    })
-                );
-            }
+    fn distinguishes_full_half_and_step_siblings() {
+        let server = Server::new(Some(blended_family_store()));
+        let siblings = match server.handle_request(Request {
+            id: "952".into(),
+            method: "get_siblings".into(),
+            params: serde_json::json!({"id": "I3"}),
+        }) {
+            OutboundMessage::Response(resp) => resp.result,
             other => panic!("expected response, got {other:?}"),
-        }
+        };
+        let ids = |key: &str, extra: &str| {
+            siblings[key]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|sibling| {
+                    format!(
+                        "{} {}",
+                        sibling["id"].as_str().unwrap(),
+                        if extra.is_empty() {
+                            String::new()
+                        } else {
+                            sibling[extra].to_string()
+                        }
+                    )
+                    .trim()
+                    .to_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids("full", ""), vec!["I4"]);
+        assert_eq!(
+            ids("half", "shared_parents"),
+            vec![r#"I6 ["I1"]"#, r#"I8 ["I2"]"#]
+        );
+        assert_eq!(
+            ids("step", "step_parent"),
+            vec![r#"I10 "I7""#, r#"I12 "I5""#]
+        );
     }
 
     #[test]
-    fn errors_when_id_missing() {
-        let server = Server::new(Some(build_store()));
-        let response = server.handle_request(Request {
-            id: "43".into(),
-            method: "get_individual".into(),
-            params: serde_json::json!({}),
-        });
+    fn walks_ancestors_and_descendants() {
+        let server = Server::new(Some(blended_family_store()));
+        let call = |method: &str, params: Value| {
+            server.handle_request(Request {
+                id: "953".into(),
+                method: method.into(),
+                params,
+            })
+        };
+        let result = |message| match message {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
 
-        match response {
-            OutboundMessage::Error(err) => {
-                assert_eq!(err.id, "43");
-                assert_eq!(err.error.code, -32602);
-            }
+        let tree = result(call(
+            "get_ancestors",
+            serde_json::json!({"id": "I3", "format": "tree"}),
+        ));
+        assert_eq!(tree["tree"]["ahnentafel"], 1);
+        assert_eq!(tree["tree"]["father"]["id"], "I1");
+        assert_eq!(tree["tree"]["mother"]["name"], "Person 2");
+        assert_eq!(tree["tree"]["mother"]["ahnentafel"], 3);
+
+        let list = result(call(
+            "get_descendants",
+            serde_json::json!({"id": "I1", "include_spouses": true}),
+        ));
+        let entries: Vec<(&str, &str, &str)> = list["individuals"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| {
+                (
+                    entry["id"].as_str().unwrap(),
+                    entry["relation"].as_str().unwrap(),
+                    entry["aboville"].as_str().unwrap_or(""),
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("I1", "root", "1"),
+                ("I3", "child", "1.1"),
+                ("I4", "child", "1.2"),
+                ("I6", "child", "1.3"),
+                ("I2", "spouse", ""),
+                ("I5", "spouse", ""),
+            ]
+        );
+
+        match call(
+            "get_descendants",
+            serde_json::json!({"id": "I1", "format": "chart"}),
+        ) {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32602),
             other => panic!("expected error, got {other:?}"),
         }
     }
 
     #[test]
-    fn errors_when_individual_not_found() {
-        let server = Server::new(Some(build_store()));
-        let response = server.handle_request(Request {
-            id: "44".into(),
-            method: "get_individual".into(),
-            params: serde_json::json!({"id": "missing"}),
-        });
+    fn describes_relationships_between_two_people() {
+        let store = GedcomStore::from_data(
+            crate::gedcom::parse_gedcom(
+                r#"0 @I1@ INDI
+1 NAME Anna /Berg/
+1 SEX F
+0 @I2@ INDI
+1 NAME Carl /Berg/
+1 SEX M
+0 @I3@ INDI
+1 NAME Dora /Berg/
+0 @I4@ INDI
+1 NAME Erik /Lund/
+0 @F1@ FAM
+1 HUSB @I4@
+1 WIFE @I1@
+1 CHIL @I2@
+1 CHIL @I3@
+"#,
+            )
+            .unwrap(),
+        );
+        let server = Server::new(Some(store));
+        let call = |params: Value| {
+            server.handle_request(Request {
+                id: "954".into(),
+                method: "get_relationship".into(),
+                params,
+            })
+        };
 
-        match response {
-            OutboundMessage::Error(err) => {
-                assert_eq!(err.id, "44");
-                assert_eq!(err.error.code, -32004);
-            }
+        let result = match call(serde_json::json!({"from": "I2", "to": "I3"})) {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
+        assert_eq!(result["related"], true);
+        let relationship = &result["relationships"][0];
+        assert_eq!(relationship["term"], "brother");
+        assert_eq!(
+            relationship["description"],
+            "Carl /Berg/ is Dora /Berg/'s brother"
+        );
+        assert_eq!(
+            relationship["common_ancestors"],
+            serde_json::json!(["I1", "I4"])
+        );
+        assert_eq!(
+            relationship["path"],
+            serde_json::json!([
+                {"id": "I2", "name": "Carl /Berg/"},
+                {"id": "I1", "name": "Anna /Berg/", "family": "F1"},
+                {"id": "I3", "name": "Dora /Berg/", "family": "F1"},
+            ])
+        );
+
+        match call(serde_json::json!({"from": "I2", "to": "I99"})) {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32004),
+            other => panic!("expected error, got {other:?}"),
+        }
+        match call(serde_json::json!({"from": "I2"})) {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32602),
             other => panic!("expected error, got {other:?}"),
         }
     }
 
     #[test]
-    fn errors_when_store_missing() {
-        let server = Server::default();
-        let response = server.handle_request(Request {
-            id: "45".into(),
-            method: "get_individual".into(),
-            params: serde_json::json!({"id": "I1"}),
-        });
+    fn finds_connections_between_sub_trees() {
+        let mut store = blended_family_store();
+        store
+            .insert_individual(Individual {
+                id: "I20".into(),
+                ..Default::default()
+            })
+            .unwrap();
+        let server = Server::new(Some(store));
+        let call = |params: Value| match server.handle_request(Request {
+            id: "955".into(),
+            method: "find_connection".into(),
+            params,
+        }) {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
 
-        match response {
-            OutboundMessage::Error(err) => {
-                assert_eq!(err.id, "45");
-                assert_eq!(err.error.code, -32000);
-            }
+        let found = call(serde_json::json!({"from": "I3", "to": "I12"}));
+        assert_eq!(found["connected"], true);
+        assert_eq!(found["hops"], 3);
+        assert_eq!(
+            found["description"],
+            "Person 3's father's wife's child is Person 12"
+        );
+        assert_eq!(found["path"][2]["family"], "F2");
+
+        let restricted = call(serde_json::json!({
+            "from": "I3", "to": "I12", "edges": ["parent", "child"], "max_hops": 3,
+        }));
+        assert_eq!(restricted["connected"], false);
+        assert_eq!(restricted["reason"], "hop_limit");
+
+        let apart = call(serde_json::json!({"from": "I3", "to": "I20"}));
+        assert_eq!(apart["connected"], false);
+        assert_eq!(apart["reason"], "disconnected");
+
+        match server.handle_request(Request {
+            id: "956".into(),
+            method: "find_connection".into(),
+            params: serde_json::json!({"from": "I3", "to": "I12", "edges": ["cousin"]}),
+        }) {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32602),
             other => panic!("expected error, got {other:?}"),
         }
     }
 
-    fn build_family_store() -> GedcomStore {
-        let data = GedcomData {
-            individuals: vec![],
-            families: vec![Family {
-                id: "F1".into(),
-                husband: Some("I1".into()),
-                wife: Some("I2".into()),
-                children: vec!["I3".into()],
-            }],
+    #[test]
+    fn analyzes_pedigree_collapse() {
+        let store = GedcomStore::from_data(
+            crate::gedcom::parse_gedcom(
+                r#"0 @I1@ INDI
+1 NAME Child
+0 @I2@ INDI
+1 NAME Father
+0 @I3@ INDI
+1 NAME Mother
+0 @I4@ INDI
+1 NAME Grandfather
+0 @I5@ INDI
+1 NAME Grandmother
+0 @F1@ FAM
+1 HUSB @I2@
+1 WIFE @I3@
+1 CHIL @I1@
+0 @F2@ FAM
+1 HUSB @I4@
+1 WIFE @I5@
+1 CHIL @I2@
+1 CHIL @I3@
+"#,
+            )
+            .unwrap(),
+        );
+        let server = Server::new(Some(store));
+        let result = match server.handle_request(Request {
+            id: "957".into(),
+            method: "analyze_pedigree".into(),
+            params: serde_json::json!({"id": "I1", "other": "I2"}),
+        }) {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
         };
-        GedcomStore::from_data(data)
-    }
 
-    fn empty_store() -> GedcomStore {
-        GedcomStore::from_data(GedcomData {
-            individuals: vec![],
-            families: vec![],
-        })
+        assert_eq!(
+            result["generations"][2],
+            serde_json::json!({
+                "generation": 2, "theoretical": 4, "known": 4, "distinct": 2, "collapse": 0.5,
+            })
+        );
+        assert_eq!(
+            result["totals"],
+            serde_json::json!({"known": 6, "distinct": 4, "collapse": 0.3333})
+        );
+        assert_eq!(result["repeated_ancestors"][0]["name"], "Grandfather");
+        assert_eq!(result["repeated_ancestors"][0]["appearances"], 2);
+        assert_eq!(result["common_ancestors"][0]["id"], "I2");
+        assert_eq!(result["common_ancestors"][0]["most_recent"], true);
     }
 
     #[test]
-    fn lists_individuals() {
-        let server = Server::new(Some(build_store()));
-        let response = server.handle_request(Request {
-            id: "200".into(),
-            method: "list_individuals".into(),
-            params: Value::Null,
-        });
-
-        match response {
-            OutboundMessage::Response(resp) => {
-                assert_eq!(resp.id, "200");
-                assert!(resp.result.is_array());
-                let arr = resp.result.as_array().unwrap();
-                assert_eq!(arr.len(), 1);
-                assert_eq!(arr[0]["id"], "I1");
+    fn bounds_pedigree_analysis_of_loops_and_long_lines() {
+        let analyze = |gedcom: &str| {
+            let store = GedcomStore::from_data(crate::gedcom::parse_gedcom(gedcom).unwrap());
+            match Server::new(Some(store)).handle_request(Request {
+                id: "959".into(),
+                method: "analyze_pedigree".into(),
+                params: serde_json::json!({"id": "I1", "max_generations": u32::MAX}),
+            }) {
+                OutboundMessage::Response(resp) => resp.result,
+                other => panic!("expected response, got {other:?}"),
             }
-            other => panic!("expected response, got {other:?}"),
-        }
-    }
+        };
 
-    #[test]
-    fn lists_families() {
-        let server = Server::new(Some(build_family_store()));
-        let response = server.handle_request(Request {
-            id: "201".into(),
-            method: "list_families".into(),
-            params: Value::Null,
-        });
+        // Corrupt data: I1 and I2 are each other's father.
+        let looped = analyze(
+            "0 @I1@ INDI\n0 @I2@ INDI\n0 @F1@ FAM\n1 HUSB @I2@\n1 CHIL @I1@\n\
+             0 @F2@ FAM\n1 HUSB @I1@\n1 CHIL @I2@\n",
+        );
+        assert_eq!(looped["generations"].as_array().unwrap().len(), 2);
+        assert_eq!(looped["totals"]["known"], 1);
 
-        match response {
-            OutboundMessage::Response(resp) => {
-                assert_eq!(resp.id, "201");
-                assert!(resp.result.is_array());
-                let arr = resp.result.as_array().unwrap();
-                assert_eq!(arr.len(), 1);
-                assert_eq!(arr[0]["id"], "F1");
-            }
-            other => panic!("expected response, got {other:?}"),
+        let mut line = String::new();
+        for id in 1..=150 {
+            line.push_str(&format!(
+                "0 @I{id}@ INDI\n0 @F{id}@ FAM\n1 HUSB @I{}@\n1 CHIL @I{id}@\n",
+                id + 1
+            ));
         }
+        let long = analyze(&line);
+        assert_eq!(
+            long["generations"].as_array().unwrap().len(),
+            MAX_ANALYSIS_GENERATIONS as usize + 1
+        );
     }
 
     #[test]
-    fn returns_family_details() {
-        let server = Server::new(Some(build_family_store()));
-        let response = server.handle_request(Request {
-            id: "100".into(),
-            method: "get_family".into(),
-            params: serde_json::json!({"id": "F1"}),
-        });
-
-        match response {
-            OutboundMessage::Response(resp) => {
-                assert_eq!(resp.id, "100");
-                assert_eq!(
-                    resp.result,
-                    serde_json::json!({
-                        "id": "F1",
-                        "husband": "I1",
-                        "wife": "I2",
-                        "children": ["I3"]
-                    })
-                );
-            }
+    fn computes_kinship_coefficients() {
+        let store = GedcomStore::from_data(
+            crate::gedcom::parse_gedcom(
+                r#"0 @I4@ INDI
+1 NAME Grandfather
+0 @F1@ FAM
+1 HUSB @I2@
+1 WIFE @I3@
+1 CHIL @I1@
+0 @F2@ FAM
+1 HUSB @I4@
+1 WIFE @I5@
+1 CHIL @I2@
+1 CHIL @I3@
+0 @I1@ INDI
+0 @I2@ INDI
+0 @I3@ INDI
+0 @I5@ INDI
+"#,
+            )
+            .unwrap(),
+        );
+        let server = Server::new(Some(store));
+        let result = match server.handle_request(Request {
+            id: "958".into(),
+            method: "get_kinship_coefficients".into(),
+            params: serde_json::json!({"id": "I1", "other": "I2"}),
+        }) {
+            OutboundMessage::Response(resp) => resp.result,
             other => panic!("expected response, got {other:?}"),
-        }
+        };
+
+        assert_eq!(result["inbreeding"]["coefficient"], 0.25);
+        assert_eq!(
+            result["inbreeding"]["contributions"][0],
+            serde_json::json!({
+                "ancestor": "I4", "name": "Grandfather", "paths": 1, "shortest": 2,
+                "inbreeding": 0.0, "contribution": 0.125,
+            })
+        );
+        // Child of a full-sibling mating and their father: kinship 3/8, and
+        // the child's own inbreeding of 1/4 scales r by 1 / sqrt(1.25).
+        let coefficient = result["relationship"]["coefficient"].as_f64().unwrap();
+        assert!((coefficient - 0.75 / 1.25f64.sqrt()).abs() < 1e-12);
     }
 
     #[test]
-    fn errors_when_family_missing() {
-        let server = Server::new(Some(build_family_store()));
-        let response = server.handle_request(Request {
-            id: "101".into(),
-            method: "get_family".into(),
-            params: serde_json::json!({"id": "missing"}),
-        });
+    fn traces_dna_lines_and_carriers() {
+        let store = GedcomStore::from_data(
+            crate::gedcom::parse_gedcom(
+                r#"0 @I1@ INDI
+1 NAME Gustav
+1 SEX M
+1 BIRT
+2 DATE 1850
+1 DEAT
+2 DATE 1920
+0 @I2@ INDI
+1 NAME Anton
+1 SEX M
+1 BIRT
+2 DATE 1900
+1 DEAT
+2 DATE 1970
+0 @I3@ INDI
+1 NAME Berta
+1 SEX F
+1 BIRT
+2 DATE 1902
+0 @I4@ INDI
+1 NAME Axel
+1 SEX M
+1 BIRT
+2 DATE 1990
+0 @F1@ FAM
+1 HUSB @I1@
+1 CHIL @I2@
+1 CHIL @I3@
+0 @F2@ FAM
+1 HUSB @I2@
+1 CHIL @I4@
+"#,
+            )
+            .unwrap(),
+        );
+        let server = Server::new(Some(store));
+        let call = |method: &str, params: Value| {
+            server.handle_request(Request {
+                id: "959".into(),
+                method: method.into(),
+                params,
+            })
+        };
+        let result = |message| match message {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
 
-        match response {
-            OutboundMessage::Error(err) => {
-                assert_eq!(err.id, "101");
-                assert_eq!(err.error.code, -32004);
-            }
+        let line = result(call(
+            "get_dna_line",
+            serde_json::json!({"id": "I3", "line": "y"}),
+        ));
+        assert_eq!(line["carries"], false);
+        assert_eq!(
+            line["ancestors"],
+            serde_json::json!([{"id": "I1", "name": "Gustav", "generation": 1}])
+        );
+
+        let carriers = result(call(
+            "find_dna_carriers",
+            serde_json::json!({"id": "I4", "line": "y"}),
+        ));
+        assert_eq!(carriers["ancestor"]["id"], "I1");
+        let ids: Vec<&str> = carriers["carriers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|carrier| carrier["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, vec!["I1", "I2", "I4"]);
+
+        let recent = result(call(
+            "find_dna_carriers",
+            serde_json::json!({"id": "I4", "line": "y", "living_only": true}),
+        ));
+        assert_eq!(recent["count"], 1);
+        assert_eq!(recent["carriers"][0]["presumed_living"], true);
+        let recent = result(call(
+            "find_dna_carriers",
+            serde_json::json!({"id": "I4", "line": "y", "born_after": "1880"}),
+        ));
+        assert_eq!(recent["count"], 2);
+
+        match call("get_dna_line", serde_json::json!({"id": "I4", "line": "x"})) {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32602),
             other => panic!("expected error, got {other:?}"),
         }
     }
 
     #[test]
-    fn errors_when_family_param_missing() {
-        let server = Server::new(Some(build_family_store()));
-        let response = server.handle_request(Request {
-            id: "102".into(),
-            method: "get_family".into(),
-            params: serde_json::json!({}),
-        });
+    fn searches_names_with_ranking_and_pages() {
+        let store = GedcomStore::from_data(
+            crate::gedcom::parse_gedcom(
+                r#"0 @I1@ INDI
+1 NAME John /Smith/
+1 BIRT
+2 DATE 1850
+0 @I2@ INDI
+1 NAME Jon /Smyth/
+0 @I3@ INDI
+1 NAME Anna /Schmidt/
+0 @I4@ INDI
+1 NAME Mary /Jones/
+1 _MARNM Smith
+0 @I5@ INDI
+1 NAME Peter /Brown/
+"#,
+            )
+            .unwrap(),
+        );
+        let server = Server::new(Some(store));
+        let call = |method: &str, params: Value| {
+            server.handle_request(Request {
+                id: "960".into(),
+                method: method.into(),
+                params,
+            })
+        };
+        let result = |message| match message {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
+        let ids = |page: &Value| -> Vec<String> {
+            page["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|found| found["id"].as_str().unwrap().to_owned())
+                .collect()
+        };
 
-        match response {
-            OutboundMessage::Error(err) => {
-                assert_eq!(err.id, "102");
-                assert_eq!(err.error.code, -32602);
+        let first = result(call(
+            "search_individuals",
+            serde_json::json!({"surname": "smith", "limit": 2}),
+        ));
+        assert_eq!(first["total"], 4);
+        assert_eq!(ids(&first), vec!["I1", "I4"]);
+        assert_eq!(first["results"][0]["birth"], "1850");
+        assert_eq!(first["results"][0]["matches"][0]["kind"], "exact");
+        let second = result(call(
+            "search_individuals",
+            serde_json::json!({"surname": "smith", "limit": 2, "cursor": first["nextCursor"]}),
+        ));
+        assert_eq!(ids(&second), vec!["I2", "I3"]);
+        assert_eq!(second["nextCursor"], Value::Null);
+
+        let strict = result(call(
+            "search_individuals",
+            serde_json::json!({"query": "John Smith", "fuzzy": false, "phonetic": false}),
+        ));
+        assert_eq!(ids(&strict), vec!["I1"]);
+
+        result(call(
+            "create_individual",
+            serde_json::json!({"id": "I9", "name": "Johanna /Smith/"}),
+        ));
+        let created = result(call(
+            "search_individuals",
+            serde_json::json!({"given": "johanna"}),
+        ));
+        assert_eq!(created["results"][0]["id"], "I9");
+        assert_eq!(created["results"][0]["score"], 1.0);
+
+        for params in [
+            serde_json::json!({}),
+            serde_json::json!({"query": "smith", "cursor": "next"}),
+        ] {
+            match call("search_individuals", params) {
+                OutboundMessage::Error(err) => assert_eq!(err.error.code, -32602),
+                other => panic!("expected error, got {other:?}"),
             }
-            other => panic!("expected error, got {other:?}"),
         }
     }
 
     #[test]
-    fn errors_when_store_missing_for_family() {
-        let server = Server::default();
-        let response = server.handle_request(Request {
-            id: "103".into(),
-            method: "get_family".into(),
-            params: serde_json::json!({"id": "F1"}),
-        });
+    fn lists_records_sorted_filtered_and_paged() {
+        let server = Server::new(Some(blended_family_store()));
+        let call = |method: &str, params: Value| {
+            server.handle_request(Request {
+                id: "961".into(),
+                method: method.into(),
+                params,
+            })
+        };
+        let result = |message| match message {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
+        let ids = |page: &Value, key: &str| -> Vec<String> {
+            page[key]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|record| record["id"].as_str().unwrap().to_owned())
+                .collect()
+        };
 
-        match response {
-            OutboundMessage::Error(err) => {
-                assert_eq!(err.id, "103");
-                assert_eq!(err.error.code, -32000);
+        let mut seen = Vec::new();
+        let mut cursor = Value::Null;
+        loop {
+            let page = result(call(
+                "list_individuals",
+                serde_json::json!({"limit": 5, "cursor": cursor}),
+            ));
+            assert_eq!(page["total"], 12);
+            seen.extend(ids(&page, "individuals"));
+            cursor = page["nextCursor"].clone();
+            if cursor.is_null() {
+                break;
             }
-            other => panic!("expected error, got {other:?}"),
         }
-    }
+        let expected: Vec<String> = (1..=12).map(|n| format!("I{n}")).collect();
+        assert_eq!(seen, expected);
 
-    #[test]
-    fn creates_individual() {
-        let server = Server::new(Some(empty_store()));
-        let response = server.handle_request(Request {
-            id: "300".into(),
-            method: "create_individual".into(),
-            params: serde_json::json!({
-                "id": "I99",
-                "name": "New Person",
-                "birth": { "date": "1 JAN 1990", "place": "Town" }
-            }),
-        });
+        let roots = result(call(
+            "list_individuals",
+            serde_json::json!({"has_no_parents": true, "sex": "F"}),
+        ));
+        // Sex falls back to the wife role.
+        assert_eq!(ids(&roots, "individuals"), vec!["I2", "I5", "I9"]);
 
-        match response {
-            OutboundMessage::Response(resp) => {
-                assert_eq!(resp.id, "300");
-                assert_eq!(resp.result["id"], "I99");
-                assert_eq!(resp.result["birth"]["date"], "1 JAN 1990");
-                assert_eq!(resp.result["birth"]["place"], "Town");
+        let families = result(call("list_families", serde_json::json!({"limit": 2})));
+        assert_eq!(ids(&families, "families"), vec!["F1", "F2"]);
+        assert_eq!(families["nextCursor"], "2");
+
+        for params in [
+            serde_json::json!({"sort": "age"}),
+            serde_json::json!({"sex": "male"}),
+            serde_json::json!({"born_after": "not a date"}),
+            serde_json::json!({"cursor": 3}),
+        ] {
+            match call("list_individuals", params) {
+                OutboundMessage::Error(err) => assert_eq!(err.error.code, -32602),
+                other => panic!("expected error, got {other:?}"),
             }
-            other => panic!("expected response, got {other:?}"),
         }
     }
 
     #[test]
-    fn create_individual_conflict() {
-        let mut base = empty_store();
-        base.insert_individual(Individual {
-            id: "I1".into(),
-            name: None,
-            birth: None,
-            death: None,
-        })
-        .unwrap();
-        let server = Server::new(Some(base));
+    fn runs_queries_with_projection_and_positioned_errors() {
+        let server = Server::new(Some(blended_family_store()));
+        let call = |query: &str| {
+            server.handle_request(Request {
+                id: "962".into(),
+                method: "query".into(),
+                params: serde_json::json!({"query": query, "limit": 2}),
+            })
+        };
+        let result = |message| match message {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
 
-        let response = server.handle_request(Request {
-            id: "301".into(),
-            method: "create_individual".into(),
-            params: serde_json::json!({
-                "id": "I1",
-                "name": "Dup"
-            }),
-        });
+        let page = result(call(
+            "individuals where not exists(parents) and count(children) > 1 select id, count(spouses)",
+        ));
+        assert_eq!(page["total"], 4);
+        assert_eq!(
+            page["results"],
+            serde_json::json!([
+                {"id": "I1", "count(spouses)": 2},
+                {"id": "I2", "count(spouses)": 2},
+            ])
+        );
+        assert_eq!(page["nextCursor"], "2");
+        assert_eq!(page["plan"]["indexes"], serde_json::json!([]));
 
-        match response {
+        let page = result(call("families where id = 'F3' select husband, children"));
+        assert_eq!(
+            page["results"],
+            serde_json::json!([{"husband": "I7", "children": ["I8"]}])
+        );
+        assert_eq!(
+            page["plan"],
+            serde_json::json!({"indexes": ["id"], "examined": 1})
+        );
+
+        match call("families where count(children) > ten") {
             OutboundMessage::Error(err) => {
-                assert_eq!(err.id, "301");
-                assert_eq!(err.error.code, -32001);
+                assert_eq!(err.error.code, -32602);
+                assert_eq!(
+                    err.error.message,
+                    "unknown field `ten` on families; expected one of id, husband, wife, spouses, children, marriage, divorce, events at column 34"
+                );
+                assert_eq!(err.error.data, Some(serde_json::json!({"column": 34})));
             }
             other => panic!("expected error, got {other:?}"),
         }
     }
 
     #[test]
-    fn creates_family() {
-        let server = Server::new(Some(empty_store()));
-        let response = server.handle_request(Request {
-            id: "400".into(),
-            method: "create_family".into(),
-            params: serde_json::json!({
-                "id": "F9",
-                "husband": "I1",
-                "wife": "I2",
-                "children": ["I3", "I4"]
-            }),
-        });
+    fn searches_text_with_snippets_and_errors() {
+        let store = GedcomStore::from_data(
+            crate::gedcom::parse_gedcom(
+                r#"0 @I1@ INDI
+1 NAME Jens /Møller/
+1 OCCU Skibstømrer
+1 NOTE @N1@
+0 @I2@ INDI
+1 NAME Karen /Hansen/
+0 @F1@ FAM
+1 HUSB @I1@
+1 WIFE @I2@
+1 MARR
+2 PLAC Ærøskøbing, Ærø, Denmark
+0 @N1@ NOTE Built the brig Anna Maria at Ærøskøbing in 1852.
+"#,
+            )
+            .unwrap(),
+        );
+        let server = Server::new(Some(store));
+        let call = |query: &str| {
+            server.handle_request(Request {
+                id: "963".into(),
+                method: "search".into(),
+                params: serde_json::json!({"query": query, "limit": 1}),
+            })
+        };
+        let result = |message| match message {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
 
-        match response {
-            OutboundMessage::Response(resp) => {
-                assert_eq!(resp.id, "400");
-                assert_eq!(resp.result["id"], "F9");
-                assert_eq!(resp.result["children"], serde_json::json!(["I3", "I4"]));
+        let page = result(call("aeroskobing"));
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["nextCursor"], "1");
+        assert_eq!(
+            page["results"],
+            serde_json::json!([{
+                "type": "family",
+                "id": "F1",
+                "husband": {"id": "I1", "name": "Jens /Møller/"},
+                "wife": {"id": "I2", "name": "Karen /Hansen/"},
+                "score": 1.5,
+                "snippets": [{"field": "place", "text": "**Ærøskøbing**, Ærø, Denmark"}],
+            }])
+        );
+
+        let page = result(call("\"anna maria\" OR hansen"));
+        assert_eq!(page["results"][0]["id"], "I2");
+        assert_eq!(page["results"][0]["score"], 3.0);
+
+        let page = result(call("moller -skib*"));
+        assert_eq!(page["total"], 0);
+
+        match call("brig AND (anna") {
+            OutboundMessage::Error(err) => {
+                assert_eq!(err.error.code, -32602);
+                assert_eq!(err.error.message, "expected `)` at column 15");
+                assert_eq!(err.error.data, Some(serde_json::json!({"column": 15})));
             }
-            other => panic!("expected response, got {other:?}"),
+            other => panic!("expected error, got {other:?}"),
         }
     }
 
     #[test]
-    fn create_family_conflict() {
-        let mut base = empty_store();
-        base.insert_family(Family {
-            id: "F1".into(),
-            husband: None,
-            wife: None,
-            children: vec![],
-        })
-        .unwrap();
-        let server = Server::new(Some(base));
+    fn serves_statistics_as_tool_and_resource() {
+        let server = Server::new(Some(blended_family_store()));
+        let call = |method: &str, params: Value| {
+            server.handle_request(Request {
+                id: "964".into(),
+                method: method.into(),
+                params,
+            })
+        };
+        let result = |message| match message {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
 
-        let response = server.handle_request(Request {
-            id: "401".into(),
-            method: "create_family".into(),
-            params: serde_json::json!({
-                "id": "F1"
-            }),
-        });
+        let stats = result(call("get_statistics", serde_json::json!({"top": 1})));
+        assert_eq!(stats["counts"]["individuals"], 12);
+        assert_eq!(stats["counts"]["families"], 5);
+        assert_eq!(
+            stats["given_names"],
+            serde_json::json!([{"name": "Person", "count": 12}])
+        );
+        assert_eq!(stats["average_children_per_family"], 1.2);
+        assert_eq!(stats["completeness"]["with_parents"], 50.0);
 
-        match response {
-            OutboundMessage::Error(err) => {
-                assert_eq!(err.id, "401");
-                assert_eq!(err.error.code, -32001);
-            }
+        let resources = result(call("resources/list", serde_json::json!({})));
+        assert_eq!(resources["resources"][0]["uri"], "gedcom://tree/summary");
+        let read = result(call(
+            "resources/read",
+            serde_json::json!({"uri": "gedcom://tree/summary"}),
+        ));
+        let text = read["contents"][0]["text"].as_str().unwrap();
+        let summary: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(summary["counts"], stats["counts"]);
+
+        match call(
+            "resources/read",
+            serde_json::json!({"uri": "gedcom://nope"}),
+        ) {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32004),
             other => panic!("expected error, got {other:?}"),
         }
     }
 
     #[test]
-    fn create_family_validates_children() {
-        let server = Server::new(Some(empty_store()));
-        let response = server.handle_request(Request {
-            id: "402".into(),
-            method: "create_family".into(),
-            params: serde_json::json!({
-                "id": "F2",
-                "children": ["I1", 2]
-            }),
-        });
+    fn audits_with_toggleable_rules() {
+        let store = GedcomStore::from_data(
+            crate::gedcom::parse_gedcom(
+                r#"0 @I1@ INDI
+1 NAME Hans /Ott/
+1 BIRT
+2 DATE 1900
+1 DEAT
+2 DATE 1850
+0 @I2@ INDI
+1 NAME Lina /Ott/
+1 BIRT
+2 DATE 1890
+0 @I3@ INDI
+1 NAME Fritz /Ott/
+1 BIRT
+2 DATE 1899
+0 @F1@ FAM
+1 HUSB @I1@
+1 WIFE @I2@
+1 CHIL @I3@
+"#,
+            )
+            .unwrap(),
+        );
+        let server = Server::new(Some(store));
+        let call = |params: Value| {
+            server.handle_request(Request {
+                id: "965".into(),
+                method: "audit".into(),
+                params,
+            })
+        };
+        let result = |message| match message {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
 
-        match response {
+        let page = result(call(serde_json::json!({})));
+        let rules: Vec<&str> = page["findings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|finding| finding["rule"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            rules,
+            vec![
+                "born_after_death",
+                "born_long_after_father_death",
+                "parent_too_young",
+                "parent_too_young"
+            ]
+        );
+        assert_eq!(page["findings"][0]["severity"], "error");
+        assert_eq!(page["findings"][0]["records"], serde_json::json!(["I1"]));
+        assert_eq!(
+            page["findings"][0]["message"],
+            "Hans Ott (I1) was born (1900) after their death (1850)"
+        );
+        assert_eq!(page["rules"].as_array().unwrap().len(), 11);
+
+        let page = result(call(serde_json::json!({
+            "rules": ["born_after_death", "parent_too_young"],
+            "disable": ["parent_too_young"],
+        })));
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["rules"], serde_json::json!(["born_after_death"]));
+
+        match call(serde_json::json!({"disable": ["too_old"]})) {
             OutboundMessage::Error(err) => {
-                assert_eq!(err.id, "402");
                 assert_eq!(err.error.code, -32602);
+                assert!(
+                    err.error
+                        .message
+                        .starts_with("unknown rule \"too_old\"; expected one of born_after_death,")
+                );
             }
             other => panic!("expected error, got {other:?}"),
         }
     }
 
     #[test]
-    fn create_handlers_require_store() {
-        let server = Server::default();
+    fn traversals_terminate_on_parent_loops() {
+        // Corrupt import: I1 is the father of their own father.
+        let store = GedcomStore::from_data(
+            crate::gedcom::parse_gedcom(
+                r#"0 @I1@ INDI
+1 NAME Abel /Roth/
+1 SEX M
+0 @I2@ INDI
+1 NAME Bernd /Roth/
+1 SEX M
+0 @I3@ INDI
+1 NAME Clara /Roth/
+1 SEX F
+0 @I4@ INDI
+1 NAME Dora /Roth/
+1 SEX F
+0 @F1@ FAM
+1 HUSB @I2@
+1 WIFE @I3@
+1 CHIL @I1@
+0 @F2@ FAM
+1 HUSB @I1@
+1 WIFE @I4@
+1 CHIL @I2@
+"#,
+            )
+            .unwrap(),
+        );
+        let server = Server::new(Some(store));
+        let call = |method: &str, params: Value| {
+            server.handle_request(Request {
+                id: "966".into(),
+                method: method.into(),
+                params,
+            })
+        };
+        let result = |message| match message {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
 
-        let resp_individual = server.handle_request(Request {
-            id: "500".into(),
-            method: "create_individual".into(),
-            params: serde_json::json!({"id": "I1"}),
-        });
-        match resp_individual {
-            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32000),
-            _ => panic!("expected server error"),
+        for (method, params) in [
+            (
+                "get_ancestors",
+                serde_json::json!({"id": "I1", "max_generations": 50}),
+            ),
+            (
+                "get_descendants",
+                serde_json::json!({"id": "I1", "max_generations": 50, "format": "tree"}),
+            ),
+            (
+                "get_relationship",
+                serde_json::json!({"from": "I1", "to": "I3"}),
+            ),
+            (
+                "find_connection",
+                serde_json::json!({"from": "I4", "to": "I3"}),
+            ),
+            (
+                "analyze_pedigree",
+                serde_json::json!({"id": "I1", "other": "I2", "max_generations": 30}),
+            ),
+            (
+                "get_kinship_coefficients",
+                serde_json::json!({"id": "I1", "other": "I2", "max_generations": 12}),
+            ),
+            ("get_dna_line", serde_json::json!({"id": "I1", "line": "y"})),
+            (
+                "find_dna_carriers",
+                serde_json::json!({"id": "I1", "line": "y"}),
+            ),
+            (
+                "query",
+                serde_json::json!({"query": "individuals where exists(parents.parents.parents)"}),
+            ),
+        ] {
+            match call(method, params) {
+                OutboundMessage::Response(_) => {}
+                other => panic!("{method} failed: {other:?}"),
+            }
         }
 
-        let resp_family = server.handle_request(Request {
-            id: "501".into(),
-            method: "create_family".into(),
-            params: serde_json::json!({"id": "F1"}),
-        });
-        match resp_family {
-            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32000),
-            _ => panic!("expected server error"),
-        }
+        let page = result(call(
+            "audit",
+            serde_json::json!({"rules": ["ancestor_cycle"]}),
+        ));
+        assert_eq!(page["total"], 1);
+        assert_eq!(
+            page["findings"][0]["message"],
+            "Abel Roth (I1) is their own ancestor: I1 → I2 → I1 (each a child of the next)"
+        );
+        assert_eq!(
+            page["findings"][0]["records"],
+            serde_json::json!(["I1", "I2", "F1", "F2"])
+        );
     }
 
     #[test]
-    fn create_individual_persists_to_storage() {
-        let tmp = tempfile::NamedTempFile::new().expect("temp file");
-        let server = Server::with_storage(empty_store(), tmp.path().to_path_buf());
+    fn finds_ranked_duplicates() {
+        let store = GedcomStore::from_data(
+            crate::gedcom::parse_gedcom(
+                r#"0 @I1@ INDI
+1 NAME Margarethe /Schmidt/
+1 BIRT
+2 DATE 4 JUN 1872
+2 PLAC Hamburg, Germany
+1 DEAT
+2 DATE 1940
+0 @I2@ INDI
+1 NAME Margaretha /Schmitt/
+1 BIRT
+2 DATE 1872
+2 PLAC Hamburg
+0 @I3@ INDI
+1 NAME Margarethe /Schmidt/
+0 @I4@ INDI
+1 NAME Otto /Brandt/
+"#,
+            )
+            .unwrap(),
+        );
+        let server = Server::new(Some(store));
+        let call = |params: Value| {
+            server.handle_request(Request {
+                id: "967".into(),
+                method: "find_duplicates".into(),
+                params,
+            })
+        };
+        let result = |message| match message {
+            OutboundMessage::Response(resp) => resp.result,
+            other => panic!("expected response, got {other:?}"),
+        };
 
-        let response = server.handle_request(Request {
-            id: "600".into(),
-            method: "create_individual".into(),
-            params: serde_json::json!({"id": "I1", "name": "Persisted"}),
-        });
+        let page = result(call(serde_json::json!({})));
+        assert_eq!(page["total"], 1);
+        let pair = &page["duplicates"][0];
+        assert_eq!(pair["a"]["id"], "I1");
+        assert_eq!(pair["b"]["id"], "I2");
+        assert_eq!(pair["matches"][1]["field"], "birth");
+        assert_eq!(pair["matches"][2]["detail"], "Hamburg, Germany ~ Hamburg");
 
-        match response {
-            OutboundMessage::Response(_) => {}
-            other => panic!("expected response, got {other:?}"),
-        }
+        // A bare name is weak evidence on its own.
+        let page = result(call(serde_json::json!({"id": "I3", "min_score": 0.3})));
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["duplicates"][0]["score"], 0.4);
+        assert_eq!(page["duplicates"][0]["b"]["id"], "I3");
 
-        let contents = std::fs::read_to_string(tmp.path()).expect("read persisted file");
-        assert!(contents.contains("I1"));
-        assert!(contents.contains("Persisted"));
+        match call(serde_json::json!({"min_score": 2})) {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32602),
+            other => panic!("expected error, got {other:?}"),
+        }
+        match call(serde_json::json!({"id": "I99"})) {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32004),
+            other => panic!("expected error, got {other:?}"),
+        }
+        match call(serde_json::json!({"id": 1})) {
+            OutboundMessage::Error(err) => assert_eq!(err.error.code, -32602),
+            other => panic!("expected error, got {other:?}"),
+        }
     }
+
     #[test]
     fn serves_lines_over_io() {
         let server = Server::new(Some(build_store()));
@@ -0,0 +1,497 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound;
+
+use crate::gedcom::Individual;
+use crate::phonetic;
+use crate::text::{edit_distance, fold, trigrams};
+
+/// Which part of a personal name a token came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NamePart {
+    Given,
+    Surname,
+}
+
+/// How a query term matched a name token, best first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    Exact,
+    Prefix,
+    Fuzzy,
+    Substring,
+    Phonetic,
+}
+
+/// Splits a GEDCOM `NAME` such as `John William /Smith/ Jr.` into folded
+/// tokens. Without slashes the last word is taken as the surname.
+pub fn name_tokens(name: &str) -> Vec<(String, NamePart)> {
+    let mut tokens = Vec::new();
+    if name.contains('/') {
+        for (idx, piece) in name.split('/').enumerate() {
+            let part = if idx % 2 == 1 {
+                NamePart::Surname
+            } else {
+                NamePart::Given
+            };
+            tokens.extend(
+                fold(piece)
+                    .split(' ')
+                    .filter(|t| !t.is_empty())
+                    .map(|t| (t.to_owned(), part)),
+            );
+        }
+    } else {
+        let words: Vec<String> = fold(name)
+            .split(' ')
+            .filter(|t| !t.is_empty())
+            .map(str::to_owned)
+            .collect();
+        let last = words.len().saturating_sub(1);
+        for (idx, word) in words.into_iter().enumerate() {
+            let part = if idx == last && idx > 0 {
+                NamePart::Surname
+            } else {
+                NamePart::Given
+            };
+            tokens.push((word, part));
+        }
+    }
+    tokens
+}
+
+/// Names an individual can be found by. A `_MARNM` without slashes is
+/// usually just the surname, so it is indexed as one.
+pub fn searchable_names(individual: &Individual) -> Vec<String> {
+    let married = individual.vendor.married_name.as_deref().map(|name| {
+        if name.contains('/') {
+            name.to_owned()
+        } else {
+            format!("/{name}/")
+        }
+    });
+    individual.name.iter().cloned().chain(married).collect()
+}
+
+/// Folded surname of a GEDCOM `NAME`, or `None` if it has none.
+pub fn surname(name: &str) -> Option<String> {
+    let words: Vec<String> = name_tokens(name)
+        .into_iter()
+        .filter(|(_, part)| *part == NamePart::Surname)
+        .map(|(word, _)| word)
+        .collect();
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// Given names and surname as written: the surname is the text between
+/// slashes, or the last word when there are none.
+pub fn split_name(name: &str) -> (Option<&str>, Option<&str>) {
+    fn non_empty(text: &str) -> Option<&str> {
+        let text = text.trim();
+        (!text.is_empty()).then_some(text)
+    }
+    match name.split_once('/') {
+        Some((given, rest)) => (
+            non_empty(given),
+            non_empty(rest.split_once('/').map_or(rest, |(surname, _)| surname)),
+        ),
+        None => match name.trim().rsplit_once(' ') {
+            Some((given, surname)) => (non_empty(given), non_empty(surname)),
+            None => (non_empty(name), None),
+        },
+    }
+}
+
+fn phonetic_codes(token: &str) -> Vec<String> {
+    let mut codes: Vec<String> = phonetic::daitch_mokotoff(token)
+        .into_iter()
+        .map(|code| format!("dm:{code}"))
+        .collect();
+    codes.extend(phonetic::soundex(token).map(|code| format!("sx:{code}")));
+    codes.extend(phonetic::cologne(token).map(|code| format!("kp:{code}")));
+    codes
+}
+
+/// Name tokens of every individual, with phonetic codes, so searches look at
+/// the vocabulary of distinct names instead of every record.
+#[derive(Debug, Clone, Default)]
+pub struct NameIndex {
+    /// Folded token -> individuals and the name part it appears in.
+    postings: BTreeMap<String, BTreeSet<(String, NamePart)>>,
+    /// Phonetic code (Soundex, Daitch–Mokotoff, Kölner Phonetik) -> tokens.
+    phonetic: HashMap<String, BTreeSet<String>>,
+    /// Trigram of the `$`-padded token -> tokens, for substring and fuzzy
+    /// lookups.
+    trigrams: HashMap<[char; 3], BTreeSet<String>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NameQuery {
+    /// Terms matched against any part of the name.
+    pub any: Vec<String>,
+    pub given: Vec<String>,
+    pub surname: Vec<String>,
+    pub fuzzy: bool,
+    pub phonetic: bool,
+}
+
+impl NameQuery {
+    pub fn is_empty(&self) -> bool {
+        self.any.is_empty() && self.given.is_empty() && self.surname.is_empty()
+    }
+}
+
+/// How one query term matched.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TermMatch {
+    pub term: String,
+    pub token: String,
+    pub kind: MatchKind,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NameMatch {
+    pub id: String,
+    /// Mean of the term scores, between 0 and 1.
+    pub score: f64,
+    pub matches: Vec<TermMatch>,
+}
+
+impl NameIndex {
+    pub fn add(&mut self, id: &str, name: &str) {
+        for (token, part) in name_tokens(name) {
+            if !self.postings.contains_key(&token) {
+                for code in phonetic_codes(&token) {
+                    self.phonetic.entry(code).or_default().insert(token.clone());
+                }
+                for gram in trigrams(&token) {
+                    self.trigrams.entry(gram).or_default().insert(token.clone());
+                }
+            }
+            self.postings
+                .entry(token)
+                .or_default()
+                .insert((id.to_owned(), part));
+        }
+    }
+
+    pub fn remove(&mut self, id: &str, name: &str) {
+        for (token, part) in name_tokens(name) {
+            let Some(postings) = self.postings.get_mut(&token) else {
+                continue;
+            };
+            postings.remove(&(id.to_owned(), part));
+            if !postings.is_empty() {
+                continue;
+            }
+            self.postings.remove(&token);
+            for code in phonetic_codes(&token) {
+                if let Some(tokens) = self.phonetic.get_mut(&code) {
+                    tokens.remove(&token);
+                    if tokens.is_empty() {
+                        self.phonetic.remove(&code);
+                    }
+                }
+            }
+            for gram in trigrams(&token) {
+                if let Some(tokens) = self.trigrams.get_mut(&gram) {
+                    tokens.remove(&token);
+                    if tokens.is_empty() {
+                        self.trigrams.remove(&gram);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Vocabulary tokens matching `term`, each with its best match. Prefixes
+    /// come from the sorted vocabulary; substrings and fuzzy matches from the
+    /// trigram index, since a substring contains every inner trigram of
+    /// `term` and `n` edits leave all but at most `3n` of its distinct
+    /// trigrams in place. A single letter therefore has no fuzzy matches.
+    fn candidates(&self, term: &str, query: &NameQuery) -> BTreeMap<&str, (MatchKind, f64)> {
+        let mut found: BTreeMap<&str, (MatchKind, f64)> = BTreeMap::new();
+        let mut offer = |token: &'_ str, kind: MatchKind, score: f64| {
+            let Some((token, _)) = self.postings.get_key_value(token) else {
+                return;
+            };
+            let entry = found.entry(token.as_str()).or_insert((kind, score));
+            if score > entry.1 {
+                *entry = (kind, score);
+            }
+        };
+
+        offer(term, MatchKind::Exact, 1.0);
+        for token in self
+            .postings
+            .range::<str, _>((Bound::Included(term), Bound::Unbounded))
+            .map(|(token, _)| token)
+            .take_while(|token| token.starts_with(term))
+        {
+            let extra = (token.len() - term.len()) as f64 / token.len() as f64;
+            offer(token, MatchKind::Prefix, 0.9 - 0.2 * extra);
+        }
+
+        let length = term.chars().count();
+        let grams = trigrams(term);
+        if length >= 3 {
+            let inner: Vec<&BTreeSet<String>> = grams
+                .iter()
+                .filter(|gram| !gram.contains(&'$'))
+                .map(|gram| self.trigrams.get(gram))
+                .collect::<Option<_>>()
+                .unwrap_or_default();
+            if let Some(rarest) = inner.iter().min_by_key(|tokens| tokens.len()) {
+                for token in rarest.iter() {
+                    if token.len() > term.len() && token.contains(term) {
+                        offer(token, MatchKind::Substring, 0.6);
+                    }
+                }
+            }
+        }
+
+        let max_distance = (length / 4).max(1);
+        let needed = grams.len().checked_sub(3 * max_distance).filter(|&n| n > 0);
+        if query.fuzzy
+            && let Some(needed) = needed
+        {
+            let mut shared: HashMap<&str, usize> = HashMap::new();
+            for gram in &grams {
+                for token in self.trigrams.get(gram).into_iter().flatten() {
+                    *shared.entry(token.as_str()).or_default() += 1;
+                }
+            }
+            for (token, count) in shared {
+                let other = token.chars().count();
+                if count < needed || other.abs_diff(length) > max_distance {
+                    continue;
+                }
+                let distance = edit_distance(term, token);
+                if distance > 0 && distance <= max_distance {
+                    let longest = length.max(other) as f64;
+                    offer(
+                        token,
+                        MatchKind::Fuzzy,
+                        0.85 * (1.0 - distance as f64 / longest),
+                    );
+                }
+            }
+        }
+
+        if query.phonetic && length >= 2 {
+            for code in phonetic_codes(term) {
+                for token in self.phonetic.get(&code).into_iter().flatten() {
+                    offer(token, MatchKind::Phonetic, 0.5);
+                }
+            }
+        }
+        found
+    }
+
+    /// Individuals whose name matches every query term, best first (ties by
+    /// ID).
+    pub fn search(&self, query: &NameQuery) -> Vec<NameMatch> {
+        let terms = query
+            .any
+            .iter()
+            .map(|term| (term, None))
+            .chain(query.given.iter().map(|term| (term, Some(NamePart::Given))))
+            .chain(
+                query
+                    .surname
+                    .iter()
+                    .map(|term| (term, Some(NamePart::Surname))),
+            );
+
+        let mut people: Option<HashMap<&str, Vec<TermMatch>>> = None;
+        for (term, wanted) in terms {
+            let mut best: HashMap<&str, TermMatch> = HashMap::new();
+            for (token, (kind, score)) in self.candidates(term, query) {
+                for (id, part) in &self.postings[token] {
+                    if wanted.is_some_and(|wanted| wanted != *part) {
+                        continue;
+                    }
+                    if people
+                        .as_ref()
+                        .is_some_and(|people| !people.contains_key(id.as_str()))
+                    {
+                        continue;
+                    }
+                    let better = best.get(id.as_str()).is_none_or(|current| {
+                        (score, std::cmp::Reverse(kind))
+                            > (current.score, std::cmp::Reverse(current.kind))
+                    });
+                    if better {
+                        best.insert(
+                            id,
+                            TermMatch {
+                                term: term.clone(),
+                                token: token.to_owned(),
+                                kind,
+                                score,
+                            },
+                        );
+                    }
+                }
+            }
+            people = Some(match people {
+                None => best
+                    .into_iter()
+                    .map(|(id, matched)| (id, vec![matched]))
+                    .collect(),
+                Some(mut people) => {
+                    people.retain(|id, _| best.contains_key(id));
+                    for (id, matched) in best {
+                        if let Some(matches) = people.get_mut(id) {
+                            matches.push(matched);
+                        }
+                    }
+                    people
+                }
+            });
+        }
+
+        let mut results: Vec<NameMatch> = people
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(id, matches)| {
+                let score = matches.iter().map(|m| m.score).sum::<f64>() / matches.len() as f64;
+                NameMatch {
+                    id: id.to_owned(),
+                    score: (score * 1000.0).round() / 1000.0,
+                    matches,
+                }
+            })
+            .collect();
+        results.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.id.cmp(&b.id)));
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> NameIndex {
+        let mut index = NameIndex::default();
+        for (id, name) in [
+            ("I1", "John William /Smith/"),
+            ("I2", "Jon /Smyth/"),
+            ("I3", "Johanna /Schmidt/"),
+            ("I4", "Smith /Johnson/"),
+            ("I5", "Maria /Müller/"),
+            ("I6", "Mary Miller"),
+        ] {
+            index.add(id, name);
+        }
+        index
+    }
+
+    fn query() -> NameQuery {
+        NameQuery {
+            fuzzy: true,
+            phonetic: true,
+            ..Default::default()
+        }
+    }
+
+    fn ids(results: &[NameMatch]) -> Vec<&str> {
+        results.iter().map(|result| result.id.as_str()).collect()
+    }
+
+    #[test]
+    fn splits_names_into_parts() {
+        assert_eq!(
+            name_tokens("Jean-Luc /de la Motte/ Jr."),
+            vec![
+                ("jean".into(), NamePart::Given),
+                ("luc".into(), NamePart::Given),
+                ("de".into(), NamePart::Surname),
+                ("la".into(), NamePart::Surname),
+                ("motte".into(), NamePart::Surname),
+                ("jr".into(), NamePart::Given),
+            ]
+        );
+        assert_eq!(
+            name_tokens("Mary Miller"),
+            vec![
+                ("mary".into(), NamePart::Given),
+                ("miller".into(), NamePart::Surname)
+            ]
+        );
+    }
+
+    #[test]
+    fn ranks_exact_prefix_fuzzy_and_phonetic_matches() {
+        let index = index();
+        let results = index.search(&NameQuery {
+            surname: vec!["smith".into()],
+            ..query()
+        });
+        // Exact, then the fuzzy Smyth, then the phonetic Schmidt; I4 only has
+        // Smith as a given name.
+        assert_eq!(ids(&results), vec!["I1", "I2", "I3"]);
+        assert_eq!(results[0].matches[0].kind, MatchKind::Exact);
+        assert_eq!(results[1].matches[0].kind, MatchKind::Fuzzy);
+        assert_eq!(results[2].matches[0].kind, MatchKind::Phonetic);
+
+        let results = index.search(&NameQuery {
+            any: vec!["joh".into()],
+            ..query()
+        });
+        // Shorter completions rank first; Jon is one edit away.
+        assert_eq!(ids(&results), vec!["I1", "I3", "I4", "I2"]);
+        assert!(
+            results[..3]
+                .iter()
+                .all(|r| r.matches[0].kind == MatchKind::Prefix)
+        );
+        assert_eq!(results[3].matches[0].kind, MatchKind::Fuzzy);
+
+        let strict = index.search(&NameQuery {
+            surname: vec!["smith".into()],
+            ..Default::default()
+        });
+        assert_eq!(ids(&strict), vec!["I1"]);
+    }
+
+    #[test]
+    fn requires_every_term_and_follows_updates() {
+        let mut index = index();
+        let results = index.search(&NameQuery {
+            given: vec!["mary".into()],
+            surname: vec!["mueller".into()],
+            ..query()
+        });
+        assert_eq!(ids(&results), vec!["I6", "I5"]);
+
+        let results = index.search(&NameQuery {
+            any: vec!["ohn".into()],
+            ..query()
+        });
+        assert_eq!(ids(&results)[..2], ["I1", "I4"]);
+        let without_fuzzy = index.search(&NameQuery {
+            any: vec!["ohn".into()],
+            ..Default::default()
+        });
+        assert_eq!(ids(&without_fuzzy), vec!["I1", "I4"]);
+        assert_eq!(without_fuzzy[1].matches[0].kind, MatchKind::Substring);
+
+        index.remove("I1", "John William /Smith/");
+        index.add("I1", "John /Baker/");
+        let results = index.search(&NameQuery {
+            surname: vec!["smith".into()],
+            ..Default::default()
+        });
+        assert!(results.is_empty());
+        assert!(
+            !index
+                .phonetic
+                .values()
+                .any(|tokens| tokens.contains("william"))
+        );
+    }
+}
@@ -0,0 +1,1062 @@
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use crate::gedcom::{Family, GedcomStore, Sex, sex_of};
+use crate::listing::compare_ids;
+
+/// How a node relates to the node it hangs off (`of`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Relation {
+    Root,
+    Father,
+    Mother,
+    Child,
+    Spouse,
+    Sibling,
+    StepChild,
+}
+
+/// One entry of an ancestor or descendant listing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TreeNode {
+    pub id: String,
+    /// Generations away from the root; spouses and collaterals share the
+    /// generation of the person they are attached to.
+    pub generation: u32,
+    pub relation: Relation,
+    /// ID of the person this node is attached to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub of: Option<String>,
+    /// Ahnentafel number (root 1, father 2n, mother 2n + 1); absent beyond
+    /// the 63rd generation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ahnentafel: Option<u64>,
+    /// d'Aboville number, e.g. `1.2.1`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aboville: Option<String>,
+    /// Set when the person was already listed via another path (pedigree
+    /// collapse or a loop); their relatives are not repeated.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub repeat: bool,
+    /// Index of the node this one is attached to.
+    #[serde(skip)]
+    pub parent: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraversalOptions {
+    pub max_generations: u32,
+    /// Only lineal relatives; otherwise ancestors' siblings and descendants'
+    /// step-children are attached as well.
+    pub direct_line: bool,
+    pub include_spouses: bool,
+}
+
+struct Traversal<'a> {
+    store: &'a GedcomStore,
+    nodes: Vec<TreeNode>,
+    seen: HashMap<&'a str, usize>,
+}
+
+impl<'a> Traversal<'a> {
+    fn new(store: &'a GedcomStore, root: &'a str) -> Self {
+        let node = TreeNode {
+            id: root.to_owned(),
+            generation: 0,
+            relation: Relation::Root,
+            of: None,
+            ahnentafel: None,
+            aboville: None,
+            repeat: false,
+            parent: None,
+        };
+        Self {
+            store,
+            nodes: vec![node],
+            seen: HashMap::from([(root, 0)]),
+        }
+    }
+
+    /// Adds a lineal relative, returning its index if it was not seen before
+    /// and so should be expanded.
+    fn push_lineal(&mut self, id: &'a str, mut node: TreeNode) -> Option<usize> {
+        let idx = self.nodes.len();
+        node.repeat = self.seen.contains_key(id);
+        let expand = !node.repeat;
+        if expand {
+            self.seen.insert(id, idx);
+        }
+        self.nodes.push(node);
+        expand.then_some(idx)
+    }
+
+    fn attach(&mut self, parent: usize, id: &str, relation: Relation) {
+        let node = &self.nodes[parent];
+        let attached = TreeNode {
+            id: id.to_owned(),
+            generation: node.generation,
+            relation,
+            of: Some(node.id.clone()),
+            ahnentafel: None,
+            aboville: None,
+            repeat: false,
+            parent: Some(parent),
+        };
+        self.nodes.push(attached);
+    }
+
+    /// Indices of lineal nodes whose relatives were expanded.
+    fn expanded(&self) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|&idx| !self.nodes[idx].repeat)
+            .collect()
+    }
+
+    fn attach_spouses(&mut self, lineal: &[usize]) {
+        for &idx in lineal {
+            let id = self.nodes[idx].id.clone();
+            let spouses: Vec<&str> = self
+                .store
+                .families_as_spouse(&id)
+                .filter_map(|family| other_spouse(family, &id))
+                .filter(|spouse| !self.seen.contains_key(spouse))
+                .collect();
+            for spouse in dedup(spouses) {
+                self.attach(idx, spouse, Relation::Spouse);
+            }
+        }
+    }
+}
+
+/// The spouse of `id` in `family`, if recorded.
+pub fn other_spouse<'a>(family: &'a Family, id: &str) -> Option<&'a str> {
+    if family.husband.as_deref() == Some(id) {
+        family.wife.as_deref()
+    } else if family.wife.as_deref() == Some(id) {
+        family.husband.as_deref()
+    } else {
+        None
+    }
+}
+
+fn dedup(mut ids: Vec<&str>) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    ids.retain(|id| seen.insert(*id));
+    ids
+}
+
+/// Father and mother of `id`, taken from the first family as child that
+/// records each.
+pub fn parents<'a>(store: &'a GedcomStore, id: &str) -> (Option<&'a str>, Option<&'a str>) {
+    let mut father = None;
+    let mut mother = None;
+    for family in store.families_as_child(id) {
+        father = father.or(family.husband.as_deref());
+        mother = mother.or(family.wife.as_deref());
+    }
+    (father, mother)
+}
+
+/// Ancestors of `root` in breadth-first (generation) order, starting with the
+/// root itself. Every person is expanded at most once, so the cost is linear
+/// in the number of ancestors even with pedigree collapse or loops.
+pub fn ancestors<'a>(
+    store: &'a GedcomStore,
+    root: &'a str,
+    options: &TraversalOptions,
+) -> Vec<TreeNode> {
+    let mut traversal = Traversal::new(store, root);
+    traversal.nodes[0].ahnentafel = Some(1);
+    let mut queue = VecDeque::from([0]);
+    while let Some(idx) = queue.pop_front() {
+        let node = &traversal.nodes[idx];
+        if node.generation >= options.max_generations {
+            continue;
+        }
+        let (generation, number) = (node.generation + 1, node.ahnentafel);
+        let (father, mother) = parents(store, &node.id);
+        let of = node.id.clone();
+        for (parent, relation, offset) in
+            [(father, Relation::Father, 0), (mother, Relation::Mother, 1)]
+        {
+            let Some(parent) = parent else {
+                continue;
+            };
+            let node = TreeNode {
+                id: parent.to_owned(),
+                generation,
+                relation,
+                of: Some(of.clone()),
+                ahnentafel: number
+                    .and_then(|number| number.checked_mul(2))
+                    .and_then(|number| number.checked_add(offset)),
+                aboville: None,
+                repeat: false,
+                parent: Some(idx),
+            };
+            if let Some(added) = traversal.push_lineal(parent, node) {
+                queue.push_back(added);
+            }
+        }
+    }
+
+    let lineal = traversal.expanded();
+    if !options.direct_line {
+        for &idx in &lineal {
+            let id = traversal.nodes[idx].id.clone();
+            let siblings: Vec<&str> = store
+                .families_as_child(&id)
+                .flat_map(|family| &family.children)
+                .map(String::as_str)
+                .filter(|sibling| *sibling != id && !traversal.seen.contains_key(sibling))
+                .collect();
+            for sibling in dedup(siblings) {
+                traversal.attach(idx, sibling, Relation::Sibling);
+            }
+        }
+    }
+    if options.include_spouses {
+        traversal.attach_spouses(&lineal);
+    }
+    traversal.nodes
+}
+
+/// Descendants of `root` in breadth-first order with d'Aboville numbers,
+/// counting children across all of a person's families: families in ID
+/// order (compared as text), then children in each family's `CHIL` order.
+pub fn descendants<'a>(
+    store: &'a GedcomStore,
+    root: &'a str,
+    options: &TraversalOptions,
+) -> Vec<TreeNode> {
+    let mut traversal = Traversal::new(store, root);
+    traversal.nodes[0].aboville = Some("1".to_owned());
+    let mut queue = VecDeque::from([0]);
+    while let Some(idx) = queue.pop_front() {
+        let node = &traversal.nodes[idx];
+        if node.generation >= options.max_generations {
+            continue;
+        }
+        let generation = node.generation + 1;
+        let number = node.aboville.clone().unwrap_or_default();
+        let of = node.id.clone();
+        let children: Vec<&str> = store
+            .families_as_spouse(&of)
+            .flat_map(|family| &family.children)
+            .map(String::as_str)
+            .collect();
+        for (position, child) in dedup(children).into_iter().enumerate() {
+            let node = TreeNode {
+                id: child.to_owned(),
+                generation,
+                relation: Relation::Child,
+                of: Some(of.clone()),
+                ahnentafel: None,
+                aboville: Some(format!("{number}.{}", position + 1)),
+                repeat: false,
+                parent: Some(idx),
+            };
+            if let Some(added) = traversal.push_lineal(child, node) {
+                queue.push_back(added);
+            }
+        }
+    }
+
+    let lineal = traversal.expanded();
+    if !options.direct_line {
+        for &idx in &lineal {
+            let id = traversal.nodes[idx].id.clone();
+            let own: Vec<&str> = store
+                .families_as_spouse(&id)
+                .map(|f| f.id.as_str())
+                .collect();
+            let step_children: Vec<&str> = store
+                .families_as_spouse(&id)
+                .filter_map(|family| other_spouse(family, &id))
+                .flat_map(|spouse| store.families_as_spouse(spouse))
+                .filter(|family| !own.contains(&family.id.as_str()))
+                .flat_map(|family| &family.children)
+                .map(String::as_str)
+                .filter(|child| !traversal.seen.contains_key(child))
+                .collect();
+            for child in dedup(step_children) {
+                traversal.attach(idx, child, Relation::StepChild);
+            }
+        }
+    }
+    if options.include_spouses {
+        traversal.attach_spouses(&lineal);
+    }
+    traversal.nodes
+}
+
+/// For each generation (0 = `root`), how many pedigree positions each
+/// ancestor fills. Counting positions per generation instead of walking
+/// every path keeps this linear in generations times ancestors, even in
+/// heavily intermarried trees. A generation of the same people as an earlier
+/// one means the parent graph loops and every later generation would repeat
+/// too, so the walk stops there.
+pub fn appearances<'a>(
+    store: &'a GedcomStore,
+    root: &'a str,
+    max_generations: u32,
+) -> Vec<HashMap<&'a str, u64>> {
+    let mut generations = vec![HashMap::from([(root, 1)])];
+    let mut seen: HashSet<BTreeSet<&str>> = HashSet::from([BTreeSet::from([root])]);
+    for _ in 0..max_generations {
+        let mut next: HashMap<&str, u64> = HashMap::new();
+        for (&person, &count) in generations.last().into_iter().flatten() {
+            let (father, mother) = parents(store, person);
+            for parent in [father, mother].into_iter().flatten() {
+                let positions = next.entry(parent).or_default();
+                *positions = positions.saturating_add(count);
+            }
+        }
+        if next.is_empty() || !seen.insert(next.keys().copied().collect()) {
+            break;
+        }
+        generations.push(next);
+    }
+    generations
+}
+
+/// Distinct versus theoretical ancestors in one generation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Implex {
+    pub generation: u32,
+    /// `2^generation`; absent when it does not fit in 64 bits.
+    pub theoretical: Option<u64>,
+    /// Positions filled by a known ancestor.
+    pub known: u64,
+    pub distinct: usize,
+    /// Share of known positions taken by someone already counted in the
+    /// same generation: `1 - distinct / known`.
+    pub collapse: f64,
+}
+
+pub fn implex(generations: &[HashMap<&str, u64>]) -> Vec<Implex> {
+    generations
+        .iter()
+        .zip(0u32..)
+        .map(|(positions, generation)| {
+            let known = positions
+                .values()
+                .fold(0u64, |sum, count| sum.saturating_add(*count));
+            Implex {
+                generation,
+                theoretical: 1u64.checked_shl(generation),
+                known,
+                distinct: positions.len(),
+                collapse: collapse(positions.len(), known),
+            }
+        })
+        .collect()
+}
+
+pub fn collapse(distinct: usize, known: u64) -> f64 {
+    if known == 0 {
+        return 0.0;
+    }
+    ((1.0 - distinct as f64 / known as f64) * 10_000.0).round() / 10_000.0
+}
+
+/// An ancestor filling more than one pedigree position.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RepeatedAncestor {
+    pub id: String,
+    pub appearances: u64,
+    /// Generations they appear in.
+    pub generations: Vec<u32>,
+}
+
+/// Ancestors appearing more than once, most repeated first.
+pub fn repeated_ancestors(generations: &[HashMap<&str, u64>]) -> Vec<RepeatedAncestor> {
+    let mut found: HashMap<&str, RepeatedAncestor> = HashMap::new();
+    for (positions, generation) in generations.iter().zip(0u32..).skip(1) {
+        for (&id, &count) in positions {
+            let entry = found.entry(id).or_insert_with(|| RepeatedAncestor {
+                id: id.to_owned(),
+                appearances: 0,
+                generations: Vec::new(),
+            });
+            entry.appearances = entry.appearances.saturating_add(count);
+            entry.generations.push(generation);
+        }
+    }
+    let mut repeated: Vec<RepeatedAncestor> = found
+        .into_values()
+        .filter(|ancestor| ancestor.appearances > 1)
+        .collect();
+    repeated.sort_by(|a, b| b.appearances.cmp(&a.appearances).then(a.id.cmp(&b.id)));
+    repeated
+}
+
+/// An ancestor shared by two people, with the generations at which it
+/// appears in each pedigree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommonAncestor {
+    pub id: String,
+    pub from_generations: Vec<u32>,
+    pub to_generations: Vec<u32>,
+    /// Fewest generations up from one person plus down to the other.
+    pub distance: u32,
+    /// No child of theirs is also a common ancestor.
+    pub most_recent: bool,
+}
+
+fn generations_of<'a>(pedigree: &[HashMap<&'a str, u64>]) -> HashMap<&'a str, Vec<u32>> {
+    let mut found: HashMap<&str, Vec<u32>> = HashMap::new();
+    for (positions, generation) in pedigree.iter().zip(0u32..) {
+        for &id in positions.keys() {
+            found.entry(id).or_default().push(generation);
+        }
+    }
+    found
+}
+
+pub fn common_ancestors(
+    store: &GedcomStore,
+    from: &[HashMap<&str, u64>],
+    to: &[HashMap<&str, u64>],
+) -> Vec<CommonAncestor> {
+    let (from, to) = (generations_of(from), generations_of(to));
+    let mut common: Vec<CommonAncestor> = from
+        .iter()
+        .filter_map(|(&id, from_generations)| {
+            let to_generations = to.get(id)?;
+            let most_recent = !store
+                .families_as_spouse(id)
+                .flat_map(|family| &family.children)
+                .any(|child| from.contains_key(child.as_str()) && to.contains_key(child.as_str()));
+            Some(CommonAncestor {
+                id: id.to_owned(),
+                from_generations: from_generations.clone(),
+                to_generations: to_generations.clone(),
+                distance: from_generations[0] + to_generations[0],
+                most_recent,
+            })
+        })
+        .collect();
+    common.sort_by(|a, b| a.distance.cmp(&b.distance).then(a.id.cmp(&b.id)));
+    common
+}
+
+/// A uniparental line: Y-DNA passes from fathers to sons, mtDNA from
+/// mothers to all their children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Lineage {
+    Paternal,
+    Maternal,
+}
+
+impl Lineage {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "y" | "ydna" | "y-dna" | "paternal" => Some(Lineage::Paternal),
+            "mt" | "mtdna" | "maternal" => Some(Lineage::Maternal),
+            _ => None,
+        }
+    }
+
+    /// Sex of the people who pass the line on.
+    pub fn transmitter(self) -> Sex {
+        match self {
+            Lineage::Paternal => Sex::Male,
+            Lineage::Maternal => Sex::Female,
+        }
+    }
+}
+
+/// `id` followed by their father, his father, ... (or mothers), ending at
+/// the first unknown parent or at a loop.
+pub fn line<'a>(store: &'a GedcomStore, id: &'a str, lineage: Lineage) -> Vec<&'a str> {
+    let mut line = vec![id];
+    let mut seen = HashSet::from([id]);
+    let mut current = id;
+    loop {
+        let (father, mother) = parents(store, current);
+        let next = match lineage {
+            Lineage::Paternal => father,
+            Lineage::Maternal => mother,
+        };
+        match next {
+            Some(next) if seen.insert(next) => {
+                line.push(next);
+                current = next;
+            }
+            _ => return line,
+        }
+    }
+}
+
+/// Descendants of `ancestor` (included, at generation 0) carrying their Y or
+/// mtDNA line, breadth first. Y passes only to known sons, so there are none
+/// unless the ancestor is a known man; mtDNA reaches every child of a woman
+/// but continues only through daughters. Sex falls back to the husband/wife
+/// role when `SEX` is missing.
+pub fn carriers<'a>(
+    store: &'a GedcomStore,
+    ancestor: &'a str,
+    lineage: Lineage,
+) -> Vec<(&'a str, u32)> {
+    let transmitter = lineage.transmitter();
+    if lineage == Lineage::Paternal && sex_of(store, ancestor) != Some(transmitter) {
+        return Vec::new();
+    }
+    let mut found = vec![(ancestor, 0)];
+    let mut seen = HashSet::from([ancestor]);
+    let mut queue = VecDeque::from([(ancestor, 0)]);
+    while let Some((person, generation)) = queue.pop_front() {
+        if sex_of(store, person) != Some(transmitter) {
+            continue;
+        }
+        for family in store.families_as_spouse(person) {
+            for child in &family.children {
+                let carries =
+                    lineage == Lineage::Maternal || sex_of(store, child) == Some(Sex::Male);
+                if carries && seen.insert(child.as_str()) {
+                    found.push((child.as_str(), generation + 1));
+                    queue.push_back((child.as_str(), generation + 1));
+                }
+            }
+        }
+    }
+    found
+}
+
+/// People who, through broken links, are their own ancestors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AncestorCycle {
+    /// Everyone caught in loops with each other, in ID order.
+    pub individuals: Vec<String>,
+    /// A shortest loop from the first of `individuals` up through parents
+    /// back to them: each link is a person and the family in which they are
+    /// a child of the next one (the last of the first).
+    pub chain: Vec<ChainLink>,
+}
+
+impl AncestorCycle {
+    /// One-line summary for logs and warnings.
+    pub fn describe(&self) -> String {
+        let chain: Vec<&str> = self
+            .chain
+            .iter()
+            .chain(self.chain.first())
+            .map(|link| link.id.as_str())
+            .collect();
+        format!(
+            "parent/child loop: {} is their own ancestor ({}); {} individuals affected",
+            self.individuals[0],
+            chain.join(" -> "),
+            self.individuals.len()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChainLink {
+    pub id: String,
+    pub family: String,
+}
+
+/// Parents of `id` in every family where they are a child, with the family.
+fn parent_links<'a>(store: &'a GedcomStore, id: &str) -> Vec<(&'a str, &'a str)> {
+    store
+        .families_as_child(id)
+        .flat_map(|family| {
+            [&family.husband, &family.wife]
+                .into_iter()
+                .flatten()
+                .map(|parent| (parent.as_str(), family.id.as_str()))
+        })
+        .collect()
+}
+
+/// Loops in the child-to-parent graph: its strongly connected components
+/// (Tarjan's algorithm, iteratively so long lines cannot overflow the
+/// stack), each with one shortest loop as evidence.
+pub fn ancestor_cycles(store: &GedcomStore) -> Vec<AncestorCycle> {
+    let mut people: Vec<&str> = store
+        .individuals()
+        .map(|individual| individual.id.as_str())
+        .chain(
+            store
+                .families()
+                .flat_map(|family| family.children.iter().map(String::as_str)),
+        )
+        .collect();
+    people.sort_by(|a, b| compare_ids(a, b));
+    people.dedup();
+
+    // Per person: visit order and the lowest order reachable.
+    let mut order: HashMap<&str, (usize, usize)> = HashMap::new();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut on_stack: HashSet<&str> = HashSet::new();
+    let mut components = Vec::new();
+    for root in people {
+        if order.contains_key(root) {
+            continue;
+        }
+        let mut work = vec![(root, parent_links(store, root), 0)];
+        order.insert(root, (order.len(), order.len()));
+        stack.push(root);
+        on_stack.insert(root);
+        while let Some((person, parents, next)) = work.last_mut() {
+            let person = *person;
+            if let Some(&(parent, _)) = parents.get(*next) {
+                *next += 1;
+                match order.get(parent) {
+                    None => {
+                        order.insert(parent, (order.len(), order.len()));
+                        stack.push(parent);
+                        on_stack.insert(parent);
+                        work.push((parent, parent_links(store, parent), 0));
+                    }
+                    Some(&(visited, _)) if on_stack.contains(parent) => {
+                        let low = &mut order.get_mut(person).expect("visited").1;
+                        *low = (*low).min(visited);
+                    }
+                    Some(_) => {}
+                }
+                continue;
+            }
+            work.pop();
+            let (visited, low) = order[person];
+            if let Some((child, _, _)) = work.last() {
+                let child_low = &mut order.get_mut(child).expect("visited").1;
+                *child_low = (*child_low).min(low);
+            }
+            if low == visited {
+                let mut component = Vec::new();
+                while let Some(member) = stack.pop() {
+                    on_stack.remove(member);
+                    component.push(member);
+                    if member == person {
+                        break;
+                    }
+                }
+                let looped = component.len() > 1
+                    || parent_links(store, person)
+                        .iter()
+                        .any(|(parent, _)| *parent == person);
+                if looped {
+                    component.sort_by(|a, b| compare_ids(a, b));
+                    components.push(component);
+                }
+            }
+        }
+    }
+
+    let mut cycles: Vec<AncestorCycle> = components
+        .into_iter()
+        .map(|component| AncestorCycle {
+            chain: shortest_loop(store, &component),
+            individuals: component.into_iter().map(str::to_owned).collect(),
+        })
+        .collect();
+    cycles.sort_by(|a, b| compare_ids(&a.individuals[0], &b.individuals[0]));
+    cycles
+}
+
+/// Loops running through family `id`: only looked for in full when one of
+/// its spouses turns out to descend from one of its children.
+pub fn cycles_through(store: &GedcomStore, id: &str) -> Vec<AncestorCycle> {
+    let Some(family) = store.get_family(id) else {
+        return Vec::new();
+    };
+    let children: HashSet<&str> = family.children.iter().map(String::as_str).collect();
+    let mut stack: Vec<&str> = [&family.husband, &family.wife]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect();
+    let mut seen = HashSet::new();
+    let mut looped = false;
+    while let Some(person) = stack.pop() {
+        if children.contains(person) {
+            looped = true;
+            break;
+        }
+        if seen.insert(person) {
+            stack.extend(
+                parent_links(store, person)
+                    .into_iter()
+                    .map(|(parent, _)| parent),
+            );
+        }
+    }
+    if !looped {
+        return Vec::new();
+    }
+    ancestor_cycles(store)
+        .into_iter()
+        .filter(|cycle| {
+            cycle
+                .individuals
+                .iter()
+                .any(|individual| children.contains(individual.as_str()))
+        })
+        .collect()
+}
+
+/// Breadth-first search from the first member of a component back to them,
+/// staying inside the component.
+fn shortest_loop(store: &GedcomStore, component: &[&str]) -> Vec<ChainLink> {
+    let start = component[0];
+    let members: HashSet<&str> = component.iter().copied().collect();
+    let mut reached: HashMap<&str, (&str, &str)> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(person) = queue.pop_front() {
+        for (parent, family) in parent_links(store, person) {
+            if !members.contains(parent) {
+                continue;
+            }
+            if parent == start {
+                let mut chain = vec![ChainLink {
+                    id: person.to_owned(),
+                    family: family.to_owned(),
+                }];
+                let mut current = person;
+                while current != start {
+                    let (child, family) = reached[current];
+                    chain.push(ChainLink {
+                        id: child.to_owned(),
+                        family: family.to_owned(),
+                    });
+                    current = child;
+                }
+                chain.reverse();
+                return chain;
+            }
+            if !reached.contains_key(parent) {
+                reached.insert(parent, (person, family));
+                queue.push_back(parent);
+            }
+        }
+    }
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gedcom::GedcomData;
+
+    fn family(id: &str, husband: &str, wife: &str, children: &[&str]) -> Family {
+        Family {
+            id: id.into(),
+            husband: Some(husband.into()),
+            wife: Some(wife.into()),
+            children: children.iter().map(|child| (*child).into()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn store(families: Vec<Family>) -> GedcomStore {
+        GedcomStore::from_data(GedcomData {
+            families,
+            ..Default::default()
+        })
+    }
+
+    const ALL: TraversalOptions = TraversalOptions {
+        max_generations: u32::MAX,
+        direct_line: true,
+        include_spouses: false,
+    };
+
+    fn summary(nodes: &[TreeNode]) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|node| {
+                let number = node
+                    .ahnentafel
+                    .map(|n| n.to_string())
+                    .or(node.aboville.clone())
+                    .unwrap_or_else(|| format!("{:?}", node.relation));
+                let repeat = if node.repeat { "*" } else { "" };
+                format!("{}:{number}{repeat}", node.id)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn numbers_ancestors_and_marks_pedigree_collapse() {
+        // First cousins I5 and I6 share grandparents G1 and G2.
+        let store = store(vec![
+            family("F1", "I5", "I6", &["I7"]),
+            family("F2", "I3", "X1", &["I5"]),
+            family("F3", "X2", "I4", &["I6"]),
+            family("F4", "G1", "G2", &["I3", "I4"]),
+        ]);
+
+        let nodes = ancestors(&store, "I7", &ALL);
+        assert_eq!(
+            summary(&nodes),
+            vec![
+                "I7:1", "I5:2", "I6:3", "I3:4", "X1:5", "X2:6", "I4:7", "G1:8", "G2:9", "G1:14*",
+                "G2:15*",
+            ]
+        );
+        assert_eq!(nodes[7].generation, 3);
+        assert_eq!(nodes[7].of.as_deref(), Some("I3"));
+
+        let limited = ancestors(
+            &store,
+            "I7",
+            &TraversalOptions {
+                max_generations: 1,
+                ..ALL
+            },
+        );
+        assert_eq!(summary(&limited), vec!["I7:1", "I5:2", "I6:3"]);
+    }
+
+    #[test]
+    fn survives_loops_in_the_parent_graph() {
+        // Corrupt data: I1 is their own grandparent.
+        let store = store(vec![
+            family("F1", "I2", "I3", &["I1"]),
+            family("F2", "I1", "I4", &["I2"]),
+        ]);
+
+        assert_eq!(
+            summary(&ancestors(&store, "I1", &ALL)),
+            vec!["I1:1", "I2:2", "I3:3", "I1:4*", "I4:5"]
+        );
+        assert_eq!(
+            summary(&descendants(&store, "I1", &ALL)),
+            vec!["I1:1", "I2:1.1", "I1:1.1.1*"]
+        );
+
+        let pedigree = appearances(&store, "I1", u32::MAX);
+        assert_eq!(pedigree.len(), 3);
+        assert_eq!(pedigree[2].get("I1"), Some(&1));
+    }
+
+    #[test]
+    fn numbers_descendants_with_spouses_and_step_children() {
+        let store = store(vec![
+            family("F1", "I1", "I2", &["I3", "I4"]),
+            family("F2", "I1", "I5", &["I6"]),
+            family("F3", "I3", "I7", &["I8"]),
+            family("F4", "I9", "I7", &["I10"]),
+        ]);
+
+        assert_eq!(
+            summary(&descendants(&store, "I1", &ALL)),
+            vec!["I1:1", "I3:1.1", "I4:1.2", "I6:1.3", "I8:1.1.1"]
+        );
+
+        let nodes = descendants(
+            &store,
+            "I1",
+            &TraversalOptions {
+                max_generations: 1,
+                direct_line: false,
+                include_spouses: true,
+            },
+        );
+        assert_eq!(
+            summary(&nodes),
+            vec![
+                "I1:1",
+                "I3:1.1",
+                "I4:1.2",
+                "I6:1.3",
+                "I10:StepChild",
+                "I2:Spouse",
+                "I5:Spouse",
+                "I7:Spouse",
+            ]
+        );
+        assert_eq!(nodes[4].of.as_deref(), Some("I3"));
+        assert_eq!(nodes[7].parent, Some(1));
+    }
+
+    #[test]
+    fn traverses_large_trees() {
+        // A complete binary descendant tree of 2^17 - 1 people.
+        let mut families = Vec::new();
+        let mut next = 2;
+        for parent in 1..(1 << 16) {
+            families.push(family(
+                &format!("F{parent}"),
+                &format!("I{parent}"),
+                &format!("S{parent}"),
+                &[&format!("I{next}"), &format!("I{}", next + 1)],
+            ));
+            next += 2;
+        }
+        let store = store(families);
+
+        let nodes = descendants(&store, "I1", &ALL);
+        assert_eq!(nodes.len(), (1 << 17) - 1);
+        let leaf = nodes.last().unwrap();
+        assert_eq!(leaf.generation, 16);
+        assert_eq!(
+            leaf.aboville.as_deref(),
+            Some("1.2.2.2.2.2.2.2.2.2.2.2.2.2.2.2.2")
+        );
+
+        let nodes = ancestors(&store, &leaf.id, &ALL);
+        // The paternal line back to I1 plus the mother of each generation.
+        assert_eq!(nodes.len(), 33);
+        assert_eq!(nodes[31].ahnentafel, Some(1 << 16));
+        assert_eq!(nodes[32].ahnentafel, Some((1 << 16) + 1));
+    }
+
+    #[test]
+    fn measures_pedigree_collapse_and_common_ancestors() {
+        let store = store(vec![
+            family("F1", "I5", "I6", &["I7"]),
+            family("F2", "I3", "X1", &["I5"]),
+            family("F3", "X2", "I4", &["I6"]),
+            family("F4", "G1", "G2", &["I3", "I4"]),
+        ]);
+
+        let pedigree = appearances(&store, "I7", 10);
+        assert_eq!(pedigree.len(), 4);
+        let top = &implex(&pedigree)[3];
+        assert_eq!(
+            (top.theoretical, top.known, top.distinct, top.collapse),
+            (Some(8), 4, 2, 0.5)
+        );
+        let repeated = repeated_ancestors(&pedigree);
+        assert_eq!(
+            repeated
+                .iter()
+                .map(|ancestor| (ancestor.id.as_str(), ancestor.appearances))
+                .collect::<Vec<_>>(),
+            vec![("G1", 2), ("G2", 2)]
+        );
+        assert_eq!(repeated[0].generations, vec![3]);
+
+        let common = common_ancestors(
+            &store,
+            &appearances(&store, "I7", 10),
+            &appearances(&store, "I5", 10),
+        );
+        let summary: Vec<(&str, u32, bool)> = common
+            .iter()
+            .map(|ancestor| {
+                (
+                    ancestor.id.as_str(),
+                    ancestor.distance,
+                    ancestor.most_recent,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("I5", 1, true),
+                ("I3", 3, false),
+                ("X1", 3, false),
+                ("G1", 5, false),
+                ("G2", 5, false),
+            ]
+        );
+        assert_eq!(common[3].from_generations, vec![3]);
+        assert_eq!(common[3].to_generations, vec![2]);
+    }
+
+    #[test]
+    fn traces_uniparental_lines_and_their_carriers() {
+        use crate::gedcom::Individual;
+
+        let sexed = [
+            ("A", Sex::Male),
+            ("B", Sex::Female),
+            ("A1", Sex::Male),
+            ("A2", Sex::Female),
+        ];
+        let store = GedcomStore::from_data(GedcomData {
+            individuals: sexed
+                .iter()
+                .map(|(id, sex)| Individual {
+                    id: (*id).into(),
+                    sex: Some(*sex),
+                    ..Default::default()
+                })
+                .collect(),
+            families: vec![
+                family("F1", "G", "GW", &["A", "B", "C"]),
+                family("F2", "A", "AW", &["A1", "A2"]),
+                family("F3", "BH", "B", &["B1", "B2"]),
+                family("F4", "X", "B2", &["B21"]),
+            ],
+            ..Default::default()
+        });
+
+        assert_eq!(line(&store, "A1", Lineage::Paternal), vec!["A1", "A", "G"]);
+        assert_eq!(
+            line(&store, "B21", Lineage::Maternal),
+            vec!["B21", "B2", "B", "GW"]
+        );
+        assert_eq!(
+            carriers(&store, "G", Lineage::Paternal),
+            vec![("G", 0), ("A", 1), ("A1", 2)]
+        );
+        // A woman without a known father heads her paternal line but has no Y.
+        assert_eq!(line(&store, "AW", Lineage::Paternal), vec!["AW"]);
+        assert!(carriers(&store, "AW", Lineage::Paternal).is_empty());
+        assert_eq!(
+            carriers(&store, "AW", Lineage::Maternal),
+            vec![("AW", 0), ("A1", 1), ("A2", 1)]
+        );
+        assert_eq!(
+            carriers(&store, "GW", Lineage::Maternal),
+            vec![
+                ("GW", 0),
+                ("A", 1),
+                ("B", 1),
+                ("C", 1),
+                ("B1", 2),
+                ("B2", 2),
+                ("B21", 3)
+            ]
+        );
+
+        let looped = self::store(vec![
+            family("F1", "X", "M", &["Y"]),
+            family("F2", "Y", "N", &["X"]),
+        ]);
+        assert_eq!(line(&looped, "X", Lineage::Paternal), vec!["X", "Y"]);
+    }
+
+    #[test]
+    fn finds_people_who_are_their_own_ancestors() {
+        let store = store(vec![
+            family("F1", "I2", "I3", &["I1"]),
+            family("F2", "I4", "I5", &["I2"]),
+            family("F3", "I1", "I6", &["I4"]),
+            family("F4", "I7", "I8", &["I9"]),
+            family("F5", "I10", "I11", &["I10"]),
+        ]);
+
+        let cycles = ancestor_cycles(&store);
+        assert_eq!(cycles.len(), 2);
+        assert_eq!(cycles[0].individuals, vec!["I1", "I2", "I4"]);
+        let chain: Vec<(&str, &str)> = cycles[0]
+            .chain
+            .iter()
+            .map(|link| (link.id.as_str(), link.family.as_str()))
+            .collect();
+        // I1 is a child of I2 (F1), a child of I4 (F2), a child of I1 (F3).
+        assert_eq!(chain, vec![("I1", "F1"), ("I2", "F2"), ("I4", "F3")]);
+        assert_eq!(cycles[1].individuals, vec!["I10"]);
+        assert_eq!(cycles[1].chain[0].family, "F5");
+        assert_eq!(
+            cycles[0].describe(),
+            "parent/child loop: I1 is their own ancestor (I1 -> I2 -> I4 -> I1); 3 individuals affected"
+        );
+
+        assert_eq!(cycles_through(&store, "F3"), vec![cycles[0].clone()]);
+        assert_eq!(cycles_through(&store, "F5"), vec![cycles[1].clone()]);
+        assert!(cycles_through(&store, "F4").is_empty());
+        assert!(cycles_through(&store, "F9").is_empty());
+    }
+}
@@ -0,0 +1,334 @@
+//! Phonetic name codes. Inputs are expected to be folded with
+//! [`crate::text::fold`]: lowercase ASCII letters, anything else is skipped.
+
+/// American Soundex, e.g. `Robert` and `Rupert` are both `R163`.
+pub fn soundex(name: &str) -> Option<String> {
+    let code = |c: char| match c {
+        'b' | 'f' | 'p' | 'v' => Some('1'),
+        'c' | 'g' | 'j' | 'k' | 'q' | 's' | 'x' | 'z' => Some('2'),
+        'd' | 't' => Some('3'),
+        'l' => Some('4'),
+        'm' | 'n' => Some('5'),
+        'r' => Some('6'),
+        _ => None,
+    };
+    let mut letters = name.chars().filter(char::is_ascii_lowercase);
+    let first = letters.next()?;
+    let mut result = first.to_ascii_uppercase().to_string();
+    let mut last = code(first);
+    for c in letters {
+        // H and W do not separate letters with the same code; vowels do.
+        if matches!(c, 'h' | 'w') {
+            continue;
+        }
+        let current = code(c);
+        if current.is_some() && current != last {
+            result.extend(current);
+            if result.len() == 4 {
+                break;
+            }
+        }
+        last = current;
+    }
+    while result.len() < 4 {
+        result.push('0');
+    }
+    Some(result)
+}
+
+/// Kölner Phonetik (Cologne phonetics), tuned for German names, e.g.
+/// `Müller-Lüdenscheidt` is `65752682`.
+pub fn cologne(name: &str) -> Option<String> {
+    let letters: Vec<char> = name.chars().filter(char::is_ascii_lowercase).collect();
+    let mut digits = String::new();
+    for (idx, &c) in letters.iter().enumerate() {
+        let previous = idx.checked_sub(1).map(|idx| letters[idx]);
+        let next = letters.get(idx + 1).copied();
+        let next_in = |set: &str| next.is_some_and(|next| set.contains(next));
+        let previous_in = |set: &str| previous.is_some_and(|previous| set.contains(previous));
+        let code = match c {
+            'a' | 'e' | 'i' | 'j' | 'o' | 'u' | 'y' => "0",
+            'h' => "",
+            'b' => "1",
+            'p' if next == Some('h') => "3",
+            'p' => "1",
+            'd' | 't' if next_in("csz") => "8",
+            'd' | 't' => "2",
+            'f' | 'v' | 'w' => "3",
+            'g' | 'k' | 'q' => "4",
+            'c' if idx == 0 => {
+                if next_in("ahkloqrux") {
+                    "4"
+                } else {
+                    "8"
+                }
+            }
+            'c' if previous_in("sz") => "8",
+            'c' if next_in("ahkoqux") => "4",
+            'c' => "8",
+            'x' if previous_in("ckq") => "8",
+            'x' => "48",
+            'l' => "5",
+            'm' | 'n' => "6",
+            'r' => "7",
+            's' | 'z' => "8",
+            _ => "",
+        };
+        digits.push_str(code);
+    }
+
+    // Collapse repeated digits first, then drop every zero but a leading one.
+    let mut collapsed = String::new();
+    for digit in digits.chars() {
+        if !collapsed.ends_with(digit) {
+            collapsed.push(digit);
+        }
+    }
+    let collapsed: String = collapsed
+        .char_indices()
+        .filter(|&(idx, digit)| digit != '0' || idx == 0)
+        .map(|(_, digit)| digit)
+        .collect();
+    (!collapsed.is_empty()).then_some(collapsed)
+}
+
+/// Codes of one Daitch–Mokotoff rule: at the start of a name, before a
+/// vowel, and elsewhere. `None` means the sound is not coded there.
+type Codes = [Option<&'static str>; 3];
+
+const fn codes(start: &'static str, vowel: &'static str, other: &'static str) -> Codes {
+    [code(start), code(vowel), code(other)]
+}
+
+const fn code(digits: &'static str) -> Option<&'static str> {
+    if digits.is_empty() {
+        None
+    } else {
+        Some(digits)
+    }
+}
+
+/// Daitch–Mokotoff rules, longest patterns first within each letter. Rules
+/// with two code sets branch into alternative codes.
+const DM_RULES: &[(&str, Codes, Option<Codes>)] = &[
+    ("schtsch", codes("2", "4", "4"), None),
+    ("schtsh", codes("2", "4", "4"), None),
+    ("schtch", codes("2", "4", "4"), None),
+    ("shtch", codes("2", "4", "4"), None),
+    ("shtsh", codes("2", "4", "4"), None),
+    ("stsch", codes("2", "4", "4"), None),
+    ("ttsch", codes("4", "4", "4"), None),
+    ("zhdzh", codes("2", "4", "4"), None),
+    ("shch", codes("2", "4", "4"), None),
+    ("scht", codes("2", "43", "43"), None),
+    ("schd", codes("2", "43", "43"), None),
+    ("stch", codes("2", "4", "4"), None),
+    ("strz", codes("2", "4", "4"), None),
+    ("strs", codes("2", "4", "4"), None),
+    ("stsh", codes("2", "4", "4"), None),
+    ("szcz", codes("2", "4", "4"), None),
+    ("szcs", codes("2", "4", "4"), None),
+    ("ttch", codes("4", "4", "4"), None),
+    ("tsch", codes("4", "4", "4"), None),
+    ("ttsz", codes("4", "4", "4"), None),
+    ("zdzh", codes("2", "4", "4"), None),
+    ("zsch", codes("4", "4", "4"), None),
+    ("chs", codes("5", "54", "54"), None),
+    ("csz", codes("4", "4", "4"), None),
+    ("czs", codes("4", "4", "4"), None),
+    ("drz", codes("4", "4", "4"), None),
+    ("drs", codes("4", "4", "4"), None),
+    ("dsh", codes("4", "4", "4"), None),
+    ("dsz", codes("4", "4", "4"), None),
+    ("dzh", codes("4", "4", "4"), None),
+    ("dzs", codes("4", "4", "4"), None),
+    ("sch", codes("4", "4", "4"), None),
+    ("sht", codes("2", "43", "43"), None),
+    ("szt", codes("2", "43", "43"), None),
+    ("shd", codes("2", "43", "43"), None),
+    ("szd", codes("2", "43", "43"), None),
+    ("tch", codes("4", "4", "4"), None),
+    ("trz", codes("4", "4", "4"), None),
+    ("trs", codes("4", "4", "4"), None),
+    ("tsh", codes("4", "4", "4"), None),
+    ("tts", codes("4", "4", "4"), None),
+    ("ttz", codes("4", "4", "4"), None),
+    ("tzs", codes("4", "4", "4"), None),
+    ("tsz", codes("4", "4", "4"), None),
+    ("zdz", codes("2", "4", "4"), None),
+    ("zhd", codes("2", "43", "43"), None),
+    ("zsh", codes("4", "4", "4"), None),
+    ("ai", codes("0", "1", ""), None),
+    ("aj", codes("0", "1", ""), None),
+    ("ay", codes("0", "1", ""), None),
+    ("au", codes("0", "7", ""), None),
+    ("ch", codes("5", "5", "5"), Some(codes("4", "4", "4"))),
+    ("ck", codes("5", "5", "5"), Some(codes("45", "45", "45"))),
+    ("cz", codes("4", "4", "4"), None),
+    ("cs", codes("4", "4", "4"), None),
+    ("ds", codes("4", "4", "4"), None),
+    ("dz", codes("4", "4", "4"), None),
+    ("dt", codes("3", "3", "3"), None),
+    ("ei", codes("0", "1", ""), None),
+    ("ej", codes("0", "1", ""), None),
+    ("ey", codes("0", "1", ""), None),
+    ("eu", codes("1", "1", ""), None),
+    ("fb", codes("7", "7", "7"), None),
+    ("ia", codes("1", "", ""), None),
+    ("ie", codes("1", "", ""), None),
+    ("io", codes("1", "", ""), None),
+    ("iu", codes("1", "", ""), None),
+    ("ks", codes("5", "54", "54"), None),
+    ("kh", codes("5", "5", "5"), None),
+    ("mn", codes("66", "66", "66"), None),
+    ("nm", codes("66", "66", "66"), None),
+    ("oi", codes("0", "1", ""), None),
+    ("oj", codes("0", "1", ""), None),
+    ("oy", codes("0", "1", ""), None),
+    ("pf", codes("7", "7", "7"), None),
+    ("ph", codes("7", "7", "7"), None),
+    ("rs", codes("94", "94", "94"), Some(codes("4", "4", "4"))),
+    ("rz", codes("94", "94", "94"), Some(codes("4", "4", "4"))),
+    ("sh", codes("4", "4", "4"), None),
+    ("sc", codes("2", "4", "4"), None),
+    ("st", codes("2", "43", "43"), None),
+    ("sd", codes("2", "43", "43"), None),
+    ("sz", codes("4", "4", "4"), None),
+    ("th", codes("3", "3", "3"), None),
+    ("ts", codes("4", "4", "4"), None),
+    ("tc", codes("4", "4", "4"), None),
+    ("tz", codes("4", "4", "4"), None),
+    ("ui", codes("0", "1", ""), None),
+    ("uj", codes("0", "1", ""), None),
+    ("uy", codes("0", "1", ""), None),
+    ("ue", codes("0", "", ""), None),
+    ("zd", codes("2", "43", "43"), None),
+    ("zh", codes("4", "4", "4"), None),
+    ("zs", codes("4", "4", "4"), None),
+    ("a", codes("0", "", ""), None),
+    ("b", codes("7", "7", "7"), None),
+    ("c", codes("5", "5", "5"), Some(codes("4", "4", "4"))),
+    ("d", codes("3", "3", "3"), None),
+    ("e", codes("0", "", ""), None),
+    ("f", codes("7", "7", "7"), None),
+    ("g", codes("5", "5", "5"), None),
+    ("h", codes("5", "5", ""), None),
+    ("i", codes("0", "", ""), None),
+    ("j", codes("1", "1", "1"), Some(codes("4", "4", "4"))),
+    ("k", codes("5", "5", "5"), None),
+    ("l", codes("8", "8", "8"), None),
+    ("m", codes("6", "6", "6"), None),
+    ("n", codes("6", "6", "6"), None),
+    ("o", codes("0", "", ""), None),
+    ("p", codes("7", "7", "7"), None),
+    ("q", codes("5", "5", "5"), None),
+    ("r", codes("9", "9", "9"), None),
+    ("s", codes("4", "4", "4"), None),
+    ("t", codes("3", "3", "3"), None),
+    ("u", codes("0", "", ""), None),
+    ("v", codes("7", "7", "7"), None),
+    ("w", codes("7", "7", "7"), None),
+    ("x", codes("5", "54", "54"), None),
+    ("y", codes("1", "", ""), None),
+    ("z", codes("4", "4", "4"), None),
+];
+
+/// Branches kept while coding one name; ambiguous names rarely need more.
+const DM_MAX_BRANCHES: usize = 32;
+
+/// Daitch–Mokotoff Soundex: one or more six-digit codes, sorted, e.g.
+/// `Peters` is `734000` or `739400`.
+pub fn daitch_mokotoff(name: &str) -> Vec<String> {
+    let letters: String = name.chars().filter(char::is_ascii_lowercase).collect();
+    if letters.is_empty() {
+        return Vec::new();
+    }
+    // Each branch: code so far and the last code appended (or skipped).
+    let mut branches: Vec<(String, Option<&str>)> = vec![(String::new(), None)];
+    let mut rest = letters.as_str();
+    let mut first = true;
+    while !rest.is_empty() {
+        let Some(&(pattern, primary, alternative)) = DM_RULES
+            .iter()
+            .find(|(pattern, _, _)| rest.starts_with(pattern))
+        else {
+            rest = &rest[1..];
+            continue;
+        };
+        rest = &rest[pattern.len()..];
+        let position = if first {
+            0
+        } else if rest.starts_with(['a', 'e', 'i', 'o', 'u']) {
+            1
+        } else {
+            2
+        };
+        first = false;
+
+        let mut next = Vec::new();
+        for (code, last) in &branches {
+            for rule in std::iter::once(primary).chain(alternative) {
+                let current = rule[position];
+                let mut code = code.clone();
+                if let Some(digits) = current
+                    && *last != current
+                {
+                    code.push_str(digits);
+                }
+                next.push((code, current));
+            }
+        }
+        next.dedup();
+        next.truncate(DM_MAX_BRANCHES);
+        branches = next;
+    }
+
+    let mut codes: Vec<String> = branches
+        .into_iter()
+        .map(|(mut code, _)| {
+            code.truncate(6);
+            while code.len() < 6 {
+                code.push('0');
+            }
+            code
+        })
+        .collect();
+    codes.sort_unstable();
+    codes.dedup();
+    codes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_soundex() {
+        assert_eq!(soundex("robert").as_deref(), Some("R163"));
+        assert_eq!(soundex("rupert").as_deref(), Some("R163"));
+        assert_eq!(soundex("tymczak").as_deref(), Some("T522"));
+        assert_eq!(soundex("pfister").as_deref(), Some("P236"));
+        assert_eq!(soundex("ashcraft").as_deref(), Some("A261"));
+        assert_eq!(soundex("lee").as_deref(), Some("L000"));
+        assert_eq!(soundex(""), None);
+    }
+
+    #[test]
+    fn computes_cologne_phonetics() {
+        assert_eq!(cologne("muller ludenscheidt").as_deref(), Some("65752682"));
+        assert_eq!(cologne("wikipedia").as_deref(), Some("3412"));
+        assert_eq!(cologne("breschnew").as_deref(), Some("17863"));
+        assert_eq!(cologne("meier"), cologne("mayr"));
+        assert_eq!(cologne("schmidt"), cologne("schmitt"));
+    }
+
+    #[test]
+    fn computes_daitch_mokotoff_codes() {
+        assert_eq!(daitch_mokotoff("moskowitz"), vec!["645740"]);
+        assert_eq!(daitch_mokotoff("auerbach"), vec!["097400", "097500"]);
+        assert_eq!(daitch_mokotoff("peters"), vec!["734000", "739400"]);
+        assert_eq!(daitch_mokotoff("kleinman"), vec!["586660"]);
+        assert!(daitch_mokotoff("schwarz").contains(&"479400".to_owned()));
+        assert!(daitch_mokotoff("").is_empty());
+    }
+}
@@ -0,0 +1,298 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Decimal-degree coordinates from a PLAC `MAP` structure.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Coordinates {
+    /// Parses GEDCOM `LATI`/`LONG` values such as `N51.5` and `W0.12`; plain
+    /// signed decimals are accepted as well.
+    pub fn parse(latitude: &str, longitude: &str) -> Option<Self> {
+        let latitude = parse_coordinate(latitude, 'N', 'S')?;
+        let longitude = parse_coordinate(longitude, 'E', 'W')?;
+        ((-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude)).then_some(
+            Self {
+                latitude,
+                longitude,
+            },
+        )
+    }
+}
+
+fn parse_coordinate(value: &str, positive: char, negative: char) -> Option<f64> {
+    let value = value.trim().to_ascii_uppercase();
+    let (sign, number) = if let Some(rest) = value.strip_prefix(positive) {
+        (1.0, rest)
+    } else if let Some(rest) = value.strip_prefix(negative) {
+        (-1.0, rest)
+    } else {
+        (1.0, value.as_str())
+    };
+    let number: f64 = number.trim().parse().ok()?;
+    number.is_finite().then_some(sign * number)
+}
+
+/// One jurisdiction of a place name, labelled with its level from the
+/// header's place form when known.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Jurisdiction {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+}
+
+/// Splits a place into its jurisdictions, smallest first as written.
+/// Components are aligned with `form` from the right (country last) when the
+/// place lists fewer jurisdictions than the form; empty components are
+/// skipped.
+pub fn hierarchy(place: &str, form: &[String]) -> Vec<Jurisdiction> {
+    let components: Vec<&str> = place.split(',').map(str::trim).collect();
+    let offset = form.len().saturating_sub(components.len());
+    components
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| !name.is_empty())
+        .map(|(idx, name)| Jurisdiction {
+            name: (*name).to_owned(),
+            level: form.get(idx + offset).cloned(),
+        })
+        .collect()
+}
+
+fn normalized_components(place: &str) -> Vec<String> {
+    place
+        .split(',')
+        .map(|component| component.trim().to_lowercase())
+        .filter(|component| !component.is_empty())
+        .collect()
+}
+
+/// Key under which spelling variants differing only in case and spacing are
+/// deduplicated.
+pub fn place_key(place: &str) -> String {
+    normalized_components(place).join(", ")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RecordRef {
+    Individual(String),
+    Family(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaceEntry {
+    pub name: String,
+    pub coordinates: Option<Coordinates>,
+    components: Vec<String>,
+    /// Number of events per record that reference this place.
+    references: BTreeMap<RecordRef, usize>,
+}
+
+impl PlaceEntry {
+    pub fn event_count(&self) -> usize {
+        self.references.values().sum()
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &RecordRef> {
+        self.references.keys()
+    }
+
+    /// Whether `query` (already normalized) names the trailing jurisdictions
+    /// of this place, e.g. `yorkshire, england` within
+    /// `leeds, yorkshire, england`.
+    fn contains(&self, query: &[String]) -> bool {
+        self.components.ends_with(query)
+    }
+}
+
+/// Deduplicated index of all places referenced by events in the store.
+#[derive(Debug, Clone, Default)]
+pub struct PlaceIndex {
+    places: BTreeMap<String, PlaceEntry>,
+    by_component: HashMap<String, BTreeSet<String>>,
+}
+
+impl PlaceIndex {
+    pub fn add(&mut self, place: &str, coordinates: Option<Coordinates>, record: RecordRef) {
+        let key = place_key(place);
+        if key.is_empty() {
+            return;
+        }
+        let entry = self.places.entry(key.clone()).or_insert_with(|| {
+            let components = normalized_components(place);
+            for component in &components {
+                self.by_component
+                    .entry(component.clone())
+                    .or_default()
+                    .insert(key.clone());
+            }
+            PlaceEntry {
+                name: place.trim().to_owned(),
+                coordinates: None,
+                components,
+                references: BTreeMap::new(),
+            }
+        });
+        if entry.coordinates.is_none() {
+            entry.coordinates = coordinates;
+        }
+        *entry.references.entry(record).or_default() += 1;
+    }
+
+    pub fn remove(&mut self, place: &str, record: &RecordRef) {
+        let key = place_key(place);
+        let Some(entry) = self.places.get_mut(&key) else {
+            return;
+        };
+        if let Some(count) = entry.references.get_mut(record) {
+            *count -= 1;
+            if *count == 0 {
+                entry.references.remove(record);
+            }
+        }
+        if entry.references.is_empty()
+            && let Some(entry) = self.places.remove(&key)
+        {
+            for component in entry.components {
+                if let Some(keys) = self.by_component.get_mut(&component) {
+                    keys.remove(&key);
+                    if keys.is_empty() {
+                        self.by_component.remove(&component);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &PlaceEntry> {
+        self.places.values()
+    }
+
+    pub fn get(&self, place: &str) -> Option<&PlaceEntry> {
+        self.places.get(&place_key(place))
+    }
+
+    /// Places matching `query`, in key order. With `include_subplaces` every
+    /// place whose trailing jurisdictions are the query matches, otherwise only
+    /// the place itself.
+    pub fn matching(&self, query: &str, include_subplaces: bool) -> Vec<&PlaceEntry> {
+        if !include_subplaces {
+            return self.get(query).into_iter().collect();
+        }
+        let query = normalized_components(query);
+        let Some(last) = query.last() else {
+            return Vec::new();
+        };
+        self.by_component
+            .get(last)
+            .into_iter()
+            .flatten()
+            .filter_map(|key| self.places.get(key))
+            .filter(|entry| entry.contains(&query))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_coordinates() {
+        assert_eq!(
+            Coordinates::parse("N53.8", "W1.55"),
+            Some(Coordinates {
+                latitude: 53.8,
+                longitude: -1.55
+            })
+        );
+        assert_eq!(
+            Coordinates::parse("-33.9", "18.4"),
+            Some(Coordinates {
+                latitude: -33.9,
+                longitude: 18.4
+            })
+        );
+        assert_eq!(Coordinates::parse("N95", "E0"), None);
+        assert_eq!(Coordinates::parse("north", "E0"), None);
+    }
+
+    #[test]
+    fn labels_jurisdictions_with_place_form() {
+        let form: Vec<String> = ["City", "County", "State", "Country"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let levels = |place: &str| {
+            hierarchy(place, &form)
+                .into_iter()
+                .map(|j| (j.name, j.level.unwrap_or_default()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            levels("Springfield, Sangamon, Illinois, USA"),
+            vec![
+                ("Springfield".into(), "City".into()),
+                ("Sangamon".into(), "County".into()),
+                ("Illinois".into(), "State".into()),
+                ("USA".into(), "Country".into()),
+            ]
+        );
+        assert_eq!(
+            levels("Yorkshire, , England"),
+            vec![
+                ("Yorkshire".into(), "County".into()),
+                ("England".into(), "Country".into()),
+            ]
+        );
+        assert_eq!(hierarchy("Leeds", &[])[0].level, None);
+    }
+
+    #[test]
+    fn indexes_and_matches_sub_places() {
+        let mut index = PlaceIndex::default();
+        let i1 = RecordRef::Individual("I1".into());
+        let i2 = RecordRef::Individual("I2".into());
+        index.add("Leeds, Yorkshire, England", None, i1.clone());
+        index.add("leeds,  yorkshire , England", None, i2.clone());
+        index.add("York, Yorkshire, England", None, i2.clone());
+        index.add("Yorkshire Dales, Iowa, USA", None, i1.clone());
+
+        assert_eq!(index.entries().count(), 3);
+        let leeds = index.get("LEEDS, YORKSHIRE, ENGLAND").unwrap();
+        assert_eq!(leeds.name, "Leeds, Yorkshire, England");
+        assert_eq!(leeds.event_count(), 2);
+
+        let names = |entries: Vec<&PlaceEntry>| {
+            entries
+                .into_iter()
+                .map(|entry| entry.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(index.matching("Yorkshire, England", true)),
+            vec!["Leeds, Yorkshire, England", "York, Yorkshire, England"]
+        );
+        assert_eq!(
+            names(index.matching("Yorkshire", true)),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            names(index.matching("Iowa, USA", true)),
+            vec!["Yorkshire Dales, Iowa, USA"]
+        );
+        assert_eq!(
+            names(index.matching("Yorkshire, England", false)),
+            Vec::<String>::new()
+        );
+
+        index.remove("York, Yorkshire, England", &i2);
+        assert!(index.get("York, Yorkshire, England").is_none());
+        assert_eq!(index.matching("York", true).len(), 0);
+    }
+}
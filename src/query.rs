@@ -0,0 +1,1378 @@
+//! A small query language over the store:
+//!
+//! ```text
+//! individuals where birth.date < 1800 and birth.place contains "Cork"
+//!     and not exists(death) select id, name, birth.date
+//! families where count(children) > 10
+//! ```
+//!
+//! Queries are parsed into an expression tree with every field resolved, a
+//! planner narrows the records to examine with the store's ID, name and place
+//! indexes, and the full condition is evaluated on what is left.
+
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use thiserror::Error;
+
+use crate::date::GedcomDate;
+use crate::gedcom::{Event, Family, GedcomStore, Individual, sex_of};
+use crate::listing::compare_ids;
+use crate::names::{NameQuery, split_name};
+use crate::place::RecordRef;
+use crate::text::{edit_distance, fold};
+
+/// A syntax or type error, with the 1-based column where it was found.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message} at column {column}")]
+pub struct QueryError {
+    pub message: String,
+    pub column: usize,
+}
+
+impl QueryError {
+    pub fn new(message: impl Into<String>, column: usize) -> Self {
+        Self {
+            message: message.into(),
+            column,
+        }
+    }
+}
+
+/// Deepest nesting of parentheses and `not` a query may use, so hostile
+/// input cannot exhaust the stack of the recursive parser.
+pub const MAX_NESTING: usize = 64;
+/// Most conditions a query may combine; each one deepens the parsed tree.
+pub const MAX_CONDITIONS: usize = 256;
+/// Most steps a field path may take, e.g. `parents.children` is two.
+pub const MAX_PATH_STEPS: usize = 16;
+/// Most distinct items a path expands to at any step; the rest are dropped,
+/// so a looping path cannot exhaust memory on a large tree.
+pub const MAX_PATH_ITEMS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Individuals,
+    Families,
+}
+
+/// What a field yields: a record to navigate further, or a value to compare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Kind {
+    Individual,
+    Family,
+    Event,
+    Text,
+    Date,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Id,
+    Name,
+    Given,
+    Surname,
+    MarriedName,
+    Sex,
+    Birth,
+    Death,
+    Events,
+    Parents,
+    Children,
+    Spouses,
+    Families,
+    Husband,
+    Wife,
+    Marriage,
+    Divorce,
+    Type,
+    Date,
+    Place,
+    Age,
+    Value,
+}
+
+/// Name, field, kind of value and whether it can yield several values.
+type FieldSpec = (&'static str, Field, Kind, bool);
+
+const INDIVIDUAL_FIELDS: &[FieldSpec] = &[
+    ("id", Field::Id, Kind::Text, false),
+    ("name", Field::Name, Kind::Text, false),
+    ("given", Field::Given, Kind::Text, false),
+    ("surname", Field::Surname, Kind::Text, false),
+    ("married_name", Field::MarriedName, Kind::Text, false),
+    ("sex", Field::Sex, Kind::Text, false),
+    ("birth", Field::Birth, Kind::Event, false),
+    ("death", Field::Death, Kind::Event, false),
+    ("events", Field::Events, Kind::Event, true),
+    ("parents", Field::Parents, Kind::Individual, true),
+    ("children", Field::Children, Kind::Individual, true),
+    ("spouses", Field::Spouses, Kind::Individual, true),
+    ("families", Field::Families, Kind::Family, true),
+];
+
+const FAMILY_FIELDS: &[FieldSpec] = &[
+    ("id", Field::Id, Kind::Text, false),
+    ("husband", Field::Husband, Kind::Individual, false),
+    ("wife", Field::Wife, Kind::Individual, false),
+    ("spouses", Field::Spouses, Kind::Individual, true),
+    ("children", Field::Children, Kind::Individual, true),
+    ("marriage", Field::Marriage, Kind::Event, true),
+    ("divorce", Field::Divorce, Kind::Event, true),
+    ("events", Field::Events, Kind::Event, true),
+];
+
+const EVENT_FIELDS: &[FieldSpec] = &[
+    ("type", Field::Type, Kind::Text, false),
+    ("date", Field::Date, Kind::Date, false),
+    ("place", Field::Place, Kind::Text, false),
+    ("age", Field::Age, Kind::Text, false),
+    ("value", Field::Value, Kind::Text, false),
+];
+
+const KEYWORDS: &[&str] = &[
+    "where",
+    "select",
+    "and",
+    "or",
+    "not",
+    "exists",
+    "count",
+    "contains",
+    "startswith",
+    "endswith",
+];
+
+fn fields_of(kind: Kind) -> &'static [FieldSpec] {
+    match kind {
+        Kind::Individual => INDIVIDUAL_FIELDS,
+        Kind::Family => FAMILY_FIELDS,
+        Kind::Event => EVENT_FIELDS,
+        Kind::Text | Kind::Date => &[],
+    }
+}
+
+fn field_names(kind: Kind) -> String {
+    fields_of(kind)
+        .iter()
+        .map(|(name, ..)| *name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A dotted field path such as `birth.place`, resolved against the target.
+#[derive(Debug, Clone)]
+struct Path {
+    steps: Vec<Field>,
+    kind: Kind,
+    multi: bool,
+    text: String,
+    column: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+impl Op {
+    fn is_text_only(self) -> bool {
+        matches!(self, Op::Contains | Op::StartsWith | Op::EndsWith)
+    }
+}
+
+/// A comparable value: folded text, a number or the Julian day range of a
+/// date (`None` for an open bound).
+#[derive(Debug, Clone, PartialEq)]
+enum Scalar {
+    Text(String),
+    Number(f64),
+    Date(Option<i64>, Option<i64>),
+}
+
+#[derive(Debug, Clone)]
+enum Term {
+    Path(Path),
+    Count(Path),
+    /// A literal, with its text as written and its column.
+    Literal(Scalar, String, usize),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Exists(Path),
+    Compare(Term, Op, Term),
+}
+
+#[derive(Debug, Clone)]
+enum Selection {
+    Path(Path),
+    Count(Path),
+}
+
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub target: Target,
+    filter: Option<Expr>,
+    select: Vec<Selection>,
+}
+
+/// How a query was run: the indexes used to narrow it down and the number
+/// of records the condition was evaluated on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Plan {
+    pub indexes: Vec<&'static str>,
+    pub examined: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    /// As written, so `1800` can also be read as a date.
+    Number(String),
+    Symbol(&'static str),
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "`{word}`"),
+            Token::Text(text) => write!(f, "string {text:?}"),
+            Token::Number(number) => write!(f, "number {number}"),
+            Token::Symbol(symbol) => write!(f, "`{symbol}`"),
+            Token::End => f.write_str("end of query"),
+        }
+    }
+}
+
+fn lex(input: &str) -> Result<Vec<(Token, usize)>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let token = if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            Token::Word(chars[start..i].iter().collect())
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            if number.parse::<f64>().is_err() {
+                return Err(QueryError::new(
+                    format!("invalid number `{number}`"),
+                    column,
+                ));
+            }
+            Token::Number(number)
+        } else if c == '"' || c == '\'' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(QueryError::new("unterminated string", column)),
+                    Some(&end) if end == c => break,
+                    Some('\\') if i + 1 < chars.len() => {
+                        text.push(chars[i + 1]);
+                        i += 2;
+                    }
+                    Some(&other) => {
+                        text.push(other);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            Token::Text(text)
+        } else {
+            let next = chars.get(i + 1).copied();
+            let (symbol, width) = match (c, next) {
+                ('!', Some('=')) | ('<', Some('>')) => ("!=", 2),
+                ('<', Some('=')) => ("<=", 2),
+                ('>', Some('=')) => (">=", 2),
+                ('=', Some('=')) => ("=", 2),
+                ('=', _) => ("=", 1),
+                ('<', _) => ("<", 1),
+                ('>', _) => (">", 1),
+                ('(', _) => ("(", 1),
+                (')', _) => (")", 1),
+                (',', _) => (",", 1),
+                ('.', _) => (".", 1),
+                _ => {
+                    return Err(QueryError::new(
+                        format!("unexpected character `{c}`"),
+                        column,
+                    ));
+                }
+            };
+            i += width;
+            Token::Symbol(symbol)
+        };
+        tokens.push((token, column));
+    }
+    tokens.push((Token::End, chars.len() + 1));
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    target: Target,
+    depth: usize,
+    conditions: usize,
+}
+
+/// Parses and type-checks a query.
+pub fn parse(input: &str) -> Result<Query, QueryError> {
+    let mut parser = Parser {
+        tokens: lex(input)?,
+        position: 0,
+        target: Target::Individuals,
+        depth: 0,
+        conditions: 0,
+    };
+    parser.query()
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn column(&self) -> usize {
+        self.tokens[self.position].1
+    }
+
+    fn advance(&mut self) -> (Token, usize) {
+        let token = self.tokens[self.position].clone();
+        if self.position + 1 < self.tokens.len() {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Token::Word(word) if word.eq_ignore_ascii_case(keyword)) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn symbol(&mut self, symbol: &'static str) -> bool {
+        if self.peek() == &Token::Symbol(symbol) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> QueryError {
+        QueryError::new(
+            format!("expected {expected}, found {}", self.peek()),
+            self.column(),
+        )
+    }
+
+    fn expect_symbol(&mut self, symbol: &'static str) -> Result<(), QueryError> {
+        if self.symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{symbol}`")))
+        }
+    }
+
+    fn query(&mut self) -> Result<Query, QueryError> {
+        self.target = match self.peek() {
+            Token::Word(word) => match word.to_ascii_lowercase().as_str() {
+                "individuals" | "individual" | "people" => Target::Individuals,
+                "families" | "family" => Target::Families,
+                _ => return Err(self.unexpected("`individuals` or `families`")),
+            },
+            _ => return Err(self.unexpected("`individuals` or `families`")),
+        };
+        self.advance();
+
+        let filter = if self.keyword("where") {
+            Some(self.or()?)
+        } else {
+            None
+        };
+        let mut select = Vec::new();
+        if self.keyword("select") {
+            loop {
+                select.push(if self.keyword("count") {
+                    Selection::Count(self.parenthesized_path()?)
+                } else {
+                    Selection::Path(self.path()?)
+                });
+                if !self.symbol(",") {
+                    break;
+                }
+            }
+        }
+        if self.peek() != &Token::End {
+            let expected = match (&filter, select.is_empty()) {
+                (_, false) => "`,` or end of query",
+                (Some(_), true) => "`and`, `or`, `select` or end of query",
+                (None, true) => "`where`, `select` or end of query",
+            };
+            return Err(self.unexpected(expected));
+        }
+        Ok(Query {
+            target: self.target,
+            filter,
+            select,
+        })
+    }
+
+    fn or(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.unary()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    /// Runs `parse` one level deeper, failing past [`MAX_NESTING`].
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, QueryError>,
+    ) -> Result<T, QueryError> {
+        if self.depth == MAX_NESTING {
+            return Err(QueryError::new("query nested too deeply", self.column()));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn unary(&mut self) -> Result<Expr, QueryError> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.nested(Self::unary)?)));
+        }
+        if self.symbol("(") {
+            let expr = self.nested(Self::or)?;
+            self.expect_symbol(")")?;
+            return Ok(expr);
+        }
+        if self.conditions == MAX_CONDITIONS {
+            return Err(QueryError::new(
+                "query has too many conditions",
+                self.column(),
+            ));
+        }
+        self.conditions += 1;
+        if self.keyword("exists") {
+            return Ok(Expr::Exists(self.parenthesized_path()?));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, QueryError> {
+        let left = self.term()?;
+        let column = self.column();
+        let op = match self.peek() {
+            Token::Symbol("=") => Op::Eq,
+            Token::Symbol("!=") => Op::Ne,
+            Token::Symbol("<") => Op::Lt,
+            Token::Symbol("<=") => Op::Le,
+            Token::Symbol(">") => Op::Gt,
+            Token::Symbol(">=") => Op::Ge,
+            Token::Word(word) => match word.to_ascii_lowercase().as_str() {
+                "contains" => Op::Contains,
+                "startswith" | "starts_with" => Op::StartsWith,
+                "endswith" | "ends_with" => Op::EndsWith,
+                _ => return Err(self.unexpected("a comparison operator")),
+            },
+            _ => return Err(self.unexpected("a comparison operator")),
+        };
+        self.advance();
+        let right = self.term()?;
+        check_comparison(left, op, right, column)
+    }
+
+    fn term(&mut self) -> Result<Term, QueryError> {
+        let column = self.column();
+        match self.peek().clone() {
+            Token::Text(text) => {
+                self.advance();
+                Ok(Term::Literal(Scalar::Text(fold(&text)), text, column))
+            }
+            Token::Number(number) => {
+                self.advance();
+                let value = number.parse().unwrap_or_default();
+                Ok(Term::Literal(Scalar::Number(value), number, column))
+            }
+            Token::Word(word) if word.eq_ignore_ascii_case("count") => {
+                self.advance();
+                Ok(Term::Count(self.parenthesized_path()?))
+            }
+            Token::Word(_) => Ok(Term::Path(self.path()?)),
+            _ => Err(self.unexpected("a field, string or number")),
+        }
+    }
+
+    fn parenthesized_path(&mut self) -> Result<Path, QueryError> {
+        self.expect_symbol("(")?;
+        let path = self.path()?;
+        self.expect_symbol(")")?;
+        Ok(path)
+    }
+
+    fn path(&mut self) -> Result<Path, QueryError> {
+        let column = self.column();
+        let mut kind = match self.target {
+            Target::Individuals => Kind::Individual,
+            Target::Families => Kind::Family,
+        };
+        let mut steps = Vec::new();
+        let mut names = Vec::new();
+        let mut multi = false;
+        loop {
+            let name = match self.peek() {
+                Token::Word(word) if !KEYWORDS.contains(&word.to_ascii_lowercase().as_str()) => {
+                    word.to_ascii_lowercase()
+                }
+                _ => return Err(self.unexpected("a field name")),
+            };
+            if steps.len() == MAX_PATH_STEPS {
+                return Err(QueryError::new("field path too long", self.column()));
+            }
+            let Some(&(_, field, next, many)) =
+                fields_of(kind).iter().find(|(field, ..)| *field == name)
+            else {
+                return Err(unknown_field(&name, &names, kind, self.column()));
+            };
+            self.advance();
+            steps.push(field);
+            names.push(name);
+            kind = next;
+            multi |= many;
+            if !self.symbol(".") {
+                break;
+            }
+        }
+        Ok(Path {
+            steps,
+            kind,
+            multi,
+            text: names.join("."),
+            column,
+        })
+    }
+}
+
+fn unknown_field(name: &str, parents: &[String], kind: Kind, column: usize) -> QueryError {
+    let owner = match kind {
+        _ if !parents.is_empty() => format!("`{}`", parents.join(".")),
+        Kind::Family => "families".to_owned(),
+        _ => "individuals".to_owned(),
+    };
+    if fields_of(kind).is_empty() {
+        return QueryError::new(format!("{owner} has no fields"), column);
+    }
+    let suggestion = fields_of(kind)
+        .iter()
+        .map(|(field, ..)| (edit_distance(name, field), *field))
+        .filter(|(distance, _)| *distance <= 2)
+        .min();
+    let hint = match suggestion {
+        Some((_, field)) => format!("did you mean `{field}`?"),
+        None => format!("expected one of {}", field_names(kind)),
+    };
+    QueryError::new(format!("unknown field `{name}` on {owner}; {hint}"), column)
+}
+
+/// Checks that both sides of a comparison are values of the same type,
+/// reading literals compared with dates as GEDCOM dates.
+fn check_comparison(left: Term, op: Op, right: Term, column: usize) -> Result<Expr, QueryError> {
+    let left = coerce(left, &right)?;
+    let right = coerce(right, &left)?;
+    let (left_type, right_type) = (term_type(&left)?, term_type(&right)?);
+    if left_type != right_type {
+        return Err(QueryError::new(
+            format!("cannot compare {left_type} with {right_type}"),
+            column,
+        ));
+    }
+    if op.is_text_only() && left_type != "text" {
+        return Err(QueryError::new(
+            format!("contains, startswith and endswith compare text, not {left_type}"),
+            column,
+        ));
+    }
+    Ok(Expr::Compare(left, op, right))
+}
+
+fn coerce(term: Term, other: &Term) -> Result<Term, QueryError> {
+    let compared_with_date = matches!(other, Term::Path(path) if path.kind == Kind::Date);
+    match term {
+        Term::Literal(Scalar::Text(_) | Scalar::Number(_), source, column)
+            if compared_with_date =>
+        {
+            match GedcomDate::parse(&source) {
+                Ok(date) if date.earliest_jdn.is_some() || date.latest_jdn.is_some() => {
+                    Ok(Term::Literal(
+                        Scalar::Date(date.earliest_jdn, date.latest_jdn),
+                        source,
+                        column,
+                    ))
+                }
+                _ => Err(QueryError::new(
+                    format!("`{source}` is not a GEDCOM date (e.g. 1800, 3 MAR 1801, BEF 1850)"),
+                    column,
+                )),
+            }
+        }
+        term => Ok(term),
+    }
+}
+
+fn term_type(term: &Term) -> Result<&'static str, QueryError> {
+    Ok(match term {
+        Term::Count(_) | Term::Literal(Scalar::Number(_), ..) => "a number",
+        Term::Literal(Scalar::Text(_), ..) => "text",
+        Term::Literal(Scalar::Date(..), ..) => "a date",
+        Term::Path(path) => match path.kind {
+            Kind::Text => "text",
+            Kind::Date => "a date",
+            kind => {
+                return Err(QueryError::new(
+                    format!(
+                        "`{}` is a record; compare one of its fields ({}) or use exists() or count()",
+                        path.text,
+                        field_names(kind)
+                    ),
+                    path.column,
+                ));
+            }
+        },
+    })
+}
+
+/// A value reached while walking a path.
+#[derive(Debug, Clone, Copy)]
+enum Item<'a> {
+    Individual(&'a Individual),
+    Family(&'a Family),
+    Event(&'a str, &'a Event),
+    Text(&'a str),
+    Date(&'a str),
+}
+
+fn step<'a>(store: &'a GedcomStore, item: Item<'a>, field: Field, out: &mut Vec<Item<'a>>) {
+    let person = |id: &Option<String>| {
+        id.as_deref()
+            .and_then(|id| store.get_individual(id))
+            .map(Item::Individual)
+    };
+    let events_tagged = |family: &'a Family, tag: &str, out: &mut Vec<Item<'a>>| {
+        out.extend(
+            family
+                .events
+                .iter()
+                .filter(|event| event.tag.as_deref() == Some(tag))
+                .map(|event| Item::Event(event.tag.as_deref().unwrap_or("EVEN"), event)),
+        );
+    };
+    match (item, field) {
+        (Item::Individual(individual), field) => match field {
+            Field::Id => out.push(Item::Text(&individual.id)),
+            Field::Name => out.extend(individual.name.as_deref().map(Item::Text)),
+            Field::Given => out.extend(
+                individual
+                    .name
+                    .as_deref()
+                    .and_then(|name| split_name(name).0)
+                    .map(Item::Text),
+            ),
+            Field::Surname => out.extend(
+                individual
+                    .name
+                    .as_deref()
+                    .and_then(|name| split_name(name).1)
+                    .map(Item::Text),
+            ),
+            Field::MarriedName => out.extend(
+                individual
+                    .vendor
+                    .married_name
+                    .as_deref()
+                    .map(|name| Item::Text(name.trim_matches('/'))),
+            ),
+            Field::Sex => {
+                out.extend(sex_of(store, &individual.id).map(|sex| Item::Text(sex.code())))
+            }
+            Field::Birth => out.extend(
+                individual
+                    .birth
+                    .as_ref()
+                    .map(|event| Item::Event("BIRT", event)),
+            ),
+            Field::Death => out.extend(
+                individual
+                    .death
+                    .as_ref()
+                    .map(|event| Item::Event("DEAT", event)),
+            ),
+            Field::Events => out.extend(
+                individual
+                    .tagged_events()
+                    .map(|(tag, event)| Item::Event(tag, event)),
+            ),
+            Field::Parents => {
+                for family in store.families_as_child(&individual.id) {
+                    out.extend(person(&family.husband));
+                    out.extend(person(&family.wife));
+                }
+            }
+            Field::Children => {
+                for family in store.families_as_spouse(&individual.id) {
+                    out.extend(
+                        family
+                            .children
+                            .iter()
+                            .filter_map(|child| store.get_individual(child))
+                            .map(Item::Individual),
+                    );
+                }
+            }
+            Field::Spouses => {
+                for family in store.families_as_spouse(&individual.id) {
+                    for spouse in [&family.husband, &family.wife] {
+                        if spouse.as_deref() != Some(individual.id.as_str()) {
+                            out.extend(person(spouse));
+                        }
+                    }
+                }
+            }
+            Field::Families => {
+                out.extend(store.families_as_spouse(&individual.id).map(Item::Family))
+            }
+            _ => {}
+        },
+        (Item::Family(family), field) => match field {
+            Field::Id => out.push(Item::Text(&family.id)),
+            Field::Husband => out.extend(person(&family.husband)),
+            Field::Wife => out.extend(person(&family.wife)),
+            Field::Spouses => {
+                out.extend(person(&family.husband));
+                out.extend(person(&family.wife));
+            }
+            Field::Children => out.extend(
+                family
+                    .children
+                    .iter()
+                    .filter_map(|child| store.get_individual(child))
+                    .map(Item::Individual),
+            ),
+            Field::Marriage => events_tagged(family, "MARR", out),
+            Field::Divorce => events_tagged(family, "DIV", out),
+            Field::Events => out.extend(
+                family
+                    .events
+                    .iter()
+                    .map(|event| Item::Event(event.tag.as_deref().unwrap_or("EVEN"), event)),
+            ),
+            _ => {}
+        },
+        (Item::Event(tag, event), field) => match field {
+            Field::Type => out.push(Item::Text(tag)),
+            Field::Date => out.extend(event.date.as_deref().map(Item::Date)),
+            Field::Place => out.extend(event.place.as_deref().map(Item::Text)),
+            Field::Age => out.extend(event.age.as_deref().map(Item::Text)),
+            Field::Value => out.extend(event.value.as_deref().map(Item::Text)),
+            _ => {}
+        },
+        (Item::Text(_) | Item::Date(_), _) => {}
+    }
+}
+
+/// Items `path` leads to from `record`. Records reached more than once are
+/// kept once, and each step keeps at most [`MAX_PATH_ITEMS`].
+fn walk<'a>(store: &'a GedcomStore, record: Item<'a>, path: &Path) -> Vec<Item<'a>> {
+    let mut items = vec![record];
+    for &field in &path.steps {
+        let mut next = Vec::new();
+        let mut stepped = Vec::new();
+        let mut seen = HashSet::new();
+        for item in items {
+            step(store, item, field, &mut stepped);
+            next.extend(stepped.drain(..).filter(|item| match item {
+                Item::Individual(individual) => seen.insert((Kind::Individual, &individual.id)),
+                Item::Family(family) => seen.insert((Kind::Family, &family.id)),
+                Item::Event(..) | Item::Text(_) | Item::Date(_) => true,
+            }));
+            if next.len() >= MAX_PATH_ITEMS {
+                next.truncate(MAX_PATH_ITEMS);
+                break;
+            }
+        }
+        items = next;
+    }
+    items
+}
+
+fn scalar(item: Item<'_>) -> Option<Scalar> {
+    match item {
+        Item::Text(text) => Some(Scalar::Text(fold(text))),
+        Item::Date(date) => GedcomDate::parse(date)
+            .ok()
+            .filter(|date| date.earliest_jdn.is_some() || date.latest_jdn.is_some())
+            .map(|date| Scalar::Date(date.earliest_jdn, date.latest_jdn)),
+        Item::Individual(_) | Item::Family(_) | Item::Event(..) => None,
+    }
+}
+
+fn values(store: &GedcomStore, record: Item<'_>, term: &Term) -> Vec<Scalar> {
+    match term {
+        Term::Literal(value, ..) => vec![value.clone()],
+        Term::Count(path) => vec![Scalar::Number(walk(store, record, path).len() as f64)],
+        Term::Path(path) => walk(store, record, path)
+            .into_iter()
+            .filter_map(scalar)
+            .collect(),
+    }
+}
+
+fn before(a: Option<i64>, b: Option<i64>) -> bool {
+    matches!((a, b), (Some(a), Some(b)) if a < b)
+}
+
+/// Dates compare by the ranges they cover: `<` means entirely before, `=`
+/// means the ranges overlap.
+fn compare(left: &Scalar, op: Op, right: &Scalar) -> bool {
+    match (left, right) {
+        (Scalar::Text(a), Scalar::Text(b)) => match op {
+            Op::Eq => a == b,
+            Op::Ne => a != b,
+            Op::Lt => a < b,
+            Op::Le => a <= b,
+            Op::Gt => a > b,
+            Op::Ge => a >= b,
+            Op::Contains => a.contains(b.as_str()),
+            Op::StartsWith => a.starts_with(b.as_str()),
+            Op::EndsWith => a.ends_with(b.as_str()),
+        },
+        (Scalar::Number(a), Scalar::Number(b)) => match op {
+            Op::Eq => a == b,
+            Op::Ne => a != b,
+            Op::Lt => a < b,
+            Op::Le => a <= b,
+            Op::Gt => a > b,
+            Op::Ge => a >= b,
+            _ => false,
+        },
+        (&Scalar::Date(a_low, a_high), &Scalar::Date(b_low, b_high)) => {
+            let overlap = !before(a_high, b_low) && !before(b_high, a_low);
+            match op {
+                Op::Eq => overlap,
+                Op::Ne => !overlap,
+                Op::Lt => before(a_high, b_low),
+                Op::Le => !before(b_high, a_low),
+                Op::Gt => before(b_high, a_low),
+                Op::Ge => !before(a_high, b_low),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+fn evaluate(store: &GedcomStore, record: Item<'_>, expr: &Expr) -> bool {
+    match expr {
+        Expr::And(a, b) => evaluate(store, record, a) && evaluate(store, record, b),
+        Expr::Or(a, b) => evaluate(store, record, a) || evaluate(store, record, b),
+        Expr::Not(expr) => !evaluate(store, record, expr),
+        Expr::Exists(path) => !walk(store, record, path).is_empty(),
+        Expr::Compare(left, op, right) => {
+            let right = values(store, record, right);
+            values(store, record, left)
+                .iter()
+                .any(|left| right.iter().any(|right| compare(left, *op, right)))
+        }
+    }
+}
+
+impl Query {
+    /// IDs of the matching records in ID order, and how they were found.
+    pub fn run<'a>(&self, store: &'a GedcomStore) -> (Vec<&'a str>, Plan) {
+        let mut indexes = BTreeSet::new();
+        let candidates = self
+            .filter
+            .as_ref()
+            .and_then(|filter| self.candidates(store, filter, &mut indexes));
+        let records: Vec<Item<'a>> = match (self.target, candidates) {
+            (Target::Individuals, Some(ids)) => ids
+                .iter()
+                .filter_map(|id| store.get_individual(id))
+                .map(Item::Individual)
+                .collect(),
+            (Target::Families, Some(ids)) => ids
+                .iter()
+                .filter_map(|id| store.get_family(id))
+                .map(Item::Family)
+                .collect(),
+            (Target::Individuals, None) => store.individuals().map(Item::Individual).collect(),
+            (Target::Families, None) => store.families().map(Item::Family).collect(),
+        };
+        let examined = records.len();
+        let mut ids: Vec<&str> = records
+            .into_iter()
+            .filter(|record| {
+                self.filter
+                    .as_ref()
+                    .is_none_or(|filter| evaluate(store, *record, filter))
+            })
+            .map(|record| match record {
+                Item::Individual(individual) => individual.id.as_str(),
+                Item::Family(family) => family.id.as_str(),
+                _ => unreachable!("queries only target records"),
+            })
+            .collect();
+        ids.sort_by(|a, b| compare_ids(a, b));
+        (
+            ids,
+            Plan {
+                indexes: indexes.into_iter().collect(),
+                examined,
+            },
+        )
+    }
+
+    /// Records that can possibly match `expr`, from the store's indexes, or
+    /// `None` when every record has to be examined.
+    fn candidates(
+        &self,
+        store: &GedcomStore,
+        expr: &Expr,
+        indexes: &mut BTreeSet<&'static str>,
+    ) -> Option<BTreeSet<String>> {
+        match expr {
+            Expr::And(a, b) => {
+                match (
+                    self.candidates(store, a, indexes),
+                    self.candidates(store, b, indexes),
+                ) {
+                    (Some(a), Some(b)) => Some(a.intersection(&b).cloned().collect()),
+                    (one, None) | (None, one) => one,
+                }
+            }
+            Expr::Or(a, b) => {
+                let (a, b) = (
+                    self.candidates(store, a, indexes),
+                    self.candidates(store, b, indexes),
+                );
+                Some(a?.union(&b?).cloned().collect())
+            }
+            Expr::Compare(Term::Path(path), op, Term::Literal(Scalar::Text(folded), ..)) => {
+                self.lookup(store, path, *op, folded, indexes)
+            }
+            Expr::Not(_) | Expr::Exists(_) | Expr::Compare(..) => None,
+        }
+    }
+
+    fn lookup(
+        &self,
+        store: &GedcomStore,
+        path: &Path,
+        op: Op,
+        folded: &str,
+        indexes: &mut BTreeSet<&'static str>,
+    ) -> Option<BTreeSet<String>> {
+        match (self.target, path.steps.as_slice(), op) {
+            (target, [Field::Id], Op::Eq) => {
+                indexes.insert("id");
+                Some(
+                    store
+                        .records_by_folded_id(folded)
+                        .filter_map(|record| match (target, record) {
+                            (Target::Individuals, RecordRef::Individual(id))
+                            | (Target::Families, RecordRef::Family(id)) => Some(id.clone()),
+                            _ => None,
+                        })
+                        .collect(),
+                )
+            }
+            (Target::Individuals, [Field::Surname], Op::Eq) if !folded.is_empty() => {
+                indexes.insert("names");
+                let query = NameQuery {
+                    surname: folded.split(' ').map(str::to_owned).collect(),
+                    ..Default::default()
+                };
+                Some(
+                    store
+                        .names()
+                        .search(&query)
+                        .into_iter()
+                        .map(|found| found.id)
+                        .collect(),
+                )
+            }
+            (
+                target,
+                [
+                    Field::Birth | Field::Death | Field::Events | Field::Marriage | Field::Divorce,
+                    Field::Place,
+                ],
+                Op::Eq | Op::Contains | Op::StartsWith | Op::EndsWith,
+            ) => {
+                indexes.insert("places");
+                let pattern = Scalar::Text(folded.to_owned());
+                let mut ids = BTreeSet::new();
+                for entry in store.places().entries() {
+                    if !compare(&Scalar::Text(fold(&entry.name)), op, &pattern) {
+                        continue;
+                    }
+                    for record in entry.records() {
+                        match (target, record) {
+                            (Target::Individuals, RecordRef::Individual(id))
+                            | (Target::Families, RecordRef::Family(id)) => {
+                                ids.insert(id.clone());
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Some(ids)
+            }
+            _ => None,
+        }
+    }
+
+    /// The selected fields of a matching record; by default the ID and name
+    /// of an individual, or the ID and spouses of a family.
+    pub fn project(&self, store: &GedcomStore, id: &str) -> Value {
+        let record = match self.target {
+            Target::Individuals => store.get_individual(id).map(Item::Individual),
+            Target::Families => store.get_family(id).map(Item::Family),
+        };
+        let Some(record) = record else {
+            return Value::Null;
+        };
+        let defaults;
+        let select = if self.select.is_empty() {
+            let source = match self.target {
+                Target::Individuals => "individuals select id, name",
+                Target::Families => "families select id, husband, wife",
+            };
+            defaults = parse(source).map(|query| query.select).unwrap_or_default();
+            &defaults
+        } else {
+            &self.select
+        };
+
+        let mut projected = serde_json::Map::new();
+        for selection in select {
+            let (key, value) = match selection {
+                Selection::Count(path) => (
+                    format!("count({})", path.text),
+                    Value::from(walk(store, record, path).len()),
+                ),
+                Selection::Path(path) => {
+                    let items: Vec<Value> = walk(store, record, path)
+                        .into_iter()
+                        .map(item_json)
+                        .collect();
+                    let value = if path.multi {
+                        Value::Array(items)
+                    } else {
+                        items.into_iter().next().unwrap_or(Value::Null)
+                    };
+                    (path.text.clone(), value)
+                }
+            };
+            projected.insert(key, value);
+        }
+        Value::Object(projected)
+    }
+}
+
+/// Records are shown by ID and events by their type, date, place, age and
+/// value; addresses are left out, as in list results.
+fn item_json(item: Item<'_>) -> Value {
+    match item {
+        Item::Individual(individual) => Value::from(individual.id.as_str()),
+        Item::Family(family) => Value::from(family.id.as_str()),
+        Item::Event(tag, event) => json!({
+            "type": tag,
+            "date": event.date,
+            "place": event.place,
+            "age": event.age,
+            "value": event.value,
+        }),
+        Item::Text(text) | Item::Date(text) => Value::from(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> GedcomStore {
+        GedcomStore::from_data(
+            crate::gedcom::parse_gedcom(
+                r#"0 @I1@ INDI
+1 NAME Patrick /Murphy/
+1 SEX M
+1 BIRT
+2 DATE 12 MAR 1790
+2 PLAC Cork, Ireland
+0 @I2@ INDI
+1 NAME Mary /Walsh/
+1 BIRT
+2 DATE ABT 1805
+2 PLAC Kinsale, Cork, Ireland
+1 DEAT
+2 DATE 1870
+0 @I3@ INDI
+1 NAME Sean /Murphy/
+1 BIRT
+2 DATE 1799
+2 PLAC Dublin, Ireland
+0 @I4@ INDI
+1 NAME John /Murphy/
+0 @F1@ FAM
+1 HUSB @I1@
+1 WIFE @I2@
+1 CHIL @I3@
+1 CHIL @I4@
+1 MARR
+2 DATE 1822
+"#,
+            )
+            .unwrap(),
+        )
+    }
+
+    fn run(store: &GedcomStore, query: &str) -> (Vec<String>, Plan) {
+        let (ids, plan) = parse(query).unwrap().run(store);
+        (ids.into_iter().map(str::to_owned).collect(), plan)
+    }
+
+    #[test]
+    fn evaluates_conditions_and_uses_indexes() {
+        let store = store();
+        let (ids, plan) = run(
+            &store,
+            r#"individuals where birth.date < 1800 and birth.place contains "Cork" and not exists(death)"#,
+        );
+        assert_eq!(ids, vec!["I1"]);
+        assert_eq!(plan.indexes, vec!["places"]);
+        assert_eq!(plan.examined, 2);
+
+        let (ids, plan) = run(
+            &store,
+            r#"individuals where surname = "murphy" and birth.date >= 1799"#,
+        );
+        assert_eq!(ids, vec!["I3"]);
+        assert_eq!(plan.indexes, vec!["names"]);
+
+        // `=` on dates means the ranges overlap; ABT 1805 overlaps 1805.
+        assert_eq!(
+            run(&store, "individuals where birth.date = 1805").0,
+            vec!["I2"]
+        );
+        assert_eq!(
+            run(
+                &store,
+                "individuals where not exists(birth) or parents.sex = 'm'"
+            )
+            .0,
+            vec!["I3", "I4"]
+        );
+
+        let (ids, plan) = run(
+            &store,
+            "families where count(children) > 1 and marriage.date < 1830",
+        );
+        assert_eq!(ids, vec!["F1"]);
+        assert!(plan.indexes.is_empty());
+        assert!(
+            run(&store, "families where count(children) > 10")
+                .0
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn index_plans_agree_with_scans() {
+        let store = store();
+        for (indexed, scanned) in [
+            (
+                "individuals where id = 'i1'",
+                "individuals where not (id != 'i1')",
+            ),
+            (
+                "individuals where id = '@I1@'",
+                "individuals where not (id != '@I1@')",
+            ),
+            (
+                "families where id = 'f1'",
+                "families where not (id != 'f1')",
+            ),
+            (
+                "individuals where id = 'F1'",
+                "individuals where not (id != 'F1')",
+            ),
+            (
+                "individuals where surname = 'MURPHY'",
+                "individuals where not (surname != 'MURPHY')",
+            ),
+            (
+                "individuals where birth.place endswith 'cork, ireland'",
+                "individuals where not not (birth.place endswith 'cork, ireland')",
+            ),
+        ] {
+            let (ids, plan) = run(&store, indexed);
+            assert!(!plan.indexes.is_empty(), "{indexed} should use an index");
+            let (expected, plan) = run(&store, scanned);
+            assert!(plan.indexes.is_empty());
+            assert_eq!(ids, expected, "{indexed}");
+        }
+        assert_eq!(run(&store, "individuals where id = 'i1'").0, vec!["I1"]);
+    }
+
+    #[test]
+    fn projects_selected_fields() {
+        let store = store();
+        let query =
+            parse("individuals where id = 'I2' select name, birth.date, spouses, count(children)")
+                .unwrap();
+        let (ids, plan) = query.run(&store);
+        assert_eq!(plan.indexes, vec!["id"]);
+        assert_eq!(
+            query.project(&store, ids[0]),
+            json!({
+                "name": "Mary /Walsh/",
+                "birth.date": "ABT 1805",
+                "spouses": ["I1"],
+                "count(children)": 2,
+            })
+        );
+        let families = parse("families").unwrap();
+        assert_eq!(
+            families.project(&store, "F1"),
+            json!({"id": "F1", "husband": "I1", "wife": "I2"})
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_columns() {
+        let error = |query: &str| parse(query).unwrap_err();
+        assert_eq!(
+            error("individuals where brith.date < 1800"),
+            QueryError::new(
+                "unknown field `brith` on individuals; did you mean `birth`?",
+                19
+            )
+        );
+        assert_eq!(
+            error("individuals where birth < 1800").message,
+            "`birth` is a record; compare one of its fields (type, date, place, age, value) or use exists() or count()"
+        );
+        assert_eq!(error("individuals where name = 'x").column, 26);
+        assert_eq!(
+            error("individuals where birth.date < 'soon'").message,
+            "`soon` is not a GEDCOM date (e.g. 1800, 3 MAR 1801, BEF 1850)"
+        );
+        assert_eq!(
+            error("individuals where name contains 3"),
+            QueryError::new("cannot compare text with a number", 24)
+        );
+        assert_eq!(
+            error("individuals where (name = 'x'"),
+            QueryError::new("expected `)`, found end of query", 30)
+        );
+        assert_eq!(
+            error("people where name = 'x' sort name"),
+            QueryError::new(
+                "expected `and`, `or`, `select` or end of query, found `sort`",
+                25
+            )
+        );
+        assert_eq!(error("persons").column, 1);
+    }
+
+    #[test]
+    fn rejects_deeply_nested_queries() {
+        let nested = |depth: usize| {
+            format!(
+                "individuals where {}name = 'x'{}",
+                "(".repeat(depth),
+                ")".repeat(depth)
+            )
+        };
+        assert!(parse(&nested(MAX_NESTING)).is_ok());
+        assert_eq!(
+            parse(&nested(100_000)).unwrap_err(),
+            QueryError::new("query nested too deeply", 20 + MAX_NESTING)
+        );
+        let nots = format!("individuals where {}name = 'x'", "not ".repeat(100_000));
+        assert_eq!(parse(&nots).unwrap_err().message, "query nested too deeply");
+    }
+
+    #[test]
+    fn bounds_looping_paths() {
+        let mut gedcom = String::from("0 @F1@ FAM\n1 HUSB @I1@\n1 WIFE @I2@\n");
+        for child in 3..=8 {
+            gedcom.push_str(&format!("1 CHIL @I{child}@\n"));
+        }
+        for id in 1..=8 {
+            gedcom.push_str(&format!("0 @I{id}@ INDI\n1 NAME Person {id}\n"));
+        }
+        let store = GedcomStore::from_data(crate::gedcom::parse_gedcom(&gedcom).unwrap());
+        let looping = |hops: usize| {
+            format!(
+                "individuals where count({}children) = 6",
+                "children.parents.".repeat(hops)
+            )
+        };
+
+        let (ids, _) = parse(&looping(7)).unwrap().run(&store);
+        assert_eq!(ids, vec!["I1", "I2"]);
+        let (ids, _) = parse("individuals where count(parents.children) = 6")
+            .unwrap()
+            .run(&store);
+        assert_eq!(ids, vec!["I3", "I4", "I5", "I6", "I7", "I8"]);
+
+        let error = parse(&looping(8)).unwrap_err();
+        assert_eq!(error.message, "field path too long");
+    }
+}
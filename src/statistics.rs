@@ -0,0 +1,472 @@
+//! Whole-tree statistics for `get_statistics` and the `gedcom://tree/summary`
+//! resource.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::date::{GedcomDate, gregorian_year};
+use crate::gedcom::{Event, GedcomStore, Individual, Sex, sex_of};
+use crate::lifespan::lifespan;
+use crate::listing::compare_ids;
+use crate::names::split_name;
+use crate::text::fold;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Statistics {
+    pub counts: Counts,
+    pub sex: SexBreakdown,
+    pub surnames: Vec<NameCount>,
+    pub given_names: Vec<NameCount>,
+    pub births_by_decade: Vec<DecadeCount>,
+    pub deaths_by_decade: Vec<DecadeCount>,
+    pub lifespan_by_century: Vec<CenturyLifespan>,
+    /// Mean number of children over all families, including childless ones.
+    pub average_children_per_family: Option<f64>,
+    pub earliest_date: Option<DatedEvent>,
+    pub latest_date: Option<DatedEvent>,
+    pub places: Vec<PlaceCount>,
+    pub completeness: Completeness,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Counts {
+    pub individuals: usize,
+    pub families: usize,
+    pub sources: usize,
+    pub notes: usize,
+    pub places: usize,
+}
+
+/// Sex from `SEX`, or the husband/wife role when it is missing.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SexBreakdown {
+    pub male: usize,
+    pub female: usize,
+    pub unknown: usize,
+    /// Men per woman.
+    pub ratio: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NameCount {
+    pub name: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DecadeCount {
+    pub decade: i64,
+    pub count: usize,
+}
+
+/// Mean age at death of those born in a century (`1800` for 1800–1899).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CenturyLifespan {
+    pub century: i64,
+    pub individuals: usize,
+    pub average_age: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DatedEvent {
+    pub date: String,
+    pub record: String,
+    pub tag: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlaceCount {
+    pub place: String,
+    pub events: usize,
+}
+
+/// Shares of individuals, in percent.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Completeness {
+    pub with_birth_date: f64,
+    pub with_parents: f64,
+    pub with_sources: f64,
+}
+
+fn round(value: f64, places: i32) -> f64 {
+    let scale = 10f64.powi(places);
+    (value * scale).round() / scale
+}
+
+fn percent(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    round(part as f64 * 100.0 / total as f64, 1)
+}
+
+/// Most frequent names first, then alphabetically; each name is shown in the
+/// spelling met first in ID order.
+#[derive(Default)]
+struct Tally {
+    counts: HashMap<String, (String, usize)>,
+}
+
+impl Tally {
+    fn add(&mut self, name: &str) {
+        let key = fold(name);
+        if key.is_empty() {
+            return;
+        }
+        self.counts
+            .entry(key)
+            .or_insert_with(|| (name.to_owned(), 0))
+            .1 += 1;
+    }
+
+    fn top(self, limit: usize) -> Vec<NameCount> {
+        let mut counts: Vec<(String, String, usize)> = self
+            .counts
+            .into_iter()
+            .map(|(key, (name, count))| (key, name, count))
+            .collect();
+        counts.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        counts
+            .into_iter()
+            .take(limit)
+            .map(|(_, name, count)| NameCount { name, count })
+            .collect()
+    }
+}
+
+/// A single Julian day to place a date on a timeline: the middle of its
+/// interval, or its only bound.
+fn midpoint(date: &GedcomDate) -> Option<i64> {
+    match (date.earliest_jdn, date.latest_jdn) {
+        (Some(earliest), Some(latest)) => Some(earliest + (latest - earliest) / 2),
+        (earliest, latest) => earliest.or(latest),
+    }
+}
+
+fn event_year(event: Option<&Event>) -> Option<i64> {
+    let date = GedcomDate::parse(event?.date.as_deref()?).ok()?;
+    midpoint(&date).map(gregorian_year)
+}
+
+fn by_decade(years: impl Iterator<Item = i64>) -> Vec<DecadeCount> {
+    let mut decades = BTreeMap::new();
+    for year in years {
+        *decades.entry(year.div_euclid(10) * 10).or_insert(0) += 1;
+    }
+    decades
+        .into_iter()
+        .map(|(decade, count)| DecadeCount { decade, count })
+        .collect()
+}
+
+fn has_sources(individual: &Individual) -> bool {
+    !individual.citations.is_empty()
+        || individual
+            .tagged_events()
+            .any(|(_, event)| !event.citations.is_empty())
+}
+
+/// Earliest and latest dated events, comparing the earliest and latest day
+/// each date could be; ties go to the lower record ID.
+fn date_range(store: &GedcomStore) -> (Option<DatedEvent>, Option<DatedEvent>) {
+    let individual_events = store.individuals().flat_map(|individual| {
+        individual
+            .tagged_events()
+            .map(move |(tag, event)| (individual.id.as_str(), tag, event))
+    });
+    let family_events = store.families().flat_map(|family| {
+        family.events.iter().map(move |event| {
+            let tag = event.tag.as_deref().unwrap_or("EVEN");
+            (family.id.as_str(), tag, event)
+        })
+    });
+
+    let mut earliest: Option<(i64, DatedEvent)> = None;
+    let mut latest: Option<(i64, DatedEvent)> = None;
+    for (record, tag, event) in individual_events.chain(family_events) {
+        let Some(raw) = event.date.as_deref() else {
+            continue;
+        };
+        let Ok(date) = GedcomDate::parse(raw) else {
+            continue;
+        };
+        let dated = || DatedEvent {
+            date: raw.to_owned(),
+            record: record.to_owned(),
+            tag: tag.to_owned(),
+        };
+        let replaces = |current: &Option<(i64, DatedEvent)>, day: i64, later: bool| {
+            current.as_ref().is_none_or(|(best, event)| {
+                let order = if later { best.cmp(&day) } else { day.cmp(best) };
+                order
+                    .then_with(|| compare_ids(record, &event.record))
+                    .is_lt()
+            })
+        };
+        if let Some(day) = date.earliest_jdn
+            && replaces(&earliest, day, false)
+        {
+            earliest = Some((day, dated()));
+        }
+        if let Some(day) = date.latest_jdn
+            && replaces(&latest, day, true)
+        {
+            latest = Some((day, dated()));
+        }
+    }
+    (
+        earliest.map(|(_, event)| event),
+        latest.map(|(_, event)| event),
+    )
+}
+
+/// Computes the statistics, listing at most `top` names and places.
+pub fn statistics(store: &GedcomStore, top: usize) -> Statistics {
+    let mut individuals: Vec<&Individual> = store.individuals().collect();
+    individuals.sort_by(|a, b| compare_ids(&a.id, &b.id));
+    let total = individuals.len();
+
+    let (mut male, mut female, mut unknown) = (0, 0, 0);
+    let (mut surnames, mut given_names) = (Tally::default(), Tally::default());
+    let mut ages: BTreeMap<i64, (usize, f64)> = BTreeMap::new();
+    let (mut with_birth_date, mut with_parents, mut with_sources) = (0, 0, 0);
+    for individual in &individuals {
+        match sex_of(store, &individual.id) {
+            Some(Sex::Male) => male += 1,
+            Some(Sex::Female) => female += 1,
+            _ => unknown += 1,
+        }
+        if let Some(name) = individual.name.as_deref() {
+            let (given, surname) = split_name(name);
+            surnames.add(surname.unwrap_or_default());
+            if let Some(first) = given.and_then(|given| given.split_whitespace().next()) {
+                given_names.add(first);
+            }
+        }
+
+        let birth_year = event_year(individual.birth.as_ref());
+        if let (Some(year), Some(age)) = (birth_year, lifespan(store, individual).age_at_death)
+            && let (Some(min), Some(max)) = (age.min_years, age.max_years)
+        {
+            let century = ages.entry(year.div_euclid(100) * 100).or_default();
+            century.0 += 1;
+            century.1 += (min + max) as f64 / 2.0;
+        }
+
+        if individual
+            .birth
+            .as_ref()
+            .is_some_and(|birth| birth.date.is_some())
+        {
+            with_birth_date += 1;
+        }
+        if store
+            .families_as_child(&individual.id)
+            .any(|family| family.husband.is_some() || family.wife.is_some())
+        {
+            with_parents += 1;
+        }
+        if has_sources(individual) {
+            with_sources += 1;
+        }
+    }
+
+    let families = store.families().count();
+    let children: usize = store.families().map(|family| family.children.len()).sum();
+
+    let mut places: Vec<PlaceCount> = store
+        .places()
+        .entries()
+        .map(|entry| PlaceCount {
+            place: entry.name.clone(),
+            events: entry.event_count(),
+        })
+        .collect();
+    places.sort_by(|a, b| b.events.cmp(&a.events).then_with(|| a.place.cmp(&b.place)));
+    let place_count = places.len();
+    places.truncate(top);
+
+    let (earliest_date, latest_date) = date_range(store);
+    Statistics {
+        counts: Counts {
+            individuals: total,
+            families,
+            sources: store.sources().count(),
+            notes: store.notes().count(),
+            places: place_count,
+        },
+        sex: SexBreakdown {
+            male,
+            female,
+            unknown,
+            ratio: (female > 0).then(|| round(male as f64 / female as f64, 2)),
+        },
+        surnames: surnames.top(top),
+        given_names: given_names.top(top),
+        births_by_decade: by_decade(
+            individuals
+                .iter()
+                .filter_map(|individual| event_year(individual.birth.as_ref())),
+        ),
+        deaths_by_decade: by_decade(
+            individuals
+                .iter()
+                .filter_map(|individual| event_year(individual.death.as_ref())),
+        ),
+        lifespan_by_century: ages
+            .into_iter()
+            .map(|(century, (count, years))| CenturyLifespan {
+                century,
+                individuals: count,
+                average_age: round(years / count as f64, 1),
+            })
+            .collect(),
+        average_children_per_family: (families > 0)
+            .then(|| round(children as f64 / families as f64, 2)),
+        earliest_date,
+        latest_date,
+        places,
+        completeness: Completeness {
+            with_birth_date: percent(with_birth_date, total),
+            with_parents: percent(with_parents, total),
+            with_sources: percent(with_sources, total),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_the_tree() {
+        let store = GedcomStore::from_data(
+            crate::gedcom::parse_gedcom(
+                r#"0 @I1@ INDI
+1 NAME Johann /Müller/
+1 SEX M
+1 BIRT
+2 DATE 12 MAR 1801
+2 PLAC Bern, Switzerland
+2 SOUR @S1@
+1 DEAT
+2 DATE 1871
+2 PLAC Bern, Switzerland
+0 @I2@ INDI
+1 NAME Anna Maria /Keller/
+1 BIRT
+2 DATE ABT 1805
+1 DEAT
+2 DATE 3 JAN 1850
+0 @I3@ INDI
+1 NAME Johann Jakob /Muller/
+1 SEX M
+1 BIRT
+2 DATE 1832
+2 PLAC Thun, Switzerland
+0 @I4@ INDI
+1 NAME Verena /Müller/
+1 SEX F
+0 @F1@ FAM
+1 HUSB @I1@
+1 WIFE @I2@
+1 CHIL @I3@
+1 CHIL @I4@
+1 MARR
+2 DATE BET 1828 AND 1830
+2 PLAC Bern, Switzerland
+0 @F2@ FAM
+1 HUSB @I3@
+0 @S1@ SOUR
+1 TITL Kirchenbuch Bern
+"#,
+            )
+            .unwrap(),
+        );
+        let stats = statistics(&store, 2);
+
+        assert_eq!(
+            stats.counts,
+            Counts {
+                individuals: 4,
+                families: 2,
+                sources: 1,
+                notes: 0,
+                places: 2,
+            }
+        );
+        // Anna is female by her role as wife.
+        assert_eq!(
+            stats.sex,
+            SexBreakdown {
+                male: 2,
+                female: 2,
+                unknown: 0,
+                ratio: Some(1.0),
+            }
+        );
+        assert_eq!(
+            stats.surnames,
+            vec![
+                NameCount {
+                    name: "Müller".into(),
+                    count: 3,
+                },
+                NameCount {
+                    name: "Keller".into(),
+                    count: 1,
+                },
+            ]
+        );
+        assert_eq!(stats.given_names[0].name, "Johann");
+        assert_eq!(stats.given_names[0].count, 2);
+        assert_eq!(
+            stats.births_by_decade,
+            vec![
+                DecadeCount {
+                    decade: 1800,
+                    count: 2,
+                },
+                DecadeCount {
+                    decade: 1830,
+                    count: 1,
+                },
+            ]
+        );
+        assert_eq!(stats.deaths_by_decade.len(), 2);
+        // 69 or 70, and 44 or 45.
+        assert_eq!(
+            stats.lifespan_by_century,
+            vec![CenturyLifespan {
+                century: 1800,
+                individuals: 2,
+                average_age: 57.0,
+            }]
+        );
+        assert_eq!(stats.average_children_per_family, Some(1.0));
+        assert_eq!(
+            stats.earliest_date,
+            Some(DatedEvent {
+                date: "12 MAR 1801".into(),
+                record: "I1".into(),
+                tag: "BIRT".into(),
+            })
+        );
+        assert_eq!(stats.latest_date.unwrap().date, "1871");
+        assert_eq!(
+            stats.places[0],
+            PlaceCount {
+                place: "Bern, Switzerland".into(),
+                events: 3,
+            }
+        );
+        assert_eq!(
+            stats.completeness,
+            Completeness {
+                with_birth_date: 75.0,
+                with_parents: 50.0,
+                with_sources: 25.0,
+            }
+        );
+    }
+}